@@ -0,0 +1,93 @@
+function setLastRefreshLabel() {
+    const label = document.getElementById('messagesLastRefresh');
+    const now = new Date();
+    label.textContent = `Last refresh: ${now.toISOString()}`;
+}
+
+// The API key is kept in the browser so it is entered only once
+const apiKeyInput = document.getElementById('apiKey');
+apiKeyInput.value = localStorage.getItem('smserApiKey') || '';
+apiKeyInput.addEventListener('change', () => {
+    localStorage.setItem('smserApiKey', apiKeyInput.value);
+    fetchMessages();
+});
+
+function apiHeaders(headers = {}) {
+    return apiKeyInput.value ? { ...headers, 'X-Api-Key': apiKeyInput.value } : headers;
+}
+
+async function fetchMessages() {
+    try {
+        const response = await fetch('/get-sms?count=10', { headers: apiHeaders() });
+        const data = await response.json();
+        const list = document.getElementById('messagesList');
+
+        if (data.status === 'success' && data.messages) {
+            setLastRefreshLabel();
+            if (data.messages.length === 0) {
+                list.innerHTML = '<div class="text-center p-4">No messages found.</div>';
+                return;
+            }
+
+            list.innerHTML = data.messages.map(msg => `
+                <div class="card msg-card border-0 border-bottom">
+                    <div class="card-body px-0">
+                        <div class="d-flex justify-content-between align-items-start">
+                            <h6 class="mb-1">${msg.Phone}</h6>
+                            <span class="date">${msg.Date}</span>
+                        </div>
+                        <p class="card-text mb-0">${msg.Content}</p>
+                    </div>
+                </div>
+            `).join('');
+        } else {
+            setLastRefreshLabel();
+            list.innerHTML = `<div class="alert alert-danger">Error: ${data.error?.message || 'Failed to load'}</div>`;
+        }
+    } catch (e) {
+        setLastRefreshLabel();
+        document.getElementById('messagesList').innerHTML = `<div class="alert alert-danger">Error connecting to server.</div>`;
+    }
+}
+
+document.getElementById('sendForm').addEventListener('submit', async (e) => {
+    e.preventDefault();
+    const btn = document.getElementById('sendBtn');
+    const alert = document.getElementById('sendAlert');
+    const to = document.getElementById('to').value;
+    const message = document.getElementById('message').value;
+
+    btn.disabled = true;
+    alert.className = 'mt-3 d-none alert';
+
+    try {
+        const response = await fetch('/send-sms?sync=true', {
+            method: 'POST',
+            headers: apiHeaders({ 'Content-Type': 'application/json' }),
+            body: JSON.stringify({ to, message, client: 'webclient' })
+        });
+
+        alert.className = `mt-3 alert alert-${response.ok ? 'success' : 'danger'}`;
+        if (response.ok) {
+            const data = await response.json();
+            alert.innerText = `Sent! (id ${data.id})`;
+        } else {
+            const data = await response.json().catch(() => ({}));
+            alert.innerText = data.error?.message || 'Failed';
+        }
+        alert.classList.remove('d-none');
+
+        if (response.ok) {
+            document.getElementById('message').value = '';
+        }
+    } catch (e) {
+        alert.className = 'mt-3 alert alert-danger';
+        alert.innerText = 'Network error.';
+        alert.classList.remove('d-none');
+    } finally {
+        btn.disabled = false;
+    }
+});
+
+// Initial load
+fetchMessages();
@@ -0,0 +1,326 @@
+// Single-page UI for reading and sending messages, using the JSON API
+
+const GSM_BASIC = '@£$¥èéùìòÇ\nØø\rÅåΔ_ΦΓΛΩΠΨΣΘΞÆæßÉ !"#¤%&\'()*+,-./0123456789:;<=>?' +
+    '¡ABCDEFGHIJKLMNOPQRSTUVWXYZÄÖÑÜ§¿abcdefghijklmnopqrstuvwxyzäöñüà';
+const GSM_EXTENDED = '^{}\\[~]|€';
+const PAGE_SIZE = 20;
+const REFRESH_MS = 30000;
+
+let view = 'inbox';
+let page = 1;
+
+function escapeHtml(s) {
+    return String(s ?? '').replace(/[&<>"']/g, c => ({
+        '&': '&amp;', '<': '&lt;', '>': '&gt;', '"': '&quot;', "'": '&#39;'
+    })[c]);
+}
+
+// The API key is kept in the browser so it is entered only once
+const apiKeyInput = document.getElementById('apiKey');
+apiKeyInput.value = localStorage.getItem('smserApiKey') || '';
+apiKeyInput.addEventListener('change', () => {
+    localStorage.setItem('smserApiKey', apiKeyInput.value);
+    refresh();
+});
+
+// Calls the JSON API, throwing the error envelope's message on failure
+async function api(path, options = {}) {
+    const headers = { ...(options.headers || {}) };
+    if (apiKeyInput.value) {
+        headers['X-Api-Key'] = apiKeyInput.value;
+    }
+    if (options.body !== undefined) {
+        headers['Content-Type'] = 'application/json';
+        options = { ...options, body: JSON.stringify(options.body) };
+    }
+    let response;
+    try {
+        response = await fetch(path, { ...options, headers });
+    } catch (e) {
+        throw new Error('Cannot reach the server');
+    }
+    const data = await response.json().catch(() => ({}));
+    if (!response.ok) {
+        const error = new Error(data.error?.message || `Request failed (${response.status})`);
+        error.fields = data.error?.fields || [];
+        throw error;
+    }
+    return data;
+}
+
+function showError(message) {
+    const error = document.getElementById('error');
+    error.textContent = message || '';
+    error.classList.toggle('d-none', !message);
+}
+
+function formatTime(unixSeconds) {
+    return unixSeconds ? new Date(unixSeconds * 1000).toLocaleString() : '';
+}
+
+async function loadMessages() {
+    const list = document.getElementById('messages');
+    const boxType = view === 'sent' ? 2 : 1;
+    try {
+        const data = await api(`/get-sms?box_type=${boxType}&count=${PAGE_SIZE}&page=${page}`);
+        showError('');
+        document.getElementById('lastRefresh').textContent =
+            `Last refresh: ${new Date().toLocaleTimeString()}`;
+        document.getElementById('page').textContent = `Page ${page}`;
+        document.getElementById('prevPage').disabled = page === 1;
+        document.getElementById('nextPage').disabled = data.messages.length < PAGE_SIZE;
+        if (data.messages.length === 0) {
+            list.innerHTML = '<div class="list-group-item text-center p-4">No messages.</div>';
+            return;
+        }
+        list.innerHTML = data.messages.map(msg => `
+            <div class="list-group-item ${msg.Smstat === 0 ? 'unread' : ''}">
+                <div class="d-flex justify-content-between align-items-start">
+                    <h6 class="mb-1">${escapeHtml(msg.Phone)}</h6>
+                    <span class="date">${escapeHtml(msg.Date)}</span>
+                </div>
+                <p class="mb-2 msg-content">${escapeHtml(msg.Content)}</p>
+                <div class="d-flex gap-2">
+                    ${view === 'inbox' ? `<button class="btn btn-sm btn-outline-primary" data-reply="${escapeHtml(msg.Phone)}">Reply</button>` : ''}
+                    ${msg.Smstat === 0 ? `<button class="btn btn-sm btn-outline-secondary" data-read="${msg.Index}">Mark read</button>` : ''}
+                    <button class="btn btn-sm btn-outline-danger" data-delete="${msg.Index}">Delete</button>
+                </div>
+            </div>
+        `).join('');
+    } catch (e) {
+        showError(e.message);
+    }
+}
+
+document.getElementById('messages').addEventListener('click', async (e) => {
+    const button = e.target.closest('button');
+    if (!button) {
+        return;
+    }
+    try {
+        if (button.dataset.reply) {
+            document.getElementById('to').value = button.dataset.reply;
+            switchView('compose');
+            document.getElementById('message').focus();
+            return;
+        }
+        if (button.dataset.read) {
+            await api('/mark-read', { method: 'POST', body: { indexes: [Number(button.dataset.read)] } });
+        }
+        if (button.dataset.delete) {
+            if (!confirm('Delete this message from the modem?')) {
+                return;
+            }
+            await api(`/sms/${button.dataset.delete}`, { method: 'DELETE' });
+        }
+        await loadMessages();
+    } catch (e) {
+        showError(e.message);
+    }
+});
+
+document.getElementById('prevPage').addEventListener('click', () => {
+    page = Math.max(1, page - 1);
+    loadMessages();
+});
+document.getElementById('nextPage').addEventListener('click', () => {
+    page += 1;
+    loadMessages();
+});
+document.getElementById('refresh').addEventListener('click', () => refresh());
+
+// Characters, encoding and parts of a message, as the modem will send it
+function estimate(text) {
+    const chars = [...text];
+    const gsm = chars.every(c => GSM_BASIC.includes(c) || GSM_EXTENDED.includes(c));
+    const length = gsm
+        ? chars.reduce((n, c) => n + (GSM_EXTENDED.includes(c) ? 2 : 1), 0)
+        : chars.length;
+    const [single, multi] = gsm ? [160, 153] : [70, 67];
+    const parts = length <= single ? 1 : Math.ceil(length / multi);
+    return { length, parts, encoding: gsm ? 'GSM-7' : 'Unicode' };
+}
+
+const messageInput = document.getElementById('message');
+messageInput.addEventListener('input', () => {
+    const { length, parts, encoding } = estimate(messageInput.value);
+    document.getElementById('length').textContent =
+        `${length} characters, ${encoding}, ${parts} SMS per recipient`;
+});
+
+function setFieldError(input, feedback, message) {
+    input.classList.toggle('is-invalid', Boolean(message));
+    document.getElementById(feedback).textContent = message || '';
+}
+
+document.getElementById('composeForm').addEventListener('submit', async (e) => {
+    e.preventDefault();
+    const toInput = document.getElementById('to');
+    const button = document.getElementById('sendBtn');
+    const result = document.getElementById('sendResult');
+    const to = toInput.value.split(/[,;\n]/).map(s => s.trim()).filter(Boolean);
+    setFieldError(toInput, 'toError', '');
+    setFieldError(messageInput, 'messageError', '');
+    result.className = 'mt-3 d-none alert';
+    button.disabled = true;
+    button.textContent = 'Sending...';
+    try {
+        const data = await api('/send-sms?sync=true', {
+            method: 'POST',
+            body: {
+                to: to.length === 1 ? to[0] : to,
+                message: messageInput.value,
+                flash: document.getElementById('flash').checked,
+                client: 'webui',
+            },
+        });
+        result.className = 'mt-3 alert alert-success';
+        result.textContent = `Sent to ${to.length} recipient(s) (id ${data.id})`;
+        messageInput.value = '';
+        messageInput.dispatchEvent(new Event('input'));
+    } catch (e) {
+        for (const field of e.fields || []) {
+            if (field.field.startsWith('to')) {
+                setFieldError(toInput, 'toError', field.message);
+            } else if (field.field === 'message') {
+                setFieldError(messageInput, 'messageError', field.message);
+            }
+        }
+        result.className = 'mt-3 alert alert-danger';
+        result.textContent = e.message;
+    } finally {
+        button.disabled = false;
+        button.textContent = 'Send';
+    }
+});
+
+async function loadOutbox() {
+    const list = document.getElementById('outbox');
+    try {
+        const data = await api('/outbox');
+        showError('');
+        if (data.messages.length === 0) {
+            list.innerHTML = '<div class="list-group-item text-center p-4">Nothing waiting to be sent.</div>';
+            return;
+        }
+        list.innerHTML = data.messages.map(msg => `
+            <div class="list-group-item">
+                <div class="d-flex justify-content-between align-items-start">
+                    <h6 class="mb-1">${escapeHtml(msg.to.join(', '))}</h6>
+                    <span class="badge text-bg-${msg.state === 'failed' ? 'danger' : 'secondary'}">${escapeHtml(msg.state)}</span>
+                </div>
+                <p class="mb-1 msg-content">${escapeHtml(msg.message)}</p>
+                <div class="date mb-2">
+                    Queued ${escapeHtml(formatTime(msg.queued_at))}, ${msg.attempts} failed attempt(s)
+                    ${msg.last_error ? `: ${escapeHtml(msg.last_error)}` : ''}
+                </div>
+                <div class="d-flex gap-2">
+                    ${msg.state === 'failed' ? `<button class="btn btn-sm btn-outline-primary" data-retry="${msg.id}">Retry</button>` : ''}
+                    ${msg.state !== 'sending' ? `<button class="btn btn-sm btn-outline-danger" data-cancel="${msg.id}">Cancel</button>` : ''}
+                </div>
+            </div>
+        `).join('');
+    } catch (e) {
+        showError(e.message);
+    }
+}
+
+document.getElementById('outbox').addEventListener('click', async (e) => {
+    const button = e.target.closest('button');
+    if (!button) {
+        return;
+    }
+    try {
+        if (button.dataset.retry) {
+            await api(`/outbox/${button.dataset.retry}/retry`, { method: 'POST' });
+        }
+        if (button.dataset.cancel) {
+            await api(`/outbox/${button.dataset.cancel}`, { method: 'DELETE' });
+        }
+        await loadOutbox();
+    } catch (e) {
+        showError(e.message);
+    }
+});
+
+function usageBar(label, usage, limit) {
+    if (limit === null || limit === undefined) {
+        return '';
+    }
+    const pct = limit > 0 ? Math.min(100, Math.round(usage * 100 / limit)) : 100;
+    const color = pct >= 90 ? 'danger' : pct >= 70 ? 'warning' : 'success';
+    return `
+        <div class="mb-2">
+            <div class="d-flex justify-content-between small"><span>${label}</span><span>${usage} / ${limit}</span></div>
+            <div class="progress" role="progressbar" aria-valuenow="${pct}" aria-valuemin="0" aria-valuemax="100">
+                <div class="progress-bar bg-${color}" style="width: ${pct}%"></div>
+            </div>
+        </div>`;
+}
+
+function limitsCard(title, status) {
+    return `
+        <div class="card shadow-sm mb-3">
+            <div class="card-header">${escapeHtml(title)}</div>
+            <div class="card-body">
+                ${usageBar('This hour', status.hourly_usage, status.hourly_limit)}
+                ${usageBar('Today', status.daily_usage, status.daily_limit)}
+            </div>
+        </div>`;
+}
+
+async function loadLimits() {
+    try {
+        const data = await api('/quota');
+        showError('');
+        document.getElementById('limits').innerHTML = [
+            limitsCard('All messages', data.global),
+            ...data.clients.map(c => limitsCard(`Client ${c.name}`, c)),
+            ...data.api_keys.map(k => limitsCard(`API key ${k.name}`, k)),
+        ].join('');
+    } catch (e) {
+        showError(e.message);
+    }
+}
+
+function refresh() {
+    switch (view) {
+        case 'inbox':
+        case 'sent':
+            return loadMessages();
+        case 'outbox':
+            return loadOutbox();
+        case 'limits':
+            return loadLimits();
+    }
+}
+
+function switchView(next) {
+    if (next !== view) {
+        page = 1;
+    }
+    view = next;
+    for (const tab of document.querySelectorAll('#tabs .nav-link')) {
+        tab.classList.toggle('active', tab.dataset.view === view);
+    }
+    const section = view === 'inbox' || view === 'sent' ? 'messages' : view;
+    for (const el of document.querySelectorAll('.view')) {
+        el.classList.toggle('d-none', el.id !== `view-${section}`);
+    }
+    showError('');
+    refresh();
+}
+
+document.getElementById('tabs').addEventListener('click', (e) => {
+    const tab = e.target.closest('.nav-link');
+    if (tab) {
+        switchView(tab.dataset.view);
+    }
+});
+
+setInterval(() => {
+    if (!document.hidden) {
+        refresh();
+    }
+}, REFRESH_MS);
+switchView('inbox');
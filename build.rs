@@ -1,5 +1,58 @@
+use clap::CommandFactory;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+// The command line arguments and the modules they use, to generate the man
+// pages from the same definitions the binary parses
+#[allow(dead_code)]
+#[path = "src/args.rs"]
+mod args;
+#[cfg(feature = "modem")]
+#[allow(dead_code)]
+#[path = "src/cron.rs"]
+mod cron;
+#[cfg(feature = "server")]
+#[allow(dead_code)]
+#[path = "src/dedup.rs"]
+mod dedup;
+#[allow(dead_code)]
+#[path = "src/encoding.rs"]
+mod encoding;
+#[allow(dead_code)]
+#[path = "src/output.rs"]
+mod output;
+#[cfg(feature = "modem")]
+#[allow(dead_code)]
+#[path = "src/profile.rs"]
+mod profile;
+#[allow(dead_code)]
+#[path = "src/segment.rs"]
+mod segment;
+#[allow(dead_code)]
+#[path = "src/template.rs"]
+mod template;
+#[allow(dead_code)]
+#[path = "src/types.rs"]
+mod types;
+
+/// Writes a man page for `command` and, named `<command>-<subcommand>.1`, for
+/// each of its subcommands into `dir`
+fn write_man_pages(command: &clap::Command, dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let mut command = command.clone();
+    command.build();
+    let mut file = std::fs::File::create(dir.join(format!("{}.1", command.get_name())))?;
+    clap_mangen::Man::new(command.clone()).render(&mut file)?;
+    for subcommand in command.get_subcommands() {
+        if subcommand.get_name() == "help" {
+            continue;
+        }
+        let name = format!("{}-{}", command.get_name(), subcommand.get_name());
+        write_man_pages(&subcommand.clone().name(name), dir)?;
+    }
+    Ok(())
+}
+
 fn main() {
     // Get git commit hash
     let output = Command::new("git")
@@ -17,4 +70,36 @@ fn main() {
     // Rerun if git HEAD changes
     println!("cargo:rerun-if-changed=.git/HEAD");
     println!("cargo:rerun-if-changed=.git/refs/heads/");
+
+    // Rebuild when embedded web assets change
+    println!("cargo:rerun-if-changed=assets");
+
+    // Man pages in OUT_DIR/man, and also in SMSER_MAN_DIR if set, for packaging
+    for module in [
+        "args", "cron", "dedup", "encoding", "output", "profile", "segment", "template", "types",
+    ] {
+        println!("cargo:rerun-if-changed=src/{}.rs", module);
+    }
+    println!("cargo:rerun-if-env-changed=SMSER_MAN_DIR");
+    let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").expect("OUT_DIR not set"));
+    // Start over, so pages of removed commands do not linger
+    std::fs::remove_dir_all(out_dir.join("man")).ok();
+    let mut man_dirs = vec![out_dir.join("man")];
+    man_dirs.extend(std::env::var_os("SMSER_MAN_DIR").map(PathBuf::from));
+    for dir in man_dirs {
+        if let Err(e) = write_man_pages(&args::Args::command(), &dir) {
+            panic!("Failed to write man pages to {}: {}", dir.display(), e);
+        }
+    }
+
+    // Generate the gRPC service, compiling the proto file without protoc
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto");
+        let descriptors =
+            protox::compile(["proto/smser.proto"], ["proto"]).expect("Failed to compile protos");
+        tonic_build::configure()
+            .compile_fds(descriptors)
+            .expect("Failed to generate gRPC code");
+    }
 }
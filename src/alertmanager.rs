@@ -1,5 +1,14 @@
+use crate::config::{Config, QuietHoursConfig};
+use crate::cron::parse_duration;
+use crate::queue::Lane;
+use crate::template;
+use crate::types::Priority;
+use chrono::{DateTime, Local, NaiveTime, TimeDelta, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tracing::warn;
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AlertManagerWebhook {
@@ -61,6 +70,223 @@ pub fn format_alert_message(webhook: &AlertManagerWebhook) -> String {
     format!("{}: {} ({}) - {}", status, alert_name, severity, summary)
 }
 
+/// Variables available to receiver templates: all common labels and
+/// annotations, plus `status`, `receiver`, `count` and `summary`
+pub fn template_vars(webhook: &AlertManagerWebhook) -> HashMap<String, String> {
+    let mut vars: HashMap<String, String> = webhook
+        .common_labels
+        .iter()
+        .chain(&webhook.common_annotations)
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    vars.insert("status".to_string(), webhook.status.to_uppercase());
+    vars.insert("receiver".to_string(), webhook.receiver.clone());
+    vars.insert("count".to_string(), webhook.alerts.len().to_string());
+    if !vars.contains_key("summary") {
+        let summary = webhook
+            .common_annotations
+            .get("description")
+            .or_else(|| webhook.common_annotations.get("message"))
+            .map(|s| s.as_str())
+            .unwrap_or("No summary");
+        vars.insert("summary".to_string(), summary.to_string());
+    }
+    vars
+}
+
+/// Formats the alert with `template` if given, else with the default format.
+/// If the template uses a label or annotation the alert doesn't have, the
+/// default format is sent instead, so the alert still goes out readable.
+pub fn render_alert_message(webhook: &AlertManagerWebhook, template: Option<&str>) -> String {
+    let Some(text) = template else {
+        return format_alert_message(webhook);
+    };
+    match template::render(text, &template_vars(webhook)) {
+        Ok(message) => message,
+        Err(e) => {
+            warn!("Alert template not usable, sending the default text: {}", e);
+            format_alert_message(webhook)
+        }
+    }
+}
+
+/// Daily period in local time; may span midnight
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuietHours {
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn from_config(config: &QuietHoursConfig) -> Result<Self, String> {
+        let parse = |s: &str| {
+            NaiveTime::parse_from_str(s, "%H:%M")
+                .map_err(|_| format!("Invalid quiet hours time '{}' (expected HH:MM)", s))
+        };
+        Ok(Self {
+            start: parse(&config.start)?,
+            end: parse(&config.end)?,
+        })
+    }
+
+    /// Time left until quiet hours end, or None outside quiet hours
+    pub fn remaining(&self, now: DateTime<Local>) -> Option<TimeDelta> {
+        let time = now.time();
+        let inside = if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        };
+        if !inside {
+            return None;
+        }
+        let remaining = self.end - time;
+        if remaining < TimeDelta::zero() {
+            Some(remaining + TimeDelta::days(1))
+        } else {
+            Some(remaining)
+        }
+    }
+}
+
+/// How a single alert is sent
+#[derive(Clone, Debug, PartialEq)]
+pub struct AlertPolicy {
+    pub priority: Priority,
+    pub lane: Lane,
+    /// Whether to send a flash SMS
+    pub flash: bool,
+    /// Delay before the alert is sent, if it falls into quiet hours
+    pub defer: Option<TimeDelta>,
+    /// Recipients replacing the receiver's
+    pub to: Option<Vec<String>>,
+    /// Template replacing the receiver's
+    pub template: Option<String>,
+    /// How long repeats of the alert are suppressed after it was sent
+    pub suppress_for: Option<TimeDelta>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Override {
+    to: Vec<String>,
+    template: Option<String>,
+    suppress_for: Option<TimeDelta>,
+    quiet_hours_exempt: bool,
+}
+
+/// Severity and alertname based alert policies
+#[derive(Debug, Default)]
+pub struct AlertPolicies {
+    /// Priority, deferral in quiet hours and flash by severity
+    severities: HashMap<String, (Priority, bool, bool)>,
+    quiet_hours: Option<QuietHours>,
+    overrides: HashMap<String, Override>,
+    /// When alerts with a suppression TTL were last sent, by suppression key
+    last_sent: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl AlertPolicies {
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let severities = config
+            .severities
+            .iter()
+            .map(|(severity, policy)| {
+                let priority = match &policy.priority {
+                    Some(p) => Priority::from_str(p).map_err(|_| {
+                        format!("Invalid priority '{}' for severity '{}'", p, severity)
+                    })?,
+                    None => Priority::Normal,
+                };
+                Ok((
+                    severity.clone(),
+                    (priority, policy.defer_in_quiet_hours, policy.flash),
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+        let overrides = config
+            .alertnames
+            .iter()
+            .map(|(alertname, o)| {
+                let suppress_for = o
+                    .suppress_for
+                    .as_deref()
+                    .map(parse_duration)
+                    .transpose()
+                    .map_err(|e| format!("Alertname '{}': {}", alertname, e))?;
+                let parsed = Override {
+                    to: o.to.clone(),
+                    template: o.template.clone(),
+                    suppress_for,
+                    quiet_hours_exempt: o.quiet_hours_exempt,
+                };
+                Ok((alertname.clone(), parsed))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            severities,
+            quiet_hours: config
+                .quiet_hours
+                .as_ref()
+                .map(QuietHours::from_config)
+                .transpose()?,
+            overrides,
+            last_sent: Mutex::default(),
+        })
+    }
+
+    /// Policy for an alert with the given common labels at `now`
+    pub fn for_alert(&self, labels: &HashMap<String, String>, now: DateTime<Local>) -> AlertPolicy {
+        let (priority, deferrable, flash) = labels
+            .get("severity")
+            .and_then(|s| self.severities.get(s))
+            .cloned()
+            .unwrap_or((Priority::Normal, false, false));
+        let alert_override = labels.get("alertname").and_then(|a| self.overrides.get(a));
+        let exempt = alert_override.is_some_and(|o| o.quiet_hours_exempt);
+        let defer = match &self.quiet_hours {
+            Some(quiet_hours) if deferrable && !exempt => quiet_hours.remaining(now),
+            _ => None,
+        };
+        AlertPolicy {
+            lane: Lane::from(&priority),
+            priority,
+            flash,
+            defer,
+            to: alert_override
+                .filter(|o| !o.to.is_empty())
+                .map(|o| o.to.clone()),
+            template: alert_override.and_then(|o| o.template.clone()),
+            suppress_for: alert_override.and_then(|o| o.suppress_for),
+        }
+    }
+
+    /// Whether an alert with `key` was sent less than `ttl` before `now`
+    pub fn is_suppressed(&self, key: &str, ttl: TimeDelta, now: DateTime<Utc>) -> bool {
+        let mut last_sent = self.last_sent.lock().unwrap();
+        last_sent.retain(|_, sent| now - *sent < MAX_SUPPRESSION);
+        last_sent.get(key).is_some_and(|sent| now - *sent < ttl)
+    }
+
+    pub fn mark_sent(&self, key: &str, now: DateTime<Utc>) {
+        self.last_sent.lock().unwrap().insert(key.to_string(), now);
+    }
+
+    /// Takes over when alerts were last sent from the policies these replace,
+    /// so reloading the config does not end running suppressions
+    pub fn keep_suppressions(&self, previous: &AlertPolicies) {
+        let last_sent = previous.last_sent.lock().unwrap().clone();
+        self.last_sent.lock().unwrap().extend(last_sent);
+    }
+}
+
+/// Suppression entries older than this are dropped
+const MAX_SUPPRESSION: TimeDelta = TimeDelta::days(7);
+
+/// Identifies repeats of an alert for suppression: the alert group and its status
+pub fn suppression_key(webhook: &AlertManagerWebhook) -> String {
+    format!("{}|{}", webhook.group_key, webhook.status)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -88,4 +314,176 @@ mod tests {
         let msg = format_alert_message(&webhook);
         assert_eq!(msg, "FIRING: TestAlert (critical) - Something is broken");
     }
+
+    #[test]
+    fn test_render_alert_message_template() {
+        let json = r#"{
+  "version": "4",
+  "groupKey": "{}:{alertname=\"DiskFull\"}",
+  "status": "resolved",
+  "receiver": "sms-oncall-db",
+  "groupLabels": {},
+  "commonLabels": {
+    "alertname": "DiskFull",
+    "instance": "db1"
+  },
+  "commonAnnotations": {
+    "description": "Disk almost full"
+  },
+  "externalURL": "http://localhost:9093",
+  "alerts": []
+}"#;
+        let webhook: AlertManagerWebhook = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            render_alert_message(
+                &webhook,
+                Some("{{status}} [{{receiver}}] {{ alertname }} on {{instance}}: {{summary}}")
+            ),
+            "RESOLVED [sms-oncall-db] DiskFull on db1: Disk almost full"
+        );
+        // A variable the alert lacks falls back to the default text
+        assert_eq!(
+            render_alert_message(&webhook, Some("{{alertname}} on {{missing}}")),
+            "RESOLVED: DiskFull (unknown) - Disk almost full"
+        );
+        assert_eq!(
+            render_alert_message(&webhook, None),
+            "RESOLVED: DiskFull (unknown) - Disk almost full"
+        );
+    }
+
+    fn local(time: &str) -> DateTime<Local> {
+        use chrono::TimeZone;
+        let time = chrono::NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap();
+        Local.from_local_datetime(&time).earliest().unwrap()
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let config = QuietHoursConfig {
+            start: "22:00".to_string(),
+            end: "07:00".to_string(),
+        };
+        let quiet = QuietHours::from_config(&config).unwrap();
+        assert_eq!(
+            quiet.remaining(local("2026-10-15 23:30")),
+            Some(TimeDelta::minutes(450))
+        );
+        assert_eq!(
+            quiet.remaining(local("2026-10-16 06:00")),
+            Some(TimeDelta::hours(1))
+        );
+        assert_eq!(quiet.remaining(local("2026-10-16 07:00")), None);
+        assert_eq!(quiet.remaining(local("2026-10-16 12:00")), None);
+
+        let daytime = QuietHours::from_config(&QuietHoursConfig {
+            start: "12:00".to_string(),
+            end: "13:00".to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            daytime.remaining(local("2026-10-16 12:45")),
+            Some(TimeDelta::minutes(15))
+        );
+        assert_eq!(daytime.remaining(local("2026-10-16 13:00")), None);
+
+        assert!(
+            QuietHours::from_config(&QuietHoursConfig {
+                start: "10pm".to_string(),
+                end: "07:00".to_string(),
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_alert_policies() {
+        let config = Config::parse(
+            r#"
+[quiet_hours]
+start = "22:00"
+end = "07:00"
+
+[severities.critical]
+priority = "urgent"
+flash = true
+
+[severities.info]
+defer_in_quiet_hours = true
+"#,
+        )
+        .unwrap();
+        let policies = AlertPolicies::from_config(&config).unwrap();
+        let labels =
+            |severity: &str| HashMap::from([("severity".to_string(), severity.to_string())]);
+        let night = local("2026-10-15 23:00");
+
+        let critical = policies.for_alert(&labels("critical"), night);
+        assert_eq!(critical.priority, Priority::Urgent);
+        assert_eq!(critical.lane, Lane::High);
+        assert_eq!(critical.defer, None);
+        assert!(critical.flash);
+
+        let info = policies.for_alert(&labels("info"), night);
+        assert_eq!(info.lane, Lane::Normal);
+        assert!(!info.flash);
+        assert_eq!(info.defer, Some(TimeDelta::hours(8)));
+        assert_eq!(
+            policies
+                .for_alert(&labels("info"), local("2026-10-16 12:00"))
+                .defer,
+            None
+        );
+
+        // Unmapped severities are sent normally
+        let warning = policies.for_alert(&labels("warning"), night);
+        assert_eq!(warning.priority, Priority::Normal);
+        assert_eq!(warning.defer, None);
+        assert_eq!(warning.to, None);
+
+        let invalid = Config::parse("[severities.critical]\npriority = \"asap\"").unwrap();
+        assert!(AlertPolicies::from_config(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_alertname_overrides() {
+        let config = Config::parse(
+            r#"
+[quiet_hours]
+start = "22:00"
+end = "07:00"
+
+[severities.info]
+defer_in_quiet_hours = true
+
+[alertnames.Watchdog]
+to = ["+441234567899"]
+template = "Watchdog {{status}}"
+suppress_for = "12h"
+quiet_hours_exempt = true
+"#,
+        )
+        .unwrap();
+        let policies = AlertPolicies::from_config(&config).unwrap();
+        let labels = HashMap::from([
+            ("alertname".to_string(), "Watchdog".to_string()),
+            ("severity".to_string(), "info".to_string()),
+        ]);
+        let policy = policies.for_alert(&labels, local("2026-10-15 23:00"));
+        assert_eq!(policy.defer, None);
+        assert_eq!(policy.to, Some(vec!["+441234567899".to_string()]));
+        assert_eq!(policy.template.as_deref(), Some("Watchdog {{status}}"));
+        assert_eq!(policy.suppress_for, Some(TimeDelta::hours(12)));
+
+        let now = Utc::now();
+        let ttl = TimeDelta::hours(12);
+        assert!(!policies.is_suppressed("key", ttl, now));
+        policies.mark_sent("key", now);
+        assert!(policies.is_suppressed("key", ttl, now + TimeDelta::hours(1)));
+        assert!(!policies.is_suppressed("key", ttl, now + TimeDelta::hours(12)));
+        assert!(!policies.is_suppressed("other", ttl, now));
+
+        let invalid = Config::parse("[alertnames.X]\nsuppress_for = \"soon\"").unwrap();
+        assert!(AlertPolicies::from_config(&invalid).is_err());
+    }
 }
@@ -0,0 +1,790 @@
+//! Command line arguments of the `smser` binary. Besides clap, this file only
+//! uses modules without further dependencies, as build.rs includes it to
+//! generate the man pages. Value types of options that belong to larger
+//! modules are defined here and re-exported there.
+
+#[cfg(feature = "server")]
+use crate::dedup::DuplicateAction;
+use crate::output::OutputFormat;
+#[cfg(feature = "modem")]
+use crate::profile::DeviceProfile;
+#[cfg(feature = "modem")]
+use crate::segment::LongMessages;
+use crate::template;
+use crate::types::{BoxType, SortType};
+use clap::Parser;
+#[cfg(feature = "modem")]
+use clap::ValueEnum;
+#[cfg(feature = "server")]
+use std::net::SocketAddr;
+#[cfg(feature = "modem")]
+use strum_macros::Display;
+#[cfg(feature = "server")]
+use strum_macros::EnumString;
+
+/// Simple program to send SMS via a Huawei E3372 modem
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+pub struct Args {
+    /// The URL of the modem (e.g., "http://192.168.8.1"). The server fails
+    /// over between several modems given as a comma-separated list or by
+    /// repeating the option; other commands use the first.
+    #[cfg(feature = "modem")]
+    #[arg(
+        long = "modem-url",
+        default_value = "http://192.168.8.1",
+        env = "SMSER_MODEM_URL",
+        value_delimiter = ','
+    )]
+    pub modem_urls: Vec<String>,
+
+    // When the modem feature is enabled, remote_url is optional (can talk directly to modem).
+    // When the modem feature is disabled, remote_url is required (client-only mode).
+    /// The URL of a remote smser server (e.g., "http://localhost:8080")
+    #[cfg(feature = "modem")]
+    #[arg(long, env = "SMSER_REMOTE_URL")]
+    pub remote_url: Option<String>,
+
+    /// The URL of a remote smser server (e.g., "http://localhost:8080")
+    #[cfg(not(feature = "modem"))]
+    #[arg(long, env = "SMSER_REMOTE_URL")]
+    pub remote_url: String,
+
+    /// API key for a remote smser server that requires one
+    #[arg(long, env = "SMSER_API_KEY", hide_env_values = true)]
+    pub api_key: Option<String>,
+
+    /// Username for modems whose firmware requires a login
+    #[cfg(feature = "modem")]
+    #[arg(long, default_value = "admin", env = "SMSER_MODEM_USERNAME")]
+    pub modem_username: String,
+
+    /// Password for modems whose firmware requires a login (no login if unset)
+    #[cfg(feature = "modem")]
+    #[arg(long, env = "SMSER_MODEM_PASSWORD", hide_env_values = true)]
+    pub modem_password: Option<String>,
+
+    /// Firmware quirks to apply, detected from the device name by default
+    #[cfg(feature = "modem")]
+    #[arg(long, value_enum, default_value_t, env = "SMSER_DEVICE_PROFILE")]
+    pub device_profile: DeviceProfile,
+
+    /// How to send messages longer than one SMS
+    #[cfg(feature = "modem")]
+    #[arg(long, value_enum, default_value_t, env = "SMSER_LONG_MESSAGES")]
+    pub long_messages: LongMessages,
+
+    /// Path to the config file (defaults to ~/.config/smser/config.toml if present)
+    #[arg(long, env = "SMSER_CONFIG")]
+    pub config: Option<std::path::PathBuf>,
+
+    /// Output format of commands that print data, such as receive, count, info and status
+    #[arg(long, value_enum, global = true, default_value_t, env = "SMSER_FORMAT")]
+    pub format: OutputFormat,
+
+    /// Only print the requested data and errors, no confirmations or progress
+    #[arg(short, long, global = true, env = "SMSER_QUIET")]
+    pub quiet: bool,
+
+    /// Deprecated alias of `--format json`, kept for scripts from when commands
+    /// had their own `--json`
+    #[arg(long, global = true, hide = true, conflicts_with = "format")]
+    pub json: bool,
+
+    /// Log more to stderr: `-v` for debug messages, `-vv` to also trace modem
+    /// requests and responses (passwords and session tokens redacted)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Log level, taking precedence over `-v` and `RUST_LOG`
+    #[arg(long, value_enum, global = true, env = "SMSER_LOG_LEVEL")]
+    pub log_level: Option<LogLevel>,
+
+    #[command(subcommand)]
+    pub command: SmsCommand,
+}
+
+/// Level of log messages for `--log-level`
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, strum_macros::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+// Parsed once per run, so the size of the `Serve` variant doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(clap::Subcommand, Clone, Debug, PartialEq)]
+pub enum SmsCommand {
+    /// Send an SMS message
+    Send {
+        /// The destination phone number
+        #[arg(short, long, required_unless_present = "recipients_file")]
+        to: Option<String>,
+
+        /// The message to send. With --recipients-file, `{{column}}` placeholders
+        /// are replaced by the values of each row.
+        #[arg(short, long, required_unless_present_any = ["template", "edit", "message_file", "stdin"])]
+        message: Option<String>,
+
+        /// Read the message from a file, e.g. for multi-line messages
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["message", "template", "stdin"])]
+        message_file: Option<std::path::PathBuf>,
+
+        /// Read the message from stdin
+        #[arg(long, conflicts_with_all = ["message", "template"])]
+        stdin: bool,
+
+        /// Compose the message in $VISUAL or $EDITOR, pre-filled with --message or
+        /// the rendered --template
+        #[arg(long, conflicts_with = "recipients_file")]
+        edit: bool,
+
+        /// Transliterate accented letters and typographic punctuation to ASCII (é→e,
+        /// ü→ue, smart quotes) so the message stays within GSM-7
+        #[arg(long)]
+        ascii: bool,
+
+        /// Send a flash SMS, shown immediately on the recipient's phone. HiLink
+        /// modems cannot send these and send a normal SMS instead; providers
+        /// configured as transports on the server get the `{{flash}}` variable.
+        #[arg(long)]
+        flash: bool,
+
+        /// Name of a message template from the config file
+        #[arg(long, conflicts_with = "message")]
+        template: Option<String>,
+
+        /// Template variable in "key=value" format (can be repeated)
+        #[arg(long = "var", value_parser = template::parse_var)]
+        vars: Vec<(String, String)>,
+
+        /// CSV file of recipients (phone number in the first column, optional header row
+        /// naming template variables)
+        #[arg(long, conflicts_with = "to")]
+        recipients_file: Option<std::path::PathBuf>,
+
+        /// Write the per-recipient result report to this file instead of stdout
+        #[arg(long, requires = "recipients_file")]
+        report: Option<std::path::PathBuf>,
+
+        /// Skip the confirmation prompt for large bulk sends
+        #[arg(short = 'y', long)]
+        yes: bool,
+
+        /// Ask for confirmation when a bulk send has more recipients than this
+        #[arg(long, default_value_t = 10, env = "SMSER_BULK_CONFIRM_THRESHOLD")]
+        confirm_threshold: usize,
+
+        /// Refuse bulk sends with more recipients than this (defaults to
+        /// max_recipients from the config file, which this cannot exceed, or 500)
+        #[arg(long, env = "SMSER_BULK_MAX_RECIPIENTS")]
+        max_recipients: Option<usize>,
+
+        /// Cost of one SMS segment, used to estimate the cost of bulk sends
+        #[arg(long, env = "SMSER_COST_PER_SEGMENT")]
+        cost_per_segment: Option<f64>,
+
+        /// Print the request that would be sent (URL, body, encoding and
+        /// segments) instead of sending the message
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Client name for per-client rate limiting
+        #[arg(long)]
+        client: Option<String>,
+    },
+    /// Reply to the sender of an inbox message, by its index (see `--format table receive`)
+    Reply {
+        /// Index of the message to reply to
+        #[arg(long)]
+        index: i32,
+
+        /// The reply
+        #[arg(short, long, required_unless_present = "edit")]
+        message: Option<String>,
+
+        /// Quote the original message below the reply
+        #[arg(long)]
+        quote: bool,
+
+        /// Compose the reply in $VISUAL or $EDITOR, pre-filled with --message
+        /// and the quote
+        #[arg(long)]
+        edit: bool,
+
+        /// Transliterate accented letters and typographic punctuation to ASCII
+        #[arg(long)]
+        ascii: bool,
+
+        /// Send the composed reply without asking for confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+
+        /// Print the request for the reply instead of sending it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Send the content of an inbox message on to another number, by its index
+    Forward {
+        /// Index of the message to forward
+        #[arg(long)]
+        index: i32,
+
+        /// The phone number to forward to
+        #[arg(short, long)]
+        to: String,
+
+        /// Transliterate accented letters and typographic punctuation to ASCII
+        #[arg(long)]
+        ascii: bool,
+
+        /// Print the request for the forwarded message instead of sending it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Receive SMS messages
+    Receive {
+        /// How many messages to read.
+        #[arg(long, default_value_t = 20)]
+        count: u32,
+
+        /// Sort in ascending order?
+        #[arg(long)]
+        ascending: bool,
+
+        /// Prefer unread messages?
+        #[arg(long)]
+        unread_preferred: bool,
+
+        /// Type of message box to read from (e.g., LocalInbox, LocalSent).
+        #[arg(long, default_value_t = BoxType::LocalInbox)]
+        box_type: BoxType,
+
+        /// Sort messages by (e.g., Date, Phone, Index).
+        #[arg(long, default_value_t = SortType::Date)]
+        sort_by: SortType,
+
+        /// Page of `--count` messages to read, starting at 1
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(i32).range(1..))]
+        page: i32,
+
+        /// Read all pages of `--count` messages
+        #[arg(long, conflicts_with = "page")]
+        all: bool,
+
+        /// Only show messages from this number; the country code may be left out
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Only show unread messages
+        #[arg(long)]
+        unread_only: bool,
+
+        /// Only show messages containing this text (case-insensitive)
+        #[arg(long)]
+        grep: Option<String>,
+
+        /// Only show messages from this date on (YYYY-MM-DD or YYYY-MM-DD HH:MM:SS)
+        #[arg(long, value_parser = parse_filter_date)]
+        since: Option<String>,
+
+        /// Only show messages up to this date (YYYY-MM-DD or YYYY-MM-DD HH:MM:SS)
+        #[arg(long, value_parser = parse_filter_date)]
+        until: Option<String>,
+    },
+    /// Start the web server
+    #[cfg(feature = "server")]
+    Serve {
+        /// The port to listen on
+        #[arg(short, long, default_value_t = 8080, env = "SMSER_PORT")]
+        port: u16,
+
+        /// The address to listen on, e.g. 127.0.0.1 to only accept local connections
+        #[arg(long, default_value = "0.0.0.0", env = "SMSER_BIND")]
+        bind: std::net::IpAddr,
+
+        /// The address and port to listen on, e.g. 127.0.0.1:8080; overrides --bind and --port
+        #[arg(long, env = "SMSER_LISTEN", conflicts_with_all = ["bind", "port"])]
+        listen: Option<SocketAddr>,
+
+        /// Also serve the gRPC API on this address and port, e.g. 127.0.0.1:50051
+        #[cfg(feature = "grpc")]
+        #[arg(long, env = "SMSER_GRPC_LISTEN")]
+        grpc_listen: Option<SocketAddr>,
+
+        /// The phone number to send alerts to
+        #[cfg(feature = "alertmanager")]
+        #[arg(long, env = "SMSER_ALERT_TO")]
+        alert_to: Option<String>,
+
+        /// Hourly SMS limit
+        #[arg(long, default_value_t = 100, env = "SMSER_HOURLY_LIMIT")]
+        hourly_limit: u32,
+
+        /// Daily SMS limit
+        #[arg(long, default_value_t = 1000, env = "SMSER_DAILY_LIMIT")]
+        daily_limit: u32,
+
+        /// Hourly SMS limit per recipient number, e.g. to stop an alert loop from
+        /// flooding one phone (0 to disable)
+        #[arg(long, default_value_t = 0, env = "SMSER_RECIPIENT_LIMIT")]
+        recipient_limit: u32,
+
+        /// Per-client rate limit in format "name:hourly:daily" (can be repeated, or
+        /// comma-separated in the environment variable)
+        #[arg(
+            long = "client-limit",
+            value_parser = parse_client_limit,
+            env = "SMSER_CLIENT_LIMITS",
+            value_delimiter = ','
+        )]
+        client_limits: Vec<ClientLimit>,
+
+        /// Path to the PEM certificate (chain) to serve HTTPS with
+        #[arg(long, env = "SMSER_TLS_CERT", requires = "tls_key")]
+        tls_cert: Option<std::path::PathBuf>,
+
+        /// Path to the PEM private key of --tls-cert
+        #[arg(long, env = "SMSER_TLS_KEY", requires = "tls_cert")]
+        tls_key: Option<std::path::PathBuf>,
+
+        /// Port for HTTP to HTTPS redirect (only used when TLS is enabled)
+        #[arg(long, env = "SMSER_HTTP_REDIRECT_PORT")]
+        http_redirect_port: Option<u16>,
+
+        /// Hostname to use for HTTPS redirects (defaults to request Host header)
+        #[arg(long, env = "SMSER_REDIRECT_HOST")]
+        redirect_host: Option<String>,
+
+        /// Log sensitive data (phone numbers, message content) - disable for privacy
+        #[arg(long, default_value_t = true, action = clap::ArgAction::Set, env = "SMSER_LOG_SENSITIVE")]
+        log_sensitive: bool,
+
+        /// Interval in seconds for polling new SMS messages (0 to disable)
+        #[arg(long, default_value_t = 300, env = "SMSER_POLL_INTERVAL")]
+        poll_interval: u64,
+
+        /// POST every received message as JSON to this URL when polling, marking
+        /// it read once the webhook answers with 2xx
+        #[arg(long, env = "SMSER_INCOMING_WEBHOOK")]
+        incoming_webhook: Option<String>,
+
+        /// Run this program for every new received message when polling, with the
+        /// message in SMSER_INDEX, SMSER_FROM, SMSER_CONTENT and SMSER_DATE and as
+        /// JSON on stdin
+        #[arg(long, env = "SMSER_ON_RECEIVE_CMD")]
+        on_receive_cmd: Option<String>,
+
+        /// Interval in seconds for exporting the inbox unread/total gauges (0 to disable)
+        #[arg(long, default_value_t = 60, env = "SMSER_INBOX_COUNT_INTERVAL")]
+        inbox_count_interval: u64,
+
+        /// When the inbox count shows the modem storage full, delete this many of the
+        /// oldest read inbox messages (disabled if unset)
+        #[arg(long, value_name = "COUNT", env = "SMSER_AUTO_PURGE")]
+        auto_purge: Option<usize>,
+
+        /// Interval in seconds for exporting the data traffic gauges (0 to disable)
+        #[arg(long, default_value_t = 300, env = "SMSER_TRAFFIC_INTERVAL")]
+        traffic_interval: u64,
+
+        /// Interval in seconds for checking the health of each modem when several
+        /// `--modem-url`s are given (0 to disable)
+        #[arg(long, default_value_t = 30, env = "SMSER_MODEM_CHECK_INTERVAL")]
+        modem_check_interval: u64,
+
+        /// How sends are spread when several `--modem-url`s are given
+        #[arg(long, value_enum, default_value_t = Balance::Failover, env = "SMSER_MODEM_BALANCE")]
+        modem_balance: Balance,
+
+        /// Path to the SQLite database archiving received messages (disabled if unset).
+        /// Also keeps queued messages across restarts.
+        #[arg(long, env = "SMSER_DB_PATH")]
+        db_path: Option<std::path::PathBuf>,
+
+        /// Times a queued message is retried, with exponential backoff, when the
+        /// modem is unavailable (0 to disable)
+        #[arg(long, default_value_t = 5, env = "SMSER_SEND_RETRIES")]
+        send_retries: u32,
+
+        /// Record every send attempt in the audit log of --db-path, keeping only a
+        /// hash of the message text or the full text
+        #[arg(long, value_enum, env = "SMSER_AUDIT", requires = "db_path")]
+        audit: Option<AuditContent>,
+
+        /// Bearer token for the /admin API (disabled if unset)
+        #[arg(long, env = "SMSER_ADMIN_TOKEN", hide_env_values = true)]
+        admin_token: Option<String>,
+
+        /// Require HTTP Basic auth with this username on every route
+        #[arg(long, env = "SMSER_BASIC_AUTH_USER", requires = "basic_auth_password")]
+        basic_auth_user: Option<String>,
+
+        /// Password for --basic-auth-user
+        #[arg(
+            long,
+            env = "SMSER_BASIC_AUTH_PASSWORD",
+            hide_env_values = true,
+            requires = "basic_auth_user"
+        )]
+        basic_auth_password: Option<String>,
+
+        /// URL notified when the rate limiter starts rejecting messages
+        #[arg(long, env = "SMSER_RATE_LIMIT_WEBHOOK")]
+        rate_limit_webhook: Option<String>,
+
+        /// Body format of rate limit notifications
+        #[arg(long, value_enum, default_value_t = NotifyFormat::Json, env = "SMSER_RATE_LIMIT_WEBHOOK_FORMAT")]
+        rate_limit_webhook_format: NotifyFormat,
+
+        /// Minimum seconds between two rate limit notifications
+        #[arg(
+            long,
+            default_value_t = 3600,
+            env = "SMSER_RATE_LIMIT_WEBHOOK_INTERVAL"
+        )]
+        rate_limit_webhook_interval: u64,
+
+        /// Catch identical messages to the same recipient within this many seconds
+        /// (0 to disable)
+        #[arg(long, default_value_t = 0, env = "SMSER_DUPLICATE_WINDOW")]
+        duplicate_window: u64,
+
+        /// Whether duplicates are rejected, sent and only flagged, or dropped
+        #[arg(long, value_enum, default_value_t = DuplicateAction::Reject, env = "SMSER_DUPLICATE_ACTION")]
+        duplicate_action: DuplicateAction,
+    },
+    /// Print the modem model, IMEI, IMSI, serial number and firmware versions
+    Info,
+    /// Print the registered operator, network type (LTE/3G) and connection state
+    Network,
+    /// Print or change the SMS service center (SMSC) number the modem sends through
+    #[cfg(feature = "modem")]
+    Sca {
+        /// New service center number in international format, e.g. +491710760000
+        #[arg(long, value_name = "NUMBER")]
+        set: Option<String>,
+    },
+    /// Print the signal strength (RSSI, RSRP, RSRQ, SINR) and serving cell
+    #[cfg(feature = "modem")]
+    Signal {
+        /// Repeat every this many seconds, e.g. while adjusting the antenna
+        #[arg(long, value_name = "SECONDS")]
+        watch: Option<u64>,
+    },
+    /// Print the number of unread and total messages per box
+    #[cfg(feature = "modem")]
+    Count,
+    /// Print the data uploaded, downloaded and time connected over mobile data
+    #[cfg(feature = "modem")]
+    Traffic,
+    /// Show the send in progress and the sent box, or cancel a stuck send. Failed
+    /// messages can be removed with `delete --index`.
+    #[cfg(feature = "modem")]
+    Outbox {
+        /// Cancel the send the modem is working on
+        #[arg(long)]
+        cancel: bool,
+
+        /// How many messages of the sent box to show
+        #[arg(long, default_value_t = 20, conflicts_with = "cancel")]
+        count: u32,
+    },
+    /// Show, or turn on or off, the modem's mobile data connection
+    #[cfg(feature = "modem")]
+    Data {
+        /// New state of the data connection (prints the current state if omitted)
+        #[arg(value_enum)]
+        state: Option<DataState>,
+    },
+    /// Delete messages from the modem by index (see `--format table receive`)
+    #[cfg(feature = "modem")]
+    Delete {
+        /// Index of a message to delete (can be repeated)
+        #[arg(long = "index", required = true)]
+        indexes: Vec<i32>,
+    },
+    /// Mark messages as read by index, e.g. after processing them
+    #[cfg(feature = "modem")]
+    MarkRead {
+        /// Index of a message to mark as read (can be repeated)
+        #[arg(long = "index", required = true)]
+        indexes: Vec<i32>,
+    },
+    /// Export every message on the modem into a JSON file
+    #[cfg(feature = "modem")]
+    Backup {
+        /// File to write the backup to
+        #[arg(long)]
+        file: std::path::PathBuf,
+
+        /// Message box to export (can be repeated)
+        #[arg(long = "box", default_values = ["local-inbox", "local-sent", "local-draft", "local-trash"])]
+        boxes: Vec<BoxType>,
+    },
+    /// Write all messages of a box to a file as mbox, CSV or JSON
+    #[cfg(feature = "modem")]
+    Export {
+        /// File format
+        #[arg(long, value_enum, default_value_t)]
+        file_format: ExportFormat,
+
+        /// File to write to (stdout if omitted)
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
+
+        /// Message box to export (can be repeated)
+        #[arg(long = "box", default_value = "local-inbox")]
+        boxes: Vec<BoxType>,
+    },
+    /// Re-import the messages of a backup file; the modem stores them as drafts
+    #[cfg(feature = "modem")]
+    Restore {
+        /// Backup file written by `smser backup`
+        #[arg(long)]
+        file: std::path::PathBuf,
+
+        /// Only restore messages from this box (can be repeated)
+        #[arg(long = "box")]
+        boxes: Vec<BoxType>,
+    },
+    /// Delete all messages in the trash (or the given boxes) to free modem storage
+    PurgeTrash {
+        /// Message box to empty (can be repeated)
+        #[arg(long = "box", default_value = "local-trash")]
+        boxes: Vec<BoxType>,
+    },
+    /// Delete the messages of a box, or only those older than a given age, to free
+    /// the modem's small message storage
+    #[cfg(feature = "modem")]
+    Clean {
+        /// Message box to clean (can be repeated)
+        #[arg(long = "box", default_value = "local-trash")]
+        boxes: Vec<BoxType>,
+
+        /// Only delete messages older than this, e.g. 30d or 12h
+        #[arg(long, value_name = "AGE", value_parser = crate::cron::parse_duration)]
+        older_than: Option<chrono::TimeDelta>,
+
+        /// List the messages that would be deleted without deleting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check the connection to the modem, printing the result and latency of each step
+    #[cfg(feature = "modem")]
+    TestModem,
+    /// Browse, read, write, reply to and delete messages in an interactive terminal inbox
+    #[cfg(feature = "tui")]
+    Tui,
+    /// Print modem reachability, SIM state, signal, unread messages and SMSC at a glance.
+    /// Exits with an error if any of them is unhealthy.
+    #[cfg(feature = "modem")]
+    Status,
+    /// Print a Grafana dashboard for the server's Prometheus metrics
+    #[cfg(feature = "server")]
+    GrafanaDashboard,
+    /// List send attempts recorded by `smser serve --audit`, newest first
+    #[cfg(feature = "server")]
+    Audit {
+        /// Path to the SQLite database given to `smser serve`
+        #[arg(long, env = "SMSER_DB_PATH")]
+        db_path: std::path::PathBuf,
+
+        /// Only attempts for this client name
+        #[arg(long)]
+        client: Option<String>,
+
+        /// Only attempts authenticated with this API key name
+        #[arg(long)]
+        api_key: Option<String>,
+
+        /// Only attempts to this recipient
+        #[arg(long)]
+        to: Option<String>,
+
+        /// Only attempts in this period, e.g. 24h or 7d
+        #[arg(long, value_name = "AGE", value_parser = crate::cron::parse_duration)]
+        since: Option<chrono::TimeDelta>,
+
+        /// Maximum number of attempts to list
+        #[arg(long, default_value_t = 50)]
+        limit: u32,
+    },
+    /// Show the encoding, length, segment count and cost of a message without sending it
+    Estimate {
+        /// The message (read from --message-file or stdin if omitted)
+        #[arg(conflicts_with_all = ["message_file", "template", "stdin"])]
+        message: Option<String>,
+
+        /// Read the message from a file, as `send --message-file` does
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["template", "stdin"])]
+        message_file: Option<std::path::PathBuf>,
+
+        /// Read the message from stdin, also the default without a message
+        #[arg(long, conflicts_with = "template")]
+        stdin: bool,
+
+        /// Name of a message template from the config file
+        #[arg(long)]
+        template: Option<String>,
+
+        /// Template variable in "key=value" format (can be repeated)
+        #[arg(long = "var", value_parser = template::parse_var)]
+        vars: Vec<(String, String)>,
+
+        /// Transliterate to ASCII first, as `send --ascii` does
+        #[arg(long)]
+        ascii: bool,
+
+        /// Cost of one SMS segment, in addition to the routes in the config file
+        #[arg(long, env = "SMSER_COST_PER_SEGMENT")]
+        cost_per_segment: Option<f64>,
+    },
+    /// Print a shell completion script, e.g. `smser completions bash > /usr/share/bash-completion/completions/smser`
+    Completions {
+        /// Shell to print the completions for
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+    /// Create or validate the config file
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+/// State of the mobile data connection for `smser data`
+#[cfg(feature = "modem")]
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum DataState {
+    On,
+    Off,
+}
+
+#[derive(clap::Subcommand, Clone, Debug, PartialEq)]
+pub enum ConfigCommand {
+    /// Write a commented starter config file (to --config or the default location)
+    Init {
+        /// Ask for the modem URL, alert number and limits
+        #[arg(short, long)]
+        interactive: bool,
+
+        /// Overwrite an existing config file
+        #[arg(long)]
+        force: bool,
+    },
+    /// Validate the config file, reporting unknown keys and invalid values
+    Check,
+}
+
+#[cfg(feature = "server")]
+/// How a pool picks the modem sending a message
+#[derive(Clone, Copy, Debug, Default, PartialEq, Display, ValueEnum, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum Balance {
+    /// Send via the active modem, switching only when it fails
+    #[default]
+    Failover,
+    /// Take turns between the healthy modems
+    RoundRobin,
+    /// Send via the healthy modem whose last message is the oldest
+    LeastRecent,
+}
+
+#[cfg(feature = "modem")]
+/// File format of `smser export`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Display, ValueEnum)]
+#[strum(serialize_all = "lowercase")]
+pub enum ExportFormat {
+    /// One email per message (mboxrd), readable by mail clients
+    Mbox,
+    /// A `box,index,status,phone,date,content` row per message
+    Csv,
+    /// An array of the messages as `--format json receive` prints them, plus their box
+    #[default]
+    Json,
+}
+
+#[cfg(feature = "server")]
+/// Body format of rate limit notifications
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum)]
+pub enum NotifyFormat {
+    /// `{"event": "rate_limited", "reason": ..., "client": ..., "timestamp": ...}`
+    #[default]
+    Json,
+    /// Plain text with a title, for an ntfy topic URL such as `https://ntfy.sh/mytopic`
+    Ntfy,
+}
+
+#[cfg(feature = "server")]
+/// How much of a message the audit log keeps
+#[derive(Clone, Copy, Debug, Default, PartialEq, ValueEnum, Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum AuditContent {
+    /// Only the SHA-256 hash of the text
+    #[default]
+    Hash,
+    /// The full text and its hash
+    Full,
+}
+
+#[cfg(feature = "server")]
+/// Per-client rate limit configuration
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientLimit {
+    pub name: String,
+    pub hourly_limit: u32,
+    pub daily_limit: u32,
+}
+
+#[cfg(feature = "server")]
+impl ClientLimit {
+    /// Parse a client limit from "name:hourly:daily" format
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = s.split(':').collect();
+        if parts.len() != 3 {
+            return Err(format!(
+                "Invalid client limit format '{}'. Expected 'name:hourly:daily'",
+                s
+            ));
+        }
+        let name = parts[0].to_string();
+        if name.is_empty() {
+            return Err("Client name cannot be empty".to_string());
+        }
+        let hourly_limit = parts[1]
+            .parse()
+            .map_err(|_| format!("Invalid hourly limit '{}'", parts[1]))?;
+        let daily_limit = parts[2]
+            .parse()
+            .map_err(|_| format!("Invalid daily limit '{}'", parts[2]))?;
+        Ok(Self {
+            name,
+            hourly_limit,
+            daily_limit,
+        })
+    }
+}
+
+pub(crate) fn parse_filter_date(s: &str) -> Result<String, String> {
+    let valid = s.char_indices().all(|(i, c)| match i {
+        4 | 7 => c == '-',
+        10 => c == ' ',
+        13 | 16 => c == ':',
+        _ => c.is_ascii_digit(),
+    });
+    if valid && (s.len() == 10 || s.len() == 19) {
+        Ok(s.to_string())
+    } else {
+        Err("expected YYYY-MM-DD or YYYY-MM-DD HH:MM:SS".to_string())
+    }
+}
+
+#[cfg(feature = "server")]
+fn parse_client_limit(s: &str) -> Result<ClientLimit, String> {
+    ClientLimit::parse(s)
+}
@@ -0,0 +1,87 @@
+use include_dir::{Dir, include_dir};
+
+/// Web assets (HTML templates, CSS, JS) compiled into the binary
+static ASSETS: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/assets");
+
+/// Returns the raw contents of an embedded asset, e.g. "index.js"
+pub fn get(path: &str) -> Option<&'static [u8]> {
+    ASSETS.get_file(path).map(|f| f.contents())
+}
+
+/// Returns the Content-Type to serve an asset with, based on its extension
+pub fn content_type(path: &str) -> &'static str {
+    match path.rsplit('.').next() {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Renders an embedded template, replacing each `{{name}}` with its value in a
+/// single pass, so placeholders inside values are kept as they are. Values are
+/// inserted verbatim, so callers must escape them as needed. Returns `None` if
+/// there is no such template.
+pub fn render(template: &str, vars: &[(&str, &str)]) -> Option<String> {
+    let mut rest = ASSETS.get_file(template)?.contents_utf8()?;
+    let mut html = String::with_capacity(rest.len());
+    while let Some(start) = rest.find("{{") {
+        html.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let value = after.find("}}").and_then(|end| {
+            vars.iter()
+                .find(|(name, _)| *name == &after[..end])
+                .map(|(_, value)| (end, value))
+        });
+        match value {
+            Some((end, value)) => {
+                html.push_str(value);
+                rest = &after[end + 2..];
+            }
+            // Unknown placeholders are left in place
+            None => {
+                html.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    html.push_str(rest);
+    Some(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_assets() {
+        assert!(get("index.html").is_some());
+        assert!(get("index.js").is_some());
+        assert!(get("ui.js").is_some());
+        assert!(get("missing.txt").is_none());
+        assert_eq!(content_type("status.css"), "text/css; charset=utf-8");
+        assert_eq!(content_type("noext"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_render() {
+        let html = render("index.html", &[("version", "9.9.9 (abc)")]).unwrap();
+        assert!(html.contains("9.9.9 (abc)"));
+        assert!(!html.contains("{{version}}"));
+        assert!(html.contains("{{repository}}"));
+
+        // Values are not expanded again
+        let html = render(
+            "index.html",
+            &[("version", "{{repository}}"), ("repository", "example.com")],
+        )
+        .unwrap();
+        assert!(html.contains("{{repository}}"));
+        assert!(html.contains("example.com"));
+
+        assert!(render("missing.html", &[]).is_none());
+    }
+}
@@ -0,0 +1,155 @@
+//! Export of every message on the modem into a JSON file, and re-import of
+//! such a file through save-sms.
+
+use crate::modem::{self, BoxType, Credentials, Error, SmsListParams, SmsMessage, SortType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Boxes included in a backup by default
+pub const DEFAULT_BOXES: [BoxType; 4] = [
+    BoxType::LocalInbox,
+    BoxType::LocalSent,
+    BoxType::LocalDraft,
+    BoxType::LocalTrash,
+];
+
+/// Messages read from a page of the sms-list API at a time
+const PAGE_SIZE: u32 = 50;
+
+/// Contents of a backup file
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Backup {
+    /// Unix timestamp in seconds of when the backup was taken
+    pub created: u64,
+    /// Messages per box, keyed by box name, e.g. `local-inbox`
+    pub boxes: BTreeMap<String, Vec<SmsMessage>>,
+}
+
+impl Backup {
+    pub fn message_count(&self) -> usize {
+        self.boxes.values().map(Vec::len).sum()
+    }
+
+    /// Messages of the given boxes, or of all boxes if `boxes` is empty
+    pub fn messages(&self, boxes: &[BoxType]) -> Vec<(BoxType, &SmsMessage)> {
+        self.boxes
+            .iter()
+            .filter_map(|(name, messages)| {
+                let box_type = BoxType::from_str(name).unwrap_or(BoxType::Unknown);
+                (boxes.is_empty() || boxes.contains(&box_type)).then_some((box_type, messages))
+            })
+            .flat_map(|(box_type, messages)| messages.iter().map(move |m| (box_type.clone(), m)))
+            .collect()
+    }
+}
+
+/// Reads every message in `boxes`, a page at a time.
+pub async fn backup(
+    modem_url: &str,
+    credentials: Option<&Credentials>,
+    boxes: &[BoxType],
+) -> Result<Backup, Error> {
+    let mut backup = Backup {
+        created: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+        ..Default::default()
+    };
+    for box_type in boxes {
+        let mut messages = Vec::new();
+        for page in 1.. {
+            // sms-list is a POST, which uses up the token
+            let (session_id, token) = modem::open_session(modem_url, credentials).await?;
+            let params = SmsListParams {
+                box_type: box_type.clone(),
+                sort_type: SortType::Index,
+                read_count: PAGE_SIZE,
+                ascending: true,
+                unread_preferred: false,
+            };
+            let response =
+                modem::get_sms_list_page(modem_url, &session_id, &token, params, page).await?;
+            let received = response.messages.message.len();
+            messages.extend(response.messages.message);
+            if received < PAGE_SIZE as usize {
+                break;
+            }
+        }
+        backup.boxes.insert(box_type.to_string(), messages);
+    }
+    Ok(backup)
+}
+
+/// Stores the messages of `boxes` (all if empty) from `backup` on the modem.
+/// The modem only accepts new messages as drafts, so they all end up in the
+/// drafts box. Returns the number of restored messages.
+pub async fn restore(
+    modem_url: &str,
+    credentials: Option<&Credentials>,
+    backup: &Backup,
+    boxes: &[BoxType],
+) -> Result<usize, Error> {
+    let mut restored = 0;
+    for (_, message) in backup.messages(boxes) {
+        let (session_id, token) = modem::open_session(modem_url, credentials).await?;
+        modem::save_sms(
+            modem_url,
+            &session_id,
+            &token,
+            &[&message.phone],
+            &message.content,
+        )
+        .await?;
+        restored += 1;
+    }
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modem::{Priority, SmsStat, SmsType};
+
+    fn message(index: i32, content: &str) -> SmsMessage {
+        SmsMessage {
+            smstat: SmsStat::Read,
+            index,
+            phone: "+491761234567".to_string(),
+            content: content.to_string(),
+            date: "2026-10-16 10:00:00".to_string(),
+            sca: String::new(),
+            save_type: 0,
+            priority: Priority::Normal,
+            sms_type: SmsType::Single,
+        }
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let mut backup = Backup {
+            created: 1_700_000_000,
+            ..Default::default()
+        };
+        backup
+            .boxes
+            .insert("local-inbox".to_string(), vec![message(40001, "Hi")]);
+        backup.boxes.insert(
+            "local-sent".to_string(),
+            vec![message(40002, "Hello"), message(40003, "Bye")],
+        );
+
+        let json = serde_json::to_string(&backup).unwrap();
+        let parsed: Backup = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, backup);
+        assert_eq!(parsed.message_count(), 3);
+        assert_eq!(parsed.messages(&[]).len(), 3);
+
+        let sent = parsed.messages(&[BoxType::LocalSent]);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].0, BoxType::LocalSent);
+        assert_eq!(sent[1].1.content, "Bye");
+    }
+}
@@ -0,0 +1,132 @@
+//! Synchronous wrappers around the async modem API, for applications that do
+//! not use tokio themselves.
+//!
+//! Each client owns a single-threaded tokio runtime. The methods must not be
+//! called from within an async context, as nesting runtimes panics.
+
+use crate::modem::{self, Credentials, Error, SmsCount, SmsListParams, SmsListResponse};
+
+/// Blocking client for a Huawei HiLink modem
+pub struct ModemClient {
+    modem_url: String,
+    credentials: Option<Credentials>,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl ModemClient {
+    pub fn new(modem_url: impl Into<String>) -> Result<Self, Error> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| Error::Other(format!("Failed to start runtime: {}", e)))?;
+        Ok(Self {
+            modem_url: modem_url.into(),
+            credentials: None,
+            runtime,
+        })
+    }
+
+    /// Logs in with these credentials before each request, for modems that require it.
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.credentials = Some(Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
+        });
+        self
+    }
+
+    pub fn modem_url(&self) -> &str {
+        &self.modem_url
+    }
+
+    /// Fetches a fresh session ID and token from the modem, logging in if
+    /// credentials are set.
+    pub fn get_session_info(&self) -> Result<(String, String), Error> {
+        self.runtime.block_on(modem::open_session(
+            &self.modem_url,
+            self.credentials.as_ref(),
+        ))
+    }
+
+    /// Sends an SMS, fetching a new session first.
+    pub fn send_sms(&self, to: &str, message: &str) -> Result<(), Error> {
+        self.send_sms_to_many(&[to], message)
+    }
+
+    /// Sends one SMS to several phone numbers in a single modem request.
+    pub fn send_sms_to_many(&self, to: &[&str], message: &str) -> Result<(), Error> {
+        self.runtime.block_on(async {
+            let (session_id, token) =
+                modem::open_session(&self.modem_url, self.credentials.as_ref()).await?;
+            modem::send_sms(&self.modem_url, &session_id, &token, to, message, false).await
+        })
+    }
+
+    /// Fetches the SMS list, fetching a new session first.
+    pub fn get_sms_list(&self, params: SmsListParams) -> Result<SmsListResponse, Error> {
+        self.runtime.block_on(async {
+            let (session_id, token) =
+                modem::open_session(&self.modem_url, self.credentials.as_ref()).await?;
+            modem::get_sms_list(&self.modem_url, &session_id, &token, params).await
+        })
+    }
+
+    /// Fetches the number of messages per box, fetching a new session first.
+    pub fn get_sms_count(&self) -> Result<SmsCount, Error> {
+        self.runtime.block_on(async {
+            let (session_id, token) =
+                modem::open_session(&self.modem_url, self.credentials.as_ref()).await?;
+            modem::get_sms_count(&self.modem_url, &session_id, &token).await
+        })
+    }
+
+    /// Enables or disables the mobile data connection, fetching a new session first.
+    pub fn set_mobile_data(&self, enabled: bool) -> Result<(), Error> {
+        self.runtime.block_on(async {
+            let (session_id, token) =
+                modem::open_session(&self.modem_url, self.credentials.as_ref()).await?;
+            modem::set_mobile_data(&self.modem_url, &session_id, &token, enabled).await
+        })
+    }
+
+    /// Deletes the messages with the given indexes, fetching a new session first.
+    pub fn delete_sms(&self, indexes: &[i32]) -> Result<(), Error> {
+        self.runtime.block_on(async {
+            let (session_id, token) =
+                modem::open_session(&self.modem_url, self.credentials.as_ref()).await?;
+            modem::delete_sms(&self.modem_url, &session_id, &token, indexes).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BoxType, SortType};
+
+    // Like the async tests, these rely on the modem being unavailable.
+
+    #[test]
+    fn test_blocking_send_sms_error() {
+        let client = ModemClient::new("http://nonexistent.com").unwrap();
+        assert_eq!(client.modem_url(), "http://nonexistent.com");
+        assert!(client.get_session_info().is_err());
+        assert!(client.send_sms("+1234567890", "Test message").is_err());
+        assert!(client.delete_sms(&[40001]).is_err());
+        assert!(client.get_sms_count().is_err());
+        assert!(client.set_mobile_data(false).is_err());
+    }
+
+    #[test]
+    fn test_blocking_get_sms_list_error() {
+        let client = ModemClient::new("http://nonexistent.com").unwrap();
+        let params = SmsListParams {
+            box_type: BoxType::LocalInbox,
+            sort_type: SortType::Date,
+            read_count: 20,
+            ascending: false,
+            unread_preferred: false,
+        };
+        assert!(client.get_sms_list(params).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+/// A single row of a recipients file
+#[derive(Debug, PartialEq)]
+pub struct Recipient {
+    pub phone: String,
+    /// Template variables from the remaining columns, keyed by header name
+    pub vars: HashMap<String, String>,
+}
+
+/// Result of sending to one recipient, written to the bulk send report
+#[derive(Debug, PartialEq)]
+pub struct RecipientResult {
+    pub phone: String,
+    pub status: String,
+    pub id: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Splits one CSV line into fields, honouring double-quoted fields with `""` escapes.
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// Parses a recipients CSV file.
+///
+/// The first column holds the phone number. If the first row starts with a
+/// `phone` or `to` header, the other header names become template variables
+/// filled from the matching columns of each row. Blank lines and lines
+/// starting with `#` are ignored.
+pub fn parse_recipients(csv: &str) -> Result<Vec<Recipient>, String> {
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty() && !l.starts_with('#'))
+        .peekable();
+
+    let mut header: Vec<String> = Vec::new();
+    if let Some((_, first)) = lines.peek() {
+        let fields = parse_csv_line(first);
+        if matches!(fields[0].to_lowercase().as_str(), "phone" | "to") {
+            header = fields;
+            lines.next();
+        }
+    }
+
+    let mut recipients = Vec::new();
+    for (line_no, line) in lines {
+        let fields = parse_csv_line(line);
+        let phone = fields[0].clone();
+        if phone.is_empty() {
+            return Err(format!("Line {}: missing phone number", line_no));
+        }
+        let vars = header
+            .iter()
+            .zip(fields.iter())
+            .skip(1)
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        recipients.push(Recipient { phone, vars });
+    }
+    Ok(recipients)
+}
+
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Formats the per-recipient results as CSV with a `phone,status,id,error` header.
+pub fn format_report(results: &[RecipientResult]) -> String {
+    let mut report = String::from("phone,status,id,error\n");
+    for r in results {
+        report.push_str(&format!(
+            "{},{},{},{}\n",
+            csv_field(&r.phone),
+            csv_field(&r.status),
+            r.id.map(|id| id.to_string()).unwrap_or_default(),
+            csv_field(r.error.as_deref().unwrap_or_default())
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recipients_plain() {
+        let recipients = parse_recipients("+441111\n\n# comment\n+442222, ignored\n").unwrap();
+        assert_eq!(recipients.len(), 2);
+        assert_eq!(recipients[0].phone, "+441111");
+        assert_eq!(recipients[1].phone, "+442222");
+        assert!(recipients[1].vars.is_empty());
+    }
+
+    #[test]
+    fn test_parse_recipients_with_header() {
+        let csv = "phone,name,meter\n+441111,\"Smith, Jo\",42\n+442222,Lee\n";
+        let recipients = parse_recipients(csv).unwrap();
+        assert_eq!(recipients.len(), 2);
+        assert_eq!(recipients[0].vars["name"], "Smith, Jo");
+        assert_eq!(recipients[0].vars["meter"], "42");
+        assert_eq!(recipients[1].vars["name"], "Lee");
+        assert!(!recipients[1].vars.contains_key("meter"));
+
+        assert!(parse_recipients("to,name\n,Bob\n").is_err());
+    }
+
+    #[test]
+    fn test_format_report() {
+        let report = format_report(&[
+            RecipientResult {
+                phone: "+441111".to_string(),
+                status: "queued".to_string(),
+                id: Some(7),
+                error: None,
+            },
+            RecipientResult {
+                phone: "+442222".to_string(),
+                status: "failed".to_string(),
+                id: None,
+                error: Some("429 Too Many Requests - Rate limit, hourly".to_string()),
+            },
+        ]);
+        assert_eq!(
+            report,
+            "phone,status,id,error\n+441111,queued,7,\n+442222,failed,,\"429 Too Many Requests - Rate limit, hourly\"\n"
+        );
+    }
+}
@@ -1,520 +1,3743 @@
+#[cfg(feature = "alertmanager")]
+use crate::alertmanager::AlertPolicies;
+#[cfg(feature = "modem")]
+pub use crate::args::DataState;
+pub use crate::args::{Args, ConfigCommand, LogLevel, SmsCommand};
+#[cfg(feature = "modem")]
+use crate::backup;
+use crate::bulk;
+#[cfg(feature = "modem")]
+use crate::client;
+#[cfg(feature = "modem")]
+use crate::client::ModemClient;
+use crate::compose;
+#[cfg(feature = "alertmanager")]
+use crate::config::AlertReceiver;
+use crate::config::{self, Config, StarterSettings};
+#[cfg(feature = "server")]
+use crate::dedup::{DuplicateAction, DuplicateGuard};
+use crate::encoding;
+#[cfg(feature = "modem")]
+use crate::export;
+#[cfg(feature = "server")]
+use crate::filter::ContentFilter;
 #[cfg(feature = "server")]
 use crate::metrics::{
     ClientLimit, RateLimiter, setup_metrics, update_client_limits_metrics, update_limits_metrics,
 };
 #[cfg(feature = "modem")]
 use crate::modem;
-use crate::types::{BoxType, SmsMessage, SortType};
-use clap::Parser;
+#[cfg(feature = "server")]
+use crate::notify::{IncomingWebhook, RateLimitNotifier, ReceiveCommand};
+#[cfg(feature = "alertmanager")]
+use crate::oncall::OnCall;
+use crate::output::{self, OutputFormat};
+#[cfg(feature = "server")]
+use crate::pool::{Balance, ModemPool};
+#[cfg(feature = "modem")]
+use crate::profile::DeviceProfile;
+#[cfg(feature = "alertmanager")]
+use crate::silence::Silences;
+#[cfg(feature = "server")]
+use crate::store::{AuditEntry, AuditFilter, Store};
+use crate::template;
+#[cfg(feature = "server")]
+use crate::transport::Routes;
+use crate::types::{
+    BoxType, DeviceInfo, ErrorKind, ErrorResponse, NetworkInfo, SmsListJson, SmsMessage, SmsStat,
+    SortType,
+};
+#[cfg(feature = "modem")]
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches};
 use serde_json;
+use std::collections::HashMap;
 #[cfg(feature = "server")]
 use std::net::SocketAddr;
 #[cfg(feature = "server")]
-use tokio::net::TcpListener;
+use std::time::Duration;
 #[cfg(feature = "server")]
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tokio::net::TcpListener;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::{EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
-/// Simple program to send SMS via a Huawei E3372 modem
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
-pub struct Args {
-    /// The URL of the modem (e.g., "http://192.168.8.1")
-    #[cfg(feature = "modem")]
-    #[arg(long, default_value = "http://192.168.8.1", env = "SMSER_MODEM_URL")]
-    pub modem_url: String,
+impl Args {
+    /// Output format for data, the one place the deprecated `--json` maps to `--format json`
+    pub fn output_format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else {
+            self.format
+        }
+    }
 
-    // When the modem feature is enabled, remote_url is optional (can talk directly to modem).
-    // When the modem feature is disabled, remote_url is required (client-only mode).
-    /// The URL of a remote smser server (e.g., "http://localhost:8080")
-    #[cfg(feature = "modem")]
-    #[arg(long, env = "SMSER_REMOTE_URL")]
-    pub remote_url: Option<String>,
+    /// Log filter from `--log-level`, `-v` or `RUST_LOG`, in that order,
+    /// falling back to `default`
+    fn log_filter(&self, default: &str) -> EnvFilter {
+        let level = self.log_level.or(match self.verbose {
+            0 => None,
+            1 => Some(LogLevel::Debug),
+            _ => Some(LogLevel::Trace),
+        });
+        match level {
+            Some(level) => EnvFilter::new(format!("smser={},tower_http={}", level, level)),
+            None => EnvFilter::new(std::env::var("RUST_LOG").unwrap_or_else(|_| default.into())),
+        }
+    }
+}
 
-    /// The URL of a remote smser server (e.g., "http://localhost:8080")
-    #[cfg(not(feature = "modem"))]
-    #[arg(long, env = "SMSER_REMOTE_URL")]
-    pub remote_url: String,
-
-    #[command(subcommand)]
-    pub command: SmsCommand,
-}
-
-#[derive(clap::Subcommand, Debug, PartialEq)]
-pub enum SmsCommand {
-    /// Send an SMS message
-    Send {
-        /// The destination phone number
-        #[arg(short, long)]
-        to: String,
-
-        /// The message to send
-        #[arg(short, long)]
-        message: String,
-
-        /// Do not actually send a message
-        #[arg(long)]
-        dry_run: bool,
-
-        /// Client name for per-client rate limiting
-        #[arg(long)]
-        client: Option<String>,
-    },
-    /// Receive SMS messages
-    Receive {
-        /// How many messages to read.
-        #[arg(long, default_value_t = 20)]
-        count: u32,
-
-        /// Sort in ascending order?
-        #[arg(long)]
-        ascending: bool,
-
-        /// Prefer unread messages?
-        #[arg(long)]
-        unread_preferred: bool,
-
-        /// Type of message box to read from (e.g., LocalInbox, LocalSent).
-        #[arg(long, default_value_t = BoxType::LocalInbox)]
-        box_type: BoxType,
-
-        /// Sort messages by (e.g., Date, Phone, Index).
-        #[arg(long, default_value_t = SortType::Date)]
-        sort_by: SortType,
-
-        /// Output messages in JSON format.
-        #[arg(long)]
-        json: bool,
-    },
-    /// Start the web server
-    #[cfg(feature = "server")]
-    Serve {
-        /// The port to listen on
-        #[arg(short, long, default_value_t = 8080)]
-        port: u16,
+/// Sets up logging: the server logs requests to stdout as before, other
+/// commands only warnings to stderr so their output stays parseable. The
+/// terminal inbox logs nothing unless asked to, as it would garble the screen.
+fn init_logging(args: &Args) {
+    let (default, writer) = match &args.command {
+        #[cfg(feature = "server")]
+        SmsCommand::Serve { .. } => (
+            "smser=debug,tower_http=debug",
+            BoxMakeWriter::new(std::io::stdout),
+        ),
+        #[cfg(feature = "tui")]
+        SmsCommand::Tui => ("off", BoxMakeWriter::new(std::io::stderr)),
+        _ if args.quiet => ("smser=error", BoxMakeWriter::new(std::io::stderr)),
+        _ => ("smser=warn", BoxMakeWriter::new(std::io::stderr)),
+    };
+    tracing_subscriber::registry()
+        .with(args.log_filter(default))
+        .with(tracing_subscriber::fmt::layer().with_writer(writer))
+        .init();
+}
 
-        /// The phone number to send alerts to
-        #[cfg(feature = "alertmanager")]
-        #[arg(long, env = "SMSER_ALERT_TO")]
-        alert_to: Option<String>,
+#[cfg(feature = "modem")]
+impl Args {
+    /// Modem login credentials, if a password is configured
+    pub fn modem_credentials(&self) -> Option<modem::Credentials> {
+        self.modem_password
+            .as_ref()
+            .map(|password| modem::Credentials {
+                username: self.modem_username.clone(),
+                password: password.clone(),
+            })
+    }
 
-        /// Hourly SMS limit
-        #[arg(long, default_value_t = 100)]
-        hourly_limit: u32,
+    /// URL of the first configured modem
+    pub fn modem_url(&self) -> &str {
+        &self.modem_urls[0]
+    }
 
-        /// Daily SMS limit
-        #[arg(long, default_value_t = 1000)]
-        daily_limit: u32,
+    /// HTTP client for the first configured modem
+    pub fn modem_client(&self) -> client::HttpModem {
+        self.modem_client_for(self.modem_url())
+    }
 
-        /// Per-client rate limit in format "name:hourly:daily" (can be repeated)
-        #[arg(long = "client-limit", value_parser = parse_client_limit)]
-        client_limits: Vec<ClientLimit>,
+    fn modem_client_for(&self, modem_url: &str) -> client::HttpModem {
+        client::HttpModem::new(modem_url, self.modem_credentials())
+            .with_profile(self.device_profile)
+            .with_long_messages(self.long_messages)
+    }
 
-        /// Path to TLS certificate file
-        #[arg(long)]
-        tls_cert: Option<std::path::PathBuf>,
+    /// Client for the server: the modem, or a pool if there are several
+    #[cfg(feature = "server")]
+    fn server_modem(
+        &self,
+        check_interval: u64,
+        balance: Balance,
+    ) -> std::sync::Arc<dyn ModemClient> {
+        use std::sync::Arc;
 
-        /// Path to TLS key file
-        #[arg(long)]
-        tls_key: Option<std::path::PathBuf>,
+        if self.modem_urls.len() == 1 {
+            return Arc::new(client::SessionCache::new(self.modem_client()));
+        }
+        let modems = self
+            .modem_urls
+            .iter()
+            .map(|url| {
+                Arc::new(client::SessionCache::new(self.modem_client_for(url)))
+                    as Arc<dyn ModemClient>
+            })
+            .collect();
+        let pool = Arc::new(ModemPool::new(modems).with_balance(balance));
+        if check_interval > 0 {
+            pool.spawn_health_checks(Duration::from_secs(check_interval));
+        }
+        pool
+    }
+}
 
-        /// Port for HTTP to HTTPS redirect (only used when TLS is enabled)
-        #[arg(long)]
-        http_redirect_port: Option<u16>,
+/// Client-side filters of `receive`, applied to the fetched messages
+#[derive(Debug, Default)]
+struct ReceiveFilter<'a> {
+    from: Option<&'a str>,
+    unread_only: bool,
+    grep: Option<&'a str>,
+    since: Option<&'a str>,
+    until: Option<&'a str>,
+}
 
-        /// Hostname to use for HTTPS redirects (defaults to request Host header)
-        #[arg(long)]
-        redirect_host: Option<String>,
+impl ReceiveFilter<'_> {
+    fn matches(&self, message: &SmsMessage) -> bool {
+        if self.unread_only && message.smstat != SmsStat::Unread {
+            return false;
+        }
+        if let Some(from) = self.from
+            && !phone_matches(&message.phone, from)
+        {
+            return false;
+        }
+        if let Some(grep) = self.grep
+            && !message
+                .content
+                .to_lowercase()
+                .contains(&grep.to_lowercase())
+        {
+            return false;
+        }
+        // Modem dates are YYYY-MM-DD HH:MM:SS, which sort as strings
+        if let Some(since) = self.since
+            && message.date.as_str() < since
+        {
+            return false;
+        }
+        if let Some(until) = self.until
+            && message.date.get(..until.len()).unwrap_or(&message.date) > until
+        {
+            return false;
+        }
+        true
+    }
+}
 
-        /// Log sensitive data (phone numbers, message content) - disable for privacy
-        #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
-        log_sensitive: bool,
+/// Whether `phone` is `from`, comparing only digits so that a number without
+/// its country code or with spaces also matches. Alphanumeric senders are
+/// compared case-insensitively.
+fn phone_matches(phone: &str, from: &str) -> bool {
+    let digits = |s: &str| s.chars().filter(char::is_ascii_digit).collect::<String>();
+    let wanted = digits(from);
+    if wanted.is_empty() || from.chars().any(char::is_alphabetic) {
+        return phone.eq_ignore_ascii_case(from);
+    }
+    let wanted = wanted.trim_start_matches('0');
+    !wanted.is_empty() && digits(phone).ends_with(wanted)
+}
 
-        /// Interval in seconds for polling new SMS messages (0 to disable)
-        #[arg(long, default_value_t = 300, env = "SMSER_POLL_INTERVAL")]
-        poll_interval: u64,
-    },
+/// Result of a successful send request
+#[derive(Debug, PartialEq)]
+pub enum SendOutcome {
+    /// Not sent; `request` describes what would have been sent
+    DryRun { request: String },
+    /// Accepted by the remote server's queue
+    Queued { id: u64 },
+    /// Accepted by the modem; the ID is only known when sent via a remote server
+    Sent { id: Option<u64> },
 }
 
-#[cfg(feature = "server")]
-fn parse_client_limit(s: &str) -> Result<ClientLimit, String> {
-    ClientLimit::parse(s)
+impl SendOutcome {
+    fn status(&self) -> &'static str {
+        match self {
+            SendOutcome::DryRun { .. } => "dry-run",
+            SendOutcome::Queued { .. } => "queued",
+            SendOutcome::Sent { .. } => "sent",
+        }
+    }
+
+    fn id(&self) -> Option<u64> {
+        match self {
+            SendOutcome::DryRun { .. } => None,
+            SendOutcome::Queued { id } => Some(*id),
+            SendOutcome::Sent { id } => *id,
+        }
+    }
 }
 
-pub async fn run() {
-    let args = Args::parse();
+/// A failed command: the message to print and the kind of error, which sets the exit code
+#[derive(Debug)]
+pub struct CommandError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
 
-    match args.command {
-        SmsCommand::Send {
-            to,
-            message,
-            dry_run,
-            client,
-        } => {
-            // Determine if we should use remote server
-            #[cfg(feature = "modem")]
-            let use_remote = args.remote_url.is_some();
-            #[cfg(not(feature = "modem"))]
-            let use_remote = true;
+impl CommandError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
 
-            if use_remote {
-                #[cfg(feature = "modem")]
-                let remote_url = args.remote_url.as_ref().unwrap();
-                #[cfg(not(feature = "modem"))]
-                let remote_url = &args.remote_url;
+    #[cfg(feature = "modem")]
+    fn modem(context: &str, e: &modem::Error) -> Self {
+        Self::new(e.kind(), format!("{}: {}", context, e))
+    }
 
-                if dry_run {
-                    println!("DRY RUN: Not sending message.");
-                    return;
-                }
-                let http_client = reqwest::Client::new();
-                let url = format!("{}/send-sms", remote_url.trim_end_matches('/'));
-                let payload = serde_json::json!({
-                    "to": to,
-                    "message": message,
-                    "client": client
-                });
+    /// Prints the error and exits the process with the matching exit code
+    pub fn exit(&self) -> ! {
+        eprintln!("{}", self.message);
+        std::process::exit(self.kind.exit_code())
+    }
+}
 
-                match http_client.post(&url).json(&payload).send().await {
-                    Ok(res) => {
-                        if res.status().is_success() {
-                            println!("SMS sent successfully via remote server!");
-                        } else {
-                            let status = res.status();
-                            let body = res.text().await.unwrap_or_default();
-                            eprintln!("Error sending SMS via remote server: {} - {}", status, body);
-                        }
-                    }
-                    Err(e) => eprintln!("Failed to connect to remote server: {}", e),
-                }
-            } else {
-                #[cfg(feature = "modem")]
-                {
-                    let (session_id, token) = match modem::get_session_info(&args.modem_url).await {
-                        Ok((s, t)) => (s, t),
-                        Err(e) => {
-                            eprintln!("Error getting session info: {}", e);
-                            return;
-                        }
-                    };
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
 
-                    match modem::send_sms(
-                        &args.modem_url,
-                        &session_id,
-                        &token,
-                        &to,
-                        &message,
-                        dry_run,
-                    )
-                    .await
-                    {
-                        Ok(()) => {
-                            if dry_run {
-                                println!("DRY RUN: Not sending message.");
-                            } else {
-                                println!("SMS sent successfully!");
-                            }
-                        }
-                        Err(e) => eprintln!("Error sending SMS: {}", e),
-                    }
-                }
+/// HTTP client for requests to the remote server, sending the `--api-key`
+/// once [`init_remote_client`] has run
+fn remote_client() -> &'static reqwest::Client {
+    REMOTE_CLIENT.get_or_init(reqwest::Client::new)
+}
+
+static REMOTE_CLIENT: std::sync::OnceLock<reqwest::Client> = std::sync::OnceLock::new();
+
+fn init_remote_client(api_key: Option<&str>) {
+    let mut headers = reqwest::header::HeaderMap::new();
+    if let Some(key) = api_key {
+        match reqwest::header::HeaderValue::from_str(key) {
+            Ok(mut value) => {
+                value.set_sensitive(true);
+                headers.insert("x-api-key", value);
+            }
+            Err(_) => {
+                CommandError::new(ErrorKind::BadRequest, "Invalid characters in --api-key").exit()
             }
         }
-        SmsCommand::Receive {
-            count,
-            ascending,
-            unread_preferred,
-            box_type,
-            sort_by,
-            json,
-        } => {
-            // Determine if we should use remote server
-            #[cfg(feature = "modem")]
-            let use_remote = args.remote_url.is_some();
-            #[cfg(not(feature = "modem"))]
-            let use_remote = true;
+    }
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()
+        .expect("Failed to create HTTP client");
+    REMOTE_CLIENT.set(client).ok();
+}
 
-            let messages = if use_remote {
-                #[cfg(feature = "modem")]
-                let remote_url = args.remote_url.as_ref().unwrap();
-                #[cfg(not(feature = "modem"))]
-                let remote_url = &args.remote_url;
+/// Builds an error from a failed remote server response, using the JSON error
+/// envelope if the server sent one.
+async fn remote_error(context: &str, res: reqwest::Response) -> CommandError {
+    let status = res.status();
+    let body = res.text().await.unwrap_or_default();
+    match serde_json::from_str::<ErrorResponse>(&body) {
+        Ok(ErrorResponse { error }) => {
+            let code = error
+                .code
+                .map(|c| format!(" (modem error code {})", c))
+                .unwrap_or_default();
+            CommandError::new(
+                error.kind,
+                format!("{} via remote server: {}{}", context, error.message, code),
+            )
+        }
+        Err(_) => CommandError::new(
+            ErrorKind::Unknown,
+            format!("{} via remote server: {} - {}", context, status, body),
+        ),
+    }
+}
 
-                let client = reqwest::Client::new();
-                let url = format!("{}/get-sms", remote_url.trim_end_matches('/'));
+/// An unreachable server exits like an unreachable modem, since either way no
+/// SMS can be sent
+fn connect_error(e: reqwest::Error) -> CommandError {
+    CommandError::new(
+        ErrorKind::ModemUnavailable,
+        format!("Failed to connect to remote server: {}", e),
+    )
+}
 
-                // Construct query parameters manually to match server's GetSmsRequest
-                let mut params = vec![
-                    ("count", count.to_string()),
-                    ("ascending", ascending.to_string()),
-                    ("unread_preferred", unread_preferred.to_string()),
-                ];
+/// Fetches a page of messages from the remote server's `/get-sms`
+async fn get_remote_sms(
+    remote_url: &str,
+    params: &[(&str, String)],
+    page: i32,
+) -> Result<Vec<SmsMessage>, CommandError> {
+    let url = format!("{}/get-sms", remote_url.trim_end_matches('/'));
+    let params = [params, &[("page", page.to_string())]].concat();
+    let res = remote_client()
+        .get(&url)
+        .query(&params)
+        .send()
+        .await
+        .map_err(connect_error)?;
+    if !res.status().is_success() {
+        return Err(remote_error("Error receiving SMS", res).await);
+    }
+    match res.json::<SmsListJson>().await {
+        Ok(list) => Ok(list.messages),
+        Err(e) => Err(CommandError::new(
+            ErrorKind::Unknown,
+            format!("Invalid response from remote server: {}", e),
+        )),
+    }
+}
 
-                // For enums, we need to pass their integer values or string representations that axum expects.
-                // Our server uses #[serde(default = "...")] which might expect strings if they are derived.
-                // Actually BoxType and SortType derive Serialize_repr/Deserialize_repr, so they expect integers.
-                params.push(("box_type", (box_type.clone() as i32).to_string()));
-                params.push(("sort_by", (sort_by.clone() as i32).to_string()));
+/// Deletes messages through the remote server's `DELETE /sms`
+#[cfg(feature = "modem")]
+async fn delete_remote_sms(remote_url: &str, indexes: &[i32]) -> Result<(), CommandError> {
+    let url = format!("{}/sms", remote_url.trim_end_matches('/'));
+    let res = remote_client()
+        .delete(&url)
+        .json(&serde_json::json!({ "indexes": indexes }))
+        .send()
+        .await
+        .map_err(connect_error)?;
+    if res.status().is_success() {
+        Ok(())
+    } else {
+        Err(remote_error("Error deleting messages", res).await)
+    }
+}
 
-                match client.get(&url).query(&params).send().await {
-                    Ok(res) => {
-                        if res.status().is_success() {
-                            let remote_res: serde_json::Value =
-                                res.json().await.unwrap_or_default();
-                            // The server returns {"status": "success", "messages": [...]}
-                            if let Some(msgs_val) = remote_res.get("messages") {
-                                let msgs: Vec<SmsMessage> =
-                                    serde_json::from_value(msgs_val.clone()).unwrap_or_default();
-                                msgs
-                            } else {
-                                eprintln!("Invalid response from remote server: {}", remote_res);
-                                return;
-                            }
-                        } else {
-                            let status = res.status();
-                            let body = res.text().await.unwrap_or_default();
-                            eprintln!(
-                                "Error receiving SMS via remote server: {} - {}",
-                                status, body
-                            );
-                            return;
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to connect to remote server: {}", e);
-                        return;
-                    }
-                }
-            } else {
-                #[cfg(feature = "modem")]
-                {
-                    let (session_id, token) = match modem::get_session_info(&args.modem_url).await {
-                        Ok((s, t)) => (s, t),
-                        Err(e) => {
-                            eprintln!("Error getting session info: {}", e);
-                            return;
-                        }
-                    };
+/// Marks messages as read through the remote server's `/mark-read`
+#[cfg(feature = "modem")]
+async fn mark_remote_read(remote_url: &str, indexes: &[i32]) -> Result<(), CommandError> {
+    let url = format!("{}/mark-read", remote_url.trim_end_matches('/'));
+    let res = remote_client()
+        .post(&url)
+        .json(&serde_json::json!({ "indexes": indexes }))
+        .send()
+        .await
+        .map_err(connect_error)?;
+    if res.status().is_success() {
+        Ok(())
+    } else {
+        Err(remote_error("Error marking messages as read", res).await)
+    }
+}
 
-                    let params = modem::SmsListParams {
-                        box_type,
-                        sort_type: sort_by,
-                        read_count: count,
-                        ascending,
-                        unread_preferred,
-                    };
+/// Looks up the inbox message with `index`, reading the inbox a page at a time
+async fn find_inbox_message(args: &Args, index: i32) -> Result<SmsMessage, CommandError> {
+    const PAGE_SIZE: u32 = 50;
+    #[cfg(feature = "modem")]
+    let remote_url = args.remote_url.as_deref();
+    #[cfg(not(feature = "modem"))]
+    let remote_url = Some(args.remote_url.as_str());
 
-                    match modem::get_sms_list(&args.modem_url, &session_id, &token, params).await {
-                        Ok(response) => response.messages.message,
-                        Err(e) => {
-                            eprintln!("Error receiving SMS: {}", e);
-                            return;
-                        }
-                    }
-                }
-                #[cfg(not(feature = "modem"))]
-                unreachable!()
+    let mut messages = Vec::new();
+    if let Some(remote_url) = remote_url {
+        let params = [
+            ("count", PAGE_SIZE.to_string()),
+            ("ascending", "true".to_string()),
+            ("box_type", (BoxType::LocalInbox as i32).to_string()),
+            ("sort_by", (SortType::Index as i32).to_string()),
+        ];
+        for page in 1.. {
+            let received = get_remote_sms(remote_url, &params, page).await?;
+            let last = received.len() < PAGE_SIZE as usize;
+            messages.extend(received);
+            if last || messages.iter().any(|m| m.index == index) {
+                break;
+            }
+        }
+    } else {
+        #[cfg(feature = "modem")]
+        {
+            let params = modem::SmsListParams {
+                box_type: BoxType::LocalInbox,
+                sort_type: SortType::Index,
+                read_count: PAGE_SIZE,
+                ascending: true,
+                unread_preferred: false,
             };
+            messages = client::get_all_sms(&args.modem_client(), params)
+                .await
+                .map_err(|e| CommandError::modem("Error reading the inbox", &e))?;
+        }
+    }
+    messages
+        .into_iter()
+        .find(|m| m.index == index)
+        .ok_or_else(|| {
+            CommandError::new(
+                ErrorKind::NotFound,
+                format!("No message with index {} in the inbox", index),
+            )
+        })
+}
 
-            if json {
-                match serde_json::to_string_pretty(&messages) {
-                    Ok(json_output) => println!("{}", json_output),
-                    Err(e) => eprintln!("Error serializing to JSON: {}", e),
-                }
-            } else {
-                println!("Received {} SMS messages:", messages.len());
-                for msg in messages {
-                    println!("  From: {}", msg.phone);
-                    println!("  Content: {}", msg.content);
-                    println!("  Date: {}", msg.date);
-                    println!("  Priority: {}", msg.priority);
-                    println!("  SmsType: {}", msg.sms_type);
-                    println!("  Smstat: {}", msg.smstat);
-                    println!("  SaveType: {}", msg.save_type);
-                    println!("  --------------------");
-                }
+/// `reply` followed by `original` with each line quoted as `> line`
+fn quote_reply(reply: &str, original: &str) -> String {
+    let quoted: Vec<String> = original.lines().map(|l| format!("> {}", l)).collect();
+    format!("{}\n\n{}", reply, quoted.join("\n"))
+}
+
+/// Text of a forwarded message: the original sender and date, then its content
+fn forward_text(original: &SmsMessage) -> String {
+    format!(
+        "Fwd from {} ({}):\n{}",
+        original.phone, original.date, original.content
+    )
+}
+
+/// Prints the outcome of sending a single SMS unless `quiet`, or exits with
+/// its error
+fn report_send(result: Result<SendOutcome, CommandError>, quiet: bool) {
+    match result {
+        Ok(SendOutcome::Queued { .. } | SendOutcome::Sent { .. }) if quiet => {}
+        Ok(SendOutcome::DryRun { request }) => {
+            if !quiet {
+                println!("DRY RUN: Not sending message.");
             }
+            print!("{}", request);
         }
-        #[cfg(feature = "server")]
-        SmsCommand::Serve {
-            port,
-            #[cfg(feature = "alertmanager")]
-            alert_to,
-            hourly_limit,
-            daily_limit,
-            client_limits,
-            tls_cert,
-            tls_key,
-            http_redirect_port,
-            redirect_host,
-            log_sensitive,
-            poll_interval,
-        } => {
-            tracing_subscriber::registry()
-                .with(tracing_subscriber::EnvFilter::new(
-                    std::env::var("RUST_LOG")
-                        .unwrap_or_else(|_| "smser=debug,tower_http=debug".into()),
-                ))
-                .with(tracing_subscriber::fmt::layer())
-                .init();
+        Ok(SendOutcome::Queued { id }) => {
+            println!("SMS queued on remote server (id {})", id)
+        }
+        Ok(SendOutcome::Sent { id: Some(id) }) => {
+            println!("SMS sent successfully via remote server! (id {})", id)
+        }
+        Ok(SendOutcome::Sent { id: None }) => println!("SMS sent successfully!"),
+        Err(e) => e.exit(),
+    }
+}
 
-            if http_redirect_port.is_some() && redirect_host.is_none() {
-                eprintln!(
-                    "Error: --http-redirect-port requires --redirect-host to avoid open redirects."
-                );
-                return;
-            }
+/// Encoding and segment count of `message`, then each request as `POST url`
+/// and its body
+fn dry_run_request(message: &str, requests: &[(String, String)]) -> String {
+    let estimate = encoding::estimate(message);
+    let mut text = format!(
+        "Encoding: {}, {} characters, {} segment(s)\n",
+        estimate.encoding,
+        message.chars().count(),
+        estimate.segments
+    );
+    for (url, body) in requests {
+        text.push_str(&format!("\nPOST {}\n{}\n", url, body));
+    }
+    text
+}
 
-            // Call server start function here
-            println!("Starting server on port {}", port);
-            if !client_limits.is_empty() {
+/// Sends a single SMS, either via the remote server or directly via the modem.
+async fn send_message(
+    args: &Args,
+    to: &str,
+    message: &str,
+    client: Option<&str>,
+    dry_run: bool,
+    flash: bool,
+) -> Result<SendOutcome, CommandError> {
+    // Determine if we should use remote server
+    #[cfg(feature = "modem")]
+    let remote_url = args.remote_url.as_deref();
+    #[cfg(not(feature = "modem"))]
+    let remote_url = Some(args.remote_url.as_str());
+
+    if let Some(remote_url) = remote_url {
+        let http_client = remote_client();
+        let url = format!("{}/send-sms", remote_url.trim_end_matches('/'));
+        let payload = serde_json::json!({
+            "to": to,
+            "message": message,
+            "client": client,
+            "flash": flash
+        });
+        if dry_run {
+            let body = serde_json::to_string_pretty(&payload).unwrap();
+            return Ok(SendOutcome::DryRun {
+                request: dry_run_request(message, &[(url, body)]),
+            });
+        }
+
+        let res = http_client
+            .post(&url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(connect_error)?;
+        if !res.status().is_success() {
+            return Err(remote_error("Error sending SMS", res).await);
+        }
+        let remote_res: serde_json::Value = res.json().await.unwrap_or_default();
+        let id = remote_res.get("id").and_then(|id| id.as_u64());
+        match (remote_res.get("status").and_then(|s| s.as_str()), id) {
+            (Some("queued"), Some(id)) => Ok(SendOutcome::Queued { id }),
+            _ => Ok(SendOutcome::Sent { id }),
+        }
+    } else {
+        #[cfg(feature = "modem")]
+        {
+            let modem = client::SessionCache::new(args.modem_client());
+            send_direct(&modem, to, message, dry_run, flash).await
+        }
+        #[cfg(not(feature = "modem"))]
+        unreachable!()
+    }
+}
+
+/// Sends a single SMS directly via the modem, reusing the session cached in `modem`
+#[cfg(feature = "modem")]
+async fn send_direct(
+    modem: &client::SessionCache<client::HttpModem>,
+    to: &str,
+    message: &str,
+    dry_run: bool,
+    flash: bool,
+) -> Result<SendOutcome, CommandError> {
+    // A dry run only builds the requests, so it works without a reachable modem
+    if dry_run {
+        let requests = modem
+            .inner()
+            .send_requests(&[to.to_string()], message)
+            .map_err(|e| CommandError::new(ErrorKind::Internal, e.to_string()))?;
+        return Ok(SendOutcome::DryRun {
+            request: dry_run_request(message, &requests),
+        });
+    }
+    let session = modem
+        .get_session()
+        .await
+        .map_err(|e| CommandError::modem("Error getting session info", &e))?;
+    if flash {
+        eprintln!("Warning: HiLink modems cannot send flash SMS, sending a normal SMS.");
+    }
+    modem
+        .send_sms(&session, &[to.to_string()], message)
+        .await
+        .map_err(|e| CommandError::modem("Error sending SMS", &e))?;
+    Ok(SendOutcome::Sent { id: None })
+}
+
+/// Pause between two SMS of a bulk send sent directly via the modem, so a
+/// long recipients file does not flood it
+#[cfg(feature = "modem")]
+const BULK_SEND_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Settings for a bulk send from a recipients file
+struct BulkOptions {
+    /// Variables applied to every row; row values take precedence
+    vars: HashMap<String, String>,
+    /// Expand placeholders even in rows without variables, set for named templates
+    /// and `--var`
+    expand: bool,
+    report: Option<std::path::PathBuf>,
+    client: Option<String>,
+    dry_run: bool,
+    yes: bool,
+    confirm_threshold: usize,
+    max_recipients: usize,
+    cost_per_segment: Option<f64>,
+    ascii: bool,
+    flash: bool,
+}
+
+/// Asks the user to confirm on stdin. Returns false if stdin is not a terminal.
+fn confirm(prompt: &str) -> bool {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+    print!("{} [y/N] ", prompt);
+    std::io::stdout().flush().ok();
+    let mut answer = String::new();
+    if std::io::stdin().lock().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Lets the user write the message in their editor until it is valid, then shows
+/// its encoding and segment count and asks for confirmation unless `yes` is set.
+/// Returns None if the user gives up.
+fn compose_message(to: &str, message: &str, ascii: bool, yes: bool) -> Option<String> {
+    let mut text = compose::template(to, message);
+    loop {
+        text = match compose::edit(&text) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return None;
+            }
+        };
+        let mut message = compose::strip_comments(&text);
+        if ascii {
+            message = encoding::transliterate(&message);
+        }
+        match compose::validate(&message) {
+            Ok(estimate) => {
+                println!("{}\n", message);
                 println!(
-                    "Per-client limits: {}",
-                    client_limits
-                        .iter()
-                        .map(|cl| format!("{}:{}/{}", cl.name, cl.hourly_limit, cl.daily_limit))
-                        .collect::<Vec<_>>()
-                        .join(", ")
+                    "{} characters, {} encoding, {} segment(s)",
+                    message.chars().count(),
+                    estimate.encoding,
+                    estimate.segments
                 );
+                let non_gsm7 = encoding::non_gsm7_chars(&message);
+                if !non_gsm7.is_empty() {
+                    let chars: Vec<String> = non_gsm7.iter().map(char::to_string).collect();
+                    println!("Characters outside GSM-7: {}", chars.join(" "));
+                }
+                if yes || confirm(&format!("Send to {}?", to)) {
+                    return Some(message);
+                }
             }
-
+            Err(e) => eprintln!("Invalid message: {}", e),
+        }
+        if !confirm("Edit again?") {
+            return None;
+        }
+    }
+}
+
+/// Message for one recipient of a bulk send. A row missing a variable fails on
+/// its own instead of sending the placeholder; like single sends, messages
+/// without any variables are sent as is.
+fn bulk_message(
+    message: &str,
+    recipient: &bulk::Recipient,
+    options: &BulkOptions,
+) -> Result<String, String> {
+    let text = if options.expand || !recipient.vars.is_empty() {
+        let mut vars = options.vars.clone();
+        vars.extend(recipient.vars.clone());
+        template::render(message, &vars)?
+    } else {
+        message.to_string()
+    };
+    Ok(if options.ascii {
+        encoding::transliterate(&text)
+    } else {
+        text
+    })
+}
+
+/// Sends the message to every recipient of a CSV file, one at a time, and
+/// writes a per-recipient result report.
+async fn send_bulk(
+    args: &Args,
+    recipients_file: &std::path::Path,
+    message: &str,
+    options: BulkOptions,
+) {
+    let recipients = match std::fs::read_to_string(recipients_file)
+        .map_err(|e| e.to_string())
+        .and_then(|csv| bulk::parse_recipients(&csv))
+    {
+        Ok(r) => r,
+        Err(e) => CommandError::new(
+            ErrorKind::BadRequest,
+            format!(
+                "Error reading recipients file {}: {}",
+                recipients_file.display(),
+                e
+            ),
+        )
+        .exit(),
+    };
+
+    if recipients.len() > options.max_recipients {
+        CommandError::new(
+            ErrorKind::BadRequest,
+            format!(
+                "Error: {} recipients exceeds the maximum of {} per bulk send (see --max-recipients and max_recipients in the config file)",
+                recipients.len(),
+                options.max_recipients
+            ),
+        )
+        .exit();
+    }
+
+    let messages: Vec<Result<String, String>> = recipients
+        .iter()
+        .map(|r| bulk_message(message, r, &options))
+        .collect();
+    let segments: usize = messages
+        .iter()
+        .flatten()
+        .map(|m| encoding::estimate(m).segments)
+        .sum();
+    let cost = options
+        .cost_per_segment
+        .map(|c| format!(", estimated cost {:.2}", c * segments as f64))
+        .unwrap_or_default();
+    if !args.quiet {
+        println!(
+            "About to send to {} recipients ({} segments{})",
+            recipients.len(),
+            segments,
+            cost
+        );
+    }
+
+    if recipients.len() > options.confirm_threshold
+        && !options.yes
+        && !options.dry_run
+        && !confirm("Proceed?")
+    {
+        CommandError::new(
+            ErrorKind::BadRequest,
+            "Aborted: bulk send not confirmed (use --yes to skip the prompt)",
+        )
+        .exit();
+    }
+
+    let client = options.client;
+    let dry_run = options.dry_run;
+    let flash = options.flash;
+    // Without a remote server all recipients share one modem session
+    #[cfg(feature = "modem")]
+    let modem = args
+        .remote_url
+        .is_none()
+        .then(|| client::SessionCache::new(args.modem_client()));
+    let mut results = Vec::with_capacity(recipients.len());
+    let mut first_error = None;
+    for (recipient, text) in recipients.into_iter().zip(messages) {
+        let text = match text {
+            Ok(text) => text,
+            Err(e) => {
+                first_error.get_or_insert(ErrorKind::BadRequest);
+                results.push(bulk::RecipientResult {
+                    phone: recipient.phone,
+                    status: "failed".to_string(),
+                    id: None,
+                    error: Some(e),
+                });
+                continue;
+            }
+        };
+        #[cfg(feature = "modem")]
+        let outcome = match &modem {
+            Some(modem) => {
+                if !dry_run && !results.is_empty() {
+                    tokio::time::sleep(BULK_SEND_INTERVAL).await;
+                }
+                send_direct(modem, &recipient.phone, &text, dry_run, flash).await
+            }
+            None => {
+                send_message(
+                    args,
+                    &recipient.phone,
+                    &text,
+                    client.as_deref(),
+                    dry_run,
+                    flash,
+                )
+                .await
+            }
+        };
+        #[cfg(not(feature = "modem"))]
+        let outcome = send_message(
+            args,
+            &recipient.phone,
+            &text,
+            client.as_deref(),
+            dry_run,
+            flash,
+        )
+        .await;
+        let result = match outcome {
+            Ok(outcome) => bulk::RecipientResult {
+                phone: recipient.phone,
+                status: outcome.status().to_string(),
+                id: outcome.id(),
+                error: None,
+            },
+            Err(e) => {
+                first_error.get_or_insert(e.kind);
+                bulk::RecipientResult {
+                    phone: recipient.phone,
+                    status: "failed".to_string(),
+                    id: None,
+                    error: Some(e.message),
+                }
+            }
+        };
+        results.push(result);
+    }
+
+    let failed = results.iter().filter(|r| r.status == "failed").count();
+    let report_csv = bulk::format_report(&results);
+    match options.report {
+        Some(path) => {
+            if let Err(e) = std::fs::write(&path, report_csv) {
+                eprintln!("Error writing report {}: {}", path.display(), e);
+            }
+        }
+        None => print!("{}", report_csv),
+    }
+    if !args.quiet {
+        println!(
+            "Bulk send complete: {} recipients, {} failed",
+            results.len(),
+            failed
+        );
+    }
+    // Exit like the first failed send, so scripts notice partial failures
+    if let Some(kind) = first_error {
+        std::process::exit(kind.exit_code());
+    }
+}
+
+/// Alerting settings read from the config file for `serve`
+#[cfg(feature = "alertmanager")]
+struct AlertSettings {
+    receivers: HashMap<String, AlertReceiver>,
+    silences: Silences,
+    oncall: OnCall,
+    policies: AlertPolicies,
+}
+
+#[cfg(feature = "alertmanager")]
+fn alert_settings(config: Config) -> Result<AlertSettings, String> {
+    Ok(AlertSettings {
+        policies: AlertPolicies::from_config(&config)?,
+        silences: Silences::from_specs(config.silences)?,
+        oncall: OnCall::from_config(config.oncall)?,
+        receivers: config.receivers,
+    })
+}
+
+/// Limits of the API keys in the config file, sorted by key name
+#[cfg(feature = "server")]
+fn key_limits(config: &Config) -> Vec<ClientLimit> {
+    let mut key_limits: Vec<ClientLimit> = config
+        .api_key_limits
+        .iter()
+        .map(|(name, limits)| ClientLimit {
+            name: name.clone(),
+            hourly_limit: limits.hourly_limit,
+            daily_limit: limits.daily_limit,
+        })
+        .collect();
+    key_limits.sort_by(|a, b| a.name.cmp(&b.name));
+    key_limits
+}
+
+/// Reads the config file again for a running `smser serve`. `matches` are
+/// those the server was started with, so options given on the command line
+/// or in the environment still take precedence over the file.
+#[cfg(feature = "server")]
+fn reload_serve_config(matches: &ArgMatches) -> Result<crate::server::ReloadedConfig, String> {
+    let mut args = Args::from_arg_matches(matches).map_err(|e| e.to_string())?;
+    let config = Config::load_or_default(args.config.as_deref())?;
+    apply_config(&mut args, matches, &config);
+    let SmsCommand::Serve {
+        hourly_limit,
+        daily_limit,
+        recipient_limit,
+        ..
+    } = args.command
+    else {
+        return Err("Not running `smser serve`".to_string());
+    };
+    Ok(crate::server::ReloadedConfig {
+        routes: Routes::from_config(&config.transports, &config.routes)?,
+        content_filter: ContentFilter::from_config(&config.filters)?,
+        key_limits: key_limits(&config),
+        hourly_limit,
+        daily_limit,
+        recipient_limit,
+        #[cfg(feature = "alertmanager")]
+        alert_policies: AlertPolicies::from_config(&config)?,
+        templates: config.templates,
+        #[cfg(feature = "alertmanager")]
+        oncall: OnCall::from_config(config.oncall)?,
+        #[cfg(feature = "alertmanager")]
+        alert_receivers: config.receivers,
+    })
+}
+
+/// Empties a message box, via the remote server if one is configured
+async fn purge_box(args: &Args, box_type: BoxType) -> Result<usize, CommandError> {
+    #[cfg(feature = "modem")]
+    let remote_url = args.remote_url.as_ref();
+    #[cfg(not(feature = "modem"))]
+    let remote_url = Some(&args.remote_url);
+
+    let Some(remote_url) = remote_url else {
+        #[cfg(feature = "modem")]
+        return client::purge_box(&args.modem_client(), box_type)
+            .await
+            .map_err(|e| CommandError::modem("Error purging messages", &e));
+        #[cfg(not(feature = "modem"))]
+        unreachable!()
+    };
+
+    let url = format!("{}/purge-trash", remote_url.trim_end_matches('/'));
+    let res = remote_client()
+        .post(&url)
+        .query(&[("box_type", (box_type as i32).to_string())])
+        .send()
+        .await
+        .map_err(connect_error)?;
+    if !res.status().is_success() {
+        return Err(remote_error("Error purging messages", res).await);
+    }
+    let body: serde_json::Value = res.json().await.unwrap_or_default();
+    body["deleted"].as_u64().map(|n| n as usize).ok_or_else(|| {
+        CommandError::new(
+            ErrorKind::Unknown,
+            format!("Invalid response from remote server: {}", body),
+        )
+    })
+}
+
+#[cfg(feature = "modem")]
+fn print_signal(signal: &modem::SignalInfo) {
+    let fields = [
+        ("RSSI", &signal.rssi),
+        ("RSRP", &signal.rsrp),
+        ("RSRQ", &signal.rsrq),
+        ("SINR", &signal.sinr),
+        ("RSCP", &signal.rscp),
+        ("Ec/Io", &signal.ecio),
+        ("Cell ID", &signal.cell_id),
+        ("PCI", &signal.pci),
+        ("Band", &signal.band),
+        ("EARFCN", &signal.earfcn),
+        ("PLMN", &signal.plmn),
+        ("DL bandwidth", &signal.dlbandwidth),
+        ("UL bandwidth", &signal.ulbandwidth),
+    ];
+    if let Some(mode) = signal.mode_name() {
+        println!("{:<13} {}", "Mode:", mode);
+    }
+    for (name, value) in fields {
+        if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+            println!("{:<13} {}", format!("{}:", name), value);
+        }
+    }
+    if let Some(quality) = signal.quality() {
+        println!("{:<13} {}", "Quality:", quality);
+    }
+}
+
+#[cfg(feature = "modem")]
+fn print_sms_count(count: &modem::SmsCount) {
+    println!(
+        "Local inbox: {} unread, {} total",
+        count.local_unread, count.local_inbox
+    );
+    println!(
+        "SIM inbox:   {} unread, {} total",
+        count.sim_unread, count.sim_inbox
+    );
+    println!(
+        "Outbox:      {} local, {} SIM",
+        count.local_outbox, count.sim_outbox
+    );
+    println!(
+        "Drafts:      {} local, {} SIM",
+        count.local_draft, count.sim_draft
+    );
+    println!("Trash:       {}", count.local_deleted);
+    println!(
+        "Capacity:    {} local, {} SIM",
+        count.local_max, count.sim_max
+    );
+}
+
+#[cfg(feature = "modem")]
+fn print_outbox(progress: &modem::SendProgress, messages: &[modem::SmsMessage]) {
+    if progress.in_progress() {
+        println!(
+            "Sending to {} ({} of {} done), cancel with `smser outbox --cancel`",
+            progress.phone.trim_end_matches(';'),
+            progress.cur_index,
+            progress.total_count
+        );
+    } else {
+        println!("No send in progress");
+    }
+    println!("Sent box ({} messages):", messages.len());
+    for msg in messages {
+        println!(
+            "  {:>6}  {}  {:<16} {:<11} {}",
+            msg.index,
+            msg.date,
+            msg.phone,
+            msg.smstat,
+            msg.content.chars().take(40).collect::<String>()
+        );
+    }
+}
+
+/// Whether `number` is `+` followed by 3 to 15 digits
+#[cfg(feature = "modem")]
+fn is_international_number(number: &str) -> bool {
+    number.strip_prefix('+').is_some_and(|digits| {
+        (3..=15).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_digit())
+    })
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`
+#[cfg(feature = "modem")]
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+#[cfg(feature = "modem")]
+fn print_traffic(stats: &modem::TrafficStatistics) {
+    let duration = |secs: u64| format!("{}h {:02}m", secs / 3600, secs / 60 % 60);
+    println!(
+        "Current connection: {} up, {} down, connected {}",
+        format_bytes(stats.current_upload),
+        format_bytes(stats.current_download),
+        duration(stats.current_connect_time)
+    );
+    println!(
+        "Total:              {} up, {} down, connected {}",
+        format_bytes(stats.total_upload),
+        format_bytes(stats.total_download),
+        duration(stats.total_connect_time)
+    );
+}
+
+/// Fetches the device information from the modem, or the remote server's `/device`
+async fn device_info(args: &Args) -> Result<DeviceInfo, CommandError> {
+    #[cfg(feature = "modem")]
+    let remote_url = args.remote_url.as_ref();
+    #[cfg(not(feature = "modem"))]
+    let remote_url = Some(&args.remote_url);
+
+    let Some(remote_url) = remote_url else {
+        #[cfg(feature = "modem")]
+        {
+            let (session_id, token) =
+                modem::open_session(args.modem_url(), args.modem_credentials().as_ref())
+                    .await
+                    .map_err(|e| CommandError::modem("Error getting session info", &e))?;
+            return modem::get_device_info(args.modem_url(), &session_id, &token)
+                .await
+                .map_err(|e| CommandError::modem("Error getting device information", &e));
+        }
+        #[cfg(not(feature = "modem"))]
+        unreachable!()
+    };
+
+    get_remote(remote_url, "device", "Error getting device information").await
+}
+
+/// Fetches the operator and network state from the modem, or the remote server's `/network`
+async fn network_info(args: &Args) -> Result<NetworkInfo, CommandError> {
+    #[cfg(feature = "modem")]
+    let remote_url = args.remote_url.as_ref();
+    #[cfg(not(feature = "modem"))]
+    let remote_url = Some(&args.remote_url);
+
+    let Some(remote_url) = remote_url else {
+        #[cfg(feature = "modem")]
+        {
+            let (session_id, token) =
+                modem::open_session(args.modem_url(), args.modem_credentials().as_ref())
+                    .await
+                    .map_err(|e| CommandError::modem("Error getting session info", &e))?;
+            return modem::get_network_info(args.modem_url(), &session_id, &token)
+                .await
+                .map_err(|e| CommandError::modem("Error getting network information", &e));
+        }
+        #[cfg(not(feature = "modem"))]
+        unreachable!()
+    };
+
+    get_remote(remote_url, "network", "Error getting network information").await
+}
+
+/// GETs `/{key}` from the remote server and parses the `key` field of the response
+async fn get_remote<T: serde::de::DeserializeOwned>(
+    remote_url: &str,
+    key: &str,
+    context: &str,
+) -> Result<T, CommandError> {
+    let url = format!("{}/{}", remote_url.trim_end_matches('/'), key);
+    let res = remote_client()
+        .get(&url)
+        .send()
+        .await
+        .map_err(connect_error)?;
+    if !res.status().is_success() {
+        return Err(remote_error(context, res).await);
+    }
+    let mut body: serde_json::Value = res.json().await.unwrap_or_default();
+    serde_json::from_value(body[key].take()).map_err(|e| {
+        CommandError::new(
+            ErrorKind::Unknown,
+            format!("Invalid response from remote server: {}", e),
+        )
+    })
+}
+
+#[cfg(feature = "server")]
+fn print_audit_log(entries: &[AuditEntry]) {
+    if entries.is_empty() {
+        println!("No send attempts recorded");
+        return;
+    }
+    for entry in entries {
+        let time = chrono::DateTime::from_timestamp(entry.recorded_at, 0)
+            .map(|t| {
+                t.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        println!(
+            "{} SMS {} attempt {} to {}: {}{}",
+            time,
+            entry.message_id,
+            entry.attempt,
+            entry.recipient,
+            entry.status,
+            entry
+                .error
+                .as_ref()
+                .map(|e| format!(" ({})", e))
+                .unwrap_or_default()
+        );
+        println!(
+            "    client: {}, API key: {}, hash: {}",
+            entry.client.as_deref().unwrap_or("none"),
+            entry.api_key.as_deref().unwrap_or("none"),
+            entry.content_hash
+        );
+        if let Some(content) = &entry.content {
+            println!("    {}", content);
+        }
+    }
+}
+
+fn print_network_info(info: &NetworkInfo) {
+    let roaming = info
+        .roaming
+        .map(|r| if r { "yes" } else { "no" }.to_string());
+    let fields = [
+        ("Operator", &info.operator),
+        ("PLMN", &info.plmn),
+        ("Network type", &info.network_type),
+        ("Connection", &info.connection),
+        ("Roaming", &roaming),
+    ];
+    if info.operator.is_none() && info.plmn.is_none() {
+        println!("{:<13} not registered", "Operator:");
+    }
+    for (name, value) in fields {
+        if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+            println!("{:<13} {}", format!("{}:", name), value);
+        }
+    }
+}
+
+fn print_device_info(info: &DeviceInfo) {
+    let fields = [
+        ("Device", &info.device_name),
+        ("Product family", &info.product_family),
+        ("Serial number", &info.serial_number),
+        ("IMEI", &info.imei),
+        ("IMSI", &info.imsi),
+        ("ICCID", &info.iccid),
+        ("Phone number", &info.msisdn),
+        ("Hardware", &info.hardware_version),
+        ("Firmware", &info.software_version),
+        ("Web UI", &info.webui_version),
+        ("MAC address", &info.mac_address),
+        ("Work mode", &info.work_mode),
+    ];
+    for (name, value) in fields {
+        if let Some(value) = value.as_deref().filter(|v| !v.is_empty()) {
+            println!("{:<15} {}", format!("{}:", name), value);
+        }
+    }
+}
+
+/// Runs one step of `test-modem`, printing its outcome and latency
+#[cfg(feature = "modem")]
+async fn timed_step<T>(
+    name: &str,
+    step: impl std::future::Future<Output = Result<T, modem::Error>>,
+) -> Result<T, CommandError> {
+    let start = std::time::Instant::now();
+    let result = step.await;
+    let ms = start.elapsed().as_millis();
+    let outcome = if result.is_ok() { "ok" } else { "FAILED" };
+    println!("{:<12} {:<8} {:>6} ms", name, outcome, ms);
+    result.map_err(|e| CommandError::modem(&format!("{} failed", name), &e))
+}
+
+#[cfg(feature = "modem")]
+async fn test_modem(
+    modem_url: &str,
+    credentials: Option<&modem::Credentials>,
+) -> Result<(), CommandError> {
+    println!("Testing modem at {}", modem_url);
+    let (session_id, token) = timed_step("SesTokInfo", modem::get_session_info(modem_url)).await?;
+    let (session_id, token) = match credentials {
+        Some(credentials) => {
+            timed_step(
+                "login",
+                modem::login(modem_url, &session_id, &token, credentials),
+            )
+            .await?
+        }
+        None => {
+            println!("{:<12} skipped (no credentials configured)", "login");
+            (session_id, token)
+        }
+    };
+    let count = timed_step(
+        "sms-count",
+        modem::get_sms_count(modem_url, &session_id, &token),
+    )
+    .await?;
+    println!(
+        "Inbox {} ({} unread), outbox {}, drafts {}, deleted {}, capacity {}",
+        count.local_inbox,
+        count.local_unread,
+        count.local_outbox,
+        count.local_draft,
+        count.local_deleted,
+        count.local_max
+    );
+    Ok(())
+}
+
+/// Result of one check of `smser status`
+#[cfg(feature = "modem")]
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Check<T> {
+    Ok(T),
+    Error(String),
+}
+
+#[cfg(feature = "modem")]
+impl<T> From<Result<T, modem::Error>> for Check<T> {
+    fn from(result: Result<T, modem::Error>) -> Self {
+        match result {
+            Ok(value) => Check::Ok(value),
+            Err(e) => Check::Error(e.to_string()),
+        }
+    }
+}
+
+/// Health snapshot printed by `smser status`. The checks after `session` are
+/// skipped if the modem cannot be reached.
+#[cfg(feature = "modem")]
+#[derive(Debug, serde::Serialize)]
+struct StatusReport {
+    modem_url: String,
+    /// Time taken to open a session, in milliseconds
+    session: Check<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sim: Option<Check<modem::PinStatus>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signal: Option<Check<modem::SignalInfo>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    messages: Option<Check<modem::SmsCount>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    smsc: Option<Check<String>>,
+}
+
+#[cfg(feature = "modem")]
+impl StatusReport {
+    /// Whether every check succeeded and the SIM is ready
+    fn healthy(&self) -> bool {
+        matches!(self.session, Check::Ok(_))
+            && matches!(&self.sim, Some(Check::Ok(sim)) if sim.is_ready())
+            && matches!(self.signal, Some(Check::Ok(_)))
+            && matches!(&self.messages, Some(Check::Ok(count)) if !count.storage_full())
+            && matches!(self.smsc, Some(Check::Ok(_)))
+    }
+}
+
+#[cfg(feature = "modem")]
+async fn modem_status(modem_url: &str, credentials: Option<&modem::Credentials>) -> StatusReport {
+    let start = std::time::Instant::now();
+    let session = modem::open_session(modem_url, credentials).await;
+    let mut report = StatusReport {
+        modem_url: modem_url.to_string(),
+        session: Check::Error(String::new()),
+        sim: None,
+        signal: None,
+        messages: None,
+        smsc: None,
+    };
+    let (session_id, token) = match session {
+        Ok(session) => session,
+        Err(e) => {
+            report.session = Check::Error(e.to_string());
+            return report;
+        }
+    };
+    report.session = Check::Ok(start.elapsed().as_millis());
+    report.sim = Some(
+        modem::get_pin_status(modem_url, &session_id, &token)
+            .await
+            .into(),
+    );
+    report.signal = Some(
+        modem::get_signal(modem_url, &session_id, &token)
+            .await
+            .into(),
+    );
+    report.messages = Some(
+        modem::get_sms_count(modem_url, &session_id, &token)
+            .await
+            .into(),
+    );
+    report.smsc = Some(
+        modem::get_sms_config(modem_url, &session_id, &token)
+            .await
+            .map(|config| config.sca)
+            .into(),
+    );
+    report
+}
+
+#[cfg(feature = "modem")]
+fn print_status(report: &StatusReport) {
+    let line = |name: &str, value: String| println!("{:<10} {}", format!("{}:", name), value);
+    let error = |e: &str| format!("error: {}", e);
+    match &report.session {
+        Check::Ok(ms) => line(
+            "Modem",
+            format!("{} (reachable, {} ms)", report.modem_url, ms),
+        ),
+        Check::Error(e) => line("Modem", format!("{} ({})", report.modem_url, error(e))),
+    }
+    if let Some(sim) = &report.sim {
+        line(
+            "SIM",
+            match sim {
+                Check::Ok(sim) => format!(
+                    "{} ({} PIN / {} PUK attempts left)",
+                    sim.sim_state_name(),
+                    sim.sim_pin_times,
+                    sim.sim_puk_times
+                ),
+                Check::Error(e) => error(e),
+            },
+        );
+    }
+    if let Some(signal) = &report.signal {
+        line(
+            "Signal",
+            match signal {
+                Check::Ok(signal) => {
+                    let level = [
+                        ("RSRP", &signal.rsrp),
+                        ("RSCP", &signal.rscp),
+                        ("RSSI", &signal.rssi),
+                    ]
+                    .into_iter()
+                    .find_map(|(name, value)| {
+                        value
+                            .as_deref()
+                            .filter(|v| !v.is_empty())
+                            .map(|v| format!("{} {}", name, v))
+                    });
+                    let parts: Vec<_> = [
+                        signal.mode_name().map(str::to_string),
+                        level,
+                        signal.quality().map(|q| format!("({})", q)),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                    if parts.is_empty() {
+                        "unknown".to_string()
+                    } else {
+                        parts.join(" ")
+                    }
+                }
+                Check::Error(e) => error(e),
+            },
+        );
+    }
+    if let Some(messages) = &report.messages {
+        line(
+            "Messages",
+            match messages {
+                Check::Ok(count) => format!(
+                    "{} unread, {}/{} stored{}",
+                    count.local_unread,
+                    count.local_used(),
+                    count.local_max,
+                    if count.storage_full() { " (FULL)" } else { "" }
+                ),
+                Check::Error(e) => error(e),
+            },
+        );
+    }
+    if let Some(smsc) = &report.smsc {
+        line(
+            "SMSC",
+            match smsc {
+                Check::Ok(sca) if sca.is_empty() => "not set".to_string(),
+                Check::Ok(sca) => sca.clone(),
+                Check::Error(e) => error(e),
+            },
+        );
+    }
+}
+
+/// Whether an argument was neither given on the command line nor via its environment variable
+#[cfg(feature = "modem")]
+fn is_unset(matches: &ArgMatches, id: &str) -> bool {
+    !matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+/// Fills in the arguments that were not given explicitly from the config file
+fn apply_config(args: &mut Args, matches: &ArgMatches, config: &Config) {
+    if args.api_key.is_none() {
+        args.api_key = config.api_key.clone();
+    }
+    #[cfg(feature = "modem")]
+    {
+        if let Some(modem_url) = &config.modem_url
+            && is_unset(matches, "modem_urls")
+        {
+            args.modem_urls = vec![modem_url.clone()];
+        }
+        if let Some(username) = &config.modem_username
+            && is_unset(matches, "modem_username")
+        {
+            args.modem_username = username.clone();
+        }
+        if args.modem_password.is_none() {
+            args.modem_password = config.modem_password.clone();
+        }
+        if let Some(profile) = &config.device_profile
+            && is_unset(matches, "device_profile")
+        {
+            match profile.parse() {
+                Ok(profile) => args.device_profile = profile,
+                Err(_) => eprintln!("Ignoring unknown device_profile {:?} in config", profile),
+            }
+        }
+        if args.remote_url.is_none() {
+            args.remote_url = config.remote_url.clone();
+        }
+    }
+    #[cfg(feature = "server")]
+    if let (
+        SmsCommand::Serve {
+            #[cfg(feature = "alertmanager")]
+            alert_to,
+            hourly_limit,
+            daily_limit,
+            recipient_limit,
+            duplicate_window,
+            duplicate_action,
+            ..
+        },
+        Some(("serve", matches)),
+    ) = (&mut args.command, matches.subcommand())
+    {
+        #[cfg(feature = "alertmanager")]
+        if alert_to.is_none() {
+            *alert_to = config.alert_to.clone();
+        }
+        if let Some(limit) = config.hourly_limit
+            && is_unset(matches, "hourly_limit")
+        {
+            *hourly_limit = limit;
+        }
+        if let Some(limit) = config.daily_limit
+            && is_unset(matches, "daily_limit")
+        {
+            *daily_limit = limit;
+        }
+        if let Some(limit) = config.recipient_limit
+            && is_unset(matches, "recipient_limit")
+        {
+            *recipient_limit = limit;
+        }
+        if let Some(window) = config.duplicate_window
+            && is_unset(matches, "duplicate_window")
+        {
+            *duplicate_window = window;
+        }
+        if let Some(action) = &config.duplicate_action
+            && is_unset(matches, "duplicate_action")
+        {
+            match action.parse() {
+                Ok(action) => *duplicate_action = action,
+                Err(_) => eprintln!("Ignoring unknown duplicate_action {:?} in config", action),
+            }
+        }
+    }
+    if let (
+        SmsCommand::Send { ascii, .. }
+        | SmsCommand::Reply { ascii, .. }
+        | SmsCommand::Forward { ascii, .. },
+        Some(true),
+    ) = (&mut args.command, config.ascii)
+    {
+        *ascii = true;
+    }
+    #[cfg(not(feature = "modem"))]
+    let _ = matches;
+}
+
+fn config_path(args: &Args) -> Result<std::path::PathBuf, CommandError> {
+    args.config
+        .clone()
+        .or_else(config::default_path)
+        .ok_or_else(|| {
+            CommandError::new(
+                ErrorKind::BadRequest,
+                "No config path: pass --config or set HOME",
+            )
+        })
+}
+
+/// Asks for a value on stdin, returning `default` for an empty answer
+fn prompt(question: &str, default: &str) -> String {
+    use std::io::{BufRead, Write};
+
+    if default.is_empty() {
+        print!("{}: ", question);
+    } else {
+        print!("{} [{}]: ", question, default);
+    }
+    std::io::stdout().flush().ok();
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).ok();
+    match answer.trim() {
+        "" => default.to_string(),
+        answer => answer.to_string(),
+    }
+}
+
+fn prompt_number(question: &str, default: u32) -> u32 {
+    loop {
+        match prompt(question, &default.to_string()).parse() {
+            Ok(n) => return n,
+            Err(_) => eprintln!("Please enter a number"),
+        }
+    }
+}
+
+fn run_config_command(args: &Args, command: &ConfigCommand) -> Result<(), CommandError> {
+    let path = config_path(args)?;
+    match command {
+        ConfigCommand::Init { interactive, force } => {
+            if path.exists() && !force {
+                return Err(CommandError::new(
+                    ErrorKind::BadRequest,
+                    format!(
+                        "{} already exists (use --force to overwrite)",
+                        path.display()
+                    ),
+                ));
+            }
+            let mut settings = StarterSettings::default();
+            if *interactive {
+                settings.modem_url = prompt("Modem URL", &settings.modem_url);
+                let alert_to = prompt("Phone number receiving alerts (optional)", "");
+                settings.alert_to = Some(alert_to).filter(|s| !s.is_empty());
+                settings.hourly_limit = prompt_number("Hourly SMS limit", settings.hourly_limit);
+                settings.daily_limit = prompt_number("Daily SMS limit", settings.daily_limit);
+            }
+            let write = || -> std::io::Result<()> {
+                if let Some(dir) = path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::write(&path, config::starter_config(&settings))
+            };
+            write().map_err(|e| {
+                CommandError::new(
+                    ErrorKind::Internal,
+                    format!("Failed to write {}: {}", path.display(), e),
+                )
+            })?;
+            if !args.quiet {
+                println!("Wrote {}", path.display());
+            }
+        }
+        ConfigCommand::Check => {
+            let invalid = |e: String| CommandError::new(ErrorKind::BadRequest, e);
+            let config = Config::load(&path).map_err(invalid)?;
+            #[cfg_attr(not(feature = "modem"), allow(unused_mut))]
+            let mut problems = config.conflicts();
+            // Values that are otherwise only ignored with a warning at startup
+            #[cfg(feature = "modem")]
+            if let Some(profile) = &config.device_profile
+                && profile.parse::<DeviceProfile>().is_err()
+            {
+                problems.push(format!("Unknown device_profile {:?}", profile));
+            }
+            #[cfg(feature = "server")]
+            if let Some(action) = &config.duplicate_action
+                && action.parse::<DuplicateAction>().is_err()
+            {
+                problems.push(format!(
+                    "Unknown duplicate_action {:?} (expected reject, flag or suppress)",
+                    action
+                ));
+            }
+            // Also check the filters and alerting settings the server builds from the config
+            #[cfg(feature = "server")]
+            if let Err(e) = ContentFilter::from_config(&config.filters) {
+                problems.push(e.to_string());
+            }
+            #[cfg(feature = "alertmanager")]
+            if let Err(e) = alert_settings(config) {
+                problems.push(e.to_string());
+            }
+            #[cfg(not(feature = "alertmanager"))]
+            let _ = config;
+            if !problems.is_empty() {
+                return Err(invalid(format!(
+                    "Invalid config {}:\n  {}",
+                    path.display(),
+                    problems.join("\n  ")
+                )));
+            }
+            if !args.quiet {
+                println!("{} is valid", path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn run() {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    init_logging(&args);
+    if args.json {
+        eprintln!("Warning: --json is deprecated, use --format json instead.");
+    }
+
+    if let SmsCommand::Config { command } = &args.command {
+        if let Err(e) = run_config_command(&args, command) {
+            e.exit();
+        }
+        return;
+    }
+    // Generated from the arguments alone, so packaging does not need a config
+    if let SmsCommand::Completions { shell } = &args.command {
+        clap_complete::generate(
+            *shell,
+            &mut Args::command(),
+            "smser",
+            &mut std::io::stdout(),
+        );
+        return;
+    }
+    let config = match Config::load_or_default(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => CommandError::new(ErrorKind::BadRequest, e).exit(),
+    };
+    apply_config(&mut args, &matches, &config);
+    init_remote_client(args.api_key.as_deref());
+
+    match args.command.clone() {
+        SmsCommand::Send {
+            to,
+            message,
+            message_file,
+            stdin,
+            template,
+            vars,
+            edit,
+            ascii,
+            flash,
+            dry_run,
+            client,
+            recipients_file,
+            report,
+            yes,
+            confirm_threshold,
+            max_recipients,
+            cost_per_segment,
+        } => {
+            let vars: HashMap<String, String> = vars.into_iter().collect();
+            // Placeholders are only expanded when a template or variables are in use,
+            // so plain messages may contain literal braces.
+            let expand = template.is_some() || !vars.is_empty();
+            let message = match template {
+                Some(name) => match config_template(&config, &name) {
+                    Ok(t) => t.to_string(),
+                    Err(e) => e.exit(),
+                },
+                None if stdin || message_file.is_some() => {
+                    match read_message(message_file.as_deref()) {
+                        Ok(m) => m,
+                        Err(e) => e.exit(),
+                    }
+                }
+                None => message.unwrap_or_default(),
+            };
+
+            if let Some(recipients_file) = recipients_file {
+                let max_recipients = match bulk_max_recipients(max_recipients, &config) {
+                    Ok(max) => max,
+                    Err(e) => e.exit(),
+                };
+                let options = BulkOptions {
+                    vars,
+                    expand,
+                    report,
+                    client,
+                    dry_run,
+                    yes,
+                    confirm_threshold,
+                    max_recipients,
+                    cost_per_segment,
+                    ascii,
+                    flash,
+                };
+                send_bulk(&args, &recipients_file, &message, options).await;
+                return;
+            }
+            let to = to.expect("--to is required without --recipients-file");
+            let message = if !expand {
+                message
+            } else {
+                match render_vars(&message, &vars) {
+                    Ok(m) => m,
+                    Err(e) => e.exit(),
+                }
+            };
+            let message = if edit {
+                match compose_message(&to, &message, ascii, yes) {
+                    Some(m) => m,
+                    None => {
+                        eprintln!("Not sending message.");
+                        return;
+                    }
+                }
+            } else if ascii {
+                encoding::transliterate(&message)
+            } else {
+                message
+            };
+            report_send(
+                send_message(&args, &to, &message, client.as_deref(), dry_run, flash).await,
+                args.quiet,
+            );
+        }
+        SmsCommand::Forward {
+            index,
+            to,
+            ascii,
+            dry_run,
+        } => {
+            let original = find_inbox_message(&args, index)
+                .await
+                .unwrap_or_else(|e| e.exit());
+            let message = forward_text(&original);
+            let message = if ascii {
+                encoding::transliterate(&message)
+            } else {
+                message
+            };
+            if !args.quiet {
+                println!("Forwarding the message from {} to {}", original.phone, to);
+            }
+            report_send(
+                send_message(&args, &to, &message, None, dry_run, false).await,
+                args.quiet,
+            );
+        }
+        SmsCommand::Reply {
+            index,
+            message,
+            quote,
+            edit,
+            ascii,
+            yes,
+            dry_run,
+        } => {
+            let original = find_inbox_message(&args, index)
+                .await
+                .unwrap_or_else(|e| e.exit());
+            if !original.phone.chars().any(|c| c.is_ascii_digit()) {
+                CommandError::new(
+                    ErrorKind::BadRequest,
+                    format!(
+                        "Cannot reply to sender '{}', it is not a phone number",
+                        original.phone
+                    ),
+                )
+                .exit();
+            }
+            let message = message.unwrap_or_default();
+            let message = if quote {
+                quote_reply(&message, &original.content)
+            } else {
+                message
+            };
+            let message = if edit {
+                match compose_message(&original.phone, &message, ascii, yes) {
+                    Some(m) => m,
+                    None => {
+                        eprintln!("Not sending message.");
+                        return;
+                    }
+                }
+            } else if ascii {
+                encoding::transliterate(&message)
+            } else {
+                message
+            };
+            if !args.quiet {
+                println!(
+                    "Replying to {} (message from {})",
+                    original.phone, original.date
+                );
+            }
+            let result = send_message(&args, &original.phone, &message, None, dry_run, false).await;
+            report_send(result, args.quiet);
+        }
+        SmsCommand::Receive {
+            count,
+            ascending,
+            unread_preferred,
+            box_type,
+            sort_by,
+            page,
+            all,
+            from,
+            unread_only,
+            grep,
+            since,
+            until,
+        } => {
+            // Determine if we should use remote server
+            #[cfg(feature = "modem")]
+            let use_remote = args.remote_url.is_some();
+            #[cfg(not(feature = "modem"))]
+            let use_remote = true;
+
+            let mut messages = if use_remote {
+                #[cfg(feature = "modem")]
+                let remote_url = args.remote_url.as_ref().unwrap();
+                #[cfg(not(feature = "modem"))]
+                let remote_url = &args.remote_url;
+
+                // Construct query parameters manually to match server's GetSmsRequest
+                let mut params = vec![
+                    ("count", count.to_string()),
+                    ("ascending", ascending.to_string()),
+                    ("unread_preferred", unread_preferred.to_string()),
+                ];
+
+                // For enums, we need to pass their integer values or string representations that axum expects.
+                // Our server uses #[serde(default = "...")] which might expect strings if they are derived.
+                // Actually BoxType and SortType derive Serialize_repr/Deserialize_repr, so they expect integers.
+                params.push(("box_type", (box_type.clone() as i32).to_string()));
+                params.push(("sort_by", (sort_by.clone() as i32).to_string()));
+
+                // The server does not report the total, so --all reads until a page is short
+                let mut messages = Vec::new();
+                for page in if all { 1.. } else { page.. } {
+                    let received = get_remote_sms(remote_url, &params, page)
+                        .await
+                        .unwrap_or_else(|e| e.exit());
+                    let last = !all || received.is_empty() || received.len() < count as usize;
+                    messages.extend(received);
+                    if last {
+                        break;
+                    }
+                }
+                messages
+            } else {
+                #[cfg(feature = "modem")]
+                {
+                    let modem = args.modem_client();
+                    let params = modem::SmsListParams {
+                        box_type,
+                        sort_type: sort_by,
+                        read_count: count,
+                        ascending,
+                        unread_preferred,
+                    };
+
+                    if all {
+                        match client::get_all_sms(&modem, params).await {
+                            Ok(messages) => messages,
+                            Err(e) => CommandError::modem("Error receiving SMS", &e).exit(),
+                        }
+                    } else {
+                        let session = match modem.get_session().await {
+                            Ok(session) => session,
+                            Err(e) => CommandError::modem("Error getting session info", &e).exit(),
+                        };
+                        match modem.get_sms_list(&session, params, page).await {
+                            Ok(response) => response.messages.message,
+                            Err(e) => CommandError::modem("Error receiving SMS", &e).exit(),
+                        }
+                    }
+                }
+                #[cfg(not(feature = "modem"))]
+                unreachable!()
+            };
+
+            let filter = ReceiveFilter {
+                from: from.as_deref(),
+                unread_only,
+                grep: grep.as_deref(),
+                since: since.as_deref(),
+                until: until.as_deref(),
+            };
+            messages.retain(|m| filter.matches(m));
+
+            let format = args.output_format();
+            if format != OutputFormat::Text {
+                output::print(&messages, format);
+            } else {
+                if !args.quiet {
+                    println!("Received {} SMS messages:", messages.len());
+                }
+                for msg in messages {
+                    println!("  From: {}", msg.phone);
+                    println!("  Content: {}", msg.content);
+                    println!("  Date: {}", msg.date);
+                    println!("  Priority: {}", msg.priority);
+                    println!("  SmsType: {}", msg.sms_type);
+                    println!("  Smstat: {}", msg.smstat);
+                    println!("  SaveType: {}", msg.save_type);
+                    println!("  --------------------");
+                }
+            }
+        }
+        #[cfg(feature = "server")]
+        SmsCommand::Serve {
+            port,
+            bind,
+            listen,
+            #[cfg(feature = "grpc")]
+            grpc_listen,
+            #[cfg(feature = "alertmanager")]
+            alert_to,
+            hourly_limit,
+            daily_limit,
+            recipient_limit,
+            client_limits,
+            tls_cert,
+            tls_key,
+            http_redirect_port,
+            redirect_host,
+            log_sensitive,
+            poll_interval,
+            incoming_webhook,
+            on_receive_cmd,
+            inbox_count_interval,
+            traffic_interval,
+            modem_check_interval,
+            modem_balance,
+            auto_purge,
+            db_path,
+            send_retries,
+            audit,
+            admin_token,
+            basic_auth_user,
+            basic_auth_password,
+            rate_limit_webhook,
+            rate_limit_webhook_format,
+            rate_limit_webhook_interval,
+            duplicate_window,
+            duplicate_action,
+        } => {
+            if http_redirect_port.is_some() && redirect_host.is_none() {
+                CommandError::new(
+                    ErrorKind::BadRequest,
+                    "Error: --http-redirect-port requires --redirect-host to avoid open redirects.",
+                )
+                .exit();
+            }
+            if incoming_webhook.is_some() && poll_interval == 0 {
+                CommandError::new(
+                    ErrorKind::BadRequest,
+                    "--incoming-webhook needs polling, set --poll-interval above 0",
+                )
+                .exit();
+            }
+            if let Some(cmd) = &on_receive_cmd {
+                if cmd.trim().is_empty() {
+                    CommandError::new(ErrorKind::BadRequest, "--on-receive-cmd is empty").exit();
+                }
+                if poll_interval == 0 {
+                    CommandError::new(
+                        ErrorKind::BadRequest,
+                        "--on-receive-cmd needs polling, set --poll-interval above 0",
+                    )
+                    .exit();
+                }
+            }
+            // Fail before listening rather than when the server starts
+            if let (Some(cert), Some(key)) = (&tls_cert, &tls_key)
+                && let Err(e) =
+                    axum_server::tls_rustls::RustlsConfig::from_pem_file(cert, key).await
+            {
+                CommandError::new(
+                    ErrorKind::BadRequest,
+                    format!(
+                        "Failed to load TLS certificate {} and key {}: {}",
+                        cert.display(),
+                        key.display(),
+                        e
+                    ),
+                )
+                .exit();
+            }
+
+            // Call server start function here
+            let addr = listen.unwrap_or(SocketAddr::new(bind, port));
+            println!("Starting server on {}", addr);
+            if !client_limits.is_empty() {
+                println!(
+                    "Per-client limits: {}",
+                    client_limits
+                        .iter()
+                        .map(|cl| format!("{}:{}/{}", cl.name, cl.hourly_limit, cl.daily_limit))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+
+            let store = match db_path {
+                Some(path) => match Store::open(&path) {
+                    Ok(store) => {
+                        println!("Message store: {}", path.display());
+                        if let Some(audit) = audit {
+                            println!("Audit log: {}", audit);
+                        }
+                        Some(store)
+                    }
+                    Err(e) => CommandError::new(
+                        ErrorKind::Internal,
+                        format!("Error opening database {}: {}", path.display(), e),
+                    )
+                    .exit(),
+                },
+                None => None,
+            };
+
+            if args.modem_urls.len() > 1 {
+                println!("Modems (in failover order): {}", args.modem_urls.join(", "));
+                println!("Modem balancing: {}", modem_balance);
+            }
+            let routes = match Routes::from_config(&config.transports, &config.routes) {
+                Ok(routes) => routes,
+                Err(e) => CommandError::new(ErrorKind::BadRequest, format!("Error: {}", e)).exit(),
+            };
+            for route in &config.routes {
+                println!("Route {}*: transport '{}'", route.prefix, route.transport);
+            }
+            let templates = config.templates.clone();
+            let api_keys = config.api_keys.clone();
+            let key_limits = key_limits(&config);
+            if !key_limits.is_empty() {
+                println!(
+                    "Per-key limits: {}",
+                    key_limits
+                        .iter()
+                        .map(|kl| format!("{}:{}/{}", kl.name, kl.hourly_limit, kl.daily_limit))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+            let content_filter = match ContentFilter::from_config(&config.filters) {
+                Ok(filter) => filter,
+                Err(e) => CommandError::new(ErrorKind::BadRequest, format!("Error: {}", e)).exit(),
+            };
+            if !content_filter.is_empty() {
+                println!("Content filter: {} rule(s)", content_filter.len());
+            }
+
+            #[cfg(feature = "alertmanager")]
+            let alerts = match alert_settings(config) {
+                Ok(alerts) => alerts,
+                Err(e) => CommandError::new(ErrorKind::BadRequest, format!("Error: {}", e)).exit(),
+            };
+            #[cfg(feature = "alertmanager")]
+            for (name, receiver) in &alerts.receivers {
+                println!(
+                    "Alert receiver /alertmanager/{}: {} recipient(s){}",
+                    name,
+                    receiver.to.len(),
+                    match &receiver.oncall {
+                        Some(oncall) => format!(" + on-call '{}'", oncall),
+                        None => String::new(),
+                    }
+                );
+            }
+
             let handle = setup_metrics();
             update_limits_metrics(hourly_limit, daily_limit);
             update_client_limits_metrics(&client_limits);
-            let rate_limiter = RateLimiter::new(hourly_limit, daily_limit, client_limits);
+            if recipient_limit > 0 {
+                println!("Per-recipient limit: {}/hour", recipient_limit);
+            }
+            let rate_limiter = RateLimiter::new(hourly_limit, daily_limit, client_limits)
+                .with_key_limits(key_limits)
+                .with_recipient_limit(recipient_limit);
+
+            let listener = match TcpListener::bind(&addr).await {
+                Ok(listener) => listener,
+                Err(e) => CommandError::new(
+                    ErrorKind::Internal,
+                    format!("Failed to listen on {}: {}", addr, e),
+                )
+                .exit(),
+            };
+            #[cfg(feature = "grpc")]
+            let grpc_listener = match grpc_listen {
+                Some(addr) => match TcpListener::bind(&addr).await {
+                    Ok(listener) => Some(listener),
+                    Err(e) => CommandError::new(
+                        ErrorKind::Internal,
+                        format!("Failed to listen for gRPC on {}: {}", addr, e),
+                    )
+                    .exit(),
+                },
+                None => None,
+            };
+            let (tx, rx) = tokio::sync::oneshot::channel();
+            tokio::spawn(async move {
+                crate::server::termination_signal().await;
+                println!("Shutting down, press Ctrl+C again to exit immediately");
+                let _ = tx.send(());
+                crate::server::termination_signal().await;
+                std::process::exit(130);
+            });
+            let config = crate::server::ServerConfig {
+                modem: args.server_modem(modem_check_interval, modem_balance),
+                routes,
+                prometheus_handle: handle,
+                rate_limiter,
+                #[cfg(feature = "alertmanager")]
+                alert_phone_number: alert_to,
+                #[cfg(feature = "alertmanager")]
+                alert_receivers: alerts.receivers,
+                #[cfg(feature = "alertmanager")]
+                silences: alerts.silences,
+                #[cfg(feature = "alertmanager")]
+                oncall: alerts.oncall,
+                #[cfg(feature = "alertmanager")]
+                alert_policies: alerts.policies,
+                tls_cert,
+                tls_key,
+                http_redirect_port,
+                redirect_host,
+                log_sensitive,
+                poll_interval,
+                incoming_webhook: incoming_webhook.map(IncomingWebhook::new),
+                receive_cmd: on_receive_cmd.map(ReceiveCommand::new),
+                inbox_count_interval,
+                traffic_interval,
+                auto_purge,
+                store,
+                admin_token,
+                api_keys,
+                basic_auth: basic_auth_user
+                    .zip(basic_auth_password)
+                    .map(|(username, password)| crate::server::BasicAuth { username, password }),
+                rate_limit_notifier: rate_limit_webhook.map(|url| {
+                    RateLimitNotifier::new(
+                        url,
+                        rate_limit_webhook_format,
+                        Duration::from_secs(rate_limit_webhook_interval),
+                    )
+                }),
+                duplicate_guard: (duplicate_window > 0).then(|| {
+                    DuplicateGuard::new(Duration::from_secs(duplicate_window), duplicate_action)
+                }),
+                templates,
+                content_filter,
+                send_retries,
+                audit,
+                reloader: Some({
+                    let matches = matches.clone();
+                    std::sync::Arc::new(move || reload_serve_config(&matches))
+                }),
+                #[cfg(feature = "grpc")]
+                grpc_listener,
+            };
+            if poll_interval > 0 {
+                println!("SMS polling enabled: every {} seconds", poll_interval);
+            } else {
+                println!("SMS polling disabled");
+            }
+            crate::server::start_server(listener, rx, config).await;
+        }
+        #[cfg(feature = "server")]
+        SmsCommand::GrafanaDashboard => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&crate::grafana::dashboard()).unwrap()
+            );
+        }
+        #[cfg(feature = "server")]
+        SmsCommand::Audit {
+            db_path,
+            client,
+            api_key,
+            to,
+            since,
+            limit,
+        } => {
+            let store = match Store::open(&db_path) {
+                Ok(store) => store,
+                Err(e) => CommandError::new(
+                    ErrorKind::Internal,
+                    format!("Error opening database {}: {}", db_path.display(), e),
+                )
+                .exit(),
+            };
+            let filter = AuditFilter {
+                client,
+                api_key,
+                recipient: to,
+                since: since.map(|age| match chrono::Utc::now().checked_sub_signed(age) {
+                    Some(time) => time.timestamp(),
+                    None => CommandError::new(ErrorKind::BadRequest, "--since is too large").exit(),
+                }),
+                limit,
+            };
+            match store.audit_log(&filter) {
+                Ok(entries) if args.output_format() != OutputFormat::Text => {
+                    output::print(&entries, args.output_format())
+                }
+                Ok(entries) => print_audit_log(&entries),
+                Err(e) => CommandError::new(
+                    ErrorKind::Internal,
+                    format!("Error reading the audit log: {}", e),
+                )
+                .exit(),
+            }
+        }
+        SmsCommand::Network => match network_info(&args).await {
+            Ok(info) if args.output_format() != OutputFormat::Text => {
+                output::print(&info, args.output_format())
+            }
+            Ok(info) => print_network_info(&info),
+            Err(e) => e.exit(),
+        },
+        SmsCommand::Info => match device_info(&args).await {
+            Ok(info) if args.output_format() != OutputFormat::Text => {
+                output::print(&info, args.output_format())
+            }
+            Ok(info) => print_device_info(&info),
+            Err(e) => e.exit(),
+        },
+        SmsCommand::PurgeTrash { boxes } => {
+            for box_type in boxes {
+                match purge_box(&args, box_type.clone()).await {
+                    Ok(_) if args.quiet => {}
+                    Ok(deleted) => println!("Deleted {} messages from {}", deleted, box_type),
+                    Err(e) => e.exit(),
+                }
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Clean {
+            boxes,
+            older_than,
+            dry_run,
+        } => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "Cleaning messages is not supported in remote mode",
+                )
+                .exit();
+            }
+            // Modem dates are in the modem's local time, which is normally ours
+            let before = older_than.map(|age| match chrono::Local::now().checked_sub_signed(age) {
+                Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                None => {
+                    CommandError::new(ErrorKind::BadRequest, "--older-than is too large").exit()
+                }
+            });
+            let modem = args.modem_client();
+            let format = args.output_format();
+            let mut cleaned = Vec::new();
+            for box_type in boxes {
+                let messages =
+                    match client::clean_box(&modem, box_type.clone(), before.as_deref(), dry_run)
+                        .await
+                    {
+                        Ok(messages) => messages,
+                        Err(e) => CommandError::modem("Error cleaning messages", &e).exit(),
+                    };
+                if format != OutputFormat::Text {
+                    cleaned.extend(messages);
+                    continue;
+                }
+                if dry_run {
+                    for m in &messages {
+                        let content: String = m
+                            .content
+                            .lines()
+                            .next()
+                            .unwrap_or("")
+                            .chars()
+                            .take(40)
+                            .collect();
+                        println!("{:>6}  {}  {:<16}  {}", m.index, m.date, m.phone, content);
+                    }
+                }
+                if !args.quiet {
+                    let verb = if dry_run { "Would delete" } else { "Deleted" };
+                    println!("{} {} messages from {}", verb, messages.len(), box_type);
+                }
+            }
+            if format != OutputFormat::Text {
+                output::print(&cleaned, format);
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Signal { watch } => {
+            let format = args.output_format();
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "Signal information is not supported in remote mode",
+                )
+                .exit();
+            }
+            loop {
+                let result = async {
+                    let (session_id, token) =
+                        modem::open_session(args.modem_url(), args.modem_credentials().as_ref())
+                            .await?;
+                    modem::get_signal(args.modem_url(), &session_id, &token).await
+                }
+                .await;
+                match result {
+                    Ok(signal) if format != OutputFormat::Text => output::print(&signal, format),
+                    Ok(signal) => print_signal(&signal),
+                    Err(e) if watch.is_some() => eprintln!("Error getting signal: {}", e),
+                    Err(e) => CommandError::modem("Error getting signal", &e).exit(),
+                }
+                let Some(interval) = watch else { break };
+                tokio::time::sleep(std::time::Duration::from_secs(interval.max(1))).await;
+                // NDJSON stays one reading per line
+                if format != OutputFormat::Ndjson {
+                    println!();
+                }
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Count => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "Message counts are not supported in remote mode",
+                )
+                .exit();
+            }
+            let result = async {
+                let (session_id, token) =
+                    modem::open_session(args.modem_url(), args.modem_credentials().as_ref())
+                        .await?;
+                modem::get_sms_count(args.modem_url(), &session_id, &token).await
+            }
+            .await;
+            match result {
+                Ok(count) if args.output_format() != OutputFormat::Text => {
+                    output::print(&count, args.output_format())
+                }
+                Ok(count) => print_sms_count(&count),
+                Err(e) => CommandError::modem("Error getting message counts", &e).exit(),
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Sca { set } => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "Changing the service center is not supported in remote mode",
+                )
+                .exit();
+            }
+            if let Some(sca) = &set
+                && !is_international_number(sca)
+            {
+                CommandError::new(
+                    ErrorKind::BadRequest,
+                    format!(
+                        "Invalid service center number '{}', expected e.g. +491710760000",
+                        sca
+                    ),
+                )
+                .exit();
+            }
+            let result = async {
+                let credentials = args.modem_credentials();
+                let (session_id, token) =
+                    modem::open_session(args.modem_url(), credentials.as_ref()).await?;
+                let mut sms_config =
+                    modem::get_sms_config(args.modem_url(), &session_id, &token).await?;
+                if let Some(sca) = set {
+                    sms_config.sca = sca;
+                    let (session_id, token) =
+                        modem::open_session(args.modem_url(), credentials.as_ref()).await?;
+                    modem::set_sms_config(args.modem_url(), &session_id, &token, &sms_config)
+                        .await?;
+                }
+                Ok::<_, modem::Error>(sms_config.sca)
+            }
+            .await;
+            match result {
+                Ok(sca) if sca.is_empty() => println!("Service center: not set"),
+                Ok(sca) => println!("Service center: {}", sca),
+                Err(e) => CommandError::modem("Error accessing the SMS config", &e).exit(),
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Outbox { cancel, count } => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "Outbox management is not supported in remote mode",
+                )
+                .exit();
+            }
+            let credentials = args.modem_credentials();
+            if cancel {
+                let result = async {
+                    let (session_id, token) =
+                        modem::open_session(args.modem_url(), credentials.as_ref()).await?;
+                    modem::cancel_send(args.modem_url(), &session_id, &token).await
+                }
+                .await;
+                match result {
+                    Ok(()) if args.quiet => {}
+                    Ok(()) => println!("Cancelled the send in progress"),
+                    Err(e) => CommandError::modem("Error cancelling the send", &e).exit(),
+                }
+                return;
+            }
+            let result = async {
+                let (session_id, token) =
+                    modem::open_session(args.modem_url(), credentials.as_ref()).await?;
+                let progress =
+                    modem::get_send_status(args.modem_url(), &session_id, &token).await?;
+                let params = modem::SmsListParams {
+                    box_type: BoxType::LocalSent,
+                    sort_type: SortType::Date,
+                    read_count: count,
+                    ascending: false,
+                    unread_preferred: false,
+                };
+                let sent =
+                    modem::get_sms_list(args.modem_url(), &session_id, &token, params).await?;
+                Ok::<_, modem::Error>((progress, sent.messages.message))
+            }
+            .await;
+            match result {
+                Ok((progress, messages)) if args.output_format() != OutputFormat::Text => {
+                    output::print(
+                        &serde_json::json!({
+                            "sending": progress,
+                            "messages": messages,
+                        }),
+                        args.output_format(),
+                    )
+                }
+                Ok((progress, messages)) => print_outbox(&progress, &messages),
+                Err(e) => CommandError::modem("Error reading the outbox", &e).exit(),
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Backup { file, boxes } => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "Backups are not supported in remote mode",
+                )
+                .exit();
+            }
+            let credentials = args.modem_credentials();
+            let backup = match backup::backup(args.modem_url(), credentials.as_ref(), &boxes).await
+            {
+                Ok(backup) => backup,
+                Err(e) => CommandError::modem("Error reading messages", &e).exit(),
+            };
+            let json = serde_json::to_string_pretty(&backup).unwrap();
+            if let Err(e) = std::fs::write(&file, json) {
+                CommandError::new(
+                    ErrorKind::Unknown,
+                    format!("Failed to write {}: {}", file.display(), e),
+                )
+                .exit();
+            }
+            if args.quiet {
+                return;
+            }
+            for (name, messages) in &backup.boxes {
+                println!("{:<12} {} messages", format!("{}:", name), messages.len());
+            }
+            println!(
+                "Saved {} messages to {}",
+                backup.message_count(),
+                file.display()
+            );
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Export {
+            file_format,
+            output,
+            boxes,
+        } => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "Exports are not supported in remote mode",
+                )
+                .exit();
+            }
+            let credentials = args.modem_credentials();
+            let backup = match backup::backup(args.modem_url(), credentials.as_ref(), &boxes).await
+            {
+                Ok(backup) => backup,
+                Err(e) => CommandError::modem("Error reading messages", &e).exit(),
+            };
+            let messages = backup.messages(&boxes);
+            let exported = export::export(&messages, file_format);
+            match output {
+                Some(path) => {
+                    if let Err(e) = std::fs::write(&path, exported) {
+                        CommandError::new(
+                            ErrorKind::Unknown,
+                            format!("Failed to write {}: {}", path.display(), e),
+                        )
+                        .exit();
+                    }
+                    if !args.quiet {
+                        eprintln!("Exported {} messages to {}", messages.len(), path.display());
+                    }
+                }
+                None => print!("{}", exported),
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Restore { file, boxes } => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "Restoring backups is not supported in remote mode",
+                )
+                .exit();
+            }
+            let backup: backup::Backup = match std::fs::read_to_string(&file)
+                .map_err(|e| e.to_string())
+                .and_then(|s| serde_json::from_str(&s).map_err(|e| e.to_string()))
+            {
+                Ok(backup) => backup,
+                Err(e) => CommandError::new(
+                    ErrorKind::BadRequest,
+                    format!("Failed to read backup {}: {}", file.display(), e),
+                )
+                .exit(),
+            };
+            let credentials = args.modem_credentials();
+            match backup::restore(args.modem_url(), credentials.as_ref(), &backup, &boxes).await {
+                Ok(_) if args.quiet => {}
+                Ok(restored) => println!("Restored {} messages as drafts", restored),
+                Err(e) => CommandError::modem("Error restoring messages", &e).exit(),
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Traffic => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "Traffic statistics are not supported in remote mode",
+                )
+                .exit();
+            }
+            let result = async {
+                let (session_id, token) =
+                    modem::open_session(args.modem_url(), args.modem_credentials().as_ref())
+                        .await?;
+                modem::get_traffic_statistics(args.modem_url(), &session_id, &token).await
+            }
+            .await;
+            match result {
+                Ok(stats) if args.output_format() != OutputFormat::Text => {
+                    output::print(&stats, args.output_format())
+                }
+                Ok(stats) => print_traffic(&stats),
+                Err(e) => CommandError::modem("Error getting traffic statistics", &e).exit(),
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Data { state } => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "Switching mobile data is not supported in remote mode",
+                )
+                .exit();
+            }
+            let result = async {
+                let credentials = args.modem_credentials();
+                if let Some(state) = state {
+                    let (session_id, token) =
+                        modem::open_session(args.modem_url(), credentials.as_ref()).await?;
+                    modem::set_mobile_data(
+                        args.modem_url(),
+                        &session_id,
+                        &token,
+                        state == DataState::On,
+                    )
+                    .await?;
+                }
+                let (session_id, token) =
+                    modem::open_session(args.modem_url(), credentials.as_ref()).await?;
+                modem::get_mobile_data(args.modem_url(), &session_id, &token).await
+            }
+            .await;
+            match result {
+                Ok(enabled) => println!("Mobile data: {}", if enabled { "on" } else { "off" }),
+                Err(e) => CommandError::modem("Error switching mobile data", &e).exit(),
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Delete { indexes } => {
+            if let Some(remote_url) = &args.remote_url {
+                match delete_remote_sms(remote_url, &indexes).await {
+                    Ok(()) if args.quiet => {}
+                    Ok(()) => println!("Deleted {} message(s)", indexes.len()),
+                    Err(e) => e.exit(),
+                }
+                return;
+            }
+            let result = async {
+                let (session_id, token) =
+                    modem::open_session(args.modem_url(), args.modem_credentials().as_ref())
+                        .await?;
+                modem::delete_sms(args.modem_url(), &session_id, &token, &indexes).await
+            }
+            .await;
+            match result {
+                Ok(()) if args.quiet => {}
+                Ok(()) => println!("Deleted {} message(s)", indexes.len()),
+                Err(e) => CommandError::modem("Error deleting messages", &e).exit(),
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::MarkRead { indexes } => {
+            if let Some(remote_url) = &args.remote_url {
+                match mark_remote_read(remote_url, &indexes).await {
+                    Ok(()) if args.quiet => {}
+                    Ok(()) => println!("Marked {} message(s) as read", indexes.len()),
+                    Err(e) => e.exit(),
+                }
+                return;
+            }
+            let result = async {
+                let (session_id, token) =
+                    modem::open_session(args.modem_url(), args.modem_credentials().as_ref())
+                        .await?;
+                modem::set_read(args.modem_url(), &session_id, &token, &indexes).await
+            }
+            .await;
+            match result {
+                Ok(()) if args.quiet => {}
+                Ok(()) => println!("Marked {} message(s) as read", indexes.len()),
+                Err(e) => CommandError::modem("Error marking messages as read", &e).exit(),
+            }
+        }
+        #[cfg(feature = "tui")]
+        SmsCommand::Tui => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "The terminal inbox is not supported in remote mode",
+                )
+                .exit();
+            }
+            if let Err(e) = crate::tui::run(&args.modem_client()).await {
+                CommandError::new(ErrorKind::Internal, format!("Terminal error: {}", e)).exit();
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::Status => {
+            if args.remote_url.is_some() {
+                CommandError::new(
+                    ErrorKind::NotConfigured,
+                    "The status report is not supported in remote mode, see /status.json",
+                )
+                .exit();
+            }
+            let report = modem_status(args.modem_url(), args.modem_credentials().as_ref()).await;
+            match args.output_format() {
+                OutputFormat::Text => print_status(&report),
+                format => output::print(&report, format),
+            }
+            match report.session {
+                Check::Error(_) => std::process::exit(ErrorKind::ModemUnavailable.exit_code()),
+                Check::Ok(_) if !report.healthy() => {
+                    std::process::exit(ErrorKind::ModemError.exit_code())
+                }
+                Check::Ok(_) => {}
+            }
+        }
+        #[cfg(feature = "modem")]
+        SmsCommand::TestModem => {
+            if let Err(e) = test_modem(args.modem_url(), args.modem_credentials().as_ref()).await {
+                e.exit();
+            }
+        }
+        SmsCommand::Estimate {
+            message,
+            message_file,
+            stdin: _,
+            template,
+            vars,
+            ascii,
+            cost_per_segment,
+        } => {
+            let message = match read_estimate_input(message, message_file, template, vars, &config)
+            {
+                Ok(m) if ascii => encoding::transliterate(&m),
+                Ok(m) => m,
+                Err(e) => e.exit(),
+            };
+            let mut costs: Vec<(String, f64)> = config.costs.into_iter().collect();
+            costs.sort_by(|a, b| a.0.cmp(&b.0));
+            if let Some(cost) = cost_per_segment {
+                costs.insert(0, ("default".to_string(), cost));
+            }
+            print_estimate(&message, &costs, args.output_format());
+        }
+        SmsCommand::Config { .. } | SmsCommand::Completions { .. } => {
+            unreachable!("handled above")
+        }
+    }
+}
+
+/// Recipients allowed per bulk send if neither `--max-recipients` nor the
+/// config file sets a limit
+const DEFAULT_MAX_RECIPIENTS: usize = 500;
+
+/// The bulk send limit: `--max-recipients` if given, which may only lower the
+/// config file's `max_recipients`
+fn bulk_max_recipients(flag: Option<usize>, config: &Config) -> Result<usize, CommandError> {
+    match (flag, config.max_recipients) {
+        (Some(flag), Some(max)) if flag > max => Err(CommandError::new(
+            ErrorKind::BadRequest,
+            format!(
+                "--max-recipients {} exceeds max_recipients = {} from the config file",
+                flag, max
+            ),
+        )),
+        (flag, max) => Ok(flag.or(max).unwrap_or(DEFAULT_MAX_RECIPIENTS)),
+    }
+}
+
+/// The config file's template `name`, or an error naming the defined templates
+fn config_template<'a>(config: &'a Config, name: &str) -> Result<&'a str, CommandError> {
+    config.template(name).map_err(|e| {
+        let mut names: Vec<&str> = config.templates.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        let defined = if names.is_empty() {
+            "no templates are defined in the config file".to_string()
+        } else {
+            format!("defined templates: {}", names.join(", "))
+        };
+        CommandError::new(ErrorKind::BadRequest, format!("{}; {}", e, defined))
+    })
+}
+
+/// Renders `template` with `vars`, telling how to pass missing variables
+fn render_vars(template: &str, vars: &HashMap<String, String>) -> Result<String, CommandError> {
+    template::render(template, vars).map_err(|e| {
+        let hint: Vec<String> = template::placeholders(template)
+            .into_iter()
+            .filter(|name| !vars.contains_key(*name))
+            .map(|name| format!("--var {}=...", name))
+            .collect();
+        CommandError::new(
+            ErrorKind::BadRequest,
+            format!("{} (pass {})", e, hint.join(" ")),
+        )
+    })
+}
+
+/// Message to estimate: the argument, a file, a rendered template or stdin
+fn read_estimate_input(
+    message: Option<String>,
+    message_file: Option<std::path::PathBuf>,
+    template: Option<String>,
+    vars: Vec<(String, String)>,
+    config: &Config,
+) -> Result<String, CommandError> {
+    if let Some(name) = template {
+        let vars: HashMap<String, String> = vars.into_iter().collect();
+        return render_vars(config_template(config, &name)?, &vars);
+    }
+    if let Some(message) = message {
+        return Ok(message);
+    }
+    read_message(message_file.as_deref())
+}
+
+/// Reads a message from `file`, or stdin if `None`
+fn read_message(file: Option<&std::path::Path>) -> Result<String, CommandError> {
+    use std::io::Read;
+
+    let text = match file {
+        Some(path) => std::fs::read_to_string(path).map_err(|e| {
+            CommandError::new(
+                ErrorKind::BadRequest,
+                format!("Failed to read {}: {}", path.display(), e),
+            )
+        })?,
+        None => {
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|e| CommandError::new(ErrorKind::BadRequest, e.to_string()))?;
+            text
+        }
+    };
+    // Drop the newline editors and `echo` add at the end of the input
+    Ok(text.strip_suffix('\n').unwrap_or(&text).to_string())
+}
+
+fn print_estimate(message: &str, costs: &[(String, f64)], format: OutputFormat) {
+    let estimate = encoding::estimate(message);
+    let non_gsm7 = encoding::non_gsm7_chars(message);
+    let characters = message.chars().count();
+    if format != OutputFormat::Text {
+        let costs: serde_json::Map<String, serde_json::Value> = costs
+            .iter()
+            .map(|(route, cost)| (route.clone(), (cost * estimate.segments as f64).into()))
+            .collect();
+        let report = serde_json::json!({
+            "encoding": estimate.encoding,
+            "characters": characters,
+            "units": estimate.units,
+            "segments": estimate.segments,
+            "non_gsm7": non_gsm7,
+            "costs": costs,
+        });
+        output::print(&report, format);
+        return;
+    }
+
+    let per_segment = if estimate.segments == 1 {
+        estimate.encoding.single_limit()
+    } else {
+        estimate.encoding.multipart_limit()
+    };
+    let unit_name = match estimate.encoding {
+        encoding::SmsEncoding::Gsm7 => "septets",
+        encoding::SmsEncoding::Ucs2 => "UTF-16 units",
+    };
+    println!("Encoding:   {}", estimate.encoding);
+    println!(
+        "Characters: {} ({} {})",
+        characters, estimate.units, unit_name
+    );
+    println!(
+        "Segments:   {} (up to {} {} each)",
+        estimate.segments, per_segment, unit_name
+    );
+    if !non_gsm7.is_empty() {
+        let chars: Vec<String> = non_gsm7.iter().map(char::to_string).collect();
+        println!("Characters outside GSM-7: {}", chars.join(" "));
+    }
+    for (route, cost) in costs {
+        println!("Cost ({}): {:.2}", route, cost * estimate.segments as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::parse_filter_date;
+    #[cfg(feature = "modem")]
+    use crate::export::ExportFormat;
+    #[cfg(feature = "modem")]
+    use crate::modem;
+    use clap::Parser;
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_send_short_flags() {
+        let args = Args::try_parse_from([
+            "smser",
+            "--modem-url",
+            "http://test.com",
+            "send",
+            "-t",
+            "1234567890",
+            "-m",
+            "Hello, world!",
+        ])
+        .expect("Failed to parse arguments");
+        assert_eq!(args.modem_url(), "http://test.com");
+        match args.command.clone() {
+            SmsCommand::Send {
+                to,
+                message,
+                dry_run,
+                ..
+            } => {
+                assert_eq!(to, Some("1234567890".to_string()));
+                assert_eq!(message, Some("Hello, world!".to_string()));
+                assert!(!dry_run);
+            }
+            _ => panic!("Expected Send command"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_send_long_flags() {
+        let args = Args::try_parse_from([
+            "smser",
+            "--modem-url",
+            "http://test.com",
+            "send",
+            "--to",
+            "1234567890",
+            "--message",
+            "Hello, world!",
+            "--dry-run",
+        ])
+        .expect("Failed to parse arguments");
+        assert_eq!(args.modem_url(), "http://test.com");
+        match args.command.clone() {
+            SmsCommand::Send {
+                to,
+                message,
+                dry_run,
+                ..
+            } => {
+                assert_eq!(to, Some("1234567890".to_string()));
+                assert_eq!(message, Some("Hello, world!".to_string()));
+                assert!(dry_run);
+            }
+            _ => panic!("Expected Send command"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_send_template() {
+        let args = Args::try_parse_from([
+            "smser",
+            "--config",
+            "/etc/smser.toml",
+            "send",
+            "-t",
+            "1234567890",
+            "--template",
+            "disk-alert",
+            "--var",
+            "host=db1",
+            "--var",
+            "pct=92",
+        ])
+        .expect("Failed to parse arguments");
+        assert_eq!(args.config, Some("/etc/smser.toml".into()));
+        match args.command {
+            SmsCommand::Send {
+                message,
+                template,
+                vars,
+                ..
+            } => {
+                assert_eq!(message, None);
+                assert_eq!(template, Some("disk-alert".to_string()));
+                assert_eq!(
+                    vars,
+                    vec![
+                        ("host".to_string(), "db1".to_string()),
+                        ("pct".to_string(), "92".to_string())
+                    ]
+                );
+            }
+            _ => panic!("Expected Send command"),
+        }
+
+        // --template and --message are mutually exclusive, and one is required
+        assert!(
+            Args::try_parse_from(["smser", "send", "-t", "1", "-m", "x", "--template", "a"])
+                .is_err()
+        );
+        assert!(Args::try_parse_from(["smser", "send", "-t", "1"]).is_err());
+        assert!(
+            Args::try_parse_from(["smser", "send", "-t", "1", "-m", "x", "--var", "bad"]).is_err()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_send_message_input() {
+        let args = Args::try_parse_from(["smser", "send", "-t", "1", "--stdin"]).unwrap();
+        assert!(matches!(args.command, SmsCommand::Send { stdin: true, .. }));
+        let args = Args::try_parse_from(["smser", "send", "-t", "1", "--message-file", "msg.txt"])
+            .unwrap();
+        assert!(matches!(
+            args.command,
+            SmsCommand::Send { message_file: Some(ref path), .. } if path.as_os_str() == "msg.txt"
+        ));
+        // Only one source of the message
+        for conflict in [
+            &["--stdin", "-m", "x"][..],
+            &["--stdin", "--message-file", "msg.txt"],
+            &["--message-file", "msg.txt", "--template", "a"],
+        ] {
+            let argv = ["smser", "send", "-t", "1"].iter().chain(conflict);
+            assert!(Args::try_parse_from(argv).is_err(), "{:?}", conflict);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_estimate_message_input() {
+        let args =
+            Args::try_parse_from(["smser", "estimate", "--message-file", "msg.txt"]).unwrap();
+        assert!(matches!(
+            args.command,
+            SmsCommand::Estimate { message_file: Some(ref path), .. } if path.as_os_str() == "msg.txt"
+        ));
+        let args = Args::try_parse_from(["smser", "estimate", "--stdin"]).unwrap();
+        assert!(matches!(
+            args.command,
+            SmsCommand::Estimate { stdin: true, .. }
+        ));
+        assert!(Args::try_parse_from(["smser", "estimate", "--file", "msg.txt"]).is_err());
+        for conflict in [
+            &["x", "--stdin"][..],
+            &["x", "--message-file", "msg.txt"],
+            &["--stdin", "--message-file", "msg.txt"],
+            &["--stdin", "--template", "a"],
+        ] {
+            let argv = ["smser", "estimate"].iter().chain(conflict);
+            assert!(Args::try_parse_from(argv).is_err(), "{:?}", conflict);
+        }
+    }
+
+    #[test]
+    fn test_read_message() {
+        let path = std::env::temp_dir().join("smser_test_message.txt");
+        std::fs::write(&path, "Line one\nLine two\n").unwrap();
+        assert_eq!(read_message(Some(&path)).unwrap(), "Line one\nLine two");
+        std::fs::remove_file(&path).unwrap();
+        assert!(read_message(Some(&path)).is_err());
+    }
+
+    #[test]
+    fn test_args_parsing_send_recipients_file() {
+        let args = Args::try_parse_from([
+            "smser",
+            "--remote-url",
+            "http://remote-server:5566",
+            "send",
+            "--recipients-file",
+            "list.csv",
+            "-m",
+            "Hello {{name}}",
+            "--report",
+            "report.csv",
+        ])
+        .expect("Failed to parse arguments");
+        match args.command {
+            SmsCommand::Send {
+                to,
+                recipients_file,
+                report,
+                ..
+            } => {
+                assert_eq!(to, None);
+                assert_eq!(recipients_file, Some("list.csv".into()));
+                assert_eq!(report, Some("report.csv".into()));
+            }
+            _ => panic!("Expected Send command"),
+        }
+
+        // Either --to or --recipients-file is required, but not both
+        assert!(Args::try_parse_from(["smser", "send", "-m", "Hello"]).is_err());
+        assert!(
+            Args::try_parse_from([
+                "smser",
+                "send",
+                "-t",
+                "123",
+                "--recipients-file",
+                "list.csv",
+                "-m",
+                "Hello"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_receive() {
+        let args = Args::try_parse_from([
+            "smser",
+            "--modem-url",
+            "http://test.com",
+            "receive",
+            "--count",
+            "50",
+            "--ascending",
+            "--unread-preferred",
+            "--box-type",
+            "local-sent",
+            "--sort-by",
+            "phone",
+            "--json",
+        ])
+        .expect("Failed to parse arguments");
+        assert_eq!(args.modem_url(), "http://test.com");
+        assert_eq!(args.output_format(), OutputFormat::Json);
+        match args.command.clone() {
+            SmsCommand::Receive {
+                count,
+                ascending,
+                unread_preferred,
+                box_type,
+                sort_by,
+                ..
+            } => {
+                assert_eq!(count, 50);
+                assert!(ascending);
+                assert!(unread_preferred);
+                assert_eq!(box_type, BoxType::LocalSent);
+                assert_eq!(sort_by, SortType::Phone);
+            }
+            _ => panic!("Expected Receive command"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_receive_pages() {
+        let args = Args::try_parse_from(["smser", "receive", "--page", "3"]).unwrap();
+        assert!(matches!(
+            args.command,
+            SmsCommand::Receive {
+                page: 3,
+                all: false,
+                ..
+            }
+        ));
+        let args = Args::try_parse_from(["smser", "receive", "--all"]).unwrap();
+        assert!(matches!(
+            args.command,
+            SmsCommand::Receive {
+                page: 1,
+                all: true,
+                ..
+            }
+        ));
+        assert!(Args::try_parse_from(["smser", "receive", "--page", "0"]).is_err());
+        assert!(Args::try_parse_from(["smser", "receive", "--all", "--page", "2"]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_reply() {
+        let args = Args::try_parse_from(["smser", "reply", "--index", "40001", "-m", "On my way"])
+            .expect("Failed to parse arguments");
+        assert_eq!(
+            args.command,
+            SmsCommand::Reply {
+                index: 40001,
+                message: Some("On my way".to_string()),
+                quote: false,
+                edit: false,
+                ascii: false,
+                yes: false,
+                dry_run: false,
+            }
+        );
+        assert!(Args::try_parse_from(["smser", "reply", "--index", "40001"]).is_err());
+        assert!(Args::try_parse_from(["smser", "reply", "--index", "40001", "--edit"]).is_ok());
+        assert!(Args::try_parse_from(["smser", "reply", "-m", "Hi"]).is_err());
+    }
+
+    #[test]
+    fn test_forward_text() {
+        use crate::types::{Priority, SmsType};
+        let original = SmsMessage {
+            smstat: SmsStat::Read,
+            index: 40001,
+            phone: "Vodafone".to_string(),
+            content: "Your data volume is used up.".to_string(),
+            date: "2026-10-16 10:00:00".to_string(),
+            sca: String::new(),
+            save_type: 0,
+            priority: Priority::Normal,
+            sms_type: SmsType::Single,
+        };
+        assert_eq!(
+            forward_text(&original),
+            "Fwd from Vodafone (2026-10-16 10:00:00):\nYour data volume is used up."
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_quiet() {
+        let args = Args::try_parse_from(["smser", "-q", "count"]).unwrap();
+        assert!(args.quiet);
+        let args = Args::try_parse_from(["smser", "delete", "--index", "1", "--quiet"]).unwrap();
+        assert!(args.quiet);
+        temp_env::with_var("SMSER_QUIET", Some("true"), || {
+            assert!(Args::try_parse_from(["smser", "count"]).unwrap().quiet);
+        });
+        assert!(!Args::try_parse_from(["smser", "count"]).unwrap().quiet);
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_log_filter() {
+        temp_env::with_var("RUST_LOG", None::<&str>, || {
+            let filter = |argv: &[&str]| {
+                Args::try_parse_from(argv)
+                    .unwrap()
+                    .log_filter("smser=warn")
+                    .to_string()
+            };
+            // Directives are ordered by the filter, so compare with a parsed one
+            let expected = |directives: &str| EnvFilter::new(directives).to_string();
+            assert_eq!(filter(&["smser", "count"]), expected("smser=warn"));
+            assert_eq!(
+                filter(&["smser", "count", "-v"]),
+                expected("smser=debug,tower_http=debug")
+            );
+            assert_eq!(
+                filter(&["smser", "-vv", "count"]),
+                expected("smser=trace,tower_http=trace")
+            );
+            assert_eq!(
+                filter(&["smser", "-vv", "count", "--log-level", "error"]),
+                expected("smser=error,tower_http=error")
+            );
+        });
+        temp_env::with_var("RUST_LOG", Some("smser=info"), || {
+            let args = Args::try_parse_from(["smser", "count"]).unwrap();
+            assert_eq!(args.log_filter("smser=warn").to_string(), "smser=info");
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_dry_run_request() {
+        let modem = client::HttpModem::new("http://192.168.8.1", None);
+        let requests = modem
+            .send_requests(&["+44 1234".to_string()], "Hi & bye")
+            .unwrap();
+        assert_eq!(
+            dry_run_request("Hi & bye", &requests),
+            "Encoding: gsm7, 8 characters, 1 segment(s)\n\n\
+             POST http://192.168.8.1/api/sms/send-sms\n\
+             <request><Index>-1</Index><Phones><Phone>+441234</Phone></Phones><Sca/>\
+             <Content>Hi &amp; bye</Content><Length>8</Length><Reserved>-1</Reserved>\
+             <Date>-1</Date></request>\n"
+        );
+
+        let modem = modem.with_long_messages(crate::segment::LongMessages::Split);
+        let requests = modem
+            .send_requests(&["+441234".to_string()], &"word ".repeat(40))
+            .unwrap();
+        assert_eq!(requests.len(), 2);
+    }
+
+    #[test]
+    fn test_bulk_max_recipients() {
+        let mut config = Config::default();
+        assert_eq!(bulk_max_recipients(None, &config).unwrap(), 500);
+        assert_eq!(bulk_max_recipients(Some(2000), &config).unwrap(), 2000);
+
+        config.max_recipients = Some(100);
+        assert_eq!(bulk_max_recipients(None, &config).unwrap(), 100);
+        assert_eq!(bulk_max_recipients(Some(20), &config).unwrap(), 20);
+        let error = bulk_max_recipients(Some(500), &config).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::BadRequest);
+        assert_eq!(
+            error.message,
+            "--max-recipients 500 exceeds max_recipients = 100 from the config file"
+        );
+    }
+
+    #[test]
+    fn test_bulk_message() {
+        let mut options = BulkOptions {
+            vars: HashMap::new(),
+            expand: false,
+            report: None,
+            client: None,
+            dry_run: false,
+            yes: false,
+            confirm_threshold: 10,
+            max_recipients: 500,
+            cost_per_segment: None,
+            ascii: false,
+            flash: false,
+        };
+        let plain = bulk::Recipient {
+            phone: "+441234".to_string(),
+            vars: HashMap::new(),
+        };
+        let named = bulk::Recipient {
+            phone: "+441234".to_string(),
+            vars: HashMap::from([("name".to_string(), "Jo".to_string())]),
+        };
+        // Without variables, literal braces are sent as is
+        assert_eq!(
+            bulk_message("Use {{x}} literally", &plain, &options).unwrap(),
+            "Use {{x}} literally"
+        );
+        assert_eq!(
+            bulk_message("Hi {{name}}", &named, &options).unwrap(),
+            "Hi Jo"
+        );
+        assert!(bulk_message("Hi {{name}} {{day}}", &named, &options).is_err());
+
+        options.expand = true;
+        assert!(bulk_message("Use {{x}} literally", &plain, &options).is_err());
+    }
+
+    #[test]
+    fn test_template_errors() {
+        let mut config = Config::default();
+        assert_eq!(
+            config_template(&config, "disk-alert").unwrap_err().message,
+            "Unknown template 'disk-alert'; no templates are defined in the config file"
+        );
+        config.templates = HashMap::from([
+            ("disk-alert".to_string(), "{{host}} at {{pct}}%".to_string()),
+            ("backup".to_string(), "Backup done".to_string()),
+        ]);
+        assert_eq!(
+            config_template(&config, "disk").unwrap_err().message,
+            "Unknown template 'disk'; defined templates: backup, disk-alert"
+        );
+
+        let template = config_template(&config, "disk-alert").unwrap();
+        let vars = HashMap::from([("host".to_string(), "db1".to_string())]);
+        let error = render_vars(template, &vars).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::BadRequest);
+        assert_eq!(
+            error.message,
+            "Missing template variables: pct (pass --var pct=...)"
+        );
+        let vars = HashMap::from([
+            ("host".to_string(), "db1".to_string()),
+            ("pct".to_string(), "93".to_string()),
+        ]);
+        assert_eq!(render_vars(template, &vars).unwrap(), "db1 at 93%");
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_clean() {
+        let args = Args::try_parse_from(["smser", "clean", "--older-than", "30d", "--dry-run"])
+            .expect("Failed to parse arguments");
+        assert_eq!(
+            args.command,
+            SmsCommand::Clean {
+                boxes: vec![BoxType::LocalTrash],
+                older_than: Some(chrono::TimeDelta::days(30)),
+                dry_run: true,
+            }
+        );
+        assert!(Args::try_parse_from(["smser", "clean", "--older-than", "30"]).is_err());
+    }
+
+    #[test]
+    fn test_man_pages() {
+        // Generated by build.rs
+        let dir = std::path::Path::new(env!("OUT_DIR")).join("man");
+        assert!(dir.join("smser.1").exists());
+        assert!(dir.join("smser-config-check.1").exists());
+        assert!(!dir.join("smser-help.1").exists());
+        assert!(!dir.join("smser-man.1").exists());
+        let send = std::fs::read_to_string(dir.join("smser-send.1")).unwrap();
+        assert!(send.contains("smser\\-send"));
+    }
+
+    #[test]
+    fn test_completions() {
+        let mut script = Vec::new();
+        clap_complete::generate(
+            clap_complete::Shell::Bash,
+            &mut Args::command(),
+            "smser",
+            &mut script,
+        );
+        let script = String::from_utf8(script).unwrap();
+        assert!(script.contains("complete -F _smser"));
+        assert!(script.contains("--older-than") || !cfg!(feature = "modem"));
+    }
+
+    #[test]
+    fn test_quote_reply() {
+        assert_eq!(
+            quote_reply("Yes, 8pm", "Dinner tonight?\nAt 8?"),
+            "Yes, 8pm\n\n> Dinner tonight?\n> At 8?"
+        );
+    }
+
+    #[test]
+    fn test_receive_filter() {
+        use crate::types::{Priority, SmsType};
+        let message = |phone: &str, content: &str, date: &str, smstat| SmsMessage {
+            smstat,
+            index: 40001,
+            phone: phone.to_string(),
+            content: content.to_string(),
+            date: date.to_string(),
+            sca: String::new(),
+            save_type: 0,
+            priority: Priority::Normal,
+            sms_type: SmsType::Single,
+        };
+        let bank = message(
+            "+49 176 1234567",
+            "Your code is 1234",
+            "2026-10-16 10:00:00",
+            SmsStat::Unread,
+        );
+        let courier = message(
+            "DHL",
+            "Parcel arriving",
+            "2026-10-14 08:30:00",
+            SmsStat::Read,
+        );
+
+        assert!(ReceiveFilter::default().matches(&bank));
+        let from = |from| ReceiveFilter {
+            from: Some(from),
+            ..Default::default()
+        };
+        assert!(from("01761234567").matches(&bank));
+        assert!(from("+491761234567").matches(&bank));
+        assert!(!from("01761234568").matches(&bank));
+        assert!(from("dhl").matches(&courier));
+        assert!(!from("dhl").matches(&bank));
+
+        let unread = ReceiveFilter {
+            unread_only: true,
+            ..Default::default()
+        };
+        assert!(unread.matches(&bank));
+        assert!(!unread.matches(&courier));
+
+        let grep = ReceiveFilter {
+            grep: Some("PARCEL"),
+            ..Default::default()
+        };
+        assert!(grep.matches(&courier));
+        assert!(!grep.matches(&bank));
+
+        let oct15 = ReceiveFilter {
+            since: Some("2026-10-15"),
+            ..Default::default()
+        };
+        assert!(oct15.matches(&bank));
+        assert!(!oct15.matches(&courier));
+        let until_oct16 = ReceiveFilter {
+            until: Some("2026-10-16"),
+            ..Default::default()
+        };
+        assert!(until_oct16.matches(&bank));
+        let until_morning = ReceiveFilter {
+            until: Some("2026-10-16 09:00:00"),
+            ..Default::default()
+        };
+        assert!(!until_morning.matches(&bank));
+        assert!(until_morning.matches(&courier));
+
+        assert!(parse_filter_date("2026-10-16").is_ok());
+        assert!(parse_filter_date("2026-10-16 10:00:00").is_ok());
+        assert!(parse_filter_date("16.10.2026").is_err());
+        assert!(parse_filter_date("2026-10-16T10:00").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_json_deprecated() {
+        let args = Args::try_parse_from(["smser", "--json", "count"]).unwrap();
+        assert_eq!(args.output_format(), OutputFormat::Json);
+        assert!(Args::try_parse_from(["smser", "count", "--json", "--format", "table"]).is_err());
+        assert!(Args::try_parse_from(["smser", "count", "--json", "--format", "json"]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_count() {
+        let args =
+            Args::try_parse_from(["smser", "count", "--json"]).expect("Failed to parse arguments");
+        assert_eq!(args.command, SmsCommand::Count);
+        assert_eq!(args.output_format(), OutputFormat::Json);
+
+        let args = Args::try_parse_from(["smser", "--format", "ndjson", "count"]).unwrap();
+        assert_eq!(args.output_format(), OutputFormat::Ndjson);
+        let args = Args::try_parse_from(["smser", "count", "--format", "table"]).unwrap();
+        assert_eq!(args.output_format(), OutputFormat::Table);
+        temp_env::with_var("SMSER_FORMAT", Some("table"), || {
+            let args = Args::try_parse_from(["smser", "count"]).unwrap();
+            assert_eq!(args.output_format(), OutputFormat::Table);
+        });
+        assert!(Args::try_parse_from(["smser", "--format", "yaml", "count"]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_status_healthy() {
+        let report = StatusReport {
+            modem_url: "http://192.168.8.1".to_string(),
+            session: Check::Ok(12),
+            sim: Some(Check::Ok(modem::PinStatus {
+                sim_state: 257,
+                ..Default::default()
+            })),
+            signal: Some(Check::Ok(modem::SignalInfo::default())),
+            messages: Some(Check::Ok(modem::SmsCount::default())),
+            smsc: Some(Check::Ok("+491710760000".to_string())),
+        };
+        assert!(report.healthy());
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["smsc"]["ok"], "+491710760000");
+
+        let report = StatusReport {
+            sim: Some(Check::Ok(modem::PinStatus {
+                sim_state: 260,
+                ..Default::default()
+            })),
+            ..report
+        };
+        assert!(!report.healthy());
+
+        let report = StatusReport {
+            session: Check::Error("connection refused".to_string()),
+            sim: None,
+            signal: None,
+            messages: None,
+            smsc: None,
+            ..report
+        };
+        assert!(!report.healthy());
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["session"]["error"], "connection refused");
+        assert!(json.get("sim").is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_is_international_number() {
+        assert!(is_international_number("+491710760000"));
+        assert!(!is_international_number("491710760000"));
+        assert!(!is_international_number("+49 171 0760000"));
+        assert!(!is_international_number("+"));
+    }
 
-            let addr = SocketAddr::from(([0, 0, 0, 0], port));
-            let listener = TcpListener::bind(&addr)
-                .await
-                .expect("Failed to bind to port");
-            let (_tx, rx) = tokio::sync::oneshot::channel(); // Create a channel
-            let config = crate::server::ServerConfig {
-                modem_url: args.modem_url,
-                prometheus_handle: handle,
-                rate_limiter,
-                #[cfg(feature = "alertmanager")]
-                alert_phone_number: alert_to,
-                tls_cert,
-                tls_key,
-                http_redirect_port,
-                redirect_host,
-                log_sensitive,
-                poll_interval,
-            };
-            if poll_interval > 0 {
-                println!("SMS polling enabled: every {} seconds", poll_interval);
-            } else {
-                println!("SMS polling disabled");
-            }
-            crate::server::start_server(listener, rx, config).await;
-        }
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(104857600), "100.0 MiB");
+        assert_eq!(format_bytes(5 << 40), "5.0 TiB");
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
     #[cfg(feature = "modem")]
-    use crate::modem;
+    fn test_args_parsing_outbox() {
+        let args = Args::try_parse_from(["smser", "outbox", "--cancel"])
+            .expect("Failed to parse arguments");
+        assert_eq!(
+            args.command,
+            SmsCommand::Outbox {
+                cancel: true,
+                count: 20,
+            }
+        );
+    }
 
     #[test]
     #[cfg(feature = "modem")]
-    fn test_args_parsing_send_short_flags() {
+    fn test_args_parsing_backup() {
+        let args = Args::try_parse_from(["smser", "backup", "--file", "messages.json"])
+            .expect("Failed to parse arguments");
+        match args.command {
+            SmsCommand::Backup { boxes, .. } => assert_eq!(boxes, backup::DEFAULT_BOXES),
+            _ => panic!("Expected Backup command"),
+        }
         let args = Args::try_parse_from([
             "smser",
-            "--modem-url",
-            "http://test.com",
-            "send",
-            "-t",
-            "1234567890",
-            "-m",
-            "Hello, world!",
+            "export",
+            "--file-format",
+            "mbox",
+            "-o",
+            "inbox.mbox",
         ])
-        .expect("Failed to parse arguments");
-        assert_eq!(args.modem_url, "http://test.com");
+        .unwrap();
         match args.command {
-            SmsCommand::Send {
-                to,
-                message,
-                dry_run,
-                ..
+            SmsCommand::Export {
+                file_format,
+                output,
+                boxes,
             } => {
-                assert_eq!(to, "1234567890");
-                assert_eq!(message, "Hello, world!");
-                assert!(!dry_run);
+                assert_eq!(file_format, ExportFormat::Mbox);
+                assert_eq!(output, Some("inbox.mbox".into()));
+                assert_eq!(boxes, [BoxType::LocalInbox]);
             }
-            _ => panic!("Expected Send command"),
+            _ => panic!("Expected Export command"),
         }
-    }
-
-    #[test]
-    #[cfg(feature = "modem")]
-    fn test_args_parsing_send_long_flags() {
         let args = Args::try_parse_from([
             "smser",
-            "--modem-url",
-            "http://test.com",
-            "send",
-            "--to",
-            "1234567890",
-            "--message",
-            "Hello, world!",
-            "--dry-run",
+            "restore",
+            "--file",
+            "messages.json",
+            "--box",
+            "local-inbox",
         ])
         .expect("Failed to parse arguments");
-        assert_eq!(args.modem_url, "http://test.com");
         match args.command {
-            SmsCommand::Send {
-                to,
-                message,
-                dry_run,
-                ..
-            } => {
-                assert_eq!(to, "1234567890");
-                assert_eq!(message, "Hello, world!");
-                assert!(dry_run);
-            }
-            _ => panic!("Expected Send command"),
+            SmsCommand::Restore { boxes, .. } => assert_eq!(boxes, [BoxType::LocalInbox]),
+            _ => panic!("Expected Restore command"),
         }
     }
 
     #[test]
     #[cfg(feature = "modem")]
-    fn test_args_parsing_receive() {
-        let args = Args::try_parse_from([
-            "smser",
-            "--modem-url",
-            "http://test.com",
-            "receive",
-            "--count",
-            "50",
-            "--ascending",
-            "--unread-preferred",
-            "--box-type",
-            "local-sent",
-            "--sort-by",
-            "phone",
-            "--json",
-        ])
-        .expect("Failed to parse arguments");
-        assert_eq!(args.modem_url, "http://test.com");
-        match args.command {
-            SmsCommand::Receive {
-                count,
-                ascending,
-                unread_preferred,
-                box_type,
-                sort_by,
-                json,
-            } => {
-                assert_eq!(count, 50);
-                assert!(ascending);
-                assert!(unread_preferred);
-                assert_eq!(box_type, BoxType::LocalSent);
-                assert_eq!(sort_by, SortType::Phone);
-                assert!(json);
+    fn test_args_parsing_data() {
+        let args =
+            Args::try_parse_from(["smser", "data", "off"]).expect("Failed to parse arguments");
+        assert_eq!(
+            args.command,
+            SmsCommand::Data {
+                state: Some(DataState::Off)
             }
-            _ => panic!("Expected Receive command"),
-        }
+        );
+        let args = Args::try_parse_from(["smser", "data"]).expect("Failed to parse arguments");
+        assert_eq!(args.command, SmsCommand::Data { state: None });
+        assert!(Args::try_parse_from(["smser", "data", "maybe"]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_delete() {
+        let args =
+            Args::try_parse_from(["smser", "delete", "--index", "40001", "--index", "40002"])
+                .expect("Failed to parse arguments");
+        assert_eq!(
+            args.command,
+            SmsCommand::Delete {
+                indexes: vec![40001, 40002]
+            }
+        );
+        assert!(Args::try_parse_from(["smser", "delete"]).is_err());
     }
 
     #[test]
@@ -523,10 +3746,27 @@ mod tests {
         temp_env::with_var("SMSER_MODEM_URL", Some("http://env-modem:8080"), || {
             let args = Args::try_parse_from(["smser", "send", "-t", "1234567890", "-m", "Hello"])
                 .expect("Failed to parse arguments");
-            assert_eq!(args.modem_url, "http://env-modem:8080");
+            assert_eq!(args.modem_url(), "http://env-modem:8080");
         });
     }
 
+    #[test]
+    #[cfg(feature = "modem")]
+    fn test_args_parsing_modem_urls_env() {
+        temp_env::with_var(
+            "SMSER_MODEM_URL",
+            Some("http://192.168.8.1,http://192.168.9.1"),
+            || {
+                let args = Args::try_parse_from(["smser", "count"]).unwrap();
+                assert_eq!(
+                    args.modem_urls,
+                    ["http://192.168.8.1", "http://192.168.9.1"]
+                );
+                assert_eq!(args.modem_url(), "http://192.168.8.1");
+            },
+        );
+    }
+
     #[test]
     #[cfg(feature = "modem")]
     fn test_args_parsing_remote_url() {
@@ -563,7 +3803,7 @@ mod tests {
     fn test_args_parsing_alert_to_env() {
         temp_env::with_var("SMSER_ALERT_TO", Some("+447700900123"), || {
             let args = Args::try_parse_from(["smser", "serve"]).expect("Failed to parse arguments");
-            match args.command {
+            match args.command.clone() {
                 SmsCommand::Serve {
                     #[cfg(feature = "alertmanager")]
                     alert_to,
@@ -576,6 +3816,121 @@ mod tests {
         });
     }
 
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_apply_config() {
+        temp_env::with_vars(
+            [
+                ("SMSER_ALERT_TO", None::<String>),
+                ("SMSER_MODEM_URL", None::<String>),
+                ("SMSER_REMOTE_URL", None::<String>),
+                ("SMSER_DEVICE_PROFILE", None::<String>),
+                ("SMSER_HOURLY_LIMIT", None::<String>),
+                ("SMSER_DAILY_LIMIT", None::<String>),
+                ("SMSER_RECIPIENT_LIMIT", None::<String>),
+                ("SMSER_DUPLICATE_WINDOW", None::<String>),
+                ("SMSER_DUPLICATE_ACTION", None::<String>),
+            ],
+            || {
+                let config = Config {
+                    modem_url: Some("http://10.0.0.1".to_string()),
+                    device_profile: Some("b535".to_string()),
+                    remote_url: Some("http://smser:8080".to_string()),
+                    alert_to: Some("+441234567890".to_string()),
+                    hourly_limit: Some(10),
+                    daily_limit: Some(20),
+                    recipient_limit: Some(5),
+                    duplicate_window: Some(600),
+                    duplicate_action: Some("suppress".to_string()),
+                    ascii: Some(true),
+                    ..Default::default()
+                };
+                let parse = |argv: &[&str]| {
+                    let matches = Args::command().try_get_matches_from(argv).unwrap();
+                    let mut args = Args::from_arg_matches(&matches).unwrap();
+                    apply_config(&mut args, &matches, &config);
+                    args
+                };
+
+                let args = parse(&["smser", "serve", "--daily-limit", "500"]);
+                assert_eq!(args.modem_url(), "http://10.0.0.1");
+                assert_eq!(args.remote_url.as_deref(), Some("http://smser:8080"));
+                assert_eq!(args.device_profile, DeviceProfile::B535);
+                match args.command {
+                    SmsCommand::Serve {
+                        #[cfg(feature = "alertmanager")]
+                        alert_to,
+                        hourly_limit,
+                        daily_limit,
+                        recipient_limit,
+                        duplicate_window,
+                        duplicate_action,
+                        ..
+                    } => {
+                        #[cfg(feature = "alertmanager")]
+                        assert_eq!(alert_to.as_deref(), Some("+441234567890"));
+                        assert_eq!(hourly_limit, 10);
+                        assert_eq!(recipient_limit, 5);
+                        assert_eq!(duplicate_window, 600);
+                        assert_eq!(duplicate_action, DuplicateAction::Suppress);
+                        // Command line options take precedence
+                        assert_eq!(daily_limit, 500);
+                    }
+                    _ => panic!("Expected Serve command"),
+                }
+
+                let args = parse(&[
+                    "smser",
+                    "--modem-url",
+                    "http://test.com",
+                    "--device-profile",
+                    "e3372",
+                    "receive",
+                ]);
+                assert_eq!(args.modem_url(), "http://test.com");
+                assert_eq!(args.device_profile, DeviceProfile::E3372);
+
+                let args = parse(&["smser", "send", "--to", "123", "--message", "hi"]);
+                assert!(matches!(args.command, SmsCommand::Send { ascii: true, .. }));
+            },
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_reload_serve_config() {
+        temp_env::with_vars(
+            [
+                ("SMSER_HOURLY_LIMIT", None::<String>),
+                ("SMSER_DAILY_LIMIT", None::<String>),
+            ],
+            || {
+                let path = std::env::temp_dir().join("smser_test_reload.toml");
+                std::fs::write(&path, "hourly_limit = 10\ndaily_limit = 20\n").unwrap();
+                let matches = Args::command()
+                    .try_get_matches_from([
+                        "smser",
+                        "--config",
+                        path.to_str().unwrap(),
+                        "serve",
+                        "--hourly-limit",
+                        "5",
+                    ])
+                    .unwrap();
+                let reloaded = reload_serve_config(&matches).unwrap();
+                assert_eq!(reloaded.hourly_limit, 5);
+                assert_eq!(reloaded.daily_limit, 20);
+
+                // The file is read again, the command line still wins
+                std::fs::write(&path, "hourly_limit = 10\ndaily_limit = 30\n").unwrap();
+                let reloaded = reload_serve_config(&matches).unwrap();
+                assert_eq!(reloaded.hourly_limit, 5);
+                assert_eq!(reloaded.daily_limit, 30);
+                std::fs::remove_file(&path).unwrap();
+            },
+        );
+    }
+
     #[test]
     #[cfg(feature = "server")]
     fn test_args_parsing_serve() {
@@ -585,6 +3940,10 @@ mod tests {
                 ("SMSER_MODEM_URL", None::<String>),
                 ("SMSER_REMOTE_URL", None::<String>),
                 ("SMSER_PORT", None::<String>),
+                ("SMSER_HOURLY_LIMIT", None::<String>),
+                ("SMSER_DAILY_LIMIT", None::<String>),
+                ("SMSER_TLS_CERT", None::<String>),
+                ("SMSER_TLS_KEY", None::<String>),
             ],
             || {
                 let args = Args::try_parse_from([
@@ -600,8 +3959,8 @@ mod tests {
                     "500",
                 ])
                 .expect("Failed to parse arguments");
-                assert_eq!(args.modem_url, "http://test.com");
-                match args.command {
+                assert_eq!(args.modem_url(), "http://test.com");
+                match args.command.clone() {
                     SmsCommand::Serve {
                         port,
                         #[cfg(feature = "alertmanager")]
@@ -626,6 +3985,178 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "grpc")]
+    fn test_args_parsing_serve_grpc() {
+        temp_env::with_var("SMSER_GRPC_LISTEN", None::<String>, || {
+            let args = Args::try_parse_from(["smser", "serve"]).unwrap();
+            match args.command {
+                SmsCommand::Serve { grpc_listen, .. } => assert_eq!(grpc_listen, None),
+                _ => panic!("Expected Serve command"),
+            }
+            let args = Args::try_parse_from(["smser", "serve", "--grpc-listen", "127.0.0.1:50051"])
+                .expect("Failed to parse arguments");
+            match args.command {
+                SmsCommand::Serve { grpc_listen, .. } => {
+                    assert_eq!(grpc_listen, Some("127.0.0.1:50051".parse().unwrap()));
+                }
+                _ => panic!("Expected Serve command"),
+            }
+            assert!(Args::try_parse_from(["smser", "serve", "--grpc-listen", "50051"]).is_err());
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_args_parsing_serve_tls() {
+        temp_env::with_vars(
+            [
+                ("SMSER_TLS_CERT", None::<String>),
+                ("SMSER_TLS_KEY", None::<String>),
+            ],
+            || {
+                // A certificate without its key would silently serve plain HTTP
+                assert!(
+                    Args::try_parse_from(["smser", "serve", "--tls-cert", "cert.pem"]).is_err()
+                );
+                assert!(Args::try_parse_from(["smser", "serve", "--tls-key", "key.pem"]).is_err());
+                assert!(
+                    Args::try_parse_from([
+                        "smser",
+                        "serve",
+                        "--tls-cert",
+                        "cert.pem",
+                        "--tls-key",
+                        "key.pem"
+                    ])
+                    .is_ok()
+                );
+            },
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_args_parsing_serve_listen() {
+        temp_env::with_vars(
+            [
+                ("SMSER_PORT", None::<String>),
+                ("SMSER_BIND", None::<String>),
+                ("SMSER_LISTEN", None::<String>),
+            ],
+            || {
+                let args = Args::try_parse_from(["smser", "serve"]).unwrap();
+                match args.command {
+                    SmsCommand::Serve { bind, listen, .. } => {
+                        assert_eq!(bind, std::net::IpAddr::from([0, 0, 0, 0]));
+                        assert_eq!(listen, None);
+                    }
+                    _ => panic!("Expected Serve command"),
+                }
+                let args = Args::try_parse_from(["smser", "serve", "--listen", "[::1]:9000"])
+                    .expect("Failed to parse arguments");
+                match args.command {
+                    SmsCommand::Serve { listen, .. } => {
+                        assert_eq!(listen, Some("[::1]:9000".parse().unwrap()));
+                    }
+                    _ => panic!("Expected Serve command"),
+                }
+                // --listen already includes the address and port
+                assert!(
+                    Args::try_parse_from([
+                        "smser",
+                        "serve",
+                        "--listen",
+                        "127.0.0.1:80",
+                        "-p",
+                        "81"
+                    ])
+                    .is_err()
+                );
+                assert!(Args::try_parse_from(["smser", "serve", "--bind", "localhost"]).is_err());
+            },
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_args_parsing_audit() {
+        temp_env::with_var("SMSER_DB_PATH", None::<String>, || {
+            let args = Args::try_parse_from([
+                "smser",
+                "audit",
+                "--db-path",
+                "smser.db",
+                "--api-key",
+                "ops",
+                "--since",
+                "24h",
+            ])
+            .expect("Failed to parse arguments");
+            assert_eq!(
+                args.command,
+                SmsCommand::Audit {
+                    db_path: "smser.db".into(),
+                    client: None,
+                    api_key: Some("ops".to_string()),
+                    to: None,
+                    since: Some(chrono::TimeDelta::hours(24)),
+                    limit: 50,
+                }
+            );
+            assert!(Args::try_parse_from(["smser", "audit"]).is_err());
+            // The audit log is kept in the database
+            assert!(Args::try_parse_from(["smser", "serve", "--audit", "full"]).is_err());
+        });
+    }
+
+    #[test]
+    #[cfg(feature = "server")]
+    fn test_args_parsing_serve_env() {
+        temp_env::with_vars(
+            [
+                ("SMSER_MODEM_URL", Some("http://10.0.0.1")),
+                ("SMSER_PORT", Some("9000")),
+                ("SMSER_HOURLY_LIMIT", Some("50")),
+                ("SMSER_DAILY_LIMIT", Some("500")),
+                ("SMSER_CLIENT_LIMITS", Some("backup:5:20,monitoring:10:100")),
+                ("SMSER_TLS_CERT", Some("/etc/smser/cert.pem")),
+                ("SMSER_TLS_KEY", Some("/etc/smser/key.pem")),
+                ("SMSER_LOG_SENSITIVE", Some("false")),
+                ("SMSER_BIND", Some("127.0.0.1")),
+                ("SMSER_LISTEN", None),
+            ],
+            || {
+                let args = Args::try_parse_from(["smser", "serve", "--daily-limit", "600"])
+                    .expect("Failed to parse arguments");
+                assert_eq!(args.modem_url(), "http://10.0.0.1");
+                match args.command {
+                    SmsCommand::Serve {
+                        port,
+                        hourly_limit,
+                        daily_limit,
+                        client_limits,
+                        tls_cert,
+                        log_sensitive,
+                        bind,
+                        ..
+                    } => {
+                        assert_eq!(port, 9000);
+                        assert_eq!(bind, std::net::IpAddr::from([127, 0, 0, 1]));
+                        assert_eq!(hourly_limit, 50);
+                        // Command line options take precedence
+                        assert_eq!(daily_limit, 600);
+                        assert_eq!(client_limits.len(), 2);
+                        assert_eq!(client_limits[1].name, "monitoring");
+                        assert_eq!(tls_cert, Some("/etc/smser/cert.pem".into()));
+                        assert!(!log_sensitive);
+                    }
+                    _ => panic!("Expected Serve command"),
+                }
+            },
+        );
+    }
+
     // These tests rely on the modem being unavailable, which is typically true during CI/CD or local development without a modem.
     // They verify that the error handling paths are correctly triggered.
 
@@ -636,6 +4167,32 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[tokio::test]
+    #[cfg(feature = "modem")]
+    async fn test_send_message_dry_run_without_modem() {
+        let args = Args::try_parse_from([
+            "smser",
+            "--modem-url",
+            "http://nonexistent.com",
+            "send",
+            "-t",
+            "+441234567890",
+            "-m",
+            "Hi",
+            "--dry-run",
+        ])
+        .unwrap();
+        let outcome = send_message(&args, "+441234567890", "Hi", None, true, false)
+            .await
+            .unwrap();
+        match outcome {
+            SendOutcome::DryRun { request } => {
+                assert!(request.contains("POST http://nonexistent.com/api/sms/send-sms"))
+            }
+            outcome => panic!("expected a dry run, got {:?}", outcome),
+        }
+    }
+
     #[tokio::test]
     #[cfg(feature = "modem")]
     async fn test_send_sms_dry_run() {
@@ -643,7 +4200,7 @@ mod tests {
             "http://nonexistent.com",
             "dummy_session_id",
             "dummy_token",
-            "+12 34 567 890",
+            &["+12 34 567 890", "+44 1234"],
             "Test message",
             true,
         )
@@ -658,7 +4215,7 @@ mod tests {
             "http://nonexistent.com",
             "dummy_session_id",
             "dummy_token",
-            "+1234567890",
+            &["+1234567890"],
             "Test message",
             false,
         )
@@ -0,0 +1,1108 @@
+//! The modem operations used by the server behind the [`ModemClient`] trait,
+//! implemented over HTTP by [`HttpModem`] and in memory by [`MockModem`] for
+//! tests.
+
+use crate::modem::{
+    self, BoxType, Credentials, DeviceInfo, Error, ModemErrorCode, NetworkInfo, Notifications,
+    PinStatus, SmsCount, SmsListParams, SmsListResponse, SmsMessage, SmsMessages, SmsStat,
+    SortType, TrafficStatistics,
+};
+use crate::profile::DeviceProfile;
+use crate::segment::{self, LongMessages};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use tracing::{debug, info, warn};
+
+/// Boxed future returned by [`ModemClient`] methods, so the trait can be used as `dyn`
+pub type ModemFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, Error>> + Send + 'a>>;
+
+/// Session ID and request verification token for talking to the modem
+#[derive(Clone, Debug, PartialEq)]
+pub struct Session {
+    pub id: String,
+    pub token: String,
+    /// Index of the modem the session belongs to, for clients spanning several modems
+    pub modem: usize,
+}
+
+/// A HiLink modem. Calls other than [`get_session`](Self::get_session) need a
+/// session; requests that change something use up its token.
+pub trait ModemClient: Send + Sync {
+    /// URL of the modem, shown on the status page
+    fn url(&self) -> &str;
+
+    /// URL of every modem behind this client, with its health if the client
+    /// keeps track of it
+    fn modems(&self) -> Vec<(String, Option<bool>)> {
+        vec![(self.url().to_string(), None)]
+    }
+
+    /// Fetches a session, logging in if the modem requires it
+    fn get_session(&self) -> ModemFuture<'_, Session>;
+
+    /// Sends `message` to `to`, returning the URL of the modem that sent it
+    fn send_sms<'a>(
+        &'a self,
+        session: &'a Session,
+        to: &'a [String],
+        message: &'a str,
+    ) -> ModemFuture<'a, String>;
+
+    /// Fetches one page of messages, starting at 1
+    fn get_sms_list<'a>(
+        &'a self,
+        session: &'a Session,
+        params: SmsListParams,
+        page: i32,
+    ) -> ModemFuture<'a, SmsListResponse>;
+
+    fn delete_sms<'a>(&'a self, session: &'a Session, indexes: &'a [i32]) -> ModemFuture<'a, ()>;
+
+    fn mark_read<'a>(&'a self, session: &'a Session, indexes: &'a [i32]) -> ModemFuture<'a, ()>;
+
+    fn get_sms_count<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, SmsCount>;
+
+    fn check_notifications<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, Notifications>;
+
+    fn get_device_info<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, DeviceInfo>;
+
+    fn get_network_info<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, NetworkInfo>;
+
+    fn get_traffic_statistics<'a>(
+        &'a self,
+        session: &'a Session,
+    ) -> ModemFuture<'a, TrafficStatistics>;
+
+    fn get_pin_status<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, PinStatus>;
+}
+
+/// [`ModemClient`] talking to a modem's HTTP API
+#[derive(Clone, Debug)]
+pub struct HttpModem {
+    url: String,
+    credentials: Option<Credentials>,
+    profile: DeviceProfile,
+    /// Profile detected on first use when `profile` is `Auto`
+    detected: OnceLock<DeviceProfile>,
+    long_messages: LongMessages,
+}
+
+impl HttpModem {
+    pub fn new(url: impl Into<String>, credentials: Option<Credentials>) -> Self {
+        Self {
+            url: url.into(),
+            credentials,
+            profile: DeviceProfile::Auto,
+            detected: OnceLock::new(),
+            long_messages: LongMessages::default(),
+        }
+    }
+
+    pub fn with_profile(mut self, profile: DeviceProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    pub fn with_long_messages(mut self, long_messages: LongMessages) -> Self {
+        self.long_messages = long_messages;
+        self
+    }
+
+    /// The configured profile, or the one matching the modem's device name
+    pub async fn profile(&self, session: &Session) -> DeviceProfile {
+        if self.profile != DeviceProfile::Auto {
+            return self.profile;
+        }
+        if let Some(profile) = self.detected.get() {
+            return *profile;
+        }
+        match modem::get_device_info(&self.url, &session.id, &session.token).await {
+            Ok(info) => {
+                let name = info.device_name.unwrap_or_default();
+                let profile = DeviceProfile::from_device_name(&name).unwrap_or_else(|| {
+                    debug!("No device profile for {:?}, using e3372", name);
+                    DeviceProfile::E3372
+                });
+                info!("Using device profile {}", profile);
+                *self.detected.get_or_init(|| profile)
+            }
+            // Retried on the next call
+            Err(e) => {
+                debug!("Failed to detect the device profile: {}", e);
+                DeviceProfile::E3372
+            }
+        }
+    }
+
+    /// The texts `message` is sent as, one per request
+    fn parts(&self, message: &str) -> Vec<String> {
+        match self.long_messages {
+            LongMessages::Concatenate => vec![message.to_string()],
+            LongMessages::Split => segment::split(message),
+        }
+    }
+
+    /// URL and XML body of each request `send_sms` would post for `message`
+    pub fn send_requests(
+        &self,
+        to: &[String],
+        message: &str,
+    ) -> Result<Vec<(String, String)>, Error> {
+        self.parts(message)
+            .iter()
+            .map(|part| modem::send_sms_request(&self.url, to, part))
+            .collect()
+    }
+}
+
+impl ModemClient for HttpModem {
+    fn url(&self) -> &str {
+        &self.url
+    }
+
+    fn get_session(&self) -> ModemFuture<'_, Session> {
+        Box::pin(async move {
+            if self.profile.requires_login() && self.credentials.is_none() {
+                return Err(Error::ModemError {
+                    code: ModemErrorCode::LoginRequired,
+                    message: format!("the {} requires a login", self.profile),
+                });
+            }
+            let (id, token) = modem::open_session(&self.url, self.credentials.as_ref()).await?;
+            Ok(Session {
+                id,
+                token,
+                modem: 0,
+            })
+        })
+    }
+
+    fn send_sms<'a>(
+        &'a self,
+        session: &'a Session,
+        to: &'a [String],
+        message: &'a str,
+    ) -> ModemFuture<'a, String> {
+        Box::pin(async move {
+            if self.long_messages == LongMessages::Concatenate {
+                let segments = crate::encoding::estimate(message).segments;
+                if segments > crate::compose::MAX_SEGMENTS {
+                    warn!(
+                        "Message needs {} segments, the modem may truncate it (see --long-messages)",
+                        segments
+                    );
+                }
+            }
+            let parts = self.parts(message);
+            for (i, part) in parts.iter().enumerate() {
+                // Every send uses up the token
+                let fresh;
+                let session = if i == 0 {
+                    session
+                } else {
+                    fresh = self.get_session().await?;
+                    &fresh
+                };
+                modem::send_sms(&self.url, &session.id, &session.token, to, part, false).await?;
+            }
+            Ok(self.url.clone())
+        })
+    }
+
+    fn get_sms_list<'a>(
+        &'a self,
+        session: &'a Session,
+        params: SmsListParams,
+        page: i32,
+    ) -> ModemFuture<'a, SmsListResponse> {
+        Box::pin(async move {
+            let profile = self.profile(session).await;
+            let mut response = modem::get_sms_list_page(
+                &self.url,
+                &session.id,
+                &session.token,
+                params.clone(),
+                page,
+            )
+            .await?;
+            if profile.ignores_sort_order() {
+                sort_messages(&mut response.messages.message, &params);
+            }
+            Ok(response)
+        })
+    }
+
+    fn delete_sms<'a>(&'a self, session: &'a Session, indexes: &'a [i32]) -> ModemFuture<'a, ()> {
+        Box::pin(modem::delete_sms(
+            &self.url,
+            &session.id,
+            &session.token,
+            indexes,
+        ))
+    }
+
+    fn mark_read<'a>(&'a self, session: &'a Session, indexes: &'a [i32]) -> ModemFuture<'a, ()> {
+        Box::pin(modem::set_read(
+            &self.url,
+            &session.id,
+            &session.token,
+            indexes,
+        ))
+    }
+
+    fn get_sms_count<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, SmsCount> {
+        Box::pin(modem::get_sms_count(&self.url, &session.id, &session.token))
+    }
+
+    fn check_notifications<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, Notifications> {
+        Box::pin(modem::check_notifications(
+            &self.url,
+            &session.id,
+            &session.token,
+        ))
+    }
+
+    fn get_device_info<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, DeviceInfo> {
+        Box::pin(modem::get_device_info(
+            &self.url,
+            &session.id,
+            &session.token,
+        ))
+    }
+
+    fn get_network_info<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, NetworkInfo> {
+        Box::pin(modem::get_network_info(
+            &self.url,
+            &session.id,
+            &session.token,
+        ))
+    }
+
+    fn get_traffic_statistics<'a>(
+        &'a self,
+        session: &'a Session,
+    ) -> ModemFuture<'a, TrafficStatistics> {
+        Box::pin(modem::get_traffic_statistics(
+            &self.url,
+            &session.id,
+            &session.token,
+        ))
+    }
+
+    fn get_pin_status<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, PinStatus> {
+        Box::pin(modem::get_pin_status(
+            &self.url,
+            &session.id,
+            &session.token,
+        ))
+    }
+}
+
+/// [`ModemClient`] that keeps the session of `M` and reuses it until the modem
+/// rejects it. A call failing with an expired session or token is retried
+/// once with a fresh session.
+pub struct SessionCache<M> {
+    inner: M,
+    session: Mutex<Option<Session>>,
+}
+
+impl<M: ModemClient> SessionCache<M> {
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            session: Mutex::new(None),
+        }
+    }
+
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    async fn refresh_session(&self) -> Result<Session, Error> {
+        let session = self.inner.get_session().await?;
+        *self.session.lock().unwrap() = Some(session.clone());
+        Ok(session)
+    }
+
+    /// Runs `call` with `session`, and again with a fresh session if the modem rejected it
+    async fn retry<T, F>(&self, session: &Session, call: impl Fn(Session) -> F) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        match call(session.clone()).await {
+            Err(Error::ModemError { code, .. }) if code.is_session_expired() => {
+                debug!("Modem session expired ({}), fetching a new one", code);
+                let session = self.refresh_session().await?;
+                call(session).await
+            }
+            result => result,
+        }
+    }
+}
+
+impl<M: ModemClient> ModemClient for SessionCache<M> {
+    fn url(&self) -> &str {
+        self.inner.url()
+    }
+
+    fn modems(&self) -> Vec<(String, Option<bool>)> {
+        self.inner.modems()
+    }
+
+    fn get_session(&self) -> ModemFuture<'_, Session> {
+        Box::pin(async move {
+            let cached = self.session.lock().unwrap().clone();
+            match cached {
+                Some(session) => Ok(session),
+                None => self.refresh_session().await,
+            }
+        })
+    }
+
+    fn send_sms<'a>(
+        &'a self,
+        session: &'a Session,
+        to: &'a [String],
+        message: &'a str,
+    ) -> ModemFuture<'a, String> {
+        Box::pin(self.retry(session, move |s| async move {
+            self.inner.send_sms(&s, to, message).await
+        }))
+    }
+
+    fn get_sms_list<'a>(
+        &'a self,
+        session: &'a Session,
+        params: SmsListParams,
+        page: i32,
+    ) -> ModemFuture<'a, SmsListResponse> {
+        Box::pin(self.retry(session, move |s| {
+            let params = params.clone();
+            async move { self.inner.get_sms_list(&s, params, page).await }
+        }))
+    }
+
+    fn delete_sms<'a>(&'a self, session: &'a Session, indexes: &'a [i32]) -> ModemFuture<'a, ()> {
+        Box::pin(self.retry(session, move |s| async move {
+            self.inner.delete_sms(&s, indexes).await
+        }))
+    }
+
+    fn mark_read<'a>(&'a self, session: &'a Session, indexes: &'a [i32]) -> ModemFuture<'a, ()> {
+        Box::pin(self.retry(session, move |s| async move {
+            self.inner.mark_read(&s, indexes).await
+        }))
+    }
+
+    fn get_sms_count<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, SmsCount> {
+        Box::pin(self.retry(session, move |s| async move {
+            self.inner.get_sms_count(&s).await
+        }))
+    }
+
+    fn check_notifications<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, Notifications> {
+        Box::pin(self.retry(session, move |s| async move {
+            self.inner.check_notifications(&s).await
+        }))
+    }
+
+    fn get_device_info<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, DeviceInfo> {
+        Box::pin(self.retry(session, move |s| async move {
+            self.inner.get_device_info(&s).await
+        }))
+    }
+
+    fn get_network_info<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, NetworkInfo> {
+        Box::pin(self.retry(session, move |s| async move {
+            self.inner.get_network_info(&s).await
+        }))
+    }
+
+    fn get_traffic_statistics<'a>(
+        &'a self,
+        session: &'a Session,
+    ) -> ModemFuture<'a, TrafficStatistics> {
+        Box::pin(self.retry(session, move |s| async move {
+            self.inner.get_traffic_statistics(&s).await
+        }))
+    }
+
+    fn get_pin_status<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, PinStatus> {
+        Box::pin(self.retry(session, move |s| async move {
+            self.inner.get_pin_status(&s).await
+        }))
+    }
+}
+
+/// Async handle on a modem for applications using smser as a library. Each
+/// call fetches a new session first.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), smser::Error> {
+/// let modem = smser::Modem::new("http://192.168.8.1");
+/// modem.send_sms("+441234567890", "Hello").await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Modem {
+    client: Arc<dyn ModemClient>,
+}
+
+impl Modem {
+    pub fn new(modem_url: impl Into<String>) -> Self {
+        Self::from_client(HttpModem::new(modem_url, None))
+    }
+
+    /// Logs in with these credentials before each request, for modems that require it.
+    pub fn with_credentials(modem_url: impl Into<String>, username: &str, password: &str) -> Self {
+        let credentials = Credentials {
+            username: username.to_string(),
+            password: password.to_string(),
+        };
+        Self::from_client(HttpModem::new(modem_url, Some(credentials)))
+    }
+
+    /// Uses another [`ModemClient`], e.g. a [`MockModem`] in tests
+    pub fn from_client(client: impl ModemClient + 'static) -> Self {
+        Self {
+            client: Arc::new(client),
+        }
+    }
+
+    pub fn modem_url(&self) -> &str {
+        self.client.url()
+    }
+
+    /// Sends an SMS
+    pub async fn send_sms(&self, to: &str, message: &str) -> Result<(), Error> {
+        self.send_sms_to_many(&[to], message).await
+    }
+
+    /// Sends one SMS to several phone numbers in a single modem request.
+    pub async fn send_sms_to_many(&self, to: &[&str], message: &str) -> Result<(), Error> {
+        let to: Vec<String> = to.iter().map(|s| s.to_string()).collect();
+        let session = self.client.get_session().await?;
+        self.client.send_sms(&session, &to, message).await?;
+        Ok(())
+    }
+
+    /// Fetches the first page of messages matching `params`
+    pub async fn get_sms_list(&self, params: SmsListParams) -> Result<Vec<SmsMessage>, Error> {
+        let session = self.client.get_session().await?;
+        let response = self.client.get_sms_list(&session, params, 1).await?;
+        Ok(response.messages.message)
+    }
+
+    /// Fetches the number of messages per box
+    pub async fn get_sms_count(&self) -> Result<SmsCount, Error> {
+        let session = self.client.get_session().await?;
+        self.client.get_sms_count(&session).await
+    }
+
+    /// Deletes the messages with the given indexes
+    pub async fn delete_sms(&self, indexes: &[i32]) -> Result<(), Error> {
+        let session = self.client.get_session().await?;
+        self.client.delete_sms(&session, indexes).await
+    }
+
+    pub async fn get_device_info(&self) -> Result<DeviceInfo, Error> {
+        let session = self.client.get_session().await?;
+        self.client.get_device_info(&session).await
+    }
+
+    pub async fn get_network_info(&self) -> Result<NetworkInfo, Error> {
+        let session = self.client.get_session().await?;
+        self.client.get_network_info(&session).await
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    messages: Vec<(BoxType, SmsMessage)>,
+    sent: Vec<(Vec<String>, String)>,
+    fail_with: Option<i32>,
+    fail_once: Option<i32>,
+    sessions: usize,
+    /// SIM state reported by the PIN status, ready if unset
+    sim_state: Option<i32>,
+}
+
+/// In-memory [`ModemClient`] for tests. Sent messages are recorded instead of
+/// sent, and all calls can be made to fail with a modem error code.
+#[derive(Default)]
+pub struct MockModem {
+    url: String,
+    state: Mutex<MockState>,
+}
+
+impl MockModem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the URL reported by [`ModemClient::url`], "mock" by default
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_string();
+        self
+    }
+
+    /// Adds messages to `box_type`
+    pub fn with_messages(self, box_type: BoxType, messages: Vec<SmsMessage>) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .messages
+            .extend(messages.into_iter().map(|m| (box_type.clone(), m)));
+        self
+    }
+
+    /// Sets the SIM state of the PIN status, see [`PinStatus::sim_state`]
+    pub fn set_sim_state(&self, sim_state: i32) {
+        self.state.lock().unwrap().sim_state = Some(sim_state);
+    }
+
+    /// Makes all following calls fail with the modem error `code`, or succeed again with `None`
+    pub fn fail_with(&self, code: Option<i32>) {
+        self.state.lock().unwrap().fail_with = code;
+    }
+
+    /// Makes only the next call fail with the modem error `code`
+    pub fn fail_once(&self, code: i32) {
+        self.state.lock().unwrap().fail_once = Some(code);
+    }
+
+    /// Number of sessions fetched so far
+    pub fn sessions(&self) -> usize {
+        self.state.lock().unwrap().sessions
+    }
+
+    /// Recipients and text of every message sent so far
+    pub fn sent(&self) -> Vec<(Vec<String>, String)> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    /// Messages currently stored in `box_type`
+    pub fn messages(&self, box_type: BoxType) -> Vec<SmsMessage> {
+        let state = self.state.lock().unwrap();
+        state
+            .messages
+            .iter()
+            .filter(|(b, _)| *b == box_type)
+            .map(|(_, m)| m.clone())
+            .collect()
+    }
+
+    /// Runs `f` on the state unless a failure is configured
+    fn with_state<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(&mut MockState) -> T,
+    ) -> ModemFuture<'static, T> {
+        let mut state = self.state.lock().unwrap();
+        let result = match state.fail_once.take().or(state.fail_with) {
+            Some(code) => Err(Error::ModemError {
+                code: ModemErrorCode::from(code),
+                message: String::new(),
+            }),
+            None => Ok(f(&mut state)),
+        };
+        Box::pin(std::future::ready(result))
+    }
+}
+
+impl ModemClient for MockModem {
+    fn url(&self) -> &str {
+        if self.url.is_empty() {
+            "mock"
+        } else {
+            &self.url
+        }
+    }
+
+    fn get_session(&self) -> ModemFuture<'_, Session> {
+        self.with_state(|state| {
+            state.sessions += 1;
+            Session {
+                id: format!("mock-session-{}", state.sessions),
+                token: "mock-token".to_string(),
+                modem: 0,
+            }
+        })
+    }
+
+    fn send_sms<'a>(
+        &'a self,
+        _session: &'a Session,
+        to: &'a [String],
+        message: &'a str,
+    ) -> ModemFuture<'a, String> {
+        let sms = (to.to_vec(), message.to_string());
+        let url = self.url().to_string();
+        self.with_state(move |state| {
+            state.sent.push(sms);
+            url
+        })
+    }
+
+    fn get_sms_list<'a>(
+        &'a self,
+        _session: &'a Session,
+        params: SmsListParams,
+        page: i32,
+    ) -> ModemFuture<'a, SmsListResponse> {
+        self.with_state(move |state| {
+            let mut messages: Vec<SmsMessage> = state
+                .messages
+                .iter()
+                .filter(|(b, _)| *b == params.box_type)
+                .map(|(_, m)| m.clone())
+                .collect();
+            sort_messages(&mut messages, &params);
+            let count = messages.len() as i32;
+            let per_page = params.read_count as usize;
+            let message = messages
+                .into_iter()
+                .skip((page.max(1) as usize - 1) * per_page)
+                .take(per_page)
+                .collect();
+            SmsListResponse {
+                count,
+                messages: SmsMessages { message },
+            }
+        })
+    }
+
+    fn delete_sms<'a>(&'a self, _session: &'a Session, indexes: &'a [i32]) -> ModemFuture<'a, ()> {
+        let indexes = indexes.to_vec();
+        self.with_state(move |state| {
+            state.messages.retain(|(_, m)| !indexes.contains(&m.index));
+        })
+    }
+
+    fn mark_read<'a>(&'a self, _session: &'a Session, indexes: &'a [i32]) -> ModemFuture<'a, ()> {
+        let indexes = indexes.to_vec();
+        self.with_state(move |state| {
+            for (_, m) in &mut state.messages {
+                if indexes.contains(&m.index) && m.smstat == SmsStat::Unread {
+                    m.smstat = SmsStat::Read;
+                }
+            }
+        })
+    }
+
+    fn get_sms_count<'a>(&'a self, _session: &'a Session) -> ModemFuture<'a, SmsCount> {
+        self.with_state(|state| {
+            let in_box = |box_type: BoxType| {
+                state
+                    .messages
+                    .iter()
+                    .filter(|(b, _)| *b == box_type)
+                    .count() as i32
+            };
+            SmsCount {
+                local_unread: state
+                    .messages
+                    .iter()
+                    .filter(|(b, m)| *b == BoxType::LocalInbox && m.smstat == SmsStat::Unread)
+                    .count() as i32,
+                local_inbox: in_box(BoxType::LocalInbox),
+                local_outbox: in_box(BoxType::LocalSent),
+                local_draft: in_box(BoxType::LocalDraft),
+                local_deleted: in_box(BoxType::LocalTrash),
+                local_max: 500,
+                ..Default::default()
+            }
+        })
+    }
+
+    fn check_notifications<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, Notifications> {
+        let count = self.get_sms_count(session);
+        Box::pin(async move {
+            let count = count.await?;
+            Ok(Notifications {
+                unread_message: count.local_unread,
+                sms_storage_full: count.storage_full() as i32,
+            })
+        })
+    }
+
+    fn get_device_info<'a>(&'a self, _session: &'a Session) -> ModemFuture<'a, DeviceInfo> {
+        self.with_state(|_| DeviceInfo {
+            device_name: Some("Mock".to_string()),
+            ..Default::default()
+        })
+    }
+
+    fn get_network_info<'a>(&'a self, _session: &'a Session) -> ModemFuture<'a, NetworkInfo> {
+        self.with_state(|_| NetworkInfo {
+            operator: Some("Mock".to_string()),
+            connection: Some("connected".to_string()),
+            ..Default::default()
+        })
+    }
+
+    fn get_traffic_statistics<'a>(
+        &'a self,
+        _session: &'a Session,
+    ) -> ModemFuture<'a, TrafficStatistics> {
+        self.with_state(|_| TrafficStatistics::default())
+    }
+
+    fn get_pin_status<'a>(&'a self, _session: &'a Session) -> ModemFuture<'a, PinStatus> {
+        self.with_state(|state| PinStatus {
+            sim_state: state.sim_state.unwrap_or(257),
+            ..Default::default()
+        })
+    }
+}
+
+/// Sorts `messages` the way the modem does for an sms-list request with `params`
+fn sort_messages(messages: &mut [SmsMessage], params: &SmsListParams) {
+    match params.sort_type {
+        SortType::Date => messages.sort_by(|a, b| a.date.cmp(&b.date)),
+        SortType::Phone => messages.sort_by(|a, b| a.phone.cmp(&b.phone)),
+        _ => messages.sort_by_key(|m| m.index),
+    }
+    if !params.ascending {
+        messages.reverse();
+    }
+    if params.unread_preferred {
+        messages.sort_by_key(|m| m.smstat != SmsStat::Unread);
+    }
+}
+
+/// Most messages the modem returns per sms-list page
+const MAX_PAGE_SIZE: u32 = 50;
+
+/// Fetches all messages matching `params`, reading pages of
+/// `params.read_count` messages until the modem's `Count` is reached.
+pub async fn get_all_sms(
+    modem: &dyn ModemClient,
+    params: SmsListParams,
+) -> Result<Vec<SmsMessage>, Error> {
+    let mut messages = Vec::new();
+    for page in 1.. {
+        // sms-list is a POST, which uses up the token
+        let session = modem.get_session().await?;
+        let response = modem.get_sms_list(&session, params.clone(), page).await?;
+        if response.messages.message.is_empty() {
+            break;
+        }
+        messages.extend(response.messages.message);
+        if messages.len() as i32 >= response.count {
+            break;
+        }
+    }
+    Ok(messages)
+}
+
+/// Deletes up to `limit` of the oldest read messages in `box_type`. Returns the
+/// number of deleted messages.
+pub async fn purge_oldest_read(
+    modem: &dyn ModemClient,
+    box_type: BoxType,
+    limit: usize,
+) -> Result<usize, Error> {
+    let mut indexes = Vec::new();
+    for page in 1.. {
+        let session = modem.get_session().await?;
+        let params = SmsListParams {
+            box_type: box_type.clone(),
+            sort_type: SortType::Date,
+            read_count: MAX_PAGE_SIZE,
+            ascending: true,
+            unread_preferred: false,
+        };
+        let response = modem.get_sms_list(&session, params, page).await?;
+        let received = response.messages.message.len();
+        indexes.extend(
+            response
+                .messages
+                .message
+                .iter()
+                .filter(|m| m.smstat == SmsStat::Read)
+                .map(|m| m.index)
+                .take(limit - indexes.len()),
+        );
+        if indexes.len() >= limit || received < MAX_PAGE_SIZE as usize {
+            break;
+        }
+    }
+    if indexes.is_empty() {
+        return Ok(0);
+    }
+    let session = modem.get_session().await?;
+    modem.delete_sms(&session, &indexes).await?;
+    Ok(indexes.len())
+}
+
+/// Deletes the messages in `box_type` dated before `before` (a modem date such
+/// as `2026-10-16 10:00:00`), or all of them if `before` is None. Returns the
+/// deleted messages, or with `dry_run` the ones that would be deleted.
+pub async fn clean_box(
+    modem: &dyn ModemClient,
+    box_type: BoxType,
+    before: Option<&str>,
+    dry_run: bool,
+) -> Result<Vec<SmsMessage>, Error> {
+    let params = SmsListParams {
+        box_type,
+        sort_type: SortType::Date,
+        read_count: MAX_PAGE_SIZE,
+        ascending: true,
+        unread_preferred: false,
+    };
+    let mut messages = get_all_sms(modem, params).await?;
+    // Modem dates sort as strings
+    messages.retain(|m| before.is_none_or(|before| m.date.as_str() < before));
+    if !dry_run {
+        let indexes: Vec<i32> = messages.iter().map(|m| m.index).collect();
+        for chunk in indexes.chunks(MAX_PAGE_SIZE as usize) {
+            // Each delete may invalidate the token
+            let session = modem.get_session().await?;
+            modem.delete_sms(&session, chunk).await?;
+        }
+    }
+    Ok(messages)
+}
+
+/// Deletes all messages in `box_type`, a page at a time. Returns the number
+/// of deleted messages.
+pub async fn purge_box(modem: &dyn ModemClient, box_type: BoxType) -> Result<usize, Error> {
+    let mut deleted = 0;
+    let mut previous = Vec::new();
+    loop {
+        // Each delete may invalidate the token, so fetch a fresh session per page
+        let session = modem.get_session().await?;
+        let params = SmsListParams {
+            box_type: box_type.clone(),
+            sort_type: SortType::Index,
+            read_count: MAX_PAGE_SIZE,
+            ascending: true,
+            unread_preferred: false,
+        };
+        let page = modem.get_sms_list(&session, params, 1).await?;
+        let indexes: Vec<i32> = page.messages.message.iter().map(|m| m.index).collect();
+        if indexes.is_empty() {
+            return Ok(deleted);
+        }
+        if indexes == previous {
+            return Err(Error::Other(format!(
+                "Modem did not delete messages {:?}",
+                indexes
+            )));
+        }
+        let session = modem.get_session().await?;
+        modem.delete_sms(&session, &indexes).await?;
+        deleted += indexes.len();
+        if indexes.len() as i32 >= page.count {
+            return Ok(deleted);
+        }
+        previous = indexes;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modem::{Priority, SmsType};
+
+    fn message(index: i32, smstat: SmsStat, date: &str) -> SmsMessage {
+        SmsMessage {
+            smstat,
+            index,
+            phone: "+491761234567".to_string(),
+            content: format!("Message {}", index),
+            date: date.to_string(),
+            sca: String::new(),
+            save_type: 0,
+            priority: Priority::Normal,
+            sms_type: SmsType::Single,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_purge_box() {
+        let trash = (0..120)
+            .map(|i| message(40000 + i, SmsStat::Read, "2026-10-16 10:00:00"))
+            .collect();
+        let modem = MockModem::new()
+            .with_messages(BoxType::LocalTrash, trash)
+            .with_messages(
+                BoxType::LocalInbox,
+                vec![message(1, SmsStat::Unread, "2026-10-16 10:00:00")],
+            );
+        assert_eq!(purge_box(&modem, BoxType::LocalTrash).await.unwrap(), 120);
+        assert!(modem.messages(BoxType::LocalTrash).is_empty());
+        assert_eq!(modem.messages(BoxType::LocalInbox).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_all_sms() {
+        let inbox = (0..7)
+            .map(|i| message(40000 + i, SmsStat::Read, "2026-10-16 10:00:00"))
+            .collect();
+        let modem = MockModem::new().with_messages(BoxType::LocalInbox, inbox);
+        let params = SmsListParams {
+            box_type: BoxType::LocalInbox,
+            sort_type: SortType::Index,
+            read_count: 3,
+            ascending: true,
+            unread_preferred: false,
+        };
+        let indexes: Vec<i32> = get_all_sms(&modem, params.clone())
+            .await
+            .unwrap()
+            .iter()
+            .map(|m| m.index)
+            .collect();
+        assert_eq!(indexes, (40000..40007).collect::<Vec<_>>());
+
+        let params = SmsListParams {
+            box_type: BoxType::LocalSent,
+            ..params
+        };
+        assert!(get_all_sms(&modem, params).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_clean_box() {
+        let trash = (0..60)
+            .map(|i| {
+                let day = if i < 55 { 1 } else { 20 };
+                message(
+                    40000 + i,
+                    SmsStat::Read,
+                    &format!("2026-09-{:02} 10:00:00", day),
+                )
+            })
+            .collect();
+        let modem = MockModem::new()
+            .with_messages(BoxType::LocalTrash, trash)
+            .with_messages(
+                BoxType::LocalInbox,
+                vec![message(1, SmsStat::Read, "2026-09-01 10:00:00")],
+            );
+
+        let old = clean_box(
+            &modem,
+            BoxType::LocalTrash,
+            Some("2026-09-15 00:00:00"),
+            true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(old.len(), 55);
+        assert_eq!(modem.messages(BoxType::LocalTrash).len(), 60);
+
+        let deleted = clean_box(
+            &modem,
+            BoxType::LocalTrash,
+            Some("2026-09-15 00:00:00"),
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(deleted, old);
+        assert_eq!(modem.messages(BoxType::LocalTrash).len(), 5);
+        assert_eq!(modem.messages(BoxType::LocalInbox).len(), 1);
+
+        let deleted = clean_box(&modem, BoxType::LocalTrash, None, false)
+            .await
+            .unwrap();
+        assert_eq!(deleted.len(), 5);
+        assert!(modem.messages(BoxType::LocalTrash).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_purge_oldest_read() {
+        let modem = MockModem::new().with_messages(
+            BoxType::LocalInbox,
+            vec![
+                message(1, SmsStat::Read, "2026-10-16 12:00:00"),
+                message(2, SmsStat::Unread, "2026-10-14 10:00:00"),
+                message(3, SmsStat::Read, "2026-10-15 10:00:00"),
+                message(4, SmsStat::Read, "2026-10-13 10:00:00"),
+            ],
+        );
+        assert_eq!(
+            purge_oldest_read(&modem, BoxType::LocalInbox, 2)
+                .await
+                .unwrap(),
+            2
+        );
+        let left: Vec<i32> = modem
+            .messages(BoxType::LocalInbox)
+            .iter()
+            .map(|m| m.index)
+            .collect();
+        assert_eq!(left, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_modem_facade() {
+        let modem = Modem::from_client(MockModem::new().with_messages(
+            BoxType::LocalInbox,
+            vec![message(1, SmsStat::Unread, "2026-10-16 10:00:00")],
+        ));
+        modem.send_sms("+1", "Hi").await.unwrap();
+        let params = SmsListParams {
+            box_type: BoxType::LocalInbox,
+            sort_type: SortType::Date,
+            read_count: 20,
+            ascending: false,
+            unread_preferred: false,
+        };
+        assert_eq!(modem.get_sms_list(params.clone()).await.unwrap().len(), 1);
+        modem.delete_sms(&[1]).await.unwrap();
+        assert!(modem.get_sms_list(params).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_session_cache() {
+        let modem = SessionCache::new(MockModem::new());
+        let session = modem.get_session().await.unwrap();
+        assert_eq!(modem.get_session().await.unwrap(), session);
+        assert_eq!(modem.inner().sessions(), 1);
+
+        // An expired session is replaced and the call retried
+        modem.inner().fail_once(125002);
+        modem
+            .send_sms(&session, &["+1".to_string()], "Hi")
+            .await
+            .unwrap();
+        assert_eq!(modem.inner().sent().len(), 1);
+        assert_eq!(modem.inner().sessions(), 2);
+        assert_ne!(modem.get_session().await.unwrap(), session);
+
+        // Other errors are not retried
+        modem.inner().fail_once(113004);
+        let err = modem.get_sms_count(&session).await.unwrap_err();
+        assert_eq!(err.code(), Some(113004));
+        assert_eq!(modem.inner().sessions(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_profile_requires_login() {
+        let modem =
+            HttpModem::new("http://nonexistent.com", None).with_profile(DeviceProfile::B535);
+        let err = modem.get_session().await.unwrap_err();
+        assert_eq!(err.code(), Some(100003));
+    }
+
+    #[tokio::test]
+    async fn test_mock_modem_failure() {
+        let modem = MockModem::new();
+        modem.fail_with(Some(113004));
+        let err = modem.get_session().await.unwrap_err();
+        assert_eq!(err.code(), Some(113004));
+        modem.fail_with(None);
+        let session = modem.get_session().await.unwrap();
+        modem
+            .send_sms(&session, &["+1".to_string()], "Hi")
+            .await
+            .unwrap();
+        assert_eq!(modem.sent(), [(vec!["+1".to_string()], "Hi".to_string())]);
+    }
+}
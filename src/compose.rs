@@ -0,0 +1,141 @@
+use crate::encoding::{self, SegmentEstimate};
+use std::path::Path;
+use std::process::Command;
+
+/// Longest message accepted from the editor, in segments
+pub const MAX_SEGMENTS: usize = 10;
+
+/// Initial contents of the file opened in the editor
+pub fn template(to: &str, message: &str) -> String {
+    format!(
+        "{}\n\n# Message to {}.\n# Lines starting with '#' are ignored.\n",
+        message, to
+    )
+}
+
+/// Message text of an edited template, without comment lines and surrounding whitespace
+pub fn strip_comments(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Checks that a message can be sent and returns how it will be encoded
+pub fn validate(message: &str) -> Result<SegmentEstimate, String> {
+    if message.trim().is_empty() {
+        return Err("message is empty".to_string());
+    }
+    let estimate = encoding::estimate(message);
+    if estimate.segments > MAX_SEGMENTS {
+        return Err(format!(
+            "message needs {} segments, at most {} are allowed",
+            estimate.segments, MAX_SEGMENTS
+        ));
+    }
+    Ok(estimate)
+}
+
+/// Checks that `number` can be a phone number: an optional `+` followed by 3
+/// to 15 digits, without spaces or other separators
+pub fn validate_number(number: &str) -> Result<(), String> {
+    let digits = number.strip_prefix('+').unwrap_or(number);
+    if digits.is_empty() {
+        return Err("number is empty".to_string());
+    }
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!(
+            "'{}' is not a phone number, only digits and a leading '+' are allowed",
+            number
+        ));
+    }
+    if !(3..=15).contains(&digits.len()) {
+        return Err(format!(
+            "'{}' has {} digits, phone numbers have 3 to 15",
+            number,
+            digits.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Editor command line: `$VISUAL`, then `$EDITOR`, then `vi`
+fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|cmd| !cmd.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+fn run_editor(path: &Path) -> Result<(), String> {
+    let command = editor_command();
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .map_err(|e| format!("failed to start editor '{}': {}", command, e))?;
+    if !status.success() {
+        return Err(format!("editor '{}' exited with {}", command, status));
+    }
+    Ok(())
+}
+
+/// Opens `text` in the user's editor and returns the saved contents
+pub fn edit(text: &str) -> Result<String, String> {
+    let path = std::env::temp_dir().join(format!("smser-message-{}.txt", std::process::id()));
+    std::fs::write(&path, text)
+        .map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    let result = run_editor(&path).and_then(|()| {
+        std::fs::read_to_string(&path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))
+    });
+    let _ = std::fs::remove_file(&path);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_comments() {
+        let text = template("+123", "Hello\n# dropped\nworld");
+        assert_eq!(strip_comments(&text), "Hello\nworld");
+        assert_eq!(strip_comments(&template("+123", "")), "");
+        assert_eq!(strip_comments("  #hash tag\n"), "#hash tag");
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate("").is_err());
+        assert!(validate(" \n").is_err());
+        assert_eq!(validate("Hello").unwrap().segments, 1);
+        assert!(validate(&"a".repeat(153 * MAX_SEGMENTS)).is_ok());
+        assert!(validate(&"a".repeat(153 * MAX_SEGMENTS + 1)).is_err());
+    }
+
+    #[test]
+    fn test_validate_number() {
+        assert!(validate_number("+441234567890").is_ok());
+        assert!(validate_number("01761234567").is_ok());
+        assert!(validate_number("112").is_ok());
+        assert!(validate_number("").is_err());
+        assert!(validate_number("+").is_err());
+        assert!(validate_number("+44 1234 567890").is_err());
+        assert!(validate_number("DHL").is_err());
+        assert!(validate_number("12").is_err());
+        assert!(validate_number("+1234567890123456").is_err());
+    }
+
+    #[test]
+    fn test_edit_keeps_text_with_noop_editor() {
+        temp_env::with_vars([("VISUAL", Some("true")), ("EDITOR", None)], || {
+            assert_eq!(edit("Hello\n").unwrap(), "Hello\n");
+        });
+    }
+}
@@ -0,0 +1,753 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Settings read from the smser config file (TOML)
+///
+/// ```toml
+/// modem_url = "http://192.168.8.1"
+/// hourly_limit = 50
+///
+/// [templates]
+/// disk-alert = "Disk usage on {{host}} is at {{pct}}%"
+///
+/// [receivers.sms-oncall-db]
+/// to = ["+441234567890"]
+/// template = "{{status}}: {{alertname}} on {{instance}}"
+///
+/// [quiet_hours]
+/// start = "22:00"
+/// end = "07:00"
+///
+/// [severities.critical]
+/// priority = "urgent"
+/// flash = true
+///
+/// [severities.info]
+/// defer_in_quiet_hours = true
+///
+/// [alertnames.Watchdog]
+/// suppress_for = "12h"
+///
+/// [oncall.db]
+/// members = ["+441234567891", "+441234567892"]
+/// start = "2026-01-05T09:00:00Z"
+/// shift = "7d"
+///
+/// [transports.provider]
+/// kind = "http-provider"
+/// url = "https://sms.example.com/api/send"
+/// fields = { to = "{{to}}", text = "{{message}}" }
+///
+/// [[routes]]
+/// prefix = "+1"
+/// transport = "provider"
+///
+/// [[filters]]
+/// pattern = '(?i)password=\S+'
+/// replace = "password=***"
+///
+/// [[silences]]
+/// cron = "0 2 * * *"
+/// duration = "1h"
+/// matchers = { alertname = "BackupRunning" }
+/// ```
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Modem URL, used unless `--modem-url` or `SMSER_MODEM_URL` is set
+    pub modem_url: Option<String>,
+    /// Modem login username, used unless `--modem-username` or `SMSER_MODEM_USERNAME` is set
+    pub modem_username: Option<String>,
+    /// Modem login password, used unless `--modem-password` or `SMSER_MODEM_PASSWORD` is set
+    pub modem_password: Option<String>,
+    /// Modem firmware profile, e.g. `b535`, used unless `--device-profile` or `SMSER_DEVICE_PROFILE` is set
+    pub device_profile: Option<String>,
+    /// Remote smser server, used unless `--remote-url` or `SMSER_REMOTE_URL` is set
+    pub remote_url: Option<String>,
+    /// API key sent to the remote server, used unless `--api-key` or `SMSER_API_KEY` is set
+    pub api_key: Option<String>,
+    /// API keys accepted by the server, by client name
+    #[serde(default)]
+    pub api_keys: HashMap<String, String>,
+    /// SMS limits of API keys, by the key's name in `api_keys`
+    #[serde(default)]
+    pub api_key_limits: HashMap<String, KeyLimits>,
+    /// Phone number receiving alerts in server mode (`--alert-to`)
+    pub alert_to: Option<String>,
+    /// Hourly SMS limit in server mode (`--hourly-limit`)
+    pub hourly_limit: Option<u32>,
+    /// Daily SMS limit in server mode (`--daily-limit`)
+    pub daily_limit: Option<u32>,
+    /// Hourly SMS limit per recipient number in server mode (`--recipient-limit`)
+    pub recipient_limit: Option<u32>,
+    /// Seconds within which identical messages to a recipient are duplicates (`--duplicate-window`)
+    pub duplicate_window: Option<u64>,
+    /// `reject`, `flag` or `suppress` for duplicates in server mode (`--duplicate-action`)
+    pub duplicate_action: Option<String>,
+    /// Transliterate sent messages to ASCII, like `smser send --ascii`
+    pub ascii: Option<bool>,
+    /// Most recipients of a bulk send; `--max-recipients` can lower but not raise it
+    pub max_recipients: Option<usize>,
+    /// Cost of one SMS segment by route name, shown by `smser estimate`
+    #[serde(default)]
+    pub costs: HashMap<String, f64>,
+    /// Named message templates with `{{variable}}` placeholders
+    #[serde(default)]
+    pub templates: HashMap<String, String>,
+    /// Alertmanager receivers, served at `/alertmanager/{name}`
+    #[serde(default)]
+    pub receivers: HashMap<String, AlertReceiver>,
+    /// Periods during which matching alerts are not sent
+    #[serde(default)]
+    pub silences: Vec<SilenceSpec>,
+    /// Named on-call schedules receivers can send to
+    #[serde(default)]
+    pub oncall: HashMap<String, OnCallSchedule>,
+    /// Daily period during which alerts may be deferred
+    pub quiet_hours: Option<QuietHoursConfig>,
+    /// How alerts are sent, by their `severity` label
+    #[serde(default)]
+    pub severities: HashMap<String, SeverityPolicy>,
+    /// Overrides for alerts with a specific `alertname` label
+    #[serde(default)]
+    pub alertnames: HashMap<String, AlertOverride>,
+    /// Named transports for sending SMS besides the modem at `modem_url`
+    #[serde(default)]
+    pub transports: HashMap<String, TransportConfig>,
+    /// Transport to use by recipient number prefix; the first matching route wins
+    #[serde(default)]
+    pub routes: Vec<RouteConfig>,
+    /// Rules blocking or rewriting outgoing messages in server mode, applied in order
+    #[serde(default)]
+    pub filters: Vec<FilterConfig>,
+}
+
+/// A transport for sending SMS, selected by `kind`
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum TransportConfig {
+    /// Another HiLink modem
+    Hilink {
+        modem_url: String,
+        #[serde(default = "default_modem_username")]
+        username: String,
+        password: Option<String>,
+        device_profile: Option<String>,
+    },
+    /// An SMS provider's HTTP API, called once per recipient with a JSON body
+    HttpProvider {
+        url: String,
+        /// JSON body fields, rendered as templates with `{{to}}` and `{{message}}`
+        fields: HashMap<String, String>,
+        /// Extra request headers, e.g. for authentication
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+    /// A GSM modem on a serial port; not supported, rejected by [`Config::parse`]
+    Serial,
+    /// An SMPP connection to an SMS centre; not supported, rejected by [`Config::parse`]
+    Smpp,
+}
+
+fn default_modem_username() -> String {
+    "admin".to_string()
+}
+
+/// Blocks or rewrites outgoing messages matching `pattern`, a regular
+/// expression, or `keyword`, a word matched case-insensitively
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FilterConfig {
+    pub pattern: Option<String>,
+    pub keyword: Option<String>,
+    /// Text replacing each match (`$1` for capture groups); matching messages
+    /// are blocked if unset
+    pub replace: Option<String>,
+}
+
+/// Sends messages to numbers starting with `prefix` via `transport`
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    pub prefix: String,
+    pub transport: String,
+}
+
+/// Settings replacing the receiver's for one alertname
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AlertOverride {
+    /// Phone numbers receiving the alert instead of the receiver's
+    #[serde(default)]
+    pub to: Vec<String>,
+    /// Message template instead of the receiver's
+    pub template: Option<String>,
+    /// Do not send the alert again with the same status within this time, e.g. "4h"
+    pub suppress_for: Option<String>,
+    /// Send the alert even during quiet hours
+    #[serde(default)]
+    pub quiet_hours_exempt: bool,
+}
+
+/// Hourly and daily SMS limits of an API key, on top of the global ones
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct KeyLimits {
+    pub hourly_limit: u32,
+    pub daily_limit: u32,
+}
+
+/// Daily period in local time, e.g. from "22:00" to "07:00"
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct QuietHoursConfig {
+    pub start: String,
+    pub end: String,
+}
+
+/// How alerts of one severity are sent
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SeverityPolicy {
+    /// `normal`, `interactive`, `urgent` or `emergency`; urgent and emergency
+    /// alerts skip ahead of other queued messages
+    pub priority: Option<String>,
+    /// Hold alerts during quiet hours and send them once quiet hours end
+    #[serde(default)]
+    pub defer_in_quiet_hours: bool,
+    /// Send alerts as flash SMS, shown immediately on the recipient's phone
+    #[serde(default)]
+    pub flash: bool,
+}
+
+/// Who is on call, from either a built-in rotation (`members`, `start` and
+/// `shift`) or an iCal feed whose event summaries name the person on call
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OnCallSchedule {
+    /// Phone numbers taking turns, in order. With `ical_url`, the rotation is
+    /// used when the feed names nobody, e.g. because it cannot be fetched.
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// Start of the first member's shift (RFC 3339)
+    pub start: Option<String>,
+    /// Length of each shift, e.g. "7d"
+    pub shift: Option<String>,
+    /// URL of an iCal feed with one event per shift
+    pub ical_url: Option<String>,
+    /// Phone numbers of the names used in the iCal feed. Summaries that
+    /// already are phone numbers (starting with '+') need no entry.
+    #[serde(default)]
+    pub contacts: HashMap<String, String>,
+}
+
+/// A one-off (`ends_at`) or recurring (`cron` and `duration`) silence.
+/// Also the body of `POST /silences`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SilenceSpec {
+    /// Labels an alert must have to be silenced; empty silences all alerts
+    #[serde(default)]
+    pub matchers: HashMap<String, String>,
+    /// Start of a one-off silence (RFC 3339, defaults to now)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<String>,
+    /// End of a one-off silence (RFC 3339)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<String>,
+    /// Start times of a recurring silence as a cron expression, in local time
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cron: Option<String>,
+    /// Length of each recurring silence, e.g. "30m" or "2h"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+}
+
+/// Where and how alerts for one Alertmanager receiver are sent
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AlertReceiver {
+    /// Phone numbers receiving the alert SMS
+    #[serde(default)]
+    pub to: Vec<String>,
+    /// On-call schedule whose current member also receives the alert SMS
+    pub oncall: Option<String>,
+    /// Message template; alert labels and annotations are available as variables
+    pub template: Option<String>,
+}
+
+impl Config {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(s).map_err(|e| e.to_string())?;
+        for (name, transport) in &config.transports {
+            let kind = match transport {
+                TransportConfig::Serial => "serial",
+                TransportConfig::Smpp => "smpp",
+                _ => continue,
+            };
+            return Err(format!(
+                "Transport '{}': unsupported transport kind '{}', use hilink or http-provider",
+                name, kind
+            ));
+        }
+        for (name, receiver) in &config.receivers {
+            match &receiver.oncall {
+                Some(oncall) if !config.oncall.contains_key(oncall) => {
+                    return Err(format!(
+                        "Receiver '{}' uses unknown on-call schedule '{}'",
+                        name, oncall
+                    ));
+                }
+                None if receiver.to.is_empty() => {
+                    return Err(format!("Receiver '{}' needs 'to' or 'oncall'", name));
+                }
+                _ => {}
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+        Self::parse(&contents).map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+
+    /// Loads the config from `path`, or from the default location if it exists.
+    /// Without either, an empty config is returned.
+    pub fn load_or_default(path: Option<&Path>) -> Result<Self, String> {
+        match path {
+            Some(path) => Self::load(path),
+            None => match default_path().filter(|p| p.exists()) {
+                Some(path) => Self::load(&path),
+                None => Ok(Self::default()),
+            },
+        }
+    }
+
+    /// Settings that parse but contradict each other, for `smser config check`
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        if self.modem_url.is_some() && self.remote_url.is_some() {
+            conflicts.push(
+                "modem_url and remote_url are both set: only `smser serve` uses modem_url"
+                    .to_string(),
+            );
+        }
+        if let (Some(hourly), Some(daily)) = (self.hourly_limit, self.daily_limit)
+            && hourly > daily
+        {
+            conflicts.push(format!(
+                "hourly_limit ({}) is above daily_limit ({}), so it never applies",
+                hourly, daily
+            ));
+        }
+        if self.duplicate_action.is_some() && self.duplicate_window.unwrap_or(0) == 0 {
+            conflicts.push(
+                "duplicate_action has no effect without a duplicate_window above 0".to_string(),
+            );
+        }
+        for route in &self.routes {
+            if !self.transports.contains_key(&route.transport) {
+                conflicts.push(format!(
+                    "Route for '{}' uses unknown transport '{}'",
+                    route.prefix, route.transport
+                ));
+            }
+        }
+        let mut names: Vec<&String> = self.api_keys.keys().collect();
+        names.sort();
+        for name in names {
+            if self.api_keys[name].is_empty() {
+                conflicts.push(format!("API key of '{}' is empty", name));
+            }
+        }
+        let mut names: Vec<&String> = self.api_key_limits.keys().collect();
+        names.sort();
+        for name in names {
+            if !self.api_keys.contains_key(name) {
+                conflicts.push(format!("Limits for unknown API key '{}'", name));
+            }
+        }
+        for (route, cost) in &self.costs {
+            if *cost < 0.0 {
+                conflicts.push(format!("Cost of route '{}' is negative ({})", route, cost));
+            }
+        }
+        conflicts
+    }
+
+    pub fn template(&self, name: &str) -> Result<&str, String> {
+        self.templates
+            .get(name)
+            .map(|t| t.as_str())
+            .ok_or_else(|| format!("Unknown template '{}'", name))
+    }
+}
+
+/// Values written into a new config file by `smser config init`
+#[derive(Clone, Debug, PartialEq)]
+pub struct StarterSettings {
+    pub modem_url: String,
+    pub alert_to: Option<String>,
+    pub hourly_limit: u32,
+    pub daily_limit: u32,
+}
+
+impl Default for StarterSettings {
+    fn default() -> Self {
+        Self {
+            modem_url: "http://192.168.8.1".to_string(),
+            alert_to: None,
+            hourly_limit: 100,
+            daily_limit: 1000,
+        }
+    }
+}
+
+/// A commented config file with `settings` filled in and examples of the
+/// other sections
+pub fn starter_config(settings: &StarterSettings) -> String {
+    let alert_to = match &settings.alert_to {
+        Some(phone) => format!("alert_to = {}", toml_string(phone)),
+        None => "# alert_to = \"+441234567890\"".to_string(),
+    };
+    format!(
+        r#"# smser configuration. Command line options and SMSER_* environment
+# variables take precedence over the settings in this file.
+
+# URL of the HiLink modem
+modem_url = {modem_url}
+
+# Login for modems whose firmware requires it
+# modem_username = "admin"
+# modem_password = "secret"
+
+# Talk to a remote smser server instead of the modem
+# remote_url = "http://smser.example.com:8080"
+# api_key = "change-me"
+
+# Transliterate messages to ASCII so they stay within GSM-7 (send --ascii)
+# ascii = true
+
+# Most recipients of a bulk send; --max-recipients can only lower it
+# max_recipients = 500
+
+# Phone number receiving Alertmanager alerts (serve)
+{alert_to}
+
+# SMS rate limits (serve)
+hourly_limit = {hourly_limit}
+daily_limit = {daily_limit}
+# At most this many SMS per hour to any one number, e.g. during an alert loop
+# recipient_limit = 5
+
+# Drop identical messages to the same recipient within 10 minutes (serve)
+# duplicate_window = 600
+# duplicate_action = "suppress"
+
+# Rewrite or block outgoing messages before they are sent, in order (serve)
+# [[filters]]
+# pattern = '(?i)password=\S+'
+# replace = "password=***"
+# [[filters]]
+# keyword = "confidential"
+
+# API keys required by /send-sms, /get-sms and the endpoints changing messages,
+# by client name (serve). Without any, these endpoints are open.
+# [api_keys]
+# grafana = "change-me"
+# Hourly and daily limits per API key, on top of the global ones (serve)
+# [api_key_limits]
+# grafana = {{ hourly_limit = 20, daily_limit = 100 }}
+
+# Cost of one SMS segment by route, shown by `smser estimate`
+# [costs]
+# domestic = 0.05
+# international = 0.15
+
+# Message templates for `smser send --template NAME`
+# [templates]
+# disk-alert = "Disk usage on {{{{host}}}} is at {{{{pct}}}}%"
+
+# Alertmanager receivers, served at /alertmanager/NAME
+# [receivers.sms-oncall-db]
+# to = ["+441234567890"]
+# template = "{{{{status}}}}: {{{{alertname}}}} on {{{{instance}}}}"
+
+# Alerts received during quiet hours may be deferred, see [severities]
+# [quiet_hours]
+# start = "22:00"
+# end = "07:00"
+
+# [severities.critical]
+# priority = "urgent"
+# flash = true
+
+# [severities.info]
+# defer_in_quiet_hours = true
+
+# [alertnames.Watchdog]
+# suppress_for = "12h"
+
+# [oncall.db]
+# members = ["+441234567891", "+441234567892"]
+# start = "2026-01-05T09:00:00Z"
+# shift = "7d"
+
+# [[silences]]
+# cron = "0 2 * * *"
+# duration = "1h"
+# matchers = {{ alertname = "BackupRunning" }}
+"#,
+        modem_url = toml_string(&settings.modem_url),
+        alert_to = alert_to,
+        hourly_limit = settings.hourly_limit,
+        daily_limit = settings.daily_limit,
+    )
+}
+
+fn toml_string(s: &str) -> String {
+    toml::Value::String(s.to_string()).to_string()
+}
+
+/// `$XDG_CONFIG_HOME/smser/config.toml`, falling back to `~/.config/smser/config.toml`
+pub fn default_path() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))?;
+    Some(base.join("smser").join("config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::parse(
+            r#"
+[templates]
+disk-alert = "Disk usage on {{host}} is at {{pct}}%"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.template("disk-alert").unwrap(),
+            "Disk usage on {{host}} is at {{pct}}%"
+        );
+        assert!(config.template("missing").is_err());
+        assert!(config.receivers.is_empty());
+
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+        assert!(Config::parse("unknown = 1").is_err());
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let config = Config::parse(
+            r#"
+modem_url = "http://192.168.8.1"
+remote_url = "http://smser:8080"
+hourly_limit = 500
+daily_limit = 100
+duplicate_action = "suppress"
+
+[costs]
+default = -0.1
+
+[api_keys]
+grafana = ""
+
+[api_key_limits]
+team-a = { hourly_limit = 10, daily_limit = 50 }
+
+[[routes]]
+prefix = "+49"
+transport = "missing"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.conflicts(),
+            vec![
+                "modem_url and remote_url are both set: only `smser serve` uses modem_url",
+                "hourly_limit (500) is above daily_limit (100), so it never applies",
+                "duplicate_action has no effect without a duplicate_window above 0",
+                "Route for '+49' uses unknown transport 'missing'",
+                "API key of 'grafana' is empty",
+                "Limits for unknown API key 'team-a'",
+                "Cost of route 'default' is negative (-0.1)",
+            ]
+        );
+        let config = Config::parse(&starter_config(&StarterSettings::default())).unwrap();
+        assert!(config.conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_starter_config() {
+        let config = Config::parse(&starter_config(&StarterSettings::default())).unwrap();
+        assert_eq!(config.modem_url.as_deref(), Some("http://192.168.8.1"));
+        assert_eq!(config.alert_to, None);
+        assert_eq!(config.hourly_limit, Some(100));
+
+        let settings = StarterSettings {
+            alert_to: Some("+441234567890".to_string()),
+            ..Default::default()
+        };
+        let starter = starter_config(&settings);
+        assert!(starter.contains("{{host}}"));
+        let config = Config::parse(&starter).unwrap();
+        assert_eq!(config.alert_to.as_deref(), Some("+441234567890"));
+
+        // Uncommenting the examples gives a valid config
+        let uncommented: String = starter
+            .lines()
+            .map(|l| l.strip_prefix("# ").unwrap_or(l))
+            .filter(|l| l.starts_with('[') || l.contains(" = "))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(Config::parse(&uncommented).is_ok(), "{}", uncommented);
+    }
+
+    #[test]
+    fn test_parse_receivers() {
+        let config = Config::parse(
+            r#"
+[receivers.sms-oncall-db]
+to = ["+441234567890", "+441234567891"]
+template = "{{alertname}} on {{instance}}"
+
+[receivers.sms-ops]
+to = ["+441234567892"]
+"#,
+        )
+        .unwrap();
+        let db = &config.receivers["sms-oncall-db"];
+        assert_eq!(db.to.len(), 2);
+        assert_eq!(
+            db.template.as_deref(),
+            Some("{{alertname}} on {{instance}}")
+        );
+        assert_eq!(config.receivers["sms-ops"].template, None);
+        assert!(Config::parse("[receivers.x]\ntemplate = \"t\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_silences() {
+        let config = Config::parse(
+            r#"
+[[silences]]
+cron = "0 2 * * *"
+duration = "1h"
+matchers = { alertname = "BackupRunning" }
+
+[[silences]]
+starts_at = "2026-10-20T08:00:00Z"
+ends_at = "2026-10-20T12:00:00Z"
+comment = "Datacenter maintenance"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.silences.len(), 2);
+        assert_eq!(config.silences[0].cron.as_deref(), Some("0 2 * * *"));
+        assert_eq!(config.silences[0].matchers["alertname"], "BackupRunning");
+        assert!(config.silences[1].matchers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_transports() {
+        let config = Config::parse(
+            r#"
+[transports.second-stick]
+kind = "hilink"
+modem_url = "http://192.168.9.1"
+
+[transports.provider]
+kind = "http-provider"
+url = "https://sms.example.com/api/send"
+fields = { to = "{{to}}", text = "{{message}}" }
+headers = { Authorization = "Bearer secret" }
+
+[[routes]]
+prefix = "+1"
+transport = "provider"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.transports["second-stick"],
+            TransportConfig::Hilink {
+                modem_url: "http://192.168.9.1".to_string(),
+                username: "admin".to_string(),
+                password: None,
+                device_profile: None,
+            }
+        );
+        assert!(matches!(
+            &config.transports["provider"],
+            TransportConfig::HttpProvider { fields, .. } if fields["text"] == "{{message}}"
+        ));
+        assert_eq!(config.routes[0].transport, "provider");
+        // Serial and SMPP transports are not implemented
+        let err = Config::parse("[transports.x]\nkind = \"smpp\"\nhost = \"smsc\"\n").unwrap_err();
+        assert!(err.contains("unsupported transport kind 'smpp'"), "{}", err);
+        let err = Config::parse("[transports.x]\nkind = \"serial\"\n").unwrap_err();
+        assert!(
+            err.contains("unsupported transport kind 'serial'"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        let config = Config::parse(
+            r#"
+[[filters]]
+pattern = '(?i)password=\S+'
+replace = "password=***"
+
+[[filters]]
+keyword = "confidential"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.filters.len(), 2);
+        assert_eq!(config.filters[0].replace.as_deref(), Some("password=***"));
+        assert_eq!(config.filters[1].keyword.as_deref(), Some("confidential"));
+        assert!(Config::parse("[[filters]]\nblock = true\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_oncall() {
+        let config = Config::parse(
+            r#"
+[receivers.sms-oncall-db]
+oncall = "db"
+
+[oncall.db]
+members = ["+441234567891", "+441234567892"]
+start = "2026-01-05T09:00:00Z"
+shift = "7d"
+
+[oncall.ops]
+ical_url = "https://calendar.example.com/ops.ics"
+contacts = { Alice = "+441234567893" }
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.receivers["sms-oncall-db"].oncall.as_deref(),
+            Some("db")
+        );
+        assert!(config.receivers["sms-oncall-db"].to.is_empty());
+        assert_eq!(config.oncall["db"].members.len(), 2);
+        assert_eq!(config.oncall["ops"].contacts["Alice"], "+441234567893");
+
+        assert!(Config::parse("[receivers.x]\noncall = \"missing\"").is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use chrono::{Datelike, TimeDelta, Timelike};
+
+/// A parsed five-field cron expression: minute, hour, day of month, month
+/// and day of week (0 or 7 is Sunday).
+///
+/// Fields support `*`, single values, ranges (`1-5`), steps (`*/15`,
+/// `0-30/10`) and comma separated lists. As in classic cron, if both day of
+/// month and day of week are restricted, a time matches if either does.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("Invalid step '{}'", step))?;
+                if step == 0 {
+                    return Err("Step must be greater than 0".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (parse_value(start, min, max)?, parse_value(end, min, max)?)
+        } else {
+            let value = parse_value(range, min, max)?;
+            // "5/10" means every 10 starting at 5
+            (value, if step > 1 { max } else { value })
+        };
+        if start > end {
+            return Err(format!("Invalid range '{}'", range));
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn parse_value(s: &str, min: u32, max: u32) -> Result<u32, String> {
+    match s.parse() {
+        Ok(value) if (min..=max).contains(&value) => Ok(value),
+        _ => Err(format!("Invalid value '{}' (expected {}-{})", s, min, max)),
+    }
+}
+
+impl Cron {
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, dom, month, dow] = fields[..] else {
+            return Err(format!(
+                "Invalid cron expression '{}': expected 5 fields",
+                expr
+            ));
+        };
+        let err = |e: String| format!("Invalid cron expression '{}': {}", expr, e);
+        let mut days_of_week = parse_field(dow, 0, 7).map_err(err)?;
+        // Sunday can be written as 0 or 7
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59).map_err(err)?,
+            hours: parse_field(hour, 0, 23).map_err(err)?,
+            days_of_month: parse_field(dom, 1, 31).map_err(err)?,
+            months: parse_field(month, 1, 12).map_err(err)?,
+            days_of_week,
+            any_day_of_month: dom.starts_with('*'),
+            any_day_of_week: dow.starts_with('*'),
+        })
+    }
+
+    /// Whether the cron expression fires in the minute of `time`.
+    pub fn matches<T: Datelike + Timelike>(&self, time: &T) -> bool {
+        let bit = |set: u64, value: u32| set & (1 << value) != 0;
+        let dom = bit(self.days_of_month, time.day());
+        let dow = bit(self.days_of_week, time.weekday().num_days_from_sunday());
+        let day = match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => dom || dow,
+            _ => dom && dow,
+        };
+        bit(self.minutes, time.minute())
+            && bit(self.hours, time.hour())
+            && bit(self.months, time.month())
+            && day
+    }
+
+    /// Whether the cron expression fired within `window` before `time`
+    /// (inclusive of the minute of `time`).
+    pub fn fired_within<T>(&self, time: &T, window: TimeDelta) -> bool
+    where
+        T: Datelike + Timelike + Copy + std::ops::Sub<TimeDelta, Output = T>,
+    {
+        (0..window.num_minutes()).any(|m| self.matches(&(*time - TimeDelta::minutes(m))))
+    }
+}
+
+/// Parses a duration such as "90m", "1h30m" or "2d".
+pub fn parse_duration(s: &str) -> Result<TimeDelta, String> {
+    let err = || format!("Invalid duration '{}' (expected e.g. 30m, 2h or 1d)", s);
+    let mut total = TimeDelta::zero();
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let value: i64 = number.parse().map_err(|_| err())?;
+        number.clear();
+        let part = match c {
+            'm' => TimeDelta::try_minutes(value),
+            'h' => TimeDelta::try_hours(value),
+            'd' => TimeDelta::try_days(value),
+            _ => return Err(err()),
+        };
+        total = part
+            .and_then(|part| total.checked_add(&part))
+            .ok_or_else(err)?;
+    }
+    if !number.is_empty() || total <= TimeDelta::zero() {
+        return Err(err());
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveDateTime};
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_cron_matches() {
+        let cron = Cron::parse("0 2 * * *").unwrap();
+        assert!(cron.matches(&at("2026-10-15 02:00")));
+        assert!(!cron.matches(&at("2026-10-15 02:01")));
+
+        // Weekdays, every 15 minutes during office hours
+        let cron = Cron::parse("*/15 9-17 * * 1-5").unwrap();
+        assert!(cron.matches(&at("2026-10-15 09:45"))); // Thursday
+        assert!(!cron.matches(&at("2026-10-15 09:50")));
+        assert!(!cron.matches(&at("2026-10-18 10:00"))); // Sunday
+
+        // Sunday as 7; day of month OR day of week
+        let cron = Cron::parse("0 0 1 * 7").unwrap();
+        assert!(cron.matches(&at("2026-10-18 00:00")));
+        assert!(cron.matches(&at("2026-10-01 00:00")));
+        assert!(!cron.matches(&at("2026-10-02 00:00")));
+
+        assert!(Cron::parse("0 2 * *").is_err());
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+        assert!(Cron::parse("5-1 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_cron_fired_within() {
+        let cron = Cron::parse("0 2 * * *").unwrap();
+        let hour = TimeDelta::hours(1);
+        assert!(cron.fired_within(&at("2026-10-15 02:00"), hour));
+        assert!(cron.fired_within(&at("2026-10-15 02:59"), hour));
+        assert!(!cron.fired_within(&at("2026-10-15 03:00"), hour));
+        assert!(!cron.fired_within(&at("2026-10-15 01:59"), hour));
+        let date = NaiveDate::from_ymd_opt(2026, 10, 16).unwrap();
+        assert!(!cron.fired_within(&date.and_hms_opt(0, 30, 0).unwrap(), hour));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90m").unwrap(), TimeDelta::minutes(90));
+        assert_eq!(parse_duration("1h30m").unwrap(), TimeDelta::minutes(90));
+        assert_eq!(parse_duration("2d").unwrap(), TimeDelta::days(2));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration("5s").is_err());
+        assert!(parse_duration("0m").is_err());
+        // Out of range values are errors, not panics
+        assert!(parse_duration("99999999999999d").is_err());
+        assert!(parse_duration("99999999999d99999999999d").is_err());
+        assert!(parse_duration("99999999999999999999m").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use strum_macros::{Display, EnumString};
+
+/// What to do with a message already sent to the same recipient within the window
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Display, ValueEnum, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum DuplicateAction {
+    /// Refuse the message
+    #[default]
+    Reject,
+    /// Send it anyway, but log and count it as a duplicate
+    Flag,
+    /// Accept the message without sending it, so repeated alerts are dropped
+    /// quietly instead of failing
+    Suppress,
+}
+
+/// Remembers recently sent recipient and message pairs to catch repeated sends
+#[derive(Clone, Debug)]
+pub struct DuplicateGuard {
+    window: Duration,
+    action: DuplicateAction,
+    /// Time each (recipient, message hash) pair was last accepted
+    sent: Arc<Mutex<HashMap<(String, u64), Instant>>>,
+}
+
+impl DuplicateGuard {
+    pub fn new(window: Duration, action: DuplicateAction) -> Self {
+        Self {
+            window,
+            action,
+            sent: Arc::default(),
+        }
+    }
+
+    pub fn action(&self) -> DuplicateAction {
+        self.action
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Records a send of `message` to `to`. Returns how long ago the same pair was
+    /// last recorded if that is within the window. Rejected duplicates are not
+    /// recorded, so the window starts at the first accepted send.
+    pub fn check(&self, to: &str, message: &str, now: Instant) -> Option<Duration> {
+        let mut hasher = DefaultHasher::new();
+        message.hash(&mut hasher);
+        let key = (to.to_string(), hasher.finish());
+
+        let mut sent = self.sent.lock().unwrap();
+        sent.retain(|_, at| now.duration_since(*at) < self.window);
+        let previous = sent.get(&key).map(|at| now.duration_since(*at));
+        if previous.is_none() || self.action == DuplicateAction::Flag {
+            sent.insert(key, now);
+        }
+        previous
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_action_parse() {
+        assert_eq!(
+            "suppress".parse::<DuplicateAction>().unwrap(),
+            DuplicateAction::Suppress
+        );
+        assert!("drop".parse::<DuplicateAction>().is_err());
+    }
+
+    #[test]
+    fn test_duplicate_window() {
+        let guard = DuplicateGuard::new(Duration::from_secs(600), DuplicateAction::Reject);
+        let now = Instant::now();
+        assert_eq!(guard.check("+1", "disk full", now), None);
+        assert_eq!(
+            guard.check("+1", "disk full", now + Duration::from_secs(60)),
+            Some(Duration::from_secs(60))
+        );
+        // Other recipients and messages are not duplicates
+        assert_eq!(guard.check("+2", "disk full", now), None);
+        assert_eq!(guard.check("+1", "disk ok", now), None);
+        // The window starts at the accepted send
+        assert_eq!(
+            guard.check("+1", "disk full", now + Duration::from_secs(600)),
+            None
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use serde::Serialize;
+use strum_macros::Display;
+
+/// Characters of the GSM 03.38 default alphabet
+const GSM7_BASIC: &str = "@£$¥èéùìòÇ\nØø\rÅåΔ_ΦΓΛΩΠΨΣΘΞÆæßÉ !\"#¤%&'()*+,-./0123456789:;<=>?\
+¡ABCDEFGHIJKLMNOPQRSTUVWXYZÄÖÑÜ§¿abcdefghijklmnopqrstuvwxyzäöñüà";
+
+/// Characters of the GSM 03.38 extension table, each taking two septets
+const GSM7_EXTENSION: &str = "\u{0c}^{}\\[~]|€";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Display)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case")]
+pub enum SmsEncoding {
+    Gsm7,
+    Ucs2,
+}
+
+/// How a message will be split into SMS segments
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SegmentEstimate {
+    pub encoding: SmsEncoding,
+    /// Septets for GSM-7, UTF-16 code units for UCS-2
+    pub units: usize,
+    pub segments: usize,
+}
+
+impl SmsEncoding {
+    /// Maximum units in a single-part message
+    pub fn single_limit(self) -> usize {
+        match self {
+            SmsEncoding::Gsm7 => 160,
+            SmsEncoding::Ucs2 => 70,
+        }
+    }
+
+    /// Maximum units per part of a multipart message (the rest is taken by the UDH)
+    pub fn multipart_limit(self) -> usize {
+        match self {
+            SmsEncoding::Gsm7 => 153,
+            SmsEncoding::Ucs2 => 67,
+        }
+    }
+}
+
+/// Number of GSM-7 septets needed for a character, or None if it needs UCS-2
+pub fn gsm7_septets(c: char) -> Option<usize> {
+    if GSM7_BASIC.contains(c) {
+        Some(1)
+    } else if GSM7_EXTENSION.contains(c) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+pub fn is_gsm7(text: &str) -> bool {
+    text.chars().all(|c| gsm7_septets(c).is_some())
+}
+
+/// ASCII replacement for a lowercase letter or a punctuation character
+fn ascii_replacement(c: char) -> Option<&'static str> {
+    Some(match c {
+        'à' | 'á' | 'â' | 'ã' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'ä' | 'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'į' | 'ı' => "i",
+        'ł' | 'ľ' | 'ĺ' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ō' | 'ő' => "o",
+        'ö' | 'ø' | 'œ' => "oe",
+        'ŕ' | 'ř' => "r",
+        'ś' | 'š' | 'ş' | 'ș' => "s",
+        'ß' => "ss",
+        'ť' | 'ţ' | 'ț' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ū' | 'ů' | 'ű' | 'ų' => "u",
+        'ü' => "ue",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        '‘' | '’' | '‚' | '‛' | '′' | '´' | '`' => "'",
+        '“' | '”' | '„' | '‟' | '″' | '«' | '»' => "\"",
+        '‐' | '‑' | '‒' | '–' | '—' | '―' | '−' => "-",
+        '…' => "...",
+        '•' | '·' => "*",
+        '×' => "x",
+        '€' => "EUR",
+        '\u{a0}' | '\u{2002}' | '\u{2003}' | '\u{2009}' | '\u{202f}' => " ",
+        '\u{200b}' | '\u{feff}' => "",
+        _ => return None,
+    })
+}
+
+/// Replaces accented letters (é→e, ü→ue) and typographic punctuation such as smart
+/// quotes and dashes with plain ASCII. Other characters are left unchanged, so the
+/// result may still need UCS-2 (see [`non_gsm7_chars`]).
+pub fn transliterate(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        match ascii_replacement(lower) {
+            Some(replacement) if lower != c => {
+                // Keep the case of the first letter: Ü→Ue
+                let mut chars = replacement.chars();
+                if let Some(first) = chars.next() {
+                    result.extend(first.to_uppercase());
+                    result.push_str(chars.as_str());
+                }
+            }
+            Some(replacement) => result.push_str(replacement),
+            None => result.push(c),
+        }
+    }
+    result
+}
+
+/// Characters of `text` outside the GSM-7 alphabet, which force UCS-2 encoding
+pub fn non_gsm7_chars(text: &str) -> Vec<char> {
+    let mut chars = Vec::new();
+    for c in text.chars() {
+        if gsm7_septets(c).is_none() && !chars.contains(&c) {
+            chars.push(c);
+        }
+    }
+    chars
+}
+
+/// Estimates the encoding and number of segments needed to send `text`.
+pub fn estimate(text: &str) -> SegmentEstimate {
+    let gsm_units: Option<usize> = text.chars().map(gsm7_septets).sum();
+    let (encoding, units) = match gsm_units {
+        Some(units) => (SmsEncoding::Gsm7, units),
+        None => (SmsEncoding::Ucs2, text.encode_utf16().count()),
+    };
+    let segments = if units <= encoding.single_limit() {
+        1
+    } else {
+        units.div_ceil(encoding.multipart_limit())
+    };
+    SegmentEstimate {
+        encoding,
+        units,
+        segments,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_gsm7() {
+        let est = estimate("Hello world");
+        assert_eq!(est.encoding, SmsEncoding::Gsm7);
+        assert_eq!(est.units, 11);
+        assert_eq!(est.segments, 1);
+
+        assert_eq!(estimate(&"a".repeat(160)).segments, 1);
+        assert_eq!(estimate(&"a".repeat(161)).segments, 2);
+        assert_eq!(estimate(&"a".repeat(306)).segments, 2);
+        assert_eq!(estimate(&"a".repeat(307)).segments, 3);
+        // Extension characters count twice
+        assert_eq!(estimate("€[]").units, 6);
+    }
+
+    #[test]
+    fn test_estimate_ucs2() {
+        let est = estimate("Grüße 😀");
+        assert_eq!(est.encoding, SmsEncoding::Ucs2);
+        // The emoji is a surrogate pair
+        assert_eq!(est.units, 8);
+        assert_eq!(estimate(&"ł".repeat(70)).segments, 1);
+        assert_eq!(estimate(&"ł".repeat(71)).segments, 2);
+        assert!(!is_gsm7("ł"));
+        assert!(is_gsm7("äöü"));
+    }
+
+    #[test]
+    fn test_transliterate() {
+        assert_eq!(transliterate("Café Müller"), "Cafe Mueller");
+        assert_eq!(transliterate("Über Łódź"), "Ueber Lodz");
+        assert_eq!(
+            transliterate("“It’s done” – 5€…"),
+            "\"It's done\" - 5EUR..."
+        );
+        assert_eq!(transliterate("ok 😀"), "ok 😀");
+        // One stray smart quote no longer forces UCS-2
+        let text = format!("{}’", "a".repeat(100));
+        assert_eq!(estimate(&text).segments, 2);
+        assert_eq!(estimate(&transliterate(&text)).segments, 1);
+        assert_eq!(non_gsm7_chars("ł’ł"), vec!['ł', '’']);
+    }
+}
@@ -0,0 +1,194 @@
+//! Writing messages read with [`backup`](crate::backup::backup) as mbox, CSV or
+//! JSON, for archiving and for other tools.
+
+pub use crate::args::ExportFormat;
+use crate::bulk::csv_field;
+use crate::modem::{BoxType, SmsMessage};
+use serde::Serialize;
+
+/// A message with the box it was read from, for the JSON export
+#[derive(Serialize)]
+struct BoxedMessage<'a> {
+    #[serde(rename = "Box")]
+    box_type: String,
+    #[serde(flatten)]
+    message: &'a SmsMessage,
+}
+
+/// Formats `messages` in `format`
+pub fn export(messages: &[(BoxType, &SmsMessage)], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Mbox => messages.iter().map(|(b, m)| mbox_entry(b, m)).collect(),
+        ExportFormat::Csv => {
+            let mut csv = String::from("box,index,status,phone,date,content\n");
+            for (box_type, m) in messages {
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{}\n",
+                    box_type,
+                    m.index,
+                    m.smstat,
+                    csv_field(&m.phone),
+                    csv_field(&m.date),
+                    csv_field(&m.content)
+                ));
+            }
+            csv
+        }
+        ExportFormat::Json => {
+            let messages: Vec<_> = messages
+                .iter()
+                .map(|(box_type, message)| BoxedMessage {
+                    box_type: box_type.to_string(),
+                    message,
+                })
+                .collect();
+            serde_json::to_string_pretty(&messages).unwrap() + "\n"
+        }
+    }
+}
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Parts of a modem date such as `2026-10-16 10:00:00`: weekday, day, month
+/// name, year and time
+fn date_parts(date: &str) -> Option<(&'static str, u32, &'static str, i32, &str)> {
+    let (day, time) = date.split_once(' ')?;
+    let mut parts = day.splitn(3, '-');
+    let year: i32 = parts.next()?.parse().ok()?;
+    let month: usize = parts.next()?.parse().ok()?;
+    let day: u32 = parts.next()?.parse().ok()?;
+    let month_name = MONTHS.get(month.checked_sub(1)?)?;
+    // Sakamoto's method
+    const OFFSETS: [i32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let y = if month < 3 { year - 1 } else { year };
+    let weekday = (y + y / 4 - y / 100 + y / 400 + OFFSETS[month - 1] + day as i32).rem_euclid(7);
+    Some((WEEKDAYS[weekday as usize], day, month_name, year, time))
+}
+
+fn mbox_entry(box_type: &BoxType, message: &SmsMessage) -> String {
+    let (from_line, date) = match date_parts(&message.date) {
+        Some((weekday, day, month, year, time)) => (
+            format!("{} {} {:>2} {} {}", weekday, month, day, time, year),
+            // The modem's time zone is unknown, which RFC 5322 writes as -0000
+            format!("{}, {} {} {} {} -0000", weekday, day, month, year, time),
+        ),
+        None => (
+            "Thu Jan  1 00:00:00 1970".to_string(),
+            "Thu, 1 Jan 1970 00:00:00 -0000".to_string(),
+        ),
+    };
+    let sent = matches!(
+        box_type,
+        BoxType::LocalSent | BoxType::SimSent | BoxType::MixSent
+    );
+    let mut entry = format!("From {} {}\n", message.phone, from_line);
+    if sent {
+        entry.push_str(&format!(
+            "To: {}\nSubject: SMS to {}\n",
+            message.phone, message.phone
+        ));
+    } else {
+        entry.push_str(&format!(
+            "From: {}\nSubject: SMS from {}\n",
+            message.phone, message.phone
+        ));
+    }
+    entry.push_str(&format!(
+        "Date: {}\nX-SMS-Box: {}\nX-SMS-Status: {}\n\
+         Content-Type: text/plain; charset=utf-8\nContent-Transfer-Encoding: 8bit\n\n",
+        date, box_type, message.smstat
+    ));
+    for line in message.content.lines() {
+        // mboxrd: quote lines that could be read as the start of the next message
+        if line.trim_start_matches('>').starts_with("From ") {
+            entry.push('>');
+        }
+        entry.push_str(line);
+        entry.push('\n');
+    }
+    entry.push('\n');
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modem::{Priority, SmsStat, SmsType};
+
+    fn message(index: i32, content: &str) -> SmsMessage {
+        SmsMessage {
+            smstat: SmsStat::Read,
+            index,
+            phone: "+491761234567".to_string(),
+            content: content.to_string(),
+            date: "2026-10-16 10:00:00".to_string(),
+            sca: String::new(),
+            save_type: 0,
+            priority: Priority::Normal,
+            sms_type: SmsType::Single,
+        }
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let first = message(40001, "Hi, there");
+        let second = message(40002, "Line 1\nLine \"2\"");
+        let messages = [(BoxType::LocalInbox, &first), (BoxType::LocalSent, &second)];
+        assert_eq!(
+            export(&messages, ExportFormat::Csv),
+            "box,index,status,phone,date,content\n\
+             local-inbox,40001,read,+491761234567,2026-10-16 10:00:00,\"Hi, there\"\n\
+             local-sent,40002,read,+491761234567,2026-10-16 10:00:00,\"Line 1\nLine \"\"2\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_export_json() {
+        let first = message(40001, "Hi");
+        let json: serde_json::Value = serde_json::from_str(&export(
+            &[(BoxType::LocalInbox, &first)],
+            ExportFormat::Json,
+        ))
+        .unwrap();
+        assert_eq!(json[0]["Box"], "local-inbox");
+        assert_eq!(json[0]["Index"], 40001);
+        assert_eq!(json[0]["Content"], "Hi");
+    }
+
+    #[test]
+    fn test_export_mbox() {
+        let first = message(40001, "From the office:\nAll good");
+        let second = message(40002, "Reply");
+        let mbox = export(
+            &[(BoxType::LocalInbox, &first), (BoxType::LocalSent, &second)],
+            ExportFormat::Mbox,
+        );
+        assert!(mbox.starts_with(
+            "From +491761234567 Fri Oct 16 10:00:00 2026\n\
+             From: +491761234567\n\
+             Subject: SMS from +491761234567\n\
+             Date: Fri, 16 Oct 2026 10:00:00 -0000\n"
+        ));
+        assert!(mbox.contains("\n\n>From the office:\nAll good\n\n"));
+        assert!(mbox.contains("To: +491761234567\n"));
+        // Each message starts with a From line
+        assert_eq!(mbox.lines().filter(|l| l.starts_with("From ")).count(), 2);
+    }
+
+    #[test]
+    fn test_date_parts() {
+        assert_eq!(
+            date_parts("2024-02-29 23:59:59"),
+            Some(("Thu", 29, "Feb", 2024, "23:59:59"))
+        );
+        assert_eq!(
+            date_parts("2000-01-01 00:00:00"),
+            Some(("Sat", 1, "Jan", 2000, "00:00:00"))
+        );
+        assert_eq!(date_parts("2026-13-01 00:00:00"), None);
+        assert_eq!(date_parts(""), None);
+    }
+}
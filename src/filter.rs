@@ -0,0 +1,143 @@
+//! Content filter rules applied to outgoing messages in server mode, to strip
+//! secrets or refuse forbidden phrases before a message reaches the modem.
+
+use crate::config::FilterConfig;
+use regex::Regex;
+use std::borrow::Cow;
+
+#[derive(Clone, Debug)]
+struct FilterRule {
+    regex: Regex,
+    /// Replacement for each match, or `None` to block matching messages
+    replace: Option<String>,
+}
+
+/// The configured rules, applied in order
+#[derive(Clone, Debug, Default)]
+pub struct ContentFilter {
+    rules: Vec<FilterRule>,
+}
+
+impl ContentFilter {
+    /// Compiles the `[[filters]]` rules of the config file
+    pub fn from_config(filters: &[FilterConfig]) -> Result<Self, String> {
+        let rules = filters
+            .iter()
+            .enumerate()
+            .map(|(i, filter)| {
+                let pattern = match (&filter.pattern, &filter.keyword) {
+                    (Some(pattern), None) => pattern.clone(),
+                    (None, Some(keyword)) => format!(r"(?i)\b{}\b", regex::escape(keyword)),
+                    _ => {
+                        return Err(format!(
+                            "Filter {} needs exactly one of pattern and keyword",
+                            i + 1
+                        ));
+                    }
+                };
+                let regex = Regex::new(&pattern)
+                    .map_err(|e| format!("Invalid pattern in filter {}: {}", i + 1, e))?;
+                Ok(FilterRule {
+                    regex,
+                    replace: filter.replace.clone(),
+                })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rewrites `message` with the replacing rules. Returns the 1-based number
+    /// of the first blocking rule that matches, checked against the message as
+    /// rewritten by the rules before it, as error.
+    pub fn apply<'a>(&self, message: &'a str) -> Result<Cow<'a, str>, usize> {
+        let mut message = Cow::Borrowed(message);
+        for (i, rule) in self.rules.iter().enumerate() {
+            match &rule.replace {
+                Some(replace) => {
+                    if let Cow::Owned(rewritten) = rule.regex.replace_all(&message, replace) {
+                        message = Cow::Owned(rewritten);
+                    }
+                }
+                None if rule.regex.is_match(&message) => return Err(i + 1),
+                None => {}
+            }
+        }
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(rules: &[(&str, Option<&str>)]) -> ContentFilter {
+        let filters: Vec<_> = rules
+            .iter()
+            .map(|(pattern, replace)| FilterConfig {
+                pattern: Some(pattern.to_string()),
+                keyword: None,
+                replace: replace.map(str::to_string),
+            })
+            .collect();
+        ContentFilter::from_config(&filters).unwrap()
+    }
+
+    #[test]
+    fn test_apply() {
+        let filter = filter(&[
+            (r"(?i)password=\S+", Some("password=***")),
+            (r"(?i)\bconfidential\b", None),
+        ]);
+        assert_eq!(filter.apply("Disk full").unwrap(), "Disk full");
+        assert_eq!(
+            filter.apply("login: password=hunter2 ok").unwrap(),
+            "login: password=*** ok"
+        );
+        assert_eq!(filter.apply("CONFIDENTIAL numbers"), Err(2));
+    }
+
+    #[test]
+    fn test_rules_apply_in_order() {
+        // The secret is stripped before the blocking rule sees it
+        let filter = filter(&[(r"token \w+", Some("token")), (r"token \w+", None)]);
+        assert_eq!(filter.apply("token abc").unwrap(), "token");
+    }
+
+    #[test]
+    fn test_from_config() {
+        let keyword = FilterConfig {
+            pattern: None,
+            keyword: Some("a.b".to_string()),
+            replace: None,
+        };
+        let filter = ContentFilter::from_config(std::slice::from_ref(&keyword)).unwrap();
+        assert_eq!(filter.apply("see A.B now"), Err(1));
+        // The keyword is matched literally and as a whole word
+        assert!(filter.apply("see axb now").is_ok());
+        assert!(filter.apply("see a.bc now").is_ok());
+
+        let both = FilterConfig {
+            pattern: Some("x".to_string()),
+            ..keyword
+        };
+        assert!(ContentFilter::from_config(&[both]).is_err());
+        let invalid = FilterConfig {
+            pattern: Some("(".to_string()),
+            keyword: None,
+            replace: None,
+        };
+        assert!(
+            ContentFilter::from_config(&[invalid])
+                .unwrap_err()
+                .starts_with("Invalid pattern in filter 1")
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use serde_json::{Value, json};
+
+/// A time series panel of the generated dashboard
+struct Panel {
+    title: &'static str,
+    unit: &'static str,
+    /// PromQL expressions and their legends
+    targets: &'static [(&'static str, &'static str)],
+}
+
+const PANELS: &[Panel] = &[
+    Panel {
+        title: "SMS sent / failed",
+        unit: "short",
+        targets: &[
+            (
+                "increase(smser_sms_sent_total[$__rate_interval])",
+                "sent {{modem}}",
+            ),
+            (
+                "increase(smser_sms_failed_total[$__rate_interval])",
+                "failed",
+            ),
+            (
+                "increase(smser_rate_limited_total[$__rate_interval])",
+                "rate limited {{client}}",
+            ),
+        ],
+    },
+    Panel {
+        title: "Send latency",
+        unit: "s",
+        targets: &[
+            ("smser_send_duration_seconds{quantile=\"0.5\"}", "modem p50"),
+            (
+                "smser_send_duration_seconds{quantile=\"0.99\"}",
+                "modem p99",
+            ),
+            (
+                "smser_queue_wait_seconds{quantile=\"0.99\"}",
+                "queue wait p99 {{lane}}",
+            ),
+        ],
+    },
+    Panel {
+        title: "Modem request latency",
+        unit: "s",
+        targets: &[(
+            "histogram_quantile(0.95, sum by (le, endpoint) (rate(smser_modem_request_duration_seconds_bucket[$__rate_interval])))",
+            "p95 {{endpoint}}",
+        )],
+    },
+    Panel {
+        title: "Send queue",
+        unit: "short",
+        targets: &[
+            ("smser_queue_depth", "depth {{lane}}"),
+            ("smser_queue_in_flight", "in flight"),
+        ],
+    },
+    Panel {
+        title: "Oldest queued SMS",
+        unit: "s",
+        targets: &[("smser_queue_oldest_age_seconds", "age")],
+    },
+    Panel {
+        title: "Rate limit usage",
+        unit: "short",
+        targets: &[
+            ("smser_hourly_usage", "hourly usage"),
+            ("smser_hourly_limit", "hourly limit"),
+            ("smser_daily_usage", "daily usage"),
+            ("smser_daily_limit", "daily limit"),
+        ],
+    },
+    Panel {
+        title: "Per-client usage",
+        unit: "short",
+        targets: &[
+            ("smser_client_hourly_usage", "{{client}} hourly"),
+            ("smser_client_daily_usage", "{{client}} daily"),
+        ],
+    },
+    Panel {
+        title: "Alerts",
+        unit: "short",
+        targets: &[
+            (
+                "sum by (status, severity) (increase(smser_alerts_received_total[$__rate_interval]))",
+                "{{status}} {{severity}}",
+            ),
+            (
+                "sum by (reason) (increase(smser_alerts_failed_total[$__rate_interval]))",
+                "failed: {{reason}}",
+            ),
+        ],
+    },
+    Panel {
+        title: "Stored messages",
+        unit: "short",
+        targets: &[
+            ("smser_sms_stored", "stored"),
+            ("smser_inbox_unread", "inbox unread"),
+            ("smser_inbox_total", "inbox total"),
+        ],
+    },
+    Panel {
+        title: "Mobile data traffic",
+        unit: "bytes",
+        targets: &[
+            ("smser_traffic_upload_bytes", "upload {{period}}"),
+            ("smser_traffic_download_bytes", "download {{period}}"),
+        ],
+    },
+    Panel {
+        title: "HTTP requests",
+        unit: "reqps",
+        targets: &[(
+            "sum by (endpoint) (rate(smser_http_requests_total[$__rate_interval]))",
+            "{{endpoint}}",
+        )],
+    },
+];
+
+/// Width of a panel in the 24 column Grafana grid
+const PANEL_WIDTH: usize = 12;
+const PANEL_HEIGHT: usize = 8;
+
+/// Builds a Grafana dashboard for the metrics exported at `/metrics`, ready
+/// to be imported. The Prometheus data source is chosen during import.
+pub fn dashboard() -> Value {
+    let panels: Vec<Value> = PANELS
+        .iter()
+        .enumerate()
+        .map(|(i, panel)| {
+            let targets: Vec<Value> = panel
+                .targets
+                .iter()
+                .zip('A'..)
+                .map(|((expr, legend), ref_id)| {
+                    json!({
+                        "datasource": {"type": "prometheus", "uid": "${DS_PROMETHEUS}"},
+                        "expr": expr,
+                        "legendFormat": legend,
+                        "refId": ref_id.to_string(),
+                    })
+                })
+                .collect();
+            json!({
+                "id": i + 1,
+                "type": "timeseries",
+                "title": panel.title,
+                "datasource": {"type": "prometheus", "uid": "${DS_PROMETHEUS}"},
+                "gridPos": {
+                    "x": (i % 2) * PANEL_WIDTH,
+                    "y": (i / 2) * PANEL_HEIGHT,
+                    "w": PANEL_WIDTH,
+                    "h": PANEL_HEIGHT,
+                },
+                "fieldConfig": {"defaults": {"unit": panel.unit}, "overrides": []},
+                "targets": targets,
+            })
+        })
+        .collect();
+
+    json!({
+        "__inputs": [{
+            "name": "DS_PROMETHEUS",
+            "label": "Prometheus",
+            "type": "datasource",
+            "pluginId": "prometheus",
+            "pluginName": "Prometheus",
+        }],
+        "title": "smser",
+        "uid": "smser",
+        "tags": ["smser", "sms"],
+        "timezone": "browser",
+        "schemaVersion": 39,
+        "refresh": "1m",
+        "time": {"from": "now-24h", "to": "now"},
+        "panels": panels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Metric names used in a PromQL expression
+    fn metric_names(expr: &str) -> Vec<&str> {
+        expr.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .filter(|token| token.starts_with("smser_"))
+            .map(|token| token.strip_suffix("_bucket").unwrap_or(token))
+            .collect()
+    }
+
+    #[test]
+    fn test_dashboard_uses_described_metrics() {
+        // Every metric is described in setup_metrics; a renamed metric fails here
+        let metrics_source = include_str!("metrics.rs");
+        for panel in PANELS {
+            for (expr, _) in panel.targets {
+                for name in metric_names(expr) {
+                    assert!(
+                        metrics_source.contains(&format!("\"{}\"", name)),
+                        "Panel '{}' uses undescribed metric {}",
+                        panel.title,
+                        name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_dashboard_json() {
+        let dashboard = dashboard();
+        let panels = dashboard["panels"].as_array().unwrap();
+        assert_eq!(panels.len(), PANELS.len());
+        assert_eq!(panels[0]["targets"][1]["refId"], "B");
+        assert_eq!(panels[1]["gridPos"]["x"], 12);
+        assert_eq!(panels[2]["gridPos"]["y"], 8);
+    }
+}
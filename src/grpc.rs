@@ -0,0 +1,217 @@
+//! gRPC API served next to the REST API, defined in `proto/smser.proto`.
+//!
+//! Calls go through the same code as the REST endpoints, so API keys, rate
+//! limits, the content filter and the send queue apply to both.
+
+use crate::modem::{self, SmsListParams};
+use crate::server::{self, ApiError, AppState, Recipients, SendOutcome, SendSmsRequest};
+use crate::types::{BoxType, ErrorKind, SmsStat, SortType};
+use metrics::counter;
+use std::str::FromStr;
+use tokio::net::TcpListener;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+use tonic::{Code, Request, Response, Status};
+use tracing::{error, warn};
+
+/// Code generated from `proto/smser.proto`, including a client for Rust services
+pub mod proto {
+    tonic::include_proto!("smser.v1");
+}
+
+use proto::smser_server::{Smser, SmserServer};
+
+/// Messages buffered per `StreamIncoming` call for a slow client
+const STREAM_BUFFER: usize = 16;
+
+/// Serves the gRPC API on `listener` until `shutdown` changes. Open
+/// `StreamIncoming` calls end on shutdown.
+pub(crate) async fn serve(listener: TcpListener, state: AppState, shutdown: watch::Receiver<bool>) {
+    match listener.local_addr() {
+        Ok(addr) => println!("gRPC listening on {}", addr),
+        Err(e) => error!("Failed to get the gRPC listen address: {}", e),
+    }
+    let service = GrpcService {
+        state,
+        shutdown: shutdown.clone(),
+    };
+    let mut shutdown = shutdown;
+    let result = tonic::transport::Server::builder()
+        .add_service(SmserServer::new(service))
+        .serve_with_incoming_shutdown(TcpListenerStream::new(listener), async move {
+            shutdown.changed().await.ok();
+        })
+        .await;
+    if let Err(e) = result {
+        error!("gRPC server failed: {}", e);
+    }
+}
+
+struct GrpcService {
+    state: AppState,
+    shutdown: watch::Receiver<bool>,
+}
+
+impl GrpcService {
+    /// Checks the API key in the `x-api-key` or `authorization: Bearer` metadata,
+    /// like the REST API checks the headers of the same name
+    fn authorize<T>(
+        &self,
+        method: &'static str,
+        request: &Request<T>,
+    ) -> Result<Option<server::ApiClient>, ApiError> {
+        counter!("smser_grpc_requests_total", "method" => method).increment(1);
+        let metadata = request.metadata();
+        let key = metadata
+            .get("x-api-key")
+            .and_then(|v| v.to_str().ok())
+            .or_else(|| {
+                metadata
+                    .get("authorization")
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| v.strip_prefix("Bearer "))
+            })
+            .unwrap_or_default();
+        server::authorize_api_key(&self.state, key)
+    }
+}
+
+#[tonic::async_trait]
+impl Smser for GrpcService {
+    async fn send_sms(
+        &self,
+        request: Request<proto::SendSmsRequest>,
+    ) -> Result<Response<proto::SendSmsResponse>, Status> {
+        let api_client = self.authorize("SendSms", &request)?;
+        let request = request.into_inner();
+        let payload = SendSmsRequest {
+            to: Recipients::Many(request.to),
+            message: request.message,
+            template: request.template,
+            vars: request.vars.into_iter().collect(),
+            flash: request.flash,
+            client: request.client,
+        };
+        let response =
+            match server::send_sms(&self.state, payload, api_client, request.sync).await? {
+                SendOutcome::Suppressed => proto::SendSmsResponse {
+                    id: None,
+                    status: "suppressed".to_string(),
+                    duplicate: true,
+                },
+                SendOutcome::Queued { id, duplicate } => proto::SendSmsResponse {
+                    id: Some(id),
+                    status: "queued".to_string(),
+                    duplicate,
+                },
+                SendOutcome::Sent { id, duplicate } => proto::SendSmsResponse {
+                    id: Some(id),
+                    status: "sent".to_string(),
+                    duplicate,
+                },
+            };
+        Ok(Response::new(response))
+    }
+
+    async fn list_sms(
+        &self,
+        request: Request<proto::ListSmsRequest>,
+    ) -> Result<Response<proto::ListSmsResponse>, Status> {
+        self.authorize("ListSms", &request)?;
+        let request = request.into_inner();
+        let box_type = if request.box_type.is_empty() {
+            BoxType::LocalInbox
+        } else {
+            BoxType::from_str(&request.box_type).map_err(|_| {
+                Status::invalid_argument(format!("Unknown box type '{}'", request.box_type))
+            })?
+        };
+        if request.page < 0 {
+            return Err(Status::invalid_argument("page starts at 1"));
+        }
+        let params = SmsListParams {
+            box_type,
+            sort_type: SortType::Date,
+            read_count: if request.count == 0 {
+                20
+            } else {
+                request.count
+            },
+            ascending: request.ascending,
+            unread_preferred: request.unread_preferred,
+        };
+        let messages = server::list_sms(&self.state, params, request.page.max(1)).await?;
+        Ok(Response::new(proto::ListSmsResponse {
+            messages: messages.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    type StreamIncomingStream = ReceiverStream<Result<proto::SmsMessage, Status>>;
+
+    async fn stream_incoming(
+        &self,
+        request: Request<proto::StreamIncomingRequest>,
+    ) -> Result<Response<Self::StreamIncomingStream>, Status> {
+        self.authorize("StreamIncoming", &request)?;
+        let Some(mut incoming) = self.state.subscribe_incoming() else {
+            return Err(Status::failed_precondition(
+                "Incoming messages need polling, set --poll-interval above 0",
+            ));
+        };
+        let (tx, rx) = mpsc::channel(STREAM_BUFFER);
+        let mut shutdown = self.shutdown.clone();
+        tokio::spawn(async move {
+            loop {
+                let message = tokio::select! {
+                    _ = shutdown.changed() => break,
+                    _ = tx.closed() => break,
+                    message = incoming.recv() => message,
+                };
+                match message {
+                    Ok(message) => {
+                        if tx.send(Ok(message.into())).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(
+                            "gRPC client too slow, skipped {} incoming messages",
+                            skipped
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
+
+impl From<modem::SmsMessage> for proto::SmsMessage {
+    fn from(message: modem::SmsMessage) -> Self {
+        Self {
+            index: message.index,
+            phone: message.phone,
+            content: message.content,
+            date: message.date,
+            unread: message.smstat == SmsStat::Unread,
+        }
+    }
+}
+
+impl From<ApiError> for Status {
+    fn from(error: ApiError) -> Self {
+        let code = match error.kind() {
+            ErrorKind::BadRequest | ErrorKind::Invalid => Code::InvalidArgument,
+            ErrorKind::NotFound => Code::NotFound,
+            ErrorKind::NotConfigured | ErrorKind::ModemError => Code::FailedPrecondition,
+            ErrorKind::Unauthorized => Code::Unauthenticated,
+            ErrorKind::RateLimited => Code::ResourceExhausted,
+            ErrorKind::Duplicate => Code::AlreadyExists,
+            ErrorKind::Blocked => Code::PermissionDenied,
+            ErrorKind::ModemUnavailable => Code::Unavailable,
+            ErrorKind::Internal | ErrorKind::Unknown => Code::Internal,
+        };
+        Status::new(code, error.message())
+    }
+}
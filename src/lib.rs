@@ -1,11 +1,72 @@
+//! Send and receive SMS through a Huawei HiLink modem such as the E3372.
+//!
+//! [`Modem`] is the entry point for other Rust services; the `smser` binary
+//! and its server are built on the same modules. Library users that need
+//! neither can depend on the crate with `default-features = false` and
+//! `features = ["modem"]`.
+
 #[cfg(feature = "alertmanager")]
 pub mod alertmanager;
+pub mod args;
+#[cfg(feature = "server")]
+pub mod assets;
+#[cfg(feature = "modem")]
+pub mod backup;
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod buildinfo;
+pub mod bulk;
 pub mod cli;
+#[cfg(feature = "modem")]
+pub mod client;
+pub mod compose;
+pub mod config;
+#[cfg(feature = "modem")]
+pub mod cron;
+#[cfg(feature = "server")]
+pub mod dedup;
+pub mod encoding;
+#[cfg(feature = "modem")]
+pub mod export;
+#[cfg(feature = "server")]
+pub mod filter;
+#[cfg(feature = "server")]
+pub mod grafana;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 #[cfg(feature = "server")]
 pub mod metrics;
 #[cfg(feature = "modem")]
 pub mod modem;
 #[cfg(feature = "server")]
+pub mod notify;
+#[cfg(feature = "alertmanager")]
+pub mod oncall;
+pub mod output;
+#[cfg(feature = "server")]
+pub mod pool;
+#[cfg(feature = "modem")]
+pub mod profile;
+#[cfg(feature = "server")]
+pub mod queue;
+pub mod segment;
+#[cfg(feature = "server")]
 pub mod server;
+#[cfg(feature = "alertmanager")]
+pub mod silence;
+#[cfg(feature = "server")]
+pub mod store;
+pub mod template;
+#[cfg(feature = "modem")]
+pub mod transport;
+#[cfg(feature = "tui")]
+pub mod tui;
 pub mod types;
+
+#[cfg(feature = "modem")]
+pub use client::{HttpModem, MockModem, Modem, ModemClient};
+#[cfg(feature = "modem")]
+pub use modem::{Credentials, Error, ModemErrorCode, SmsCount, SmsListParams};
+pub use types::{
+    BoxType, DeviceInfo, NetworkInfo, Priority, SmsMessage, SmsStat, SmsType, SortType,
+};
@@ -1,5 +1,6 @@
-use metrics::{Unit, describe_counter, describe_gauge, gauge};
-use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+pub use crate::args::ClientLimit;
+use metrics::{Unit, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
 use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, OnceLock};
@@ -7,48 +8,25 @@ use std::time::{Duration, Instant};
 
 static PROMETHEUS_HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
 
-/// Per-client rate limit configuration
-#[derive(Clone, Debug, PartialEq)]
-pub struct ClientLimit {
-    pub name: String,
-    pub hourly_limit: u32,
-    pub daily_limit: u32,
-}
+/// Buckets of `smser_modem_request_duration_seconds`, up to the modem client's
+/// 10 second timeout
+const MODEM_REQUEST_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
 
-impl ClientLimit {
-    /// Parse a client limit from "name:hourly:daily" format
-    pub fn parse(s: &str) -> Result<Self, String> {
-        let parts: Vec<&str> = s.split(':').collect();
-        if parts.len() != 3 {
-            return Err(format!(
-                "Invalid client limit format '{}'. Expected 'name:hourly:daily'",
-                s
-            ));
-        }
-        let name = parts[0].to_string();
-        if name.is_empty() {
-            return Err("Client name cannot be empty".to_string());
-        }
-        let hourly_limit = parts[1]
-            .parse()
-            .map_err(|_| format!("Invalid hourly limit '{}'", parts[1]))?;
-        let daily_limit = parts[2]
-            .parse()
-            .map_err(|_| format!("Invalid daily limit '{}'", parts[2]))?;
-        Ok(Self {
-            name,
-            hourly_limit,
-            daily_limit,
-        })
-    }
-}
+const HOUR: Duration = Duration::from_secs(3600);
+const DAY: Duration = Duration::from_secs(86400);
 
 #[derive(Debug, Serialize)]
 pub struct RateLimitStatus {
     pub hourly_usage: u32,
     pub hourly_limit: u32,
+    pub hourly_remaining: u32,
+    /// Seconds until the hourly usage is reset
+    pub hourly_reset_seconds: u64,
     pub daily_usage: u32,
     pub daily_limit: u32,
+    pub daily_remaining: u32,
+    /// Seconds until the daily usage is reset
+    pub daily_reset_seconds: u64,
 }
 
 #[derive(Debug, Serialize)]
@@ -56,26 +34,62 @@ pub struct ClientRateLimitStatus {
     pub name: String,
     pub hourly_usage: u32,
     pub hourly_limit: u32,
+    pub hourly_remaining: u32,
+    /// Seconds until the hourly usage is reset, `None` if the client has not sent yet
+    pub hourly_reset_seconds: Option<u64>,
     pub daily_usage: u32,
     pub daily_limit: u32,
+    pub daily_remaining: u32,
+    /// Seconds until the daily usage is reset, `None` if the client has not sent yet
+    pub daily_reset_seconds: Option<u64>,
+}
+
+/// Usage and limits of an API key. Usage is tracked for every key that sends,
+/// the limits only apply to keys that have them.
+#[derive(Debug, Serialize)]
+pub struct ApiKeyRateLimitStatus {
+    pub name: String,
+    pub hourly_usage: u32,
+    pub hourly_limit: Option<u32>,
+    pub hourly_remaining: Option<u32>,
+    /// Seconds until the hourly usage is reset, `None` if the key has not sent yet
+    pub hourly_reset_seconds: Option<u64>,
+    pub daily_usage: u32,
+    pub daily_limit: Option<u32>,
+    pub daily_remaining: Option<u32>,
+    /// Seconds until the daily usage is reset, `None` if the key has not sent yet
+    pub daily_reset_seconds: Option<u64>,
+}
+
+/// Seconds until a usage window that started at `last_reset` ends
+fn reset_seconds(last_reset: Instant, window: Duration) -> u64 {
+    window.saturating_sub(last_reset.elapsed()).as_secs()
 }
 
 #[derive(Clone, Debug)]
 pub struct RateLimiter {
-    hourly_limit: u32,
-    daily_limit: u32,
-    client_limits: HashMap<String, (u32, u32)>, // name -> (hourly, daily)
     state: Arc<Mutex<RateLimitState>>,
 }
 
 #[derive(Debug)]
 struct RateLimitState {
+    hourly_limit: u32,
+    daily_limit: u32,
+    client_limits: HashMap<String, (u32, u32)>, // name -> (hourly, daily)
     hourly_count: u32,
     daily_count: u32,
     last_reset_hour: Instant,
     last_reset_day: Instant,
     // Per-client state: name -> (hourly_count, daily_count, last_reset_hour, last_reset_day)
     client_state: HashMap<String, ClientRateLimitState>,
+    key_limits: HashMap<String, (u32, u32)>, // API key name -> (hourly, daily)
+    key_state: HashMap<String, ClientRateLimitState>,
+    /// Hourly limit per destination number, 0 for none
+    recipient_limit: u32,
+    recipient_state: HashMap<String, ClientRateLimitState>,
+    /// Global limits changed at runtime, kept over config reloads
+    hourly_override: Option<u32>,
+    daily_override: Option<u32>,
 }
 
 #[derive(Debug)]
@@ -98,28 +112,41 @@ impl ClientRateLimitState {
 
     fn update(&mut self) {
         let now = Instant::now();
-        if now.duration_since(self.last_reset_hour) >= Duration::from_secs(3600) {
+        if now.duration_since(self.last_reset_hour) >= HOUR {
             self.hourly_count = 0;
             self.last_reset_hour = now;
         }
-        if now.duration_since(self.last_reset_day) >= Duration::from_secs(86400) {
+        if now.duration_since(self.last_reset_day) >= DAY {
             self.daily_count = 0;
             self.last_reset_day = now;
         }
     }
+
+    /// Checks that `count` more messages fit within `limits`, without counting them
+    fn check(&mut self, limits: (u32, u32), count: u32, owner: &str) -> Result<(), String> {
+        self.update();
+        let (hourly, daily) = limits;
+        if self.hourly_count + count > hourly {
+            return Err(format!("{} hourly limit of {} reached", owner, hourly));
+        }
+        if self.daily_count + count > daily {
+            return Err(format!("{} daily limit of {} reached", owner, daily));
+        }
+        Ok(())
+    }
 }
 
 impl RateLimitState {
     fn update(&mut self) {
         let now = Instant::now();
         // Check if hour has passed
-        if now.duration_since(self.last_reset_hour) >= Duration::from_secs(3600) {
+        if now.duration_since(self.last_reset_hour) >= HOUR {
             self.hourly_count = 0;
             self.last_reset_hour = now;
         }
 
         // Check if day has passed
-        if now.duration_since(self.last_reset_day) >= Duration::from_secs(86400) {
+        if now.duration_since(self.last_reset_day) >= DAY {
             self.daily_count = 0;
             self.last_reset_day = now;
         }
@@ -133,60 +160,164 @@ impl RateLimiter {
             .map(|cl| (cl.name, (cl.hourly_limit, cl.daily_limit)))
             .collect();
         Self {
-            hourly_limit,
-            daily_limit,
-            client_limits: client_limits_map,
             state: Arc::new(Mutex::new(RateLimitState {
+                hourly_limit,
+                daily_limit,
+                client_limits: client_limits_map,
                 hourly_count: 0,
                 daily_count: 0,
                 last_reset_hour: Instant::now(),
                 last_reset_day: Instant::now(),
                 client_state: HashMap::new(),
+                key_limits: HashMap::new(),
+                key_state: HashMap::new(),
+                recipient_limit: 0,
+                recipient_state: HashMap::new(),
+                hourly_override: None,
+                daily_override: None,
             })),
         }
     }
 
+    /// Sets the limits of API keys, by key name
+    pub fn with_key_limits(self, key_limits: Vec<ClientLimit>) -> Self {
+        self.set_key_limits(key_limits);
+        self
+    }
+
+    /// Limits the messages to each destination number per hour; 0 disables it
+    pub fn with_recipient_limit(self, recipient_limit: u32) -> Self {
+        self.set_recipient_limit(recipient_limit);
+        self
+    }
+
+    /// Replaces the limits of all API keys; keys without limits are only
+    /// subject to the global limits. Usage so far is kept.
+    pub fn set_key_limits(&self, key_limits: Vec<ClientLimit>) {
+        self.state.lock().unwrap().key_limits = key_limits
+            .into_iter()
+            .map(|kl| (kl.name, (kl.hourly_limit, kl.daily_limit)))
+            .collect();
+    }
+
+    /// Changes the per-recipient hourly limit; 0 disables it
+    pub fn set_recipient_limit(&self, recipient_limit: u32) {
+        self.state.lock().unwrap().recipient_limit = recipient_limit;
+    }
+
     pub fn check_and_increment(&self, client: Option<&str>) -> Result<(), String> {
-        let mut state = self.state.lock().unwrap();
+        self.check_and_increment_by(client, 1)
+    }
+
+    /// Counts `count` messages at once, e.g. one per recipient of a multi-recipient
+    /// SMS. Nothing is counted unless all of them fit within the limits.
+    pub fn check_and_increment_by(&self, client: Option<&str>, count: u32) -> Result<(), String> {
+        self.check_and_increment_inner(client, None, &[], count)
+    }
+
+    /// Counts a message to each of `recipients`, also against the limits of
+    /// the API key named `api_key` and the per-recipient limit
+    pub fn check_and_increment_send(
+        &self,
+        client: Option<&str>,
+        api_key: Option<&str>,
+        recipients: &[String],
+    ) -> Result<(), String> {
+        self.check_and_increment_inner(client, api_key, recipients, recipients.len() as u32)
+    }
+
+    fn check_and_increment_inner(
+        &self,
+        client: Option<&str>,
+        api_key: Option<&str>,
+        recipients: &[String],
+        count: u32,
+    ) -> Result<(), String> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
         state.update();
 
         // Check global limits first
-        if state.hourly_count >= self.hourly_limit {
-            return Err(format!("Hourly limit of {} reached", self.hourly_limit));
+        if state.hourly_count + count > state.hourly_limit {
+            return Err(format!("Hourly limit of {} reached", state.hourly_limit));
         }
 
-        if state.daily_count >= self.daily_limit {
-            return Err(format!("Daily limit of {} reached", self.daily_limit));
+        if state.daily_count + count > state.daily_limit {
+            return Err(format!("Daily limit of {} reached", state.daily_limit));
         }
 
         // Check per-client limits if client is specified and configured
         if let Some(client_name) = client
-            && let Some(&(client_hourly, client_daily)) = self.client_limits.get(client_name)
+            && let Some(&(client_hourly, client_daily)) = state.client_limits.get(client_name)
         {
             // Get or create client state
             let client_state = state
                 .client_state
                 .entry(client_name.to_string())
                 .or_insert_with(ClientRateLimitState::new);
-            client_state.update();
+            client_state.check(
+                (client_hourly, client_daily),
+                count,
+                &format!("Client '{}'", client_name),
+            )?;
+        }
+        // If client name provided but not configured, just use global limits
 
-            if client_state.hourly_count >= client_hourly {
-                return Err(format!(
-                    "Client '{}' hourly limit of {} reached",
-                    client_name, client_hourly
-                ));
+        let mut per_recipient: Vec<(&String, u32)> = Vec::new();
+        if state.recipient_limit > 0 {
+            for to in recipients {
+                match per_recipient.iter_mut().find(|(r, _)| *r == to) {
+                    Some((_, n)) => *n += 1,
+                    None => per_recipient.push((to, 1)),
+                }
             }
+            // Forget recipients whose window is over, so the map stays small
+            state
+                .recipient_state
+                .retain(|_, s| s.last_reset_hour.elapsed() < HOUR);
+            for (to, n) in &per_recipient {
+                let used = state.recipient_state.get(*to).map_or(0, |s| s.hourly_count);
+                if used + n > state.recipient_limit {
+                    let retry = match state.recipient_state.get(*to) {
+                        Some(s) if used > 0 => {
+                            format!(", retry in {}s", reset_seconds(s.last_reset_hour, HOUR))
+                        }
+                        _ => String::new(),
+                    };
+                    return Err(format!(
+                        "Recipient hourly limit of {} reached for {}{}",
+                        state.recipient_limit, to, retry
+                    ));
+                }
+            }
+        }
 
-            if client_state.daily_count >= client_daily {
-                return Err(format!(
-                    "Client '{}' daily limit of {} reached",
-                    client_name, client_daily
-                ));
+        // Usage is tracked for every API key, limits only apply to configured ones
+        if let Some(key_name) = api_key {
+            let key_state = state
+                .key_state
+                .entry(key_name.to_string())
+                .or_insert_with(ClientRateLimitState::new);
+            if let Some(&limits) = state.key_limits.get(key_name) {
+                key_state.check(limits, count, &format!("API key '{}'", key_name))?;
+            } else {
+                key_state.update();
             }
+            key_state.hourly_count += count;
+            key_state.daily_count += count;
+            gauge!("smser_api_key_hourly_usage", "key" => key_name.to_string())
+                .set(key_state.hourly_count as f64);
+            gauge!("smser_api_key_daily_usage", "key" => key_name.to_string())
+                .set(key_state.daily_count as f64);
+        }
 
+        if let Some(client_name) = client
+            && let Some(client_state) = state.client_state.get_mut(client_name)
+            && state.client_limits.contains_key(client_name)
+        {
             // Increment client counters
-            client_state.hourly_count += 1;
-            client_state.daily_count += 1;
+            client_state.hourly_count += count;
+            client_state.daily_count += count;
 
             // Update client metrics
             gauge!("smser_client_hourly_usage", "client" => client_name.to_string())
@@ -194,11 +325,18 @@ impl RateLimiter {
             gauge!("smser_client_daily_usage", "client" => client_name.to_string())
                 .set(client_state.daily_count as f64);
         }
-        // If client name provided but not configured, just use global limits
+
+        for (to, n) in per_recipient {
+            state
+                .recipient_state
+                .entry(to.clone())
+                .or_insert_with(ClientRateLimitState::new)
+                .hourly_count += n;
+        }
 
         // Increment global counters
-        state.hourly_count += 1;
-        state.daily_count += 1;
+        state.hourly_count += count;
+        state.daily_count += count;
 
         // Update global metrics
         gauge!("smser_hourly_usage").set(state.hourly_count as f64);
@@ -213,43 +351,208 @@ impl RateLimiter {
 
         RateLimitStatus {
             hourly_usage: state.hourly_count,
-            hourly_limit: self.hourly_limit,
+            hourly_limit: state.hourly_limit,
+            hourly_remaining: state.hourly_limit.saturating_sub(state.hourly_count),
+            hourly_reset_seconds: reset_seconds(state.last_reset_hour, HOUR),
             daily_usage: state.daily_count,
-            daily_limit: self.daily_limit,
+            daily_limit: state.daily_limit,
+            daily_remaining: state.daily_limit.saturating_sub(state.daily_count),
+            daily_reset_seconds: reset_seconds(state.last_reset_day, DAY),
         }
     }
 
-    /// Returns status for all configured clients
+    /// Returns status for all configured clients, sorted by name
     pub fn get_client_status(&self) -> Vec<ClientRateLimitStatus> {
-        let mut state = self.state.lock().unwrap();
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
 
-        self.client_limits
+        let mut clients: Vec<_> = state
+            .client_limits
             .iter()
             .map(|(name, &(hourly_limit, daily_limit))| {
-                let (hourly_usage, daily_usage) =
-                    if let Some(client_state) = state.client_state.get_mut(name) {
-                        client_state.update();
-                        (client_state.hourly_count, client_state.daily_count)
-                    } else {
-                        (0, 0)
-                    };
+                let client_state = state.client_state.get_mut(name).map(|client_state| {
+                    client_state.update();
+                    &*client_state
+                });
+                let hourly_usage = client_state.map_or(0, |s| s.hourly_count);
+                let daily_usage = client_state.map_or(0, |s| s.daily_count);
 
                 ClientRateLimitStatus {
                     name: name.clone(),
                     hourly_usage,
                     hourly_limit,
+                    hourly_remaining: hourly_limit.saturating_sub(hourly_usage),
+                    hourly_reset_seconds: client_state
+                        .map(|s| reset_seconds(s.last_reset_hour, HOUR)),
                     daily_usage,
                     daily_limit,
+                    daily_remaining: daily_limit.saturating_sub(daily_usage),
+                    daily_reset_seconds: client_state.map(|s| reset_seconds(s.last_reset_day, DAY)),
+                }
+            })
+            .collect();
+        clients.sort_by(|a, b| a.name.cmp(&b.name));
+        clients
+    }
+
+    /// Returns status for all API keys with limits or usage, sorted by name
+    pub fn get_key_status(&self) -> Vec<ApiKeyRateLimitStatus> {
+        let mut guard = self.state.lock().unwrap();
+        let state = &mut *guard;
+
+        let mut names: Vec<String> = state
+            .key_limits
+            .keys()
+            .chain(state.key_state.keys())
+            .cloned()
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .map(|name| {
+                let limits = state.key_limits.get(&name).copied();
+                let key_state = state.key_state.get_mut(&name).map(|key_state| {
+                    key_state.update();
+                    &*key_state
+                });
+                let hourly_usage = key_state.map_or(0, |s| s.hourly_count);
+                let daily_usage = key_state.map_or(0, |s| s.daily_count);
+
+                ApiKeyRateLimitStatus {
+                    hourly_usage,
+                    hourly_limit: limits.map(|(hourly, _)| hourly),
+                    hourly_remaining: limits.map(|(hourly, _)| hourly.saturating_sub(hourly_usage)),
+                    hourly_reset_seconds: key_state.map(|s| reset_seconds(s.last_reset_hour, HOUR)),
+                    daily_usage,
+                    daily_limit: limits.map(|(_, daily)| daily),
+                    daily_remaining: limits.map(|(_, daily)| daily.saturating_sub(daily_usage)),
+                    daily_reset_seconds: key_state.map(|s| reset_seconds(s.last_reset_day, DAY)),
+                    name,
                 }
             })
             .collect()
     }
+
+    /// Changes the global limits and adds or replaces per-client limits,
+    /// updating the limit gauges. Global limits changed here take precedence
+    /// over those of a reloaded config.
+    pub fn set_limits(
+        &self,
+        hourly_limit: Option<u32>,
+        daily_limit: Option<u32>,
+        client_limits: &[ClientLimit],
+    ) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(hourly) = hourly_limit {
+            state.hourly_limit = hourly;
+            state.hourly_override = Some(hourly);
+        }
+        if let Some(daily) = daily_limit {
+            state.daily_limit = daily;
+            state.daily_override = Some(daily);
+        }
+        for cl in client_limits {
+            state
+                .client_limits
+                .insert(cl.name.clone(), (cl.hourly_limit, cl.daily_limit));
+        }
+        update_limits_metrics(state.hourly_limit, state.daily_limit);
+        update_client_limits_metrics(client_limits);
+    }
+
+    /// Sets the global limits of a reloaded config, keeping those changed by
+    /// `set_limits`. Returns whether any of them was kept.
+    pub fn set_config_limits(&self, hourly_limit: u32, daily_limit: u32) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.hourly_limit = state.hourly_override.unwrap_or(hourly_limit);
+        state.daily_limit = state.daily_override.unwrap_or(daily_limit);
+        update_limits_metrics(state.hourly_limit, state.daily_limit);
+        state.hourly_override.is_some() || state.daily_override.is_some()
+    }
+
+    /// The `smser_api_key_hourly_limit` and `smser_api_key_daily_limit`
+    /// metrics in the Prometheus text format. They are rendered from the
+    /// current limits on each scrape instead of being kept as gauges, so keys
+    /// whose limits a reload removed are no longer reported.
+    pub fn render_key_limits(&self) -> String {
+        let state = self.state.lock().unwrap();
+        if state.key_limits.is_empty() {
+            return String::new();
+        }
+        let mut limits: Vec<(&String, (u32, u32))> = state
+            .key_limits
+            .iter()
+            .map(|(name, &limits)| (name, limits))
+            .collect();
+        limits.sort();
+        let mut out = String::new();
+        for (metric, help, pick) in [
+            (
+                "smser_api_key_hourly_limit",
+                "Configured hourly SMS limit per API key",
+                (|(hourly, _)| hourly) as fn((u32, u32)) -> u32,
+            ),
+            (
+                "smser_api_key_daily_limit",
+                "Configured daily SMS limit per API key",
+                |(_, daily)| daily,
+            ),
+        ] {
+            out.push_str(&format!(
+                "# HELP {} {}\n# TYPE {} gauge\n",
+                metric, help, metric
+            ));
+            for (name, limits) in &limits {
+                let name = name
+                    .replace('\\', "\\\\")
+                    .replace('"', "\\\"")
+                    .replace('\n', "\\n");
+                out.push_str(&format!(
+                    "{}{{key=\"{}\"}} {}\n",
+                    metric,
+                    name,
+                    pick(*limits)
+                ));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Resets all usage counters, globally, per client and per API key
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        state.hourly_count = 0;
+        state.daily_count = 0;
+        state.last_reset_hour = now;
+        state.last_reset_day = now;
+        for (name, client_state) in state.client_state.iter_mut() {
+            *client_state = ClientRateLimitState::new();
+            gauge!("smser_client_hourly_usage", "client" => name.clone()).set(0.0);
+            gauge!("smser_client_daily_usage", "client" => name.clone()).set(0.0);
+        }
+        for (name, key_state) in state.key_state.iter_mut() {
+            *key_state = ClientRateLimitState::new();
+            gauge!("smser_api_key_hourly_usage", "key" => name.clone()).set(0.0);
+            gauge!("smser_api_key_daily_usage", "key" => name.clone()).set(0.0);
+        }
+        state.recipient_state.clear();
+        gauge!("smser_hourly_usage").set(0.0);
+        gauge!("smser_daily_usage").set(0.0);
+    }
 }
 
 pub fn setup_metrics() -> PrometheusHandle {
     PROMETHEUS_HANDLE
         .get_or_init(|| {
-            let builder = PrometheusBuilder::new();
+            let builder = PrometheusBuilder::new()
+                .set_buckets_for_metric(
+                    Matcher::Full("smser_modem_request_duration_seconds".to_string()),
+                    MODEM_REQUEST_BUCKETS,
+                )
+                .expect("invalid histogram buckets");
             let handle = builder
                 .install_recorder()
                 .expect("failed to install Prometheus recorder");
@@ -264,6 +567,31 @@ pub fn setup_metrics() -> PrometheusHandle {
                 Unit::Count,
                 "Total number of HTTP requests"
             );
+            describe_counter!(
+                "smser_grpc_requests_total",
+                Unit::Count,
+                "Total number of gRPC calls by method"
+            );
+            describe_counter!(
+                "smser_rate_limited_total",
+                Unit::Count,
+                "Total number of SMS rejected by the rate limiter"
+            );
+            describe_counter!(
+                "smser_duplicates_total",
+                Unit::Count,
+                "Total number of SMS caught by the duplicate window, by action"
+            );
+            describe_counter!(
+                "smser_sms_blocked_total",
+                Unit::Count,
+                "Total number of SMS refused by a content filter rule"
+            );
+            describe_counter!(
+                "smser_auto_purged_total",
+                Unit::Count,
+                "Total number of read messages deleted because the modem storage was full"
+            );
             describe_counter!(
                 "smser_sms_country_total",
                 Unit::Count,
@@ -297,11 +625,96 @@ pub fn setup_metrics() -> PrometheusHandle {
                 Unit::Count,
                 "Configured daily SMS limit per client"
             );
+            describe_gauge!(
+                "smser_api_key_hourly_usage",
+                Unit::Count,
+                "Current hourly SMS usage per API key"
+            );
+            describe_gauge!(
+                "smser_api_key_daily_usage",
+                Unit::Count,
+                "Current daily SMS usage per API key"
+            );
+            describe_counter!(
+                "smser_alerts_received_total",
+                Unit::Count,
+                "Total number of alert payloads received by source, status and severity"
+            );
+            describe_counter!(
+                "smser_alerts_failed_total",
+                Unit::Count,
+                "Total number of alerts not delivered, by failed step"
+            );
+            describe_counter!(
+                "smser_sms_failed_total",
+                Unit::Count,
+                "Total number of SMS the modem failed to send"
+            );
+            describe_gauge!(
+                "smser_queue_depth",
+                Unit::Count,
+                "Number of SMS waiting in the send queue per lane"
+            );
+            describe_gauge!(
+                "smser_queue_oldest_age_seconds",
+                Unit::Seconds,
+                "Age of the oldest SMS waiting in the send queue"
+            );
+            describe_gauge!(
+                "smser_queue_in_flight",
+                Unit::Count,
+                "Number of SMS currently being handed to the modem"
+            );
+            describe_histogram!(
+                "smser_queue_wait_seconds",
+                Unit::Seconds,
+                "Time SMS spent in the send queue before the worker picked them up"
+            );
+            describe_histogram!(
+                "smser_send_duration_seconds",
+                Unit::Seconds,
+                "Time taken to hand an SMS to the modem"
+            );
+            describe_histogram!(
+                "smser_modem_request_duration_seconds",
+                Unit::Seconds,
+                "Time taken by requests to the modem API per endpoint"
+            );
             describe_gauge!(
                 "smser_sms_stored",
                 Unit::Count,
                 "Number of SMS messages stored on the SIM"
             );
+            describe_gauge!(
+                "smser_inbox_unread",
+                Unit::Count,
+                "Number of unread messages in the modem inbox"
+            );
+            describe_gauge!(
+                "smser_inbox_total",
+                Unit::Count,
+                "Number of messages in the modem inbox"
+            );
+            describe_gauge!(
+                "smser_storage_full",
+                Unit::Count,
+                "1 if the modem message storage is full and new messages cannot be received"
+            );
+            describe_gauge!(
+                "smser_traffic_upload_bytes",
+                Unit::Bytes,
+                "Bytes uploaded over mobile data, for the current connection or in total"
+            );
+            describe_gauge!(
+                "smser_traffic_download_bytes",
+                Unit::Bytes,
+                "Bytes downloaded over mobile data, for the current connection or in total"
+            );
+            describe_gauge!(
+                "smser_traffic_connect_time_seconds",
+                Unit::Seconds,
+                "Time connected to mobile data, for the current connection or in total"
+            );
             describe_gauge!(
                 "smser_start_time_seconds",
                 Unit::Seconds,
@@ -318,6 +731,12 @@ pub fn setup_metrics() -> PrometheusHandle {
         .clone()
 }
 
+/// Records how long a request to the modem API took, by endpoint (e.g. `sms-list`)
+pub fn record_modem_request(endpoint: &str, duration: Duration) {
+    histogram!("smser_modem_request_duration_seconds", "endpoint" => endpoint.to_string())
+        .record(duration.as_secs_f64());
+}
+
 pub fn update_limits_metrics(hourly: u32, daily: u32) {
     gauge!("smser_hourly_limit").set(hourly as f64);
     gauge!("smser_daily_limit").set(daily as f64);
@@ -355,6 +774,17 @@ mod tests {
         assert!(result.unwrap_err().contains("Daily limit of 2 reached"));
     }
 
+    #[test]
+    fn test_rate_limiter_increment_by() {
+        let limiter = RateLimiter::new(5, 10, vec![]);
+        assert!(limiter.check_and_increment_by(None, 3).is_ok());
+        // All or nothing: 3 more would exceed the limit, so none are counted
+        assert!(limiter.check_and_increment_by(None, 3).is_err());
+        assert_eq!(limiter.get_status().hourly_usage, 3);
+        assert!(limiter.check_and_increment_by(None, 2).is_ok());
+        assert_eq!(limiter.get_status().hourly_remaining, 0);
+    }
+
     #[test]
     fn test_rate_limiter_client_limit() {
         let client_limits = vec![ClientLimit {
@@ -379,6 +809,112 @@ mod tests {
         assert!(limiter.check_and_increment(Some("unknown_client")).is_ok());
     }
 
+    #[test]
+    fn test_rate_limiter_key_limit() {
+        let client_limits = vec![ClientLimit {
+            name: "app".to_string(),
+            hourly_limit: 1,
+            daily_limit: 10,
+        }];
+        let key_limits = vec![ClientLimit {
+            name: "team-a".to_string(),
+            hourly_limit: 3,
+            daily_limit: 10,
+        }];
+        let limiter = RateLimiter::new(100, 1000, client_limits).with_key_limits(key_limits);
+        let to = |n: usize| vec!["+441234567890".to_string(); n];
+
+        assert!(
+            limiter
+                .check_and_increment_send(None, Some("team-a"), &to(2))
+                .is_ok()
+        );
+        let result = limiter.check_and_increment_send(None, Some("team-a"), &to(2));
+        assert_eq!(
+            result.unwrap_err(),
+            "API key 'team-a' hourly limit of 3 reached"
+        );
+        // Keys without limits only count usage
+        assert!(
+            limiter
+                .check_and_increment_send(None, Some("team-b"), &to(5))
+                .is_ok()
+        );
+
+        // Nothing is counted when the client limit rejects the messages
+        assert!(
+            limiter
+                .check_and_increment_send(Some("app"), Some("team-a"), &to(2))
+                .is_err()
+        );
+        assert!(
+            limiter
+                .check_and_increment_send(Some("app"), Some("team-a"), &to(1))
+                .is_ok()
+        );
+
+        let keys = limiter.get_key_status();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].name, "team-a");
+        assert_eq!(keys[0].hourly_usage, 3);
+        assert_eq!(keys[0].hourly_remaining, Some(0));
+        assert_eq!(keys[1].name, "team-b");
+        assert_eq!(keys[1].daily_usage, 5);
+        assert_eq!(keys[1].daily_limit, None);
+        assert_eq!(limiter.get_status().hourly_usage, 8);
+
+        limiter.reset();
+        assert_eq!(limiter.get_key_status()[0].hourly_usage, 0);
+    }
+
+    #[test]
+    fn test_rate_limiter_recipient_limit() {
+        let limiter = RateLimiter::new(100, 1000, vec![]).with_recipient_limit(2);
+        let alice = "+441234567890".to_string();
+        let bob = "+441234567891".to_string();
+
+        assert!(
+            limiter
+                .check_and_increment_send(None, None, std::slice::from_ref(&alice))
+                .is_ok()
+        );
+        let result = limiter.check_and_increment_send(None, None, &[alice.clone(), alice.clone()]);
+        assert!(
+            result
+                .unwrap_err()
+                .starts_with("Recipient hourly limit of 2 reached for +441234567890, retry in")
+        );
+        // Nothing is counted for the other recipients of a rejected message
+        assert!(
+            limiter
+                .check_and_increment_send(None, None, &[bob.clone(), alice.clone(), alice.clone()])
+                .is_err()
+        );
+        assert!(
+            limiter
+                .check_and_increment_send(None, None, &[bob.clone(), bob.clone()])
+                .is_ok()
+        );
+        assert!(
+            limiter
+                .check_and_increment_send(None, None, std::slice::from_ref(&alice))
+                .is_ok()
+        );
+        assert!(
+            limiter
+                .check_and_increment_send(None, None, std::slice::from_ref(&alice))
+                .is_err()
+        );
+        assert_eq!(limiter.get_status().hourly_usage, 4);
+
+        limiter.reset();
+        assert!(
+            limiter
+                .check_and_increment_send(None, None, &[alice])
+                .is_ok()
+        );
+    }
+
     #[test]
     fn test_rate_limiter_client_counts_against_global() {
         let client_limits = vec![ClientLimit {
@@ -409,6 +945,94 @@ mod tests {
         assert!(ClientLimit::parse("name:abc:20").is_err());
     }
 
+    #[test]
+    fn test_rate_limiter_set_limits_and_reset() {
+        let limiter = RateLimiter::new(1, 10, vec![]);
+        assert!(limiter.check_and_increment(None).is_ok());
+        assert!(limiter.check_and_increment(None).is_err());
+
+        limiter.set_limits(
+            Some(2),
+            None,
+            &[ClientLimit {
+                name: "app".to_string(),
+                hourly_limit: 1,
+                daily_limit: 5,
+            }],
+        );
+        let status = limiter.get_status();
+        assert_eq!(status.hourly_limit, 2);
+        assert_eq!(status.daily_limit, 10);
+        assert_eq!(status.hourly_usage, 1);
+        assert_eq!(status.hourly_remaining, 1);
+        assert_eq!(status.daily_remaining, 9);
+        assert!(status.hourly_reset_seconds <= 3600);
+        assert!(
+            limiter.get_client_status()[0]
+                .hourly_reset_seconds
+                .is_none()
+        );
+        assert!(limiter.check_and_increment(Some("app")).is_ok());
+        assert!(limiter.check_and_increment(None).is_err());
+        let client = &limiter.get_client_status()[0];
+        assert_eq!(client.hourly_remaining, 0);
+        assert!(client.daily_reset_seconds.is_some());
+
+        limiter.reset();
+        let status = limiter.get_status();
+        assert_eq!(status.hourly_usage, 0);
+        assert_eq!(status.daily_usage, 0);
+        assert_eq!(limiter.get_client_status()[0].hourly_usage, 0);
+        assert!(limiter.check_and_increment(Some("app")).is_ok());
+        assert!(limiter.check_and_increment(Some("app")).is_err());
+    }
+
+    #[test]
+    fn test_rate_limiter_config_limits_keep_overrides() {
+        let limiter = RateLimiter::new(1, 10, vec![]);
+        assert!(!limiter.set_config_limits(2, 20));
+        assert_eq!(limiter.get_status().hourly_limit, 2);
+
+        limiter.set_limits(Some(5), None, &[]);
+        assert!(limiter.set_config_limits(3, 30));
+        let status = limiter.get_status();
+        assert_eq!(status.hourly_limit, 5);
+        assert_eq!(status.daily_limit, 30);
+    }
+
+    #[test]
+    fn test_rate_limiter_render_key_limits() {
+        let limiter = RateLimiter::new(100, 1000, vec![]);
+        assert_eq!(limiter.render_key_limits(), "");
+
+        limiter.set_key_limits(vec![ClientLimit {
+            name: "grafana".to_string(),
+            hourly_limit: 5,
+            daily_limit: 50,
+        }]);
+        let rendered = limiter.render_key_limits();
+        assert!(rendered.contains("# TYPE smser_api_key_hourly_limit gauge\n"));
+        assert!(rendered.contains("smser_api_key_hourly_limit{key=\"grafana\"} 5\n"));
+        assert!(rendered.contains("smser_api_key_daily_limit{key=\"grafana\"} 50\n"));
+
+        // Keys without limits after a reload are no longer reported
+        limiter.set_key_limits(vec![]);
+        assert_eq!(limiter.render_key_limits(), "");
+    }
+
+    #[test]
+    fn test_modem_request_histogram() {
+        let handle = setup_metrics();
+        record_modem_request("SesTokInfo", Duration::from_millis(300));
+        let rendered = handle.render();
+        assert!(rendered.contains(
+            "smser_modem_request_duration_seconds_bucket{endpoint=\"SesTokInfo\",le=\"0.25\"} 0"
+        ));
+        assert!(rendered.contains(
+            "smser_modem_request_duration_seconds_bucket{endpoint=\"SesTokInfo\",le=\"0.5\"} 1"
+        ));
+    }
+
     #[test]
     fn test_rate_limiter_reset_logic_simulated() {
         // Since we can't easily mock Instant::now() without extra dependencies,
@@ -1,10 +1,97 @@
 use quick_xml::de::from_str;
-use quick_xml::se::to_string;
+use quick_xml::se::{QuoteLevel, Serializer};
 use reqwest::Client as HttpClient;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
 
 // Re-export types for backwards compatibility
-pub use crate::types::{BoxType, Priority, SmsMessage, SmsStat, SmsType, SortType};
+use crate::types::ErrorKind;
+pub use crate::types::{
+    BoxType, DeviceInfo, NetworkInfo, Priority, SmsListResponse, SmsMessage, SmsMessages, SmsStat,
+    SmsType, SortType,
+};
+
+/// Serializes a request body. Besides `&`, `<` and `>`, quotes are escaped
+/// too, like the modem's web UI does, so message content reaches the
+/// firmware's XML parser unchanged.
+fn to_string<T: Serialize>(value: &T) -> Result<String, quick_xml::SeError> {
+    let mut xml = String::new();
+    let mut serializer = Serializer::new(&mut xml);
+    serializer.set_quote_level(QuoteLevel::Full);
+    value.serialize(serializer)?;
+    Ok(xml)
+}
+
+/// HTTP client shared by all modem requests, so connections to the modem are
+/// kept alive and reused instead of opened for every call
+fn http_client() -> &'static HttpClient {
+    static CLIENT: OnceLock<HttpClient> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        HttpClient::builder()
+            .timeout(Duration::from_secs(10))
+            .connect_timeout(Duration::from_secs(5))
+            .pool_idle_timeout(Duration::from_secs(30))
+            .tcp_keepalive(Duration::from_secs(60))
+            .build()
+            .expect("Failed to create HTTP client")
+    })
+}
+
+/// XML elements whose content is a credential or session secret
+const SECRET_ELEMENTS: [&str; 3] = ["Password", "SesInfo", "TokInfo"];
+
+/// Replaces the content of [`SECRET_ELEMENTS`] in a modem request or response
+fn redact(xml: &str) -> String {
+    let mut redacted = xml.to_string();
+    for name in SECRET_ELEMENTS {
+        let (open, close) = (format!("<{}>", name), format!("</{}>", name));
+        let mut from = 0;
+        while let Some(start) = redacted[from..].find(&open).map(|i| from + i + open.len()) {
+            let Some(end) = redacted[start..].find(&close).map(|i| start + i) else {
+                break;
+            };
+            redacted.replace_range(start..end, "***");
+            from = start + "***".len() + close.len();
+        }
+    }
+    redacted
+}
+
+/// Logs the body of a modem request at trace level (`-vv`), secrets redacted
+fn trace_request(method: &str, url: &str, body: &str) {
+    if body.is_empty() {
+        tracing::trace!("{} {}", method, url);
+    } else {
+        tracing::trace!("{} {}\n{}", method, url, redact(body));
+    }
+}
+
+/// Logs the body of a modem response at trace level (`-vv`), secrets redacted
+fn trace_response(url: &str, body: &str) {
+    tracing::trace!("Response from {}\n{}", url, redact(body));
+}
+
+/// Records how long a modem request took, labelled with the last segment of
+/// its URL (e.g. `sms-list`). Only the server exports it.
+fn record_duration(url: &str, started: Instant) {
+    #[cfg(feature = "server")]
+    crate::metrics::record_modem_request(url.rsplit('/').next().unwrap_or(url), started.elapsed());
+    #[cfg(not(feature = "server"))]
+    let _ = (url, started);
+}
+
+/// Sends a request to the modem and reads the response body, recording the
+/// time taken even if the request fails or times out
+async fn execute(request: reqwest::RequestBuilder, url: &str) -> Result<String, Error> {
+    let started = Instant::now();
+    let response = async { request.send().await?.text().await }.await;
+    record_duration(url, started);
+    let response_text = response?;
+    trace_response(url, &response_text);
+    Ok(response_text)
+}
 
 #[derive(Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename = "error")]
@@ -13,12 +100,143 @@ pub struct ModemErrorResponse {
     pub message: String,
 }
 
+/// Error codes reported by HiLink modems in `<error><code>`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModemErrorCode {
+    /// 100002: the modem does not support the API
+    NotSupported,
+    /// 100003: the modem requires a login
+    LoginRequired,
+    /// 100004: the modem is busy
+    SystemBusy,
+    /// 100005: the request XML is malformed
+    FormatError,
+    /// 100006: a request parameter is invalid
+    ParameterError,
+    /// 108001: unknown username
+    WrongUsername,
+    /// 108002: wrong password
+    WrongPassword,
+    /// 108003: another session is already logged in
+    AlreadyLoggedIn,
+    /// 108006: wrong username or password
+    WrongCredentials,
+    /// 108007: too many failed logins
+    TooManyLogins,
+    /// 113004: the SMS system is busy, e.g. still sending the previous message
+    SmsBusy,
+    /// 113018: the message could not be stored
+    SmsSaveFailed,
+    /// 113053: the message storage is full
+    SmsStorageFull,
+    /// 125001: the request verification token is wrong
+    WrongToken,
+    /// 125002: the session is wrong or expired
+    WrongSession,
+    /// 125003: the session token is wrong or was already used
+    WrongSessionToken,
+    Other(i32),
+}
+
+impl ModemErrorCode {
+    /// Whether the session or its token is no longer valid and a new one has to be fetched
+    pub fn is_session_expired(self) -> bool {
+        matches!(
+            self,
+            Self::WrongToken | Self::WrongSession | Self::WrongSessionToken
+        )
+    }
+
+    pub fn as_i32(self) -> i32 {
+        match self {
+            Self::NotSupported => 100002,
+            Self::LoginRequired => 100003,
+            Self::SystemBusy => 100004,
+            Self::FormatError => 100005,
+            Self::ParameterError => 100006,
+            Self::WrongUsername => 108001,
+            Self::WrongPassword => 108002,
+            Self::AlreadyLoggedIn => 108003,
+            Self::WrongCredentials => 108006,
+            Self::TooManyLogins => 108007,
+            Self::SmsBusy => 113004,
+            Self::SmsSaveFailed => 113018,
+            Self::SmsStorageFull => 113053,
+            Self::WrongToken => 125001,
+            Self::WrongSession => 125002,
+            Self::WrongSessionToken => 125003,
+            Self::Other(code) => code,
+        }
+    }
+
+    /// What the code means for an operator, `None` for unknown codes
+    pub fn description(self) -> Option<&'static str> {
+        Some(match self {
+            Self::NotSupported => "not supported by this modem",
+            Self::LoginRequired => "the modem requires a login, set --modem-password",
+            Self::SystemBusy => "the modem is busy, try again later",
+            Self::FormatError => "the modem could not parse the request",
+            Self::ParameterError => "invalid request parameter",
+            Self::WrongUsername => "unknown modem username",
+            Self::WrongPassword => "wrong modem password",
+            Self::AlreadyLoggedIn => "another session is already logged in to the modem",
+            Self::WrongCredentials => "wrong modem username or password",
+            Self::TooManyLogins => "too many failed logins, the modem is locked for a while",
+            Self::SmsBusy => "the modem is still busy sending a previous message",
+            Self::SmsSaveFailed => "the modem could not store the message",
+            Self::SmsStorageFull => "the modem's message storage is full",
+            Self::WrongToken => "invalid request token",
+            Self::WrongSession => "the modem session expired",
+            Self::WrongSessionToken => "the modem session token was already used",
+            Self::Other(_) => return None,
+        })
+    }
+}
+
+impl From<i32> for ModemErrorCode {
+    fn from(code: i32) -> Self {
+        [
+            Self::NotSupported,
+            Self::LoginRequired,
+            Self::SystemBusy,
+            Self::FormatError,
+            Self::ParameterError,
+            Self::WrongUsername,
+            Self::WrongPassword,
+            Self::AlreadyLoggedIn,
+            Self::WrongCredentials,
+            Self::TooManyLogins,
+            Self::SmsBusy,
+            Self::SmsSaveFailed,
+            Self::SmsStorageFull,
+            Self::WrongToken,
+            Self::WrongSession,
+            Self::WrongSessionToken,
+        ]
+        .into_iter()
+        .find(|known| known.as_i32() == code)
+        .unwrap_or(Self::Other(code))
+    }
+}
+
+impl std::fmt::Display for ModemErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.description() {
+            Some(description) => write!(f, "{} ({})", self.as_i32(), description),
+            None => write!(f, "{}", self.as_i32()),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     ReqwestError(reqwest::Error),
     XmlParseError(quick_xml::DeError),
     XmlSerializeError(quick_xml::SeError),
-    ModemError { code: i32, message: String },
+    ModemError {
+        code: ModemErrorCode,
+        message: String,
+    },
     SessionError(String),
     Other(String),
 }
@@ -29,6 +247,9 @@ impl std::fmt::Display for Error {
             Error::ReqwestError(e) => write!(f, "HTTP request error: {}", e),
             Error::XmlParseError(e) => write!(f, "XML parsing error: {}", e),
             Error::XmlSerializeError(e) => write!(f, "XML serialization error: {}", e),
+            Error::ModemError { code, message } if message.is_empty() => {
+                write!(f, "Modem error code {}", code)
+            }
             Error::ModemError { code, message } => {
                 write!(f, "Modem error code {}: {}", code, message)
             }
@@ -40,6 +261,45 @@ impl std::fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
+impl Error {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::ModemError { .. } => ErrorKind::ModemError,
+            Error::XmlSerializeError(_) => ErrorKind::Internal,
+            _ => ErrorKind::ModemUnavailable,
+        }
+    }
+
+    /// The modem's numeric error code, if any
+    pub fn code(&self) -> Option<i32> {
+        match self {
+            Error::ModemError { code, .. } => Some(code.as_i32()),
+            _ => None,
+        }
+    }
+
+    /// Whether the request itself is malformed, so it fails on every modem
+    /// and every retry
+    pub fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            Error::ModemError {
+                code: ModemErrorCode::FormatError | ModemErrorCode::ParameterError,
+                ..
+            } | Error::XmlSerializeError(_)
+        )
+    }
+}
+
+impl From<ModemErrorResponse> for Error {
+    fn from(err: ModemErrorResponse) -> Self {
+        Error::ModemError {
+            code: err.code.into(),
+            message: err.message,
+        }
+    }
+}
+
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
         Error::ReqwestError(err)
@@ -86,25 +346,8 @@ pub struct SmsListRequest {
     pub unread_preferred: i32,
 }
 
-/// Represents the Messages wrapper in the SMS list response
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
-#[serde(rename = "Messages")]
-pub struct SmsMessages {
-    #[serde(rename = "Message", default)]
-    pub message: Vec<SmsMessage>,
-}
-
-/// Represents the SMS list response XML
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
-#[serde(rename = "response")]
-pub struct SmsListResponse {
-    #[serde(rename = "Count")]
-    pub count: i32,
-    #[serde(rename = "Messages")]
-    pub messages: SmsMessages,
-}
-
 /// Parameters for fetching the SMS list.
+#[derive(Clone, Debug)]
 pub struct SmsListParams {
     pub box_type: BoxType,
     pub sort_type: SortType,
@@ -120,14 +363,23 @@ pub async fn get_sms_list(
     token: &str,
     params: SmsListParams,
 ) -> Result<SmsListResponse, Error> {
-    let client = HttpClient::builder()
-        .timeout(std::time::Duration::new(10, 0)) // 10 seconds
-        .build()?;
+    get_sms_list_page(modem_url, session_id, token, params, 1).await
+}
+
+/// Fetches one page of `params.read_count` messages from the modem, starting at 1.
+pub async fn get_sms_list_page(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+    params: SmsListParams,
+    page: i32,
+) -> Result<SmsListResponse, Error> {
+    let client = http_client();
     let url = format!("{}/api/sms/sms-list", modem_url);
 
     let sms_list_request = SmsListRequest {
-        page_index: 1,
-        read_count: params.read_count, // Fetching up to 20 messages for now
+        page_index: page,
+        read_count: params.read_count,
         box_type: params.box_type,
         sort_type: params.sort_type,
         ascending: if params.ascending { 1 } else { 0 },
@@ -138,27 +390,22 @@ pub async fn get_sms_list(
 
     let cookie = format!("SessionID={}", session_id);
 
-    let response = client
+    trace_request("POST", &url, &xml_payload);
+    let request = client
         .post(&url)
         .header("Cookie", cookie)
         .header("X-Requested-With", "XMLHttpRequest")
         .header("__RequestVerificationToken", token)
         .header("Content-Type", "text/xml")
-        .body(xml_payload)
-        .send()
-        .await?;
-
-    let response_text = response.text().await?;
+        .body(xml_payload);
+    let response_text = execute(request, &url).await?;
 
     match from_str::<SmsListResponse>(&response_text) {
         Ok(sms_list_response) => Ok(sms_list_response),
         Err(e) => {
             let error_response: Result<ModemErrorResponse, _> = from_str(&response_text);
             match error_response {
-                Ok(err) => Err(Error::ModemError {
-                    code: err.code,
-                    message: err.message,
-                }),
+                Ok(err) => Err(err.into()),
                 Err(_) => Err(Error::Other(format!(
                     "Failed to get SMS list: {} Error: {}",
                     response_text, e
@@ -195,14 +442,50 @@ pub struct SmsRequest {
     pub date: i32,
 }
 
+impl SmsRequest {
+    /// Request for a new message to `to`, with whitespace removed from the numbers
+    pub fn new(to: &[impl AsRef<str>], message: &str) -> Self {
+        let phone = to
+            .iter()
+            .map(|phone| {
+                phone
+                    .as_ref()
+                    .chars()
+                    .filter(|c| !c.is_whitespace())
+                    .collect()
+            })
+            .collect();
+        SmsRequest {
+            index: -1,
+            phones: Phones { phone },
+            sca: "".to_string(),
+            content: message.to_string(),
+            // Septets for GSM-7 or UTF-16 units for UCS-2, not bytes
+            length: crate::encoding::estimate(message).units as i32,
+            reserved: -1,
+            date: -1,
+        }
+    }
+}
+
 /// Fetches the session ID and token from the modem.
 pub async fn get_session_info(modem_url: &str) -> Result<(String, String), Error> {
-    let client = HttpClient::builder()
-        .timeout(std::time::Duration::new(10, 0)) // 10 seconds
-        .build()?;
+    fetch_session_info(modem_url, None).await
+}
+
+/// Fetches a session ID and token; with `session_id`, a new token for that session.
+async fn fetch_session_info(
+    modem_url: &str,
+    session_id: Option<&str>,
+) -> Result<(String, String), Error> {
+    let client = http_client();
     let url = format!("{}/api/webserver/SesTokInfo", modem_url);
-    let response = client.get(&url).send().await?;
-    let response_text = response.text().await?;
+    let mut request = client.get(&url);
+    if let Some(session_id) = session_id {
+        request = request.header("Cookie", format!("SessionID={}", session_id));
+    }
+    trace_request("GET", &url, "");
+    let response_text = execute(request, &url).await?;
 
     let session_info: Result<SessionInfo, _> = from_str(&response_text);
 
@@ -220,10 +503,7 @@ pub async fn get_session_info(modem_url: &str) -> Result<(String, String), Error
         Err(_) => {
             let error_response: Result<ModemErrorResponse, _> = from_str(&response_text);
             match error_response {
-                Ok(err) => Err(Error::ModemError {
-                    code: err.code,
-                    message: err.message,
-                }),
+                Ok(err) => Err(err.into()),
                 Err(_) => Err(Error::Other(format!(
                     "Failed to get session info: {}",
                     response_text
@@ -233,62 +513,179 @@ pub async fn get_session_info(modem_url: &str) -> Result<(String, String), Error
     }
 }
 
-/// Sends an SMS message via the modem.
+/// Credentials for modems whose firmware requires a login before the SMS API works
+#[derive(Clone, PartialEq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+/// Represents the login request XML
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename = "request")]
+pub struct LoginRequest {
+    #[serde(rename = "Username")]
+    pub username: String,
+    #[serde(rename = "Password")]
+    pub password: String,
+    pub password_type: i32,
+}
+
+/// base64 of the hex encoded SHA-256 digest, the building block of password_type 4
+fn sha256_hex_base64(data: &str) -> String {
+    use base64::Engine;
+    use sha2::{Digest, Sha256};
+
+    let hex: String = Sha256::digest(data.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    base64::engine::general_purpose::STANDARD.encode(hex)
+}
+
+/// Hashes the password for `/api/user/login` with password_type 4:
+/// `b64(sha256(username + b64(sha256(password)) + token))`, each digest hex encoded
+pub fn login_password(credentials: &Credentials, token: &str) -> String {
+    sha256_hex_base64(&format!(
+        "{}{}{}",
+        credentials.username,
+        sha256_hex_base64(&credentials.password),
+        token
+    ))
+}
+
+/// Logs in to the modem with a session from `get_session_info`. Returns the
+/// authenticated session ID and a fresh token for the next request.
+pub async fn login(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+    credentials: &Credentials,
+) -> Result<(String, String), Error> {
+    let client = http_client();
+    let url = format!("{}/api/user/login", modem_url);
+
+    let xml_payload = to_string(&LoginRequest {
+        username: credentials.username.clone(),
+        password: login_password(credentials, token),
+        password_type: 4,
+    })?;
+
+    trace_request("POST", &url, &xml_payload);
+    let started = Instant::now();
+    let response = client
+        .post(&url)
+        .header("Cookie", format!("SessionID={}", session_id))
+        .header("X-Requested-With", "XMLHttpRequest")
+        .header("__RequestVerificationToken", token)
+        .header("Content-Type", "text/xml")
+        .body(xml_payload)
+        .send()
+        .await;
+    record_duration(&url, started);
+    let response = response?;
+
+    // The modem starts a new session on login and may hand out the next token
+    let new_session_id = response
+        .headers()
+        .get_all(reqwest::header::SET_COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .find_map(|cookie| {
+            let value = cookie.strip_prefix("SessionID=")?;
+            Some(value.split(';').next().unwrap_or(value).to_string())
+        })
+        .unwrap_or_else(|| session_id.to_string());
+    let new_token = [
+        "__RequestVerificationTokenone",
+        "__RequestVerificationToken",
+    ]
+    .iter()
+    .filter_map(|name| response.headers().get(*name)?.to_str().ok())
+    .filter_map(|tokens| tokens.split('#').next())
+    .find(|token| !token.is_empty())
+    .map(str::to_string);
+    let response_text = response.text().await?;
+    trace_response(&url, &response_text);
+
+    if !response_text.contains("<response>OK</response>") {
+        return match from_str::<ModemErrorResponse>(&response_text) {
+            Ok(err) => Err(err.into()),
+            Err(_) => Err(Error::Other(format!("Failed to log in: {}", response_text))),
+        };
+    }
+
+    match new_token {
+        Some(token) => Ok((new_session_id, token)),
+        None => fetch_session_info(modem_url, Some(&new_session_id)).await,
+    }
+}
+
+/// Fetches a session and, if `credentials` are set, logs in with it. Returns
+/// the session ID and token to pass to the other API functions.
+pub async fn open_session(
+    modem_url: &str,
+    credentials: Option<&Credentials>,
+) -> Result<(String, String), Error> {
+    let (session_id, token) = get_session_info(modem_url).await?;
+    match credentials {
+        Some(credentials) => login(modem_url, &session_id, &token, credentials).await,
+        None => Ok((session_id, token)),
+    }
+}
+
+/// URL and XML body of the request [`send_sms`] posts to send `message` to `to`
+pub fn send_sms_request(
+    modem_url: &str,
+    to: &[impl AsRef<str>],
+    message: &str,
+) -> Result<(String, String), Error> {
+    let url = format!("{}/api/sms/send-sms", modem_url);
+    Ok((url, to_string(&SmsRequest::new(to, message))?))
+}
+
+/// Sends an SMS message via the modem to one or more phone numbers in a single request.
 pub async fn send_sms(
     modem_url: &str,
     session_id: &str,
     token: &str,
-    to: &str,
+    to: &[impl AsRef<str>],
     message: &str,
     dry_run: bool,
 ) -> Result<(), Error> {
-    let client = HttpClient::builder()
-        .timeout(std::time::Duration::new(10, 0)) // 10 seconds
-        .build()?;
-    let url = format!("{}/api/sms/send-sms", modem_url);
-
-    let to_clean: String = to.chars().filter(|c| !c.is_whitespace()).collect();
-
-    let sms_request = SmsRequest {
-        index: -1,
-        phones: Phones {
-            phone: vec![to_clean],
-        },
-        sca: "".to_string(),
-        content: message.to_string(),
-        length: message.len() as i32,
-        reserved: -1,
-        date: -1,
-    };
-
-    let xml_payload = to_string(&sms_request)?;
+    let client = http_client();
+    let (url, xml_payload) = send_sms_request(modem_url, to, message)?;
 
     let cookie = format!("SessionID={}", session_id);
 
     if dry_run {
         Ok(())
     } else {
-        let response = client
+        trace_request("POST", &url, &xml_payload);
+        let request = client
             .post(&url)
             .header("Cookie", cookie)
             .header("X-Requested-With", "XMLHttpRequest")
             .header("__RequestVerificationToken", token)
             .header("Content-Type", "text/xml")
-            .body(xml_payload)
-            .send()
-            .await?;
-
-        let response_text = response.text().await?;
+            .body(xml_payload);
+        let response_text = execute(request, &url).await?;
 
         if response_text.contains("<response>OK</response>") {
             Ok(())
         } else {
             let error_response: Result<ModemErrorResponse, _> = from_str(&response_text);
             match error_response {
-                Ok(err) => Err(Error::ModemError {
-                    code: err.code,
-                    message: err.message,
-                }),
+                Ok(err) => Err(err.into()),
                 Err(_) => Err(Error::Other(format!(
                     "Failed to send SMS: {}",
                     response_text
@@ -297,3 +694,1084 @@ pub async fn send_sms(
         }
     }
 }
+
+/// Represents the XML response from /api/sms/sms-count
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "response")]
+pub struct SmsCount {
+    #[serde(rename = "LocalUnread")]
+    pub local_unread: i32,
+    #[serde(rename = "LocalInbox")]
+    pub local_inbox: i32,
+    #[serde(rename = "LocalOutbox")]
+    pub local_outbox: i32,
+    #[serde(rename = "LocalDraft")]
+    pub local_draft: i32,
+    #[serde(rename = "LocalDeleted")]
+    pub local_deleted: i32,
+    #[serde(rename = "SimUnread")]
+    pub sim_unread: i32,
+    #[serde(rename = "SimInbox")]
+    pub sim_inbox: i32,
+    #[serde(rename = "SimOutbox")]
+    pub sim_outbox: i32,
+    #[serde(rename = "SimDraft")]
+    pub sim_draft: i32,
+    /// Capacity of the modem's message storage
+    #[serde(rename = "LocalMax")]
+    pub local_max: i32,
+    #[serde(rename = "SimMax")]
+    pub sim_max: i32,
+    #[serde(rename = "NewMsg", default)]
+    pub new_msg: i32,
+}
+
+impl SmsCount {
+    /// Messages in all local boxes, which share the local storage
+    pub fn local_used(&self) -> i32 {
+        self.local_inbox + self.local_outbox + self.local_draft + self.local_deleted
+    }
+
+    /// Whether the local storage is full, so new messages cannot be received
+    pub fn storage_full(&self) -> bool {
+        self.local_max > 0 && self.local_used() >= self.local_max
+    }
+}
+
+/// GETs `path` from the modem API and parses the response, or the modem's error.
+/// `what` describes the request in error messages.
+async fn get_api<T: DeserializeOwned>(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+    path: &str,
+    what: &str,
+) -> Result<T, Error> {
+    let client = http_client();
+    let url = format!("{}{}", modem_url, path);
+
+    trace_request("GET", &url, "");
+    let request = client
+        .get(&url)
+        .header("Cookie", format!("SessionID={}", session_id))
+        .header("X-Requested-With", "XMLHttpRequest")
+        .header("__RequestVerificationToken", token);
+    let response_text = execute(request, &url).await?;
+
+    match from_str::<T>(&response_text) {
+        Ok(value) => Ok(value),
+        Err(e) => match from_str::<ModemErrorResponse>(&response_text) {
+            Ok(err) => Err(err.into()),
+            Err(_) => Err(Error::Other(format!(
+                "Failed to get {}: {} Error: {}",
+                what, response_text, e
+            ))),
+        },
+    }
+}
+
+/// Fetches the number of messages per box from the modem.
+pub async fn get_sms_count(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+) -> Result<SmsCount, Error> {
+    get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/sms/sms-count",
+        "SMS count",
+    )
+    .await
+}
+
+/// Represents the XML response from /api/device/signal. Values are reported as
+/// strings with units, e.g. `-95dBm`; which fields are present depends on the
+/// network mode.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "response")]
+pub struct SignalInfo {
+    /// Network mode: 0 = GSM, 2 = WCDMA, 7 = LTE
+    #[serde(default)]
+    pub mode: Option<String>,
+    #[serde(default)]
+    pub rssi: Option<String>,
+    /// LTE reference signal received power
+    #[serde(default)]
+    pub rsrp: Option<String>,
+    /// LTE reference signal received quality
+    #[serde(default)]
+    pub rsrq: Option<String>,
+    /// LTE signal to interference plus noise ratio
+    #[serde(default)]
+    pub sinr: Option<String>,
+    /// WCDMA received signal code power
+    #[serde(default)]
+    pub rscp: Option<String>,
+    /// WCDMA energy per chip to interference ratio
+    #[serde(default)]
+    pub ecio: Option<String>,
+    #[serde(default)]
+    pub cell_id: Option<String>,
+    /// Physical cell ID
+    #[serde(default)]
+    pub pci: Option<String>,
+    #[serde(default)]
+    pub band: Option<String>,
+    #[serde(default)]
+    pub earfcn: Option<String>,
+    #[serde(default)]
+    pub plmn: Option<String>,
+    #[serde(default)]
+    pub dlbandwidth: Option<String>,
+    #[serde(default)]
+    pub ulbandwidth: Option<String>,
+}
+
+impl SignalInfo {
+    /// Name of the network mode
+    pub fn mode_name(&self) -> Option<&'static str> {
+        match self.mode.as_deref()? {
+            "0" => Some("GSM"),
+            "2" => Some("WCDMA"),
+            "7" => Some("LTE"),
+            _ => None,
+        }
+    }
+
+    /// Rough rating of the signal: RSRP for LTE, RSCP for WCDMA, RSSI otherwise
+    pub fn quality(&self) -> Option<&'static str> {
+        let (value, thresholds) = if let Some(rsrp) = self.rsrp.as_deref().and_then(parse_level) {
+            (rsrp, [-80.0, -90.0, -100.0])
+        } else if let Some(rscp) = self.rscp.as_deref().and_then(parse_level) {
+            (rscp, [-75.0, -85.0, -95.0])
+        } else {
+            (
+                self.rssi.as_deref().and_then(parse_level)?,
+                [-65.0, -75.0, -85.0],
+            )
+        };
+        Some(if value >= thresholds[0] {
+            "excellent"
+        } else if value >= thresholds[1] {
+            "good"
+        } else if value >= thresholds[2] {
+            "fair"
+        } else {
+            "poor"
+        })
+    }
+}
+
+/// Numeric part of a signal value such as `-95dBm` or `>=-51dBm`
+pub fn parse_level(value: &str) -> Option<f64> {
+    let value = value.trim_start_matches(['>', '<', '=']);
+    let end = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '-' || c == '.'))
+        .unwrap_or(value.len());
+    value[..end].parse().ok()
+}
+
+/// Fetches the signal strength and serving cell from the modem.
+pub async fn get_signal(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+) -> Result<SignalInfo, Error> {
+    get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/device/signal",
+        "signal information",
+    )
+    .await
+}
+
+/// Represents the XML response from /api/pin/status
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "response")]
+pub struct PinStatus {
+    /// 255 = no SIM, 257 = ready, 260 = PIN required, 261 = PUK required
+    #[serde(rename = "SimState")]
+    pub sim_state: i32,
+    /// Whether the SIM asks for a PIN on startup
+    #[serde(rename = "PinOptState", default)]
+    pub pin_opt_state: i32,
+    /// PIN attempts left
+    #[serde(rename = "SimPinTimes", default)]
+    pub sim_pin_times: i32,
+    /// PUK attempts left
+    #[serde(rename = "SimPukTimes", default)]
+    pub sim_puk_times: i32,
+}
+
+impl PinStatus {
+    /// Description of the SIM state
+    pub fn sim_state_name(&self) -> &'static str {
+        match self.sim_state {
+            255 => "no SIM",
+            256 => "SIM error",
+            257 => "ready",
+            258 => "PIN disabled",
+            259 => "PIN validated",
+            260 => "PIN required",
+            261 => "PUK required",
+            _ => "unknown",
+        }
+    }
+
+    /// Whether the SIM is unlocked and can send messages
+    pub fn is_ready(&self) -> bool {
+        matches!(self.sim_state, 257..=259)
+    }
+}
+
+/// Fetches the SIM and PIN state from the modem.
+pub async fn get_pin_status(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+) -> Result<PinStatus, Error> {
+    get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/pin/status",
+        "PIN status",
+    )
+    .await
+}
+
+/// Fetches the model, identifiers and firmware versions of the modem.
+pub async fn get_device_info(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+) -> Result<DeviceInfo, Error> {
+    get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/device/information",
+        "device information",
+    )
+    .await
+}
+
+/// Represents the XML of /api/sms/config, both the response and the request to
+/// change it. Only the fields the modem accepts back are kept.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "request")]
+pub struct SmsConfig {
+    #[serde(rename = "SaveMode", default)]
+    pub save_mode: i32,
+    /// Validity period of sent messages
+    #[serde(rename = "Validity", default)]
+    pub validity: i32,
+    /// Number of the SMS service center
+    #[serde(rename = "Sca", default)]
+    pub sca: String,
+    /// `1` to request delivery reports
+    #[serde(rename = "UseSReport", default)]
+    pub use_sreport: i32,
+    #[serde(rename = "SendType", default)]
+    pub send_type: i32,
+}
+
+/// Fetches the SMS settings, including the service center number.
+pub async fn get_sms_config(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+) -> Result<SmsConfig, Error> {
+    get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/sms/config",
+        "SMS config",
+    )
+    .await
+}
+
+/// Changes the SMS settings.
+pub async fn set_sms_config(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+    config: &SmsConfig,
+) -> Result<(), Error> {
+    post_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/sms/config",
+        config,
+        "change SMS config",
+    )
+    .await
+}
+
+/// Represents the XML response from /api/net/current-plmn
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "response")]
+pub struct CurrentPlmn {
+    #[serde(rename = "FullName", default)]
+    pub full_name: Option<String>,
+    #[serde(rename = "ShortName", default)]
+    pub short_name: Option<String>,
+    #[serde(rename = "Numeric", default)]
+    pub numeric: Option<String>,
+}
+
+/// Represents the XML response from /api/monitoring/status. Only the fields
+/// describing the network registration are kept.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "response")]
+pub struct MonitoringStatus {
+    #[serde(rename = "ConnectionStatus", default)]
+    pub connection_status: Option<String>,
+    #[serde(rename = "CurrentNetworkType", default)]
+    pub current_network_type: Option<String>,
+    /// Finer grained network type reported by newer firmware
+    #[serde(rename = "CurrentNetworkTypeEx", default)]
+    pub current_network_type_ex: Option<String>,
+    /// `2` when the SIM is registered with a network
+    #[serde(rename = "ServiceStatus", default)]
+    pub service_status: Option<String>,
+    #[serde(rename = "RoamingStatus", default)]
+    pub roaming_status: Option<String>,
+}
+
+impl MonitoringStatus {
+    /// Name of the radio access technology, preferring `CurrentNetworkTypeEx`
+    pub fn network_type_name(&self) -> Option<&'static str> {
+        let ex = match self.current_network_type_ex.as_deref().unwrap_or_default() {
+            "1" => Some("GSM"),
+            "2" => Some("GPRS"),
+            "3" => Some("EDGE"),
+            "41" => Some("WCDMA"),
+            "42" => Some("HSDPA"),
+            "43" => Some("HSUPA"),
+            "44" => Some("HSPA"),
+            "45" => Some("HSPA+"),
+            "46" => Some("DC-HSPA+"),
+            "101" => Some("LTE"),
+            _ => None,
+        };
+        ex.or(match self.current_network_type.as_deref()? {
+            "0" => Some("no service"),
+            "1" => Some("GSM"),
+            "2" => Some("GPRS"),
+            "3" => Some("EDGE"),
+            "4" => Some("WCDMA"),
+            "5" => Some("HSDPA"),
+            "6" => Some("HSUPA"),
+            "7" => Some("HSPA"),
+            "9" => Some("HSPA+"),
+            "19" => Some("LTE"),
+            _ => None,
+        })
+    }
+
+    /// State of the data connection
+    pub fn connection_name(&self) -> Option<&'static str> {
+        match self.connection_status.as_deref()? {
+            "900" => Some("connecting"),
+            "901" => Some("connected"),
+            "902" => Some("disconnected"),
+            "903" => Some("disconnecting"),
+            _ => Some("failed"),
+        }
+    }
+}
+
+/// Fetches the operator and network registration from the modem.
+pub async fn get_network_info(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+) -> Result<NetworkInfo, Error> {
+    let plmn: CurrentPlmn = get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/net/current-plmn",
+        "current operator",
+    )
+    .await?;
+    let status: MonitoringStatus = get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/monitoring/status",
+        "network status",
+    )
+    .await?;
+    Ok(network_info(plmn, &status))
+}
+
+fn network_info(plmn: CurrentPlmn, status: &MonitoringStatus) -> NetworkInfo {
+    let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+    NetworkInfo {
+        operator: non_empty(plmn.full_name).or(non_empty(plmn.short_name)),
+        plmn: non_empty(plmn.numeric),
+        network_type: status.network_type_name().map(str::to_string),
+        connection: status.connection_name().map(str::to_string),
+        roaming: status.roaming_status.as_deref().map(|s| s == "1"),
+    }
+}
+
+/// Represents the XML response from /api/sms/send-status, the progress of the
+/// send the modem is working on. Phone lists are separated by `;`.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "response")]
+pub struct SendProgress {
+    /// Recipients of the send in progress, empty if nothing is being sent
+    #[serde(rename = "Phone", default)]
+    pub phone: String,
+    #[serde(rename = "SucPhone", default)]
+    pub suc_phone: String,
+    #[serde(rename = "FailPhone", default)]
+    pub fail_phone: String,
+    #[serde(rename = "TotalCount", default)]
+    pub total_count: i32,
+    #[serde(rename = "CurIndex", default)]
+    pub cur_index: i32,
+}
+
+impl SendProgress {
+    pub fn in_progress(&self) -> bool {
+        !self.phone.trim_matches(';').is_empty()
+    }
+}
+
+/// Fetches the progress of the send the modem is working on.
+pub async fn get_send_status(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+) -> Result<SendProgress, Error> {
+    get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/sms/send-status",
+        "send status",
+    )
+    .await
+}
+
+/// Represents the cancel-send request XML, `<request>1</request>`
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename = "request")]
+pub struct CancelSendRequest(pub i32);
+
+/// Cancels the send the modem is working on, e.g. one stuck on a flaky network.
+pub async fn cancel_send(modem_url: &str, session_id: &str, token: &str) -> Result<(), Error> {
+    post_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/sms/cancel-send",
+        &CancelSendRequest(1),
+        "cancel send",
+    )
+    .await
+}
+
+/// Stores a message in the modem's drafts without sending it.
+pub async fn save_sms(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+    to: &[impl AsRef<str>],
+    message: &str,
+) -> Result<(), Error> {
+    post_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/sms/save-sms",
+        &SmsRequest::new(to, message),
+        "save SMS",
+    )
+    .await
+}
+
+/// Represents the SMS delete request XML
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename = "request")]
+pub struct DeleteSmsRequest {
+    #[serde(rename = "Index")]
+    pub index: Vec<i32>,
+}
+
+/// POSTs `request` as XML to `path` and checks for an OK response, or the
+/// modem's error. `what` describes the request in error messages.
+async fn post_api(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+    path: &str,
+    request: &impl Serialize,
+    what: &str,
+) -> Result<(), Error> {
+    let client = http_client();
+    let url = format!("{}{}", modem_url, path);
+
+    let xml_payload = to_string(request)?;
+
+    trace_request("POST", &url, &xml_payload);
+    let request = client
+        .post(&url)
+        .header("Cookie", format!("SessionID={}", session_id))
+        .header("X-Requested-With", "XMLHttpRequest")
+        .header("__RequestVerificationToken", token)
+        .header("Content-Type", "text/xml")
+        .body(xml_payload);
+    let response_text = execute(request, &url).await?;
+
+    if response_text.contains("<response>OK</response>") {
+        Ok(())
+    } else {
+        match from_str::<ModemErrorResponse>(&response_text) {
+            Ok(err) => Err(err.into()),
+            Err(_) => Err(Error::Other(format!(
+                "Failed to {}: {}",
+                what, response_text
+            ))),
+        }
+    }
+}
+
+/// Deletes the messages with the given indexes from the modem.
+pub async fn delete_sms(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+    indexes: &[i32],
+) -> Result<(), Error> {
+    let request = DeleteSmsRequest {
+        index: indexes.to_vec(),
+    };
+    post_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/sms/delete-sms",
+        &request,
+        "delete SMS",
+    )
+    .await
+}
+
+/// Represents the SMS set-read request XML, listing indexes like the delete request
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename = "request")]
+pub struct SetReadRequest {
+    #[serde(rename = "Index")]
+    pub index: Vec<i32>,
+}
+
+/// Marks the messages with the given indexes as read.
+pub async fn set_read(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+    indexes: &[i32],
+) -> Result<(), Error> {
+    let request = SetReadRequest {
+        index: indexes.to_vec(),
+    };
+    post_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/sms/set-read",
+        &request,
+        "mark SMS as read",
+    )
+    .await
+}
+
+/// Represents the XML response from /api/monitoring/check-notifications, a
+/// cheap way to find out whether new messages arrived
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "response")]
+pub struct Notifications {
+    /// Number of unread messages in the inbox
+    #[serde(rename = "UnreadMessage", default)]
+    pub unread_message: i32,
+    /// `1` when the modem's message storage is full
+    #[serde(rename = "SmsStorageFull", default)]
+    pub sms_storage_full: i32,
+}
+
+/// Fetches the unread message count and storage full flag from the modem.
+pub async fn check_notifications(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+) -> Result<Notifications, Error> {
+    get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/monitoring/check-notifications",
+        "notifications",
+    )
+    .await
+}
+
+/// Represents the XML response from /api/monitoring/traffic-statistics. `Current*`
+/// values cover the current data connection, `Total*` values everything since
+/// the statistics were last cleared. Byte counts and seconds.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "response")]
+pub struct TrafficStatistics {
+    #[serde(rename = "CurrentConnectTime")]
+    pub current_connect_time: u64,
+    #[serde(rename = "CurrentUpload")]
+    pub current_upload: u64,
+    #[serde(rename = "CurrentDownload")]
+    pub current_download: u64,
+    /// Bytes per second
+    #[serde(rename = "CurrentUploadRate", default)]
+    pub current_upload_rate: u64,
+    /// Bytes per second
+    #[serde(rename = "CurrentDownloadRate", default)]
+    pub current_download_rate: u64,
+    #[serde(rename = "TotalUpload")]
+    pub total_upload: u64,
+    #[serde(rename = "TotalDownload")]
+    pub total_download: u64,
+    #[serde(rename = "TotalConnectTime")]
+    pub total_connect_time: u64,
+}
+
+/// Fetches the data traffic counters from the modem.
+pub async fn get_traffic_statistics(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+) -> Result<TrafficStatistics, Error> {
+    get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/monitoring/traffic-statistics",
+        "traffic statistics",
+    )
+    .await
+}
+
+/// Represents the XML of /api/dialup/mobile-data-switch, both the response and
+/// the request to change it. `1` means the data connection is enabled.
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "request")]
+pub struct MobileDataSwitch {
+    pub dataswitch: i32,
+}
+
+/// Fetches whether the mobile data connection is enabled.
+pub async fn get_mobile_data(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+) -> Result<bool, Error> {
+    let switch: MobileDataSwitch = get_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/dialup/mobile-data-switch",
+        "mobile data switch",
+    )
+    .await?;
+    Ok(switch.dataswitch == 1)
+}
+
+/// Enables or disables the mobile data connection.
+pub async fn set_mobile_data(
+    modem_url: &str,
+    session_id: &str,
+    token: &str,
+    enabled: bool,
+) -> Result<(), Error> {
+    let request = MobileDataSwitch {
+        dataswitch: enabled as i32,
+    };
+    post_api(
+        modem_url,
+        session_id,
+        token,
+        "/api/dialup/mobile-data-switch",
+        &request,
+        "switch mobile data",
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact() {
+        assert_eq!(
+            redact("<request><Username>admin</Username><Password>c2VjcmV0</Password></request>"),
+            "<request><Username>admin</Username><Password>***</Password></request>"
+        );
+        assert_eq!(
+            redact("<response><SesInfo>SessionID=abc</SesInfo><TokInfo>tok</TokInfo></response>"),
+            "<response><SesInfo>***</SesInfo><TokInfo>***</TokInfo></response>"
+        );
+        assert_eq!(redact("<Password>unclosed"), "<Password>unclosed");
+    }
+
+    #[test]
+    fn test_set_read_request_xml() {
+        let xml = to_string(&SetReadRequest { index: vec![40001] }).unwrap();
+        assert_eq!(xml, "<request><Index>40001</Index></request>");
+    }
+
+    #[test]
+    fn test_delete_sms_request_xml() {
+        let xml = to_string(&DeleteSmsRequest {
+            index: vec![40001, 40002],
+        })
+        .unwrap();
+        assert_eq!(
+            xml,
+            "<request><Index>40001</Index><Index>40002</Index></request>"
+        );
+    }
+
+    #[test]
+    fn test_mobile_data_switch_xml() {
+        let xml = to_string(&MobileDataSwitch { dataswitch: 0 }).unwrap();
+        assert_eq!(xml, "<request><dataswitch>0</dataswitch></request>");
+        let switch: MobileDataSwitch =
+            from_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?><response><dataswitch>1</dataswitch></response>")
+                .unwrap();
+        assert_eq!(switch.dataswitch, 1);
+    }
+
+    #[test]
+    fn test_parse_traffic_statistics() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response>
+<CurrentConnectTime>3600</CurrentConnectTime>
+<CurrentUpload>1024</CurrentUpload>
+<CurrentDownload>2048</CurrentDownload>
+<CurrentDownloadRate>0</CurrentDownloadRate>
+<CurrentUploadRate>0</CurrentUploadRate>
+<TotalUpload>5242880</TotalUpload>
+<TotalDownload>104857600</TotalDownload>
+<TotalConnectTime>86400</TotalConnectTime>
+<showtraffic>1</showtraffic>
+</response>"#;
+        let stats: TrafficStatistics = from_str(xml).unwrap();
+        assert_eq!(stats.current_connect_time, 3600);
+        assert_eq!(stats.current_download, 2048);
+        assert_eq!(stats.total_upload, 5242880);
+        assert_eq!(stats.total_download, 104857600);
+        assert_eq!(stats.total_connect_time, 86400);
+    }
+
+    #[test]
+    fn test_parse_network_info() {
+        let plmn: CurrentPlmn = from_str(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><State>0</State><FullName>Telekom.de</FullName><ShortName>Telekom</ShortName><Numeric>26201</Numeric><Rat>7</Rat><Spn></Spn></response>"#,
+        )
+        .unwrap();
+        let status: MonitoringStatus = from_str(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><ConnectionStatus>901</ConnectionStatus><WifiConnectionStatus></WifiConnectionStatus><SignalStrength></SignalStrength><SignalIcon>4</SignalIcon><CurrentNetworkType>19</CurrentNetworkType><CurrentServiceDomain>3</CurrentServiceDomain><RoamingStatus>0</RoamingStatus><ServiceStatus>2</ServiceStatus><SimStatus>1</SimStatus><CurrentNetworkTypeEx>101</CurrentNetworkTypeEx></response>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            network_info(plmn, &status),
+            NetworkInfo {
+                operator: Some("Telekom.de".to_string()),
+                plmn: Some("26201".to_string()),
+                network_type: Some("LTE".to_string()),
+                connection: Some("connected".to_string()),
+                roaming: Some(false),
+            }
+        );
+
+        // Older firmware without CurrentNetworkTypeEx
+        let status = MonitoringStatus {
+            current_network_type: Some("9".to_string()),
+            connection_status: Some("902".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(status.network_type_name(), Some("HSPA+"));
+        assert_eq!(status.connection_name(), Some("disconnected"));
+    }
+
+    #[test]
+    fn test_parse_notifications() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response>
+<UnreadMessage>3</UnreadMessage>
+<SmsStorageFull>0</SmsStorageFull>
+<OnlineUpdateStatus>10</OnlineUpdateStatus>
+<SimOperEvent>0</SimOperEvent>
+</response>"#;
+        let notifications: Notifications = from_str(xml).unwrap();
+        assert_eq!(notifications.unread_message, 3);
+        assert_eq!(notifications.sms_storage_full, 0);
+    }
+
+    #[test]
+    fn test_sms_config_xml() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<response>
+<SaveMode>0</SaveMode>
+<Validity>10752</Validity>
+<Sca>+491710760000</Sca>
+<UseSReport>0</UseSReport>
+<SendType>1</SendType>
+<pagesize>20</pagesize>
+<maxphone>50</maxphone>
+</response>"#;
+        let mut config: SmsConfig = from_str(xml).unwrap();
+        assert_eq!(config.sca, "+491710760000");
+        assert_eq!(config.validity, 10752);
+
+        config.sca = "+491760000443".to_string();
+        assert_eq!(
+            to_string(&config).unwrap(),
+            "<request><SaveMode>0</SaveMode><Validity>10752</Validity><Sca>+491760000443</Sca><UseSReport>0</UseSReport><SendType>1</SendType></request>"
+        );
+    }
+
+    #[test]
+    fn test_send_status_xml() {
+        assert_eq!(
+            to_string(&CancelSendRequest(1)).unwrap(),
+            "<request>1</request>"
+        );
+        let progress: SendProgress = from_str(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<response><Phone>+491761234567;</Phone><SucPhone></SucPhone><FailPhone></FailPhone><TotalCount>1</TotalCount><CurIndex>0</CurIndex></response>"#,
+        )
+        .unwrap();
+        assert!(progress.in_progress());
+        assert_eq!(progress.total_count, 1);
+        assert!(!SendProgress::default().in_progress());
+    }
+
+    #[test]
+    fn test_storage_full() {
+        let mut count = SmsCount {
+            local_inbox: 480,
+            local_outbox: 15,
+            local_deleted: 4,
+            local_max: 500,
+            ..Default::default()
+        };
+        assert_eq!(count.local_used(), 499);
+        assert!(!count.storage_full());
+        count.local_draft = 1;
+        assert!(count.storage_full());
+        // Some modems report no capacity
+        assert!(!SmsCount::default().storage_full());
+    }
+
+    #[test]
+    fn test_modem_error_code() {
+        assert_eq!(ModemErrorCode::from(113004), ModemErrorCode::SmsBusy);
+        assert_eq!(ModemErrorCode::from(125003).as_i32(), 125003);
+        assert_eq!(ModemErrorCode::from(999), ModemErrorCode::Other(999));
+
+        let err: Error = ModemErrorResponse {
+            code: 113004,
+            message: String::new(),
+        }
+        .into();
+        assert_eq!(err.code(), Some(113004));
+        assert_eq!(
+            err.to_string(),
+            "Modem error code 113004 (the modem is still busy sending a previous message)"
+        );
+        let err: Error = ModemErrorResponse {
+            code: 999,
+            message: "odd".to_string(),
+        }
+        .into();
+        assert_eq!(err.to_string(), "Modem error code 999: odd");
+    }
+
+    #[test]
+    fn test_login_password() {
+        let credentials = Credentials {
+            username: "admin".to_string(),
+            password: "admin".to_string(),
+        };
+        assert_eq!(
+            sha256_hex_base64("admin"),
+            "OGM2OTc2ZTViNTQxMDQxNWJkZTkwOGJkNGRlZTE1ZGZiMTY3YTljODczZmM0YmI4YTgxZjZmMmFiNDQ4YTkxOA=="
+        );
+        let hashed = login_password(&credentials, "token");
+        assert_eq!(
+            hashed,
+            "OTYxMzMzMjZkNWFkZmY0YmM4MWVhYzNkMjEyNjliOWExZWFmOGQwZjJjMjAwMzMzY2M0ZWEwZjIyZGU2M2NhMg=="
+        );
+        assert!(!format!("{:?}", credentials).contains("password: \"admin\""));
+
+        let xml = to_string(&LoginRequest {
+            username: "admin".to_string(),
+            password: hashed.clone(),
+            password_type: 4,
+        })
+        .unwrap();
+        assert_eq!(
+            xml,
+            format!(
+                "<request><Username>admin</Username><Password>{}</Password><password_type>4</password_type></request>",
+                hashed
+            )
+        );
+    }
+
+    #[test]
+    fn test_sms_request_xml_multiple_phones() {
+        let xml = to_string(&SmsRequest {
+            index: -1,
+            phones: Phones {
+                phone: vec!["+441234567890".to_string(), "+441234567891".to_string()],
+            },
+            sca: "".to_string(),
+            content: "Hi".to_string(),
+            length: 2,
+            reserved: -1,
+            date: -1,
+        })
+        .unwrap();
+        assert!(
+            xml.contains(
+                "<Phones><Phone>+441234567890</Phone><Phone>+441234567891</Phone></Phones>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_sms_request_escapes_content() {
+        let message = "Tom & Jerry <3 \"quoted\" 'single' ]]> a > b";
+        let xml = to_string(&SmsRequest::new(&["+441234567890"], message)).unwrap();
+        assert!(xml.contains(
+            "<Content>Tom &amp; Jerry &lt;3 &quot;quoted&quot; &apos;single&apos; ]]&gt; a &gt; b</Content>"
+        ));
+
+        // The content as stored by the modem reads back unchanged, whitespace included
+        let content = xml
+            .split("<Content>")
+            .nth(1)
+            .and_then(|rest| rest.split("</Content>").next())
+            .unwrap();
+        let response: SmsListResponse = from_str(&format!(
+            "<response><Count>1</Count><Messages><Message><Smstat>0</Smstat><Index>40001</Index>\
+<Phone>+441234567890</Phone><Content> {}\n</Content><Date>2026-10-16 10:00:00</Date><Sca></Sca>\
+<SaveType>0</SaveType><Priority>0</Priority><SmsType>1</SmsType></Message></Messages></response>",
+            content
+        ))
+        .unwrap();
+        assert_eq!(
+            response.messages.message[0].content,
+            format!(" {}\n", message)
+        );
+    }
+
+    #[test]
+    fn test_sms_request_length() {
+        let to = ["+441234567890"];
+        assert_eq!(SmsRequest::new(&to, "Hello").length, 5);
+        // GSM-7 umlauts take one septet, extension characters two
+        assert_eq!(SmsRequest::new(&to, "Grüße").length, 5);
+        assert_eq!(SmsRequest::new(&to, "5€").length, 3);
+        // UCS-2 counts UTF-16 units, two for an emoji
+        assert_eq!(SmsRequest::new(&to, "Łódź").length, 4);
+        assert_eq!(SmsRequest::new(&to, "Hi 😀").length, 5);
+    }
+
+    #[test]
+    fn test_parse_device_info() {
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+<response><DeviceName>E3372h-320</DeviceName><SerialNumber>G4PDW19A01234567</SerialNumber>\
+<Imei>861234567890123</Imei><Imsi>234150123456789</Imsi><Iccid>8944110012345678901</Iccid>\
+<Msisdn></Msisdn><HardwareVersion>CL4E3372HM</HardwareVersion>\
+<SoftwareVersion>10.0.3.1(H195SP1C983)</SoftwareVersion><WebUIVersion>WEBUI 10.0.3.1</WebUIVersion>\
+<MacAddress1>00:1E:10:1F:00:00</MacAddress1><MacAddress2></MacAddress2>\
+<ProductFamily>LTE</ProductFamily><Classify>hilink</Classify><workmode>LTE</workmode></response>";
+        let info: DeviceInfo = from_str(xml).unwrap();
+        assert_eq!(info.device_name.as_deref(), Some("E3372h-320"));
+        assert_eq!(info.imei.as_deref(), Some("861234567890123"));
+        assert_eq!(
+            info.software_version.as_deref(),
+            Some("10.0.3.1(H195SP1C983)")
+        );
+        assert_eq!(info.work_mode.as_deref(), Some("LTE"));
+
+        // Older models report fewer fields
+        let info: DeviceInfo =
+            from_str("<response><DeviceName>E3131</DeviceName></response>").unwrap();
+        assert_eq!(info.imei, None);
+    }
+
+    #[test]
+    fn test_parse_signal() {
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+<response><pci>287</pci><sc></sc><cell_id>30273793</cell_id><rssi>-67dBm</rssi>\
+<rsrp>-95dBm</rsrp><rsrq>-10.0dB</rsrq><sinr>12dB</sinr><rscp></rscp><ecio></ecio>\
+<mode>7</mode><ulbandwidth>10MHz</ulbandwidth><dlbandwidth>10MHz</dlbandwidth>\
+<band>20</band><earfcn>DL:6300 UL:24300</earfcn><plmn>23415</plmn></response>";
+        let signal: SignalInfo = from_str(xml).unwrap();
+        assert_eq!(signal.rsrp.as_deref(), Some("-95dBm"));
+        assert_eq!(signal.mode_name(), Some("LTE"));
+        assert_eq!(signal.quality(), Some("fair"));
+
+        assert_eq!(parse_level("-10.0dB"), Some(-10.0));
+        assert_eq!(parse_level(">=-51dBm"), Some(-51.0));
+        assert_eq!(parse_level(""), None);
+    }
+
+    #[test]
+    fn test_parse_sms_count() {
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+<response><LocalUnread>2</LocalUnread><LocalInbox>15</LocalInbox><LocalOutbox>3</LocalOutbox>\
+<LocalDraft>0</LocalDraft><LocalDeleted>1</LocalDeleted><SimUnread>0</SimUnread>\
+<SimInbox>0</SimInbox><SimOutbox>0</SimOutbox><SimDraft>0</SimDraft><LocalMax>500</LocalMax>\
+<SimMax>30</SimMax><SimUsed>0</SimUsed><NewMsg>1</NewMsg></response>";
+        let count: SmsCount = from_str(xml).unwrap();
+        assert_eq!(count.local_unread, 2);
+        assert_eq!(count.local_inbox, 15);
+        assert_eq!(count.local_deleted, 1);
+        assert_eq!(count.local_max, 500);
+        assert_eq!(count.new_msg, 1);
+    }
+
+    #[test]
+    fn test_parse_pin_status() {
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+<response><SimState>257</SimState><PinOptState>258</PinOptState><SimPinTimes>3</SimPinTimes>\
+<SimPukTimes>10</SimPukTimes></response>";
+        let status: PinStatus = from_str(xml).unwrap();
+        assert_eq!(status.sim_pin_times, 3);
+        assert_eq!(status.sim_puk_times, 10);
+        assert_eq!(status.sim_state_name(), "ready");
+        assert!(status.is_ready());
+
+        let status = PinStatus {
+            sim_state: 260,
+            ..Default::default()
+        };
+        assert_eq!(status.sim_state_name(), "PIN required");
+        assert!(!status.is_ready());
+    }
+}
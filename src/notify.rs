@@ -0,0 +1,241 @@
+pub use crate::args::NotifyFormat;
+use crate::types::SmsMessage;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info};
+
+/// Notifies a webhook when the rate limiter starts rejecting messages,
+/// at most once per `interval`
+#[derive(Clone, Debug)]
+pub struct RateLimitNotifier {
+    url: String,
+    format: NotifyFormat,
+    interval: Duration,
+    last_sent: Arc<Mutex<Option<Instant>>>,
+    client: reqwest::Client,
+}
+
+impl RateLimitNotifier {
+    pub fn new(url: String, format: NotifyFormat, interval: Duration) -> Self {
+        Self {
+            url,
+            format,
+            interval,
+            last_sent: Arc::default(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Whether a notification is due now; marks it as sent if so
+    fn take_slot(&self, now: Instant) -> bool {
+        let mut last_sent = self.last_sent.lock().unwrap();
+        if last_sent.is_some_and(|t| now.duration_since(t) < self.interval) {
+            return false;
+        }
+        *last_sent = Some(now);
+        true
+    }
+
+    /// Sends a notification in the background unless one was sent recently
+    pub fn notify(&self, reason: &str, client: Option<&str>) {
+        if !self.take_slot(Instant::now()) {
+            return;
+        }
+        let request = match self.format {
+            NotifyFormat::Json => self.client.post(&self.url).json(&serde_json::json!({
+                "event": "rate_limited",
+                "reason": reason,
+                "client": client,
+                "timestamp": SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+            })),
+            NotifyFormat::Ntfy => self
+                .client
+                .post(&self.url)
+                .header("Title", "smser rate limit exceeded")
+                .header("Priority", "high")
+                .header("Tags", "warning")
+                .body(match client {
+                    Some(client) => format!("{} (client: {})", reason, client),
+                    None => reason.to_string(),
+                }),
+        };
+        let url = self.url.clone();
+        tokio::spawn(async move {
+            match request.send().await.and_then(|r| r.error_for_status()) {
+                Ok(_) => info!("Sent rate limit notification to {}", url),
+                Err(e) => error!("Failed to send rate limit notification to {}: {}", url, e),
+            }
+        });
+    }
+}
+
+/// Forwards received messages to a webhook, turning smser into a two-way gateway
+#[derive(Clone, Debug)]
+pub struct IncomingWebhook {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl IncomingWebhook {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("Failed to create HTTP client"),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// POSTs `message` as `{"index": ..., "from": ..., "content": ..., "date": ...}`,
+    /// failing unless the webhook answers with a 2xx status
+    pub async fn forward(&self, message: &SmsMessage) -> Result<(), reqwest::Error> {
+        self.client
+            .post(&self.url)
+            .json(&message_json(message))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+fn message_json(message: &SmsMessage) -> serde_json::Value {
+    serde_json::json!({
+        "index": message.index,
+        "from": message.phone,
+        "content": message.content,
+        "date": message.date,
+    })
+}
+
+/// Runs a program for every new received message. The message is passed as
+/// `SMSER_INDEX`, `SMSER_FROM`, `SMSER_CONTENT` and `SMSER_DATE` and as the
+/// webhook's JSON on stdin.
+#[derive(Clone, Debug)]
+pub struct ReceiveCommand {
+    command: String,
+    timeout: Duration,
+}
+
+impl ReceiveCommand {
+    /// `command` is split on whitespace into the program and its arguments
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    pub fn command(&self) -> &str {
+        &self.command
+    }
+
+    /// Runs the command for `message`, failing if it cannot be started, exits
+    /// unsuccessfully or is still running after 30 seconds
+    pub async fn run(&self, message: &SmsMessage) -> Result<(), String> {
+        let mut words = self.command.split_whitespace();
+        let program = words.next().ok_or("empty command")?;
+        let mut child = tokio::process::Command::new(program)
+            .args(words)
+            .env("SMSER_INDEX", message.index.to_string())
+            .env("SMSER_FROM", &message.phone)
+            .env("SMSER_CONTENT", &message.content)
+            .env("SMSER_DATE", &message.date)
+            .stdin(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("failed to start: {}", e))?;
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = message_json(message).to_string() + "\n";
+        let status = tokio::time::timeout(self.timeout, async {
+            // A command that ignores stdin may exit before reading it
+            let _ = stdin.write_all(input.as_bytes()).await;
+            drop(stdin);
+            child.wait().await
+        })
+        .await
+        .map_err(|_| format!("timed out after {}s", self.timeout.as_secs()))?
+        .map_err(|e| e.to_string())?;
+        if !status.success() {
+            return Err(format!("exited with {}", status));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifier_is_rate_limited() {
+        let notifier = RateLimitNotifier::new(
+            "http://localhost/hook".to_string(),
+            NotifyFormat::Json,
+            Duration::from_secs(3600),
+        );
+        let now = Instant::now();
+        assert!(notifier.take_slot(now));
+        assert!(!notifier.take_slot(now + Duration::from_secs(60)));
+        assert!(notifier.take_slot(now + Duration::from_secs(3600)));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_receive_command() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("smser-receive-cmd-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("hook.sh");
+        let output = dir.join("out");
+        std::fs::write(
+            &script,
+            "#!/bin/sh\necho \"$SMSER_INDEX $SMSER_FROM $SMSER_CONTENT\" > \"$1\"\ncat >> \"$1\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let message = SmsMessage {
+            smstat: crate::modem::SmsStat::Unread,
+            index: 40001,
+            phone: "+441234567890".to_string(),
+            content: "Ping".to_string(),
+            date: "2026-10-16 10:00:00".to_string(),
+            sca: String::new(),
+            save_type: 0,
+            priority: crate::modem::Priority::Normal,
+            sms_type: crate::modem::SmsType::Single,
+        };
+        let command = ReceiveCommand::new(format!("{} {}", script.display(), output.display()));
+        command.run(&message).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&output).unwrap(),
+            "40001 +441234567890 Ping\n\
+             {\"index\":40001,\"from\":\"+441234567890\",\"content\":\"Ping\",\"date\":\"2026-10-16 10:00:00\"}\n"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let error = ReceiveCommand::new("false".to_string())
+            .run(&message)
+            .await
+            .unwrap_err();
+        assert!(error.starts_with("exited with"), "{}", error);
+        assert!(
+            ReceiveCommand::new("/nonexistent/smser-hook".to_string())
+                .run(&message)
+                .await
+                .is_err()
+        );
+    }
+}
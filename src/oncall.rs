@@ -0,0 +1,393 @@
+use crate::config::OnCallSchedule;
+use crate::cron::parse_duration;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeDelta, TimeZone, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+/// How long a fetched iCal feed is used before it is fetched again
+const ICAL_REFRESH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+
+/// HTTP client for iCal feeds. Feeds are fetched while an alert waits, so a
+/// hanging server must not hold it up for long.
+fn http_client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .connect_timeout(Duration::from_secs(5))
+            .build()
+            .expect("Failed to create HTTP client")
+    })
+}
+
+/// An on-call shift from an iCal feed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shift {
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    /// The event summary, naming who is on call
+    pub summary: String,
+}
+
+#[derive(Debug, Default)]
+struct IcalCache {
+    shifts: Vec<Shift>,
+    fetched_at: Option<Instant>,
+}
+
+/// Members take turns, each for one `shift`, starting at `start`
+#[derive(Debug)]
+struct Rotation {
+    members: Vec<String>,
+    start: DateTime<Utc>,
+    shift: TimeDelta,
+}
+
+impl Rotation {
+    fn from_config(members: Vec<String>, start: &str, shift: &str) -> Result<Self, String> {
+        let start = DateTime::parse_from_rfc3339(start)
+            .map_err(|e| format!("Invalid start '{}': {}", start, e))?
+            .with_timezone(&Utc);
+        Ok(Self {
+            members,
+            start,
+            shift: parse_duration(shift)?,
+        })
+    }
+
+    fn member(&self, now: DateTime<Utc>) -> &str {
+        rotation_member(&self.members, self.start, self.shift, now)
+    }
+}
+
+#[derive(Debug)]
+enum Schedule {
+    Rotation(Rotation),
+    Ical {
+        url: String,
+        contacts: HashMap<String, String>,
+        cache: Mutex<IcalCache>,
+        /// Used when the feed names nobody for the current time, e.g. because
+        /// it could not be fetched yet
+        fallback: Option<Rotation>,
+    },
+}
+
+/// Named on-call schedules used to route alerts
+#[derive(Clone, Debug, Default)]
+pub struct OnCall {
+    schedules: Arc<HashMap<String, Schedule>>,
+}
+
+impl OnCall {
+    pub fn from_config(schedules: HashMap<String, OnCallSchedule>) -> Result<Self, String> {
+        let schedules = schedules
+            .into_iter()
+            .map(|(name, config)| {
+                let schedule = Schedule::from_config(config)
+                    .map_err(|e| format!("Invalid on-call schedule '{}': {}", name, e))?;
+                Ok((name, schedule))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self {
+            schedules: Arc::new(schedules),
+        })
+    }
+
+    /// Phone number of whoever is on call now in schedule `name`
+    pub async fn current(&self, name: &str) -> Result<String, String> {
+        let schedule = self
+            .schedules
+            .get(name)
+            .ok_or_else(|| format!("Unknown on-call schedule '{}'", name))?;
+        let now = Utc::now();
+        match schedule {
+            Schedule::Rotation(rotation) => Ok(rotation.member(now).to_string()),
+            Schedule::Ical {
+                url,
+                contacts,
+                cache,
+                fallback,
+            } => {
+                let stale = cache
+                    .lock()
+                    .unwrap()
+                    .fetched_at
+                    .is_none_or(|t| t.elapsed() >= ICAL_REFRESH_INTERVAL);
+                if stale {
+                    refresh_ical(name, url, cache).await;
+                }
+                let cache = cache.lock().unwrap();
+                let shift = cache
+                    .shifts
+                    .iter()
+                    .find(|s| s.starts_at <= now && now < s.ends_at);
+                let shift = match (shift, fallback) {
+                    (Some(shift), _) => shift,
+                    (None, Some(rotation)) => {
+                        warn!(
+                            "No current shift in the feed of schedule '{}', using its rotation",
+                            name
+                        );
+                        return Ok(rotation.member(now).to_string());
+                    }
+                    (None, None) => {
+                        return Err(format!("Nobody is on call in schedule '{}'", name));
+                    }
+                };
+                match contacts.get(&shift.summary) {
+                    Some(phone) => Ok(phone.clone()),
+                    None if shift.summary.starts_with('+') => Ok(shift.summary.clone()),
+                    None => Err(format!(
+                        "No phone number for '{}' in schedule '{}'",
+                        shift.summary, name
+                    )),
+                }
+            }
+        }
+    }
+}
+
+impl Schedule {
+    fn from_config(config: OnCallSchedule) -> Result<Self, String> {
+        let rotation = match (config.start, config.shift) {
+            (Some(start), Some(shift)) if !config.members.is_empty() => {
+                Some(Rotation::from_config(config.members, &start, &shift)?)
+            }
+            (None, None) if config.members.is_empty() => None,
+            _ => return Err("'members', 'start' and 'shift' must be given together".to_string()),
+        };
+        match (config.ical_url, rotation) {
+            (Some(url), fallback) => Ok(Schedule::Ical {
+                url,
+                contacts: config.contacts,
+                cache: Mutex::default(),
+                fallback,
+            }),
+            (None, Some(rotation)) => Ok(Schedule::Rotation(rotation)),
+            (None, None) => {
+                Err("expected either 'members', 'start' and 'shift', or 'ical_url'".to_string())
+            }
+        }
+    }
+}
+
+fn rotation_member(
+    members: &[String],
+    start: DateTime<Utc>,
+    shift: TimeDelta,
+    now: DateTime<Utc>,
+) -> &str {
+    let shifts = (now - start).num_seconds().div_euclid(shift.num_seconds());
+    &members[shifts.rem_euclid(members.len() as i64) as usize]
+}
+
+async fn refresh_ical(name: &str, url: &str, cache: &Mutex<IcalCache>) {
+    let result = async {
+        let response = http_client().get(url).send().await?.error_for_status()?;
+        response.text().await
+    }
+    .await;
+    let mut cache = cache.lock().unwrap();
+    // Also back off after errors; the previous shifts stay in use
+    cache.fetched_at = Some(Instant::now());
+    match result {
+        Ok(body) => {
+            cache.shifts = parse_ical(&body);
+            info!(
+                "Fetched on-call schedule '{}': {} shifts",
+                name,
+                cache.shifts.len()
+            );
+        }
+        Err(e) => error!("Failed to fetch on-call schedule '{}': {}", name, e),
+    }
+}
+
+/// Parses the events of an iCalendar feed. Recurring events (RRULE) are not
+/// expanded, only their first occurrence is used.
+pub fn parse_ical(ical: &str) -> Vec<Shift> {
+    // Continuation lines start with a space or tab
+    let unfolded = ical
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    let mut shifts = Vec::new();
+    let (mut start, mut end, mut summary) = (None, None, None);
+    for line in unfolded.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let (name, params) = key.split_once(';').unwrap_or((key, ""));
+        match name {
+            "BEGIN" if value == "VEVENT" => (start, end, summary) = (None, None, None),
+            "DTSTART" => start = parse_ical_time(value, params),
+            "DTEND" => end = parse_ical_time(value, params),
+            "SUMMARY" => summary = Some(value.trim().replace("\\,", ",")),
+            "END" if value == "VEVENT" => {
+                if let (Some(starts_at), Some(ends_at), Some(summary)) =
+                    (start.take(), end.take(), summary.take())
+                {
+                    shifts.push(Shift {
+                        starts_at,
+                        ends_at,
+                        summary,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    shifts
+}
+
+/// Parses UTC (`...Z`), floating and date-only values. Times with a TZID are
+/// treated as local time.
+fn parse_ical_time(value: &str, params: &str) -> Option<DateTime<Utc>> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(time.and_utc());
+    }
+    let time = if params.contains("VALUE=DATE") || value.len() == 8 {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)?
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?
+    };
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_rotation_member() {
+        let members = vec!["+441".to_string(), "+442".to_string(), "+443".to_string()];
+        let start = utc("2026-10-05T09:00:00Z");
+        let week = TimeDelta::days(7);
+        let member = |now| rotation_member(&members, start, week, utc(now));
+        assert_eq!(member("2026-10-05T09:00:00Z"), "+441");
+        assert_eq!(member("2026-10-12T08:59:59Z"), "+441");
+        assert_eq!(member("2026-10-12T09:00:00Z"), "+442");
+        assert_eq!(member("2026-10-26T09:00:00Z"), "+441");
+        // Before the start the rotation runs backwards
+        assert_eq!(member("2026-10-05T08:00:00Z"), "+443");
+    }
+
+    #[test]
+    fn test_parse_ical() {
+        let ical = "BEGIN:VCALENDAR\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART:20261012T090000Z\r\n\
+DTEND:20261019T090000Z\r\n\
+SUMMARY:Alice\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+DTSTART:20261019T090000Z\r\n\
+DTEND:20261026T0900\r\n \
+00Z\r\n\
+SUMMARY:+441234567890\r\n\
+END:VEVENT\r\n\
+BEGIN:VEVENT\r\n\
+SUMMARY:Missing dates\r\n\
+END:VEVENT\r\n\
+END:VCALENDAR\r\n";
+        let shifts = parse_ical(ical);
+        assert_eq!(shifts.len(), 2);
+        assert_eq!(
+            shifts[0],
+            Shift {
+                starts_at: utc("2026-10-12T09:00:00Z"),
+                ends_at: utc("2026-10-19T09:00:00Z"),
+                summary: "Alice".to_string(),
+            }
+        );
+        assert_eq!(shifts[1].ends_at, utc("2026-10-26T09:00:00Z"));
+        assert!(parse_ical_time("20261012", "VALUE=DATE").is_some());
+    }
+
+    #[test]
+    fn test_schedule_from_config() {
+        let rotation = OnCallSchedule {
+            members: vec!["+441".to_string()],
+            start: Some("2026-10-05T09:00:00Z".to_string()),
+            shift: Some("7d".to_string()),
+            ..Default::default()
+        };
+        assert!(Schedule::from_config(rotation.clone()).is_ok());
+        assert!(
+            Schedule::from_config(OnCallSchedule {
+                members: vec![],
+                ..rotation.clone()
+            })
+            .is_err()
+        );
+        // A rotation next to a feed is its fallback
+        assert!(matches!(
+            Schedule::from_config(OnCallSchedule {
+                ical_url: Some("http://example.com/oncall.ics".to_string()),
+                ..rotation.clone()
+            }),
+            Ok(Schedule::Ical {
+                fallback: Some(_),
+                ..
+            })
+        ));
+        assert!(
+            Schedule::from_config(OnCallSchedule {
+                start: None,
+                ..rotation
+            })
+            .is_err()
+        );
+        assert!(
+            Schedule::from_config(OnCallSchedule {
+                ical_url: Some("http://example.com/oncall.ics".to_string()),
+                ..Default::default()
+            })
+            .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ical_fallback() {
+        // Nothing listens on the port once the listener is dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/oncall.ics", listener.local_addr().unwrap());
+        drop(listener);
+
+        let schedule = |members: Vec<String>| OnCallSchedule {
+            members,
+            start: None,
+            shift: None,
+            ical_url: Some(url.clone()),
+            contacts: HashMap::new(),
+        };
+        let oncall =
+            OnCall::from_config(HashMap::from([("ops".to_string(), schedule(Vec::new()))]))
+                .unwrap();
+        assert!(oncall.current("ops").await.is_err());
+
+        let oncall = OnCall::from_config(HashMap::from([(
+            "ops".to_string(),
+            OnCallSchedule {
+                start: Some("2026-10-05T09:00:00Z".to_string()),
+                shift: Some("7d".to_string()),
+                ..schedule(vec!["+441".to_string()])
+            },
+        )]))
+        .unwrap();
+        assert_eq!(oncall.current("ops").await.unwrap(), "+441");
+    }
+}
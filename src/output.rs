@@ -0,0 +1,183 @@
+//! The `--format` of commands that print data: their own text, or the data as
+//! JSON, NDJSON or a table for scripts.
+
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::Value;
+use strum_macros::Display;
+
+/// Output format of commands that print data
+#[derive(Clone, Copy, Debug, Default, PartialEq, Display, ValueEnum)]
+#[strum(serialize_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// Pretty-printed JSON
+    Json,
+    /// Compact JSON, one line per item of a list
+    Ndjson,
+    /// Aligned columns: a row per item of a list, or per field otherwise
+    Table,
+}
+
+/// Prints `value` in `format`, see [`render`]
+pub fn print<T: Serialize>(value: &T, format: OutputFormat) {
+    print!("{}", render(value, format));
+}
+
+/// Formats `value` in a structured `format`. Commands print their own text, so
+/// `Text` falls back to JSON.
+pub fn render<T: Serialize>(value: &T, format: OutputFormat) -> String {
+    let value = serde_json::to_value(value).expect("output is serializable");
+    match format {
+        OutputFormat::Text | OutputFormat::Json => {
+            serde_json::to_string_pretty(&value).unwrap() + "\n"
+        }
+        OutputFormat::Ndjson => match value {
+            Value::Array(items) => items.iter().map(|item| item.to_string() + "\n").collect(),
+            value => value.to_string() + "\n",
+        },
+        OutputFormat::Table => align(&table_rows(&value)),
+    }
+}
+
+fn table_rows(value: &Value) -> Vec<Vec<String>> {
+    match value {
+        Value::Array(items) => {
+            let mut columns: Vec<&String> = Vec::new();
+            for key in items
+                .iter()
+                .filter_map(Value::as_object)
+                .flat_map(|o| o.keys())
+            {
+                if !columns.contains(&key) {
+                    columns.push(key);
+                }
+            }
+            if columns.is_empty() {
+                return items.iter().map(|item| vec![cell(item)]).collect();
+            }
+            let header = columns.iter().map(|c| c.to_uppercase()).collect();
+            let rows = items.iter().map(|item| {
+                columns
+                    .iter()
+                    .map(|c| item.get(c.as_str()).map(cell).unwrap_or_default())
+                    .collect()
+            });
+            std::iter::once(header).chain(rows).collect()
+        }
+        Value::Object(_) => {
+            let mut rows = Vec::new();
+            flatten("", value, &mut rows);
+            rows
+        }
+        value => vec![vec![cell(value)]],
+    }
+}
+
+/// Adds a `key value` row per field of `value`, naming nested fields `a.b`
+fn flatten(key: &str, value: &Value, rows: &mut Vec<Vec<String>>) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields {
+                let name = if key.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}.{}", key, name)
+                };
+                flatten(&name, field, rows);
+            }
+        }
+        value => rows.push(vec![key.to_string(), cell(value)]),
+    }
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        // Keep each item on one line
+        Value::String(s) => s.replace(['\r', '\n'], " "),
+        Value::Array(items) if items.iter().all(|i| !i.is_object() && !i.is_array()) => {
+            items.iter().map(cell).collect::<Vec<_>>().join(", ")
+        }
+        value => value.to_string(),
+    }
+}
+
+fn align(rows: &[Vec<String>]) -> String {
+    let mut widths = Vec::new();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            let width = cell.chars().count();
+            match widths.get_mut(i) {
+                Some(w) if *w < width => *w = width,
+                Some(_) => {}
+                None => widths.push(width),
+            }
+        }
+    }
+    let mut output = String::new();
+    for row in rows {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            line.push_str(cell);
+            if i + 1 < row.len() {
+                let padding = widths[i] - cell.chars().count() + 2;
+                line.extend(std::iter::repeat_n(' ', padding));
+            }
+        }
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_ndjson() {
+        let messages = json!([{"Index": 1, "Content": "Hi"}, {"Index": 2, "Content": "Yo"}]);
+        assert_eq!(
+            render(&messages, OutputFormat::Ndjson),
+            "{\"Index\":1,\"Content\":\"Hi\"}\n{\"Index\":2,\"Content\":\"Yo\"}\n"
+        );
+        assert_eq!(
+            render(&json!({"unread": 3}), OutputFormat::Ndjson),
+            "{\"unread\":3}\n"
+        );
+        assert_eq!(render(&json!([]), OutputFormat::Ndjson), "");
+    }
+
+    #[test]
+    fn test_render_table_list() {
+        let messages = json!([
+            {"Index": 40001, "Phone": "+491761234567", "Content": "Line 1\nLine 2"},
+            {"Index": 7, "Phone": "DHL", "Content": "Parcel", "Sca": null},
+        ]);
+        assert_eq!(
+            render(&messages, OutputFormat::Table),
+            "INDEX  PHONE          CONTENT        SCA\n\
+             40001  +491761234567  Line 1 Line 2\n\
+             7      DHL            Parcel\n"
+        );
+    }
+
+    #[test]
+    fn test_render_table_object() {
+        let status = json!({
+            "modem_url": "http://192.168.8.1",
+            "session": {"ok": 42},
+            "costs": ["a", "b"],
+        });
+        assert_eq!(
+            render(&status, OutputFormat::Table),
+            "modem_url   http://192.168.8.1\n\
+             session.ok  42\n\
+             costs       a, b\n"
+        );
+    }
+}
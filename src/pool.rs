@@ -0,0 +1,448 @@
+//! Several modems used as one [`ModemClient`]. Calls go to the active modem;
+//! when it is unreachable or returns an error, the next healthy modem takes
+//! over. A background health check brings recovered modems back. Outgoing
+//! messages can also be spread over all healthy modems, see [`Balance`].
+
+pub use crate::args::Balance;
+use crate::client::{ModemClient, ModemFuture, Session};
+use crate::modem::{
+    DeviceInfo, Error, NetworkInfo, Notifications, PinStatus, SmsCount, SmsListParams,
+    SmsListResponse, TrafficStatistics,
+};
+use metrics::gauge;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+struct Member {
+    client: Arc<dyn ModemClient>,
+    healthy: AtomicBool,
+    last_sent: Mutex<Option<Instant>>,
+}
+
+/// Modems tried in order, starting with the active one
+pub struct ModemPool {
+    members: Vec<Member>,
+    active: AtomicUsize,
+    balance: Balance,
+    /// Next modem in round-robin order
+    next: AtomicUsize,
+}
+
+impl ModemPool {
+    pub fn new(modems: Vec<Arc<dyn ModemClient>>) -> Self {
+        assert!(!modems.is_empty(), "A modem pool needs at least one modem");
+        let pool = Self {
+            members: modems
+                .into_iter()
+                .map(|client| Member {
+                    client,
+                    healthy: AtomicBool::new(true),
+                    last_sent: Mutex::new(None),
+                })
+                .collect(),
+            active: AtomicUsize::new(0),
+            balance: Balance::default(),
+            next: AtomicUsize::new(0),
+        };
+        for index in 0..pool.members.len() {
+            pool.set_healthy(index, true);
+        }
+        pool
+    }
+
+    pub fn with_balance(mut self, balance: Balance) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    /// URL and health of every modem
+    pub fn status(&self) -> Vec<(String, bool)> {
+        self.members
+            .iter()
+            .map(|m| {
+                (
+                    m.client.url().to_string(),
+                    m.healthy.load(Ordering::Relaxed),
+                )
+            })
+            .collect()
+    }
+
+    /// Fetches a session from every modem, marking the ones that fail unhealthy
+    pub async fn health_check(&self) {
+        for (index, member) in self.members.iter().enumerate() {
+            let healthy = member.client.get_session().await.is_ok();
+            self.set_healthy(index, healthy);
+        }
+        let active = self.active.load(Ordering::Relaxed);
+        if !self.members[active].healthy.load(Ordering::Relaxed)
+            && let Some(next) = self.candidates(active).into_iter().next()
+            && self.members[next].healthy.load(Ordering::Relaxed)
+        {
+            self.activate(next);
+        }
+    }
+
+    /// Runs [`health_check`](Self::health_check) every `interval`
+    pub fn spawn_health_checks(self: &Arc<Self>, interval: Duration) {
+        let pool = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                pool.health_check().await;
+            }
+        });
+    }
+
+    fn set_healthy(&self, index: usize, healthy: bool) {
+        let member = &self.members[index];
+        if member.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+            if healthy {
+                info!("Modem {} is healthy again", member.client.url());
+            } else {
+                warn!("Modem {} is unhealthy", member.client.url());
+            }
+        }
+        gauge!("smser_modem_up", "modem" => member.client.url().to_string()).set(if healthy {
+            1.0
+        } else {
+            0.0
+        });
+    }
+
+    fn activate(&self, index: usize) {
+        if self.active.swap(index, Ordering::Relaxed) != index {
+            warn!("Failing over to modem {}", self.members[index].client.url());
+        }
+    }
+
+    /// Modems other than `failed` to try, healthy ones first
+    fn candidates(&self, failed: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.members.len()).filter(|&i| i != failed).collect();
+        order.sort_by_key(|&i| !self.members[i].healthy.load(Ordering::Relaxed));
+        order
+    }
+
+    fn is_healthy(&self, index: usize) -> bool {
+        self.members[index].healthy.load(Ordering::Relaxed)
+    }
+
+    /// Modem to send the next message, `current` unless balancing picks another
+    fn sender(&self, current: usize) -> usize {
+        let healthy: Vec<usize> = (0..self.members.len())
+            .filter(|&i| self.is_healthy(i))
+            .collect();
+        if healthy.is_empty() {
+            return current;
+        }
+        match self.balance {
+            Balance::Failover => current,
+            Balance::RoundRobin => {
+                healthy[self.next.fetch_add(1, Ordering::Relaxed) % healthy.len()]
+            }
+            Balance::LeastRecent => *healthy
+                .iter()
+                .min_by_key(|&&i| *self.members[i].last_sent.lock().unwrap())
+                .unwrap(),
+        }
+    }
+
+    async fn session_on(&self, index: usize) -> Result<Session, Error> {
+        let mut session = self.members[index].client.get_session().await?;
+        session.modem = index;
+        Ok(session)
+    }
+
+    /// Runs `call` on the session's modem, and on the other modems with fresh
+    /// sessions while it fails
+    async fn call<T, F>(
+        &self,
+        session: &Session,
+        call: impl Fn(Arc<dyn ModemClient>, Session) -> F,
+    ) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        let first = session.modem.min(self.members.len() - 1);
+        let error = match call(self.members[first].client.clone(), session.clone()).await {
+            Err(e) if fails_over(&e) => e,
+            result => return result,
+        };
+        warn!(
+            "Modem {} failed: {}",
+            self.members[first].client.url(),
+            error
+        );
+        self.set_healthy(first, false);
+        for index in self.candidates(first) {
+            let result = match self.session_on(index).await {
+                Ok(session) => call(self.members[index].client.clone(), session).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Err(e) if fails_over(&e) => {
+                    warn!("Modem {} failed: {}", self.members[index].client.url(), e);
+                    self.set_healthy(index, false);
+                }
+                result => {
+                    self.set_healthy(index, true);
+                    self.activate(index);
+                    return result;
+                }
+            }
+        }
+        Err(error)
+    }
+}
+
+/// Whether another modem might succeed where one failed with `error`.
+/// Malformed requests fail everywhere.
+fn fails_over(error: &Error) -> bool {
+    !error.is_permanent()
+}
+
+impl ModemClient for ModemPool {
+    fn url(&self) -> &str {
+        self.members[self.active.load(Ordering::Relaxed)]
+            .client
+            .url()
+    }
+
+    fn modems(&self) -> Vec<(String, Option<bool>)> {
+        self.status()
+            .into_iter()
+            .map(|(url, healthy)| (url, Some(healthy)))
+            .collect()
+    }
+
+    fn get_session(&self) -> ModemFuture<'_, Session> {
+        Box::pin(async move {
+            let active = self.active.load(Ordering::Relaxed);
+            let error = match self.session_on(active).await {
+                Err(e) if fails_over(&e) => e,
+                result => return result,
+            };
+            warn!(
+                "Modem {} failed: {}",
+                self.members[active].client.url(),
+                error
+            );
+            self.set_healthy(active, false);
+            for index in self.candidates(active) {
+                match self.session_on(index).await {
+                    Ok(session) => {
+                        self.set_healthy(index, true);
+                        self.activate(index);
+                        return Ok(session);
+                    }
+                    Err(e) => {
+                        warn!("Modem {} failed: {}", self.members[index].client.url(), e);
+                        self.set_healthy(index, false);
+                    }
+                }
+            }
+            Err(error)
+        })
+    }
+
+    fn send_sms<'a>(
+        &'a self,
+        session: &'a Session,
+        to: &'a [String],
+        message: &'a str,
+    ) -> ModemFuture<'a, String> {
+        Box::pin(async move {
+            let sender = self.sender(session.modem);
+            let session = match sender == session.modem {
+                true => session.clone(),
+                // The call below fails over if the sender cannot be reached
+                false => self.session_on(sender).await.unwrap_or_else(|_| Session {
+                    modem: sender,
+                    ..session.clone()
+                }),
+            };
+            self.call(&session, move |modem, s| async move {
+                let url = modem.send_sms(&s, to, message).await?;
+                *self.members[s.modem].last_sent.lock().unwrap() = Some(Instant::now());
+                Ok(url)
+            })
+            .await
+        })
+    }
+
+    fn get_sms_list<'a>(
+        &'a self,
+        session: &'a Session,
+        params: SmsListParams,
+        page: i32,
+    ) -> ModemFuture<'a, SmsListResponse> {
+        Box::pin(self.call(session, move |modem, s| {
+            let params = params.clone();
+            async move { modem.get_sms_list(&s, params, page).await }
+        }))
+    }
+
+    fn delete_sms<'a>(&'a self, session: &'a Session, indexes: &'a [i32]) -> ModemFuture<'a, ()> {
+        Box::pin(self.call(session, move |modem, s| async move {
+            modem.delete_sms(&s, indexes).await
+        }))
+    }
+
+    fn mark_read<'a>(&'a self, session: &'a Session, indexes: &'a [i32]) -> ModemFuture<'a, ()> {
+        Box::pin(self.call(session, move |modem, s| async move {
+            modem.mark_read(&s, indexes).await
+        }))
+    }
+
+    fn get_sms_count<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, SmsCount> {
+        Box::pin(self.call(session, move |modem, s| async move {
+            modem.get_sms_count(&s).await
+        }))
+    }
+
+    fn check_notifications<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, Notifications> {
+        Box::pin(self.call(session, move |modem, s| async move {
+            modem.check_notifications(&s).await
+        }))
+    }
+
+    fn get_device_info<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, DeviceInfo> {
+        Box::pin(self.call(session, move |modem, s| async move {
+            modem.get_device_info(&s).await
+        }))
+    }
+
+    fn get_network_info<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, NetworkInfo> {
+        Box::pin(self.call(session, move |modem, s| async move {
+            modem.get_network_info(&s).await
+        }))
+    }
+
+    fn get_traffic_statistics<'a>(
+        &'a self,
+        session: &'a Session,
+    ) -> ModemFuture<'a, TrafficStatistics> {
+        Box::pin(self.call(session, move |modem, s| async move {
+            modem.get_traffic_statistics(&s).await
+        }))
+    }
+
+    fn get_pin_status<'a>(&'a self, session: &'a Session) -> ModemFuture<'a, PinStatus> {
+        Box::pin(self.call(session, move |modem, s| async move {
+            modem.get_pin_status(&s).await
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockModem;
+
+    fn pool() -> (Arc<MockModem>, Arc<MockModem>, ModemPool) {
+        let first = Arc::new(MockModem::new().with_url("http://first"));
+        let second = Arc::new(MockModem::new().with_url("http://second"));
+        let pool = ModemPool::new(vec![first.clone(), second.clone()]);
+        (first, second, pool)
+    }
+
+    #[tokio::test]
+    async fn test_failover() {
+        let (first, second, pool) = pool();
+        let to = ["+1".to_string()];
+        let session = pool.get_session().await.unwrap();
+        assert_eq!(session.modem, 0);
+        pool.send_sms(&session, &to, "One").await.unwrap();
+        assert_eq!(first.sent().len(), 1);
+
+        // The send is retried on the second modem, which stays active
+        first.fail_with(Some(100004));
+        pool.send_sms(&session, &to, "Two").await.unwrap();
+        assert_eq!(second.sent().len(), 1);
+        assert_eq!(pool.url(), "http://second");
+        assert_eq!(pool.get_session().await.unwrap().modem, 1);
+        assert_eq!(
+            pool.status(),
+            [
+                ("http://first".to_string(), false),
+                ("http://second".to_string(), true)
+            ]
+        );
+        assert_eq!(
+            pool.modems(),
+            [
+                ("http://first".to_string(), Some(false)),
+                ("http://second".to_string(), Some(true))
+            ]
+        );
+
+        // Malformed requests are not retried elsewhere
+        second.fail_with(Some(100006));
+        let session = pool.get_session().await;
+        assert_eq!(session.unwrap_err().code(), Some(100006));
+
+        // Failing over back once the second modem is down too
+        first.fail_with(None);
+        second.fail_with(Some(100004));
+        pool.health_check().await;
+        assert_eq!(pool.url(), "http://first");
+    }
+
+    #[tokio::test]
+    async fn test_round_robin() {
+        let (first, second, pool) = pool();
+        let pool = pool.with_balance(Balance::RoundRobin);
+        let to = ["+1".to_string()];
+        let session = pool.get_session().await.unwrap();
+        let mut senders = Vec::new();
+        for _ in 0..4 {
+            senders.push(pool.send_sms(&session, &to, "Hi").await.unwrap());
+        }
+        assert_eq!(
+            senders,
+            [
+                "http://first",
+                "http://second",
+                "http://first",
+                "http://second"
+            ]
+        );
+
+        // Unhealthy modems are skipped
+        second.fail_with(Some(100004));
+        pool.health_check().await;
+        pool.send_sms(&session, &to, "Hi").await.unwrap();
+        pool.send_sms(&session, &to, "Hi").await.unwrap();
+        assert_eq!(first.sent().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_least_recent() {
+        let (first, second, pool) = pool();
+        let pool = pool.with_balance(Balance::LeastRecent);
+        let to = ["+1".to_string()];
+        let session = pool.get_session().await.unwrap();
+        pool.send_sms(&session, &to, "One").await.unwrap();
+        pool.send_sms(&session, &to, "Two").await.unwrap();
+        pool.send_sms(&session, &to, "Three").await.unwrap();
+        assert_eq!(first.sent().len(), 2);
+        assert_eq!(second.sent().len(), 1);
+        assert_eq!(
+            pool.send_sms(&session, &to, "Four").await.unwrap(),
+            "http://second"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_all_modems_down() {
+        let (first, second, pool) = pool();
+        first.fail_with(Some(100004));
+        second.fail_with(Some(113004));
+        let err = pool.get_session().await.unwrap_err();
+        assert_eq!(err.code(), Some(100004));
+        assert!(pool.status().iter().all(|(_, healthy)| !healthy));
+    }
+}
@@ -0,0 +1,61 @@
+//! Quirks of the HiLink firmwares smser has been used with. The profile is
+//! picked with `--device-profile`, or detected from the `DeviceName` reported
+//! by /api/device/information.
+
+use clap::ValueEnum;
+use strum_macros::{Display, EnumString};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Display, ValueEnum, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum DeviceProfile {
+    /// Detect the profile from the device name, falling back to `e3372`
+    #[default]
+    Auto,
+    /// E3372 USB stick in HiLink mode
+    E3372,
+    /// E8372 USB stick with Wi-Fi hotspot
+    E8372,
+    /// E5576 mobile Wi-Fi router
+    E5576,
+    /// B535 home router
+    B535,
+}
+
+impl DeviceProfile {
+    /// Profile for a `DeviceName`, `None` for unknown devices
+    pub fn from_device_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_uppercase();
+        [Self::E3372, Self::E8372, Self::E5576, Self::B535]
+            .into_iter()
+            .find(|profile| name.contains(&profile.to_string().to_ascii_uppercase()))
+    }
+
+    /// Whether the firmware only answers API calls after a login
+    pub fn requires_login(self) -> bool {
+        matches!(self, Self::E8372 | Self::E5576 | Self::B535)
+    }
+
+    /// Whether the firmware ignores `SortType`/`Ascending` in sms-list requests,
+    /// so messages have to be sorted after fetching them
+    pub fn ignores_sort_order(self) -> bool {
+        matches!(self, Self::E5576 | Self::B535)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_device_name() {
+        assert_eq!(
+            DeviceProfile::from_device_name("E3372h-320"),
+            Some(DeviceProfile::E3372)
+        );
+        assert_eq!(
+            DeviceProfile::from_device_name("b535-232"),
+            Some(DeviceProfile::B535)
+        );
+        assert_eq!(DeviceProfile::from_device_name("E5785Lh-22c"), None);
+    }
+}
@@ -0,0 +1,1354 @@
+use crate::client::ModemClient;
+use crate::modem::Error as ModemError;
+use crate::server::{ModemHealth, Reloadable};
+use crate::store::{AuditContent, QueuedMessage, SendAttempt, Store};
+use crate::transport::{Hilink, Routes};
+use crate::types::Priority;
+use metrics::{counter, gauge, histogram};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use strum_macros::{Display, EnumString};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{error, info, warn};
+
+/// Who asked for an SMS to be sent, as recorded in the audit log
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Caller {
+    /// Client name given with the request
+    pub client: Option<String>,
+    /// Name of the API key the request was authenticated with
+    pub api_key: Option<String>,
+}
+
+impl Caller {
+    pub fn client(client: Option<String>) -> Self {
+        Self {
+            client,
+            api_key: None,
+        }
+    }
+}
+
+/// An SMS waiting to be handed to the modem
+#[derive(Debug)]
+pub struct OutgoingSms {
+    pub id: u64,
+    /// Recipients, all sent the message in a single modem request
+    pub to: Vec<String>,
+    pub message: String,
+    pub client: Option<String>,
+    pub api_key: Option<String>,
+    pub lane: Lane,
+    /// Whether to send a flash SMS, shown immediately on the recipient's phone
+    pub flash: bool,
+    pub queued_at: Instant,
+    /// Failed send attempts so far
+    pub attempts: u32,
+    /// Unix timestamp in seconds before which the message is not sent
+    pub send_at: Option<u64>,
+    /// Notified with the modem result once the message has been processed
+    pub reply: Option<oneshot::Sender<Result<(), ModemError>>>,
+}
+
+impl OutgoingSms {
+    fn entry(&self, state: OutboxState) -> OutboxEntry {
+        OutboxEntry {
+            id: self.id,
+            to: self.to.clone(),
+            message: self.message.clone(),
+            client: self.client.clone(),
+            api_key: self.api_key.clone(),
+            lane: self.lane,
+            flash: self.flash,
+            state,
+            queued_at: unix_now(),
+            attempts: self.attempts,
+            send_at: self.send_at,
+            last_attempt_at: None,
+            last_error: None,
+        }
+    }
+
+    fn queued(&self) -> QueuedMessage {
+        QueuedMessage {
+            id: self.id,
+            to: self.to.clone(),
+            content: self.message.clone(),
+            client: self.client.clone(),
+            api_key: self.api_key.clone(),
+            lane: self.lane,
+            flash: self.flash,
+            attempts: self.attempts,
+            send_at: self.send_at,
+        }
+    }
+}
+
+/// Delay before the first retry of a failed send; doubled for every further attempt
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Longest delay between retries
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(600);
+
+/// Delay before retrying a message that failed `attempts` times
+fn retry_delay(attempts: u32) -> Duration {
+    RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Queue lane of an outgoing SMS; the high lane is always drained first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Display, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Lane {
+    High,
+    #[default]
+    Normal,
+}
+
+impl From<&Priority> for Lane {
+    fn from(priority: &Priority) -> Self {
+        match priority {
+            Priority::Urgent | Priority::Emergency => Lane::High,
+            _ => Lane::Normal,
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Time left until `send_at`, a Unix timestamp in seconds
+fn delay_until(send_at: Option<u64>) -> Duration {
+    Duration::from_secs(send_at.unwrap_or_default().saturating_sub(unix_now()))
+}
+
+/// State of an outgoing SMS in the outbox
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum OutboxState {
+    /// Waiting for the worker, possibly for a retry after a failed attempt
+    Queued,
+    /// Being handed to the modem
+    Sending,
+    /// Given up on after the last attempt failed
+    Failed,
+}
+
+/// An outgoing SMS that has not been sent yet, as listed by `GET /outbox`
+#[derive(Clone, Debug, Serialize)]
+pub struct OutboxEntry {
+    pub id: u64,
+    pub to: Vec<String>,
+    pub message: String,
+    pub client: Option<String>,
+    pub api_key: Option<String>,
+    pub lane: Lane,
+    pub flash: bool,
+    pub state: OutboxState,
+    /// Unix timestamp in seconds when the message was queued
+    pub queued_at: u64,
+    /// Failed send attempts so far
+    pub attempts: u32,
+    /// Unix timestamp in seconds before which the message is not sent
+    pub send_at: Option<u64>,
+    /// Unix timestamp in seconds when the last attempt started
+    pub last_attempt_at: Option<u64>,
+    /// Error of the last failed attempt
+    pub last_error: Option<String>,
+}
+
+/// Why an outbox entry cannot be retried or cancelled
+#[derive(Debug, PartialEq, Display)]
+pub enum OutboxError {
+    /// No queued or failed message has the ID
+    NotFound,
+    /// The message is already being handed to the modem
+    Sending,
+    /// The message is not failed, so there is nothing to retry
+    NotFailed,
+    /// The worker has stopped
+    Closed,
+}
+
+/// Messages queued but not yet processed by the worker, by ID. IDs increase
+/// over time, so the first entry is the oldest message.
+#[derive(Clone, Debug, Default)]
+struct Pending {
+    messages: Arc<Mutex<BTreeMap<u64, (Instant, OutboxEntry)>>>,
+}
+
+impl Pending {
+    fn insert(&self, sms: &OutgoingSms) {
+        self.messages
+            .lock()
+            .unwrap()
+            .insert(sms.id, (sms.queued_at, sms.entry(OutboxState::Queued)));
+        self.update_metrics();
+    }
+
+    fn remove(&self, id: u64) {
+        self.messages.lock().unwrap().remove(&id);
+        self.update_metrics();
+    }
+
+    /// Marks the message as being sent. Returns false if it was cancelled.
+    fn start(&self, id: u64) -> bool {
+        let mut messages = self.messages.lock().unwrap();
+        let Some((_, entry)) = messages.get_mut(&id) else {
+            return false;
+        };
+        entry.state = OutboxState::Sending;
+        entry.last_attempt_at = Some(unix_now());
+        true
+    }
+
+    /// Leaves only `to` as recipients, after the others got the message
+    fn set_recipients(&self, id: u64, to: &[String]) {
+        if let Some((_, entry)) = self.messages.lock().unwrap().get_mut(&id) {
+            entry.to = to.to_vec();
+        }
+    }
+
+    /// Puts the message back into the queue after the failed attempt `error`
+    fn requeue(&self, id: u64, attempts: u32, error: &ModemError) {
+        if let Some((_, entry)) = self.messages.lock().unwrap().get_mut(&id) {
+            entry.state = OutboxState::Queued;
+            entry.attempts = attempts;
+            entry.last_error = Some(error.to_string());
+        }
+    }
+
+    /// Removes the message unless it is being sent
+    fn cancel(&self, id: u64) -> Result<OutboxEntry, OutboxError> {
+        let mut messages = self.messages.lock().unwrap();
+        match messages.get(&id) {
+            None => return Err(OutboxError::NotFound),
+            Some((_, entry)) if entry.state == OutboxState::Sending => {
+                return Err(OutboxError::Sending);
+            }
+            Some(_) => {}
+        }
+        let (_, entry) = messages.remove(&id).expect("message is pending");
+        drop(messages);
+        self.update_metrics();
+        Ok(entry)
+    }
+
+    fn get(&self, id: u64) -> Option<OutboxEntry> {
+        let messages = self.messages.lock().unwrap();
+        messages.get(&id).map(|(_, entry)| entry.clone())
+    }
+
+    fn entries(&self) -> Vec<OutboxEntry> {
+        let messages = self.messages.lock().unwrap();
+        messages.values().map(|(_, entry)| entry.clone()).collect()
+    }
+
+    fn len(&self) -> usize {
+        self.messages.lock().unwrap().len()
+    }
+
+    /// Sets the queue depth and oldest message age gauges
+    fn update_metrics(&self) {
+        let messages = self.messages.lock().unwrap();
+        for lane in [Lane::High, Lane::Normal] {
+            let depth = messages.values().filter(|(_, e)| e.lane == lane).count();
+            gauge!("smser_queue_depth", "lane" => lane.to_string()).set(depth as f64);
+        }
+        let oldest_age = messages
+            .values()
+            .map(|(queued_at, _)| queued_at.elapsed().as_secs_f64())
+            .fold(0.0, f64::max);
+        gauge!("smser_queue_oldest_age_seconds").set(oldest_age);
+    }
+}
+
+/// Number of failed messages kept for retrying; the oldest are dropped first
+const FAILED_SIZE: usize = 100;
+
+/// Queued messages given up on, by ID, until they are retried or cancelled
+#[derive(Clone, Debug, Default)]
+struct Failed {
+    messages: Arc<Mutex<BTreeMap<u64, OutboxEntry>>>,
+}
+
+impl Failed {
+    fn insert(&self, mut entry: OutboxEntry, error: &ModemError) {
+        entry.state = OutboxState::Failed;
+        entry.last_error = Some(error.to_string());
+        let mut messages = self.messages.lock().unwrap();
+        messages.insert(entry.id, entry);
+        while messages.len() > FAILED_SIZE {
+            messages.pop_first();
+        }
+        gauge!("smser_outbox_failed").set(messages.len() as f64);
+    }
+
+    fn remove(&self, id: u64) -> Option<OutboxEntry> {
+        let mut messages = self.messages.lock().unwrap();
+        let entry = messages.remove(&id);
+        gauge!("smser_outbox_failed").set(messages.len() as f64);
+        entry
+    }
+
+    fn entries(&self) -> Vec<OutboxEntry> {
+        self.messages.lock().unwrap().values().cloned().collect()
+    }
+}
+
+/// Number of recently sent messages kept for the status page
+const HISTORY_SIZE: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Display, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum SendStatus {
+    Sent,
+    Failed,
+}
+
+/// Outcome of a processed outgoing SMS
+#[derive(Clone, Debug, Serialize)]
+pub struct SendRecord {
+    pub id: u64,
+    /// Unix timestamp in seconds when the modem call finished
+    pub timestamp: u64,
+    /// Destinations with the middle digits masked, separated by commas
+    pub to: String,
+    pub status: SendStatus,
+    /// Modem error code, if the modem rejected the message
+    pub error_code: Option<i32>,
+}
+
+/// Ring buffer of the most recent outgoing SMS results
+#[derive(Clone, Debug, Default)]
+pub struct SendHistory {
+    records: Arc<Mutex<VecDeque<SendRecord>>>,
+}
+
+impl SendHistory {
+    fn record(&self, sms: &OutgoingSms, result: &Result<(), ModemError>) -> SendRecord {
+        let record = SendRecord {
+            id: sms.id,
+            timestamp: unix_now(),
+            to: sms
+                .to
+                .iter()
+                .map(|phone| mask_phone(phone))
+                .collect::<Vec<_>>()
+                .join(", "),
+            status: if result.is_ok() {
+                SendStatus::Sent
+            } else {
+                SendStatus::Failed
+            },
+            error_code: match result {
+                Err(e) => e.code(),
+                _ => None,
+            },
+        };
+        let mut records = self.records.lock().unwrap();
+        if records.len() >= HISTORY_SIZE {
+            records.pop_front();
+        }
+        records.push_back(record.clone());
+        record
+    }
+
+    /// Returns the recorded sends, newest first
+    pub fn recent(&self) -> Vec<SendRecord> {
+        self.records.lock().unwrap().iter().rev().cloned().collect()
+    }
+}
+
+/// Masks the middle of a phone number, e.g. "+441234567890" -> "+44*******890"
+pub fn mask_phone(phone: &str) -> String {
+    let chars: Vec<char> = phone.chars().collect();
+    if chars.len() <= 6 {
+        return "*".repeat(chars.len());
+    }
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            if i < 3 || i >= chars.len() - 3 {
+                *c
+            } else {
+                '*'
+            }
+        })
+        .collect()
+}
+
+/// Senders of the two queue lanes
+#[derive(Clone, Debug)]
+struct Lanes {
+    high: mpsc::UnboundedSender<OutgoingSms>,
+    normal: mpsc::UnboundedSender<OutgoingSms>,
+}
+
+impl Lanes {
+    fn send(&self, sms: OutgoingSms) -> Result<(), ModemError> {
+        let tx = match sms.lane {
+            Lane::High => &self.high,
+            Lane::Normal => &self.normal,
+        };
+        tx.send(sms)
+            .map_err(|_| ModemError::Other("Send queue is closed".to_string()))
+    }
+
+    /// Sends `sms` to its lane once its `send_at` time has come
+    fn schedule(&self, sms: OutgoingSms) -> Result<(), ModemError> {
+        let delay = delay_until(sms.send_at);
+        if delay.is_zero() {
+            return self.send(sms);
+        }
+        if self.high.is_closed() {
+            return Err(ModemError::Other("Send queue is closed".to_string()));
+        }
+        let lanes = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = lanes.send(sms);
+        });
+        Ok(())
+    }
+}
+
+/// Queue of outgoing SMS, drained by a single worker task so that only one
+/// request talks to the modem at a time. With a store, queued messages are
+/// kept in its outbox until they are sent, so they survive restarts.
+#[derive(Clone, Debug)]
+pub struct SendQueue {
+    lanes: Lanes,
+    next_id: Arc<AtomicU64>,
+    pending: Pending,
+    failed: Failed,
+    history: SendHistory,
+    store: Option<Store>,
+    /// Set once the queue is shut down; held by the worker while it sends
+    stopped: Arc<tokio::sync::Mutex<bool>>,
+    routes: Reloadable<Routes>,
+}
+
+impl SendQueue {
+    /// Creates the queue and spawns the worker sending messages via the modem,
+    /// or the transport `routes` pick for the recipient. Results are also
+    /// recorded in `store`, if set, and messages left in its outbox are queued
+    /// again. Queued messages failing with a transient error are retried up
+    /// to `retries` times with exponential backoff. With `audit`, every send
+    /// attempt is also written to the audit log of `store`.
+    pub fn start(
+        modem: Arc<dyn ModemClient>,
+        routes: Routes,
+        log_sensitive: bool,
+        modem_health: ModemHealth,
+        store: Option<Store>,
+        retries: u32,
+        audit: Option<AuditContent>,
+    ) -> Self {
+        let (high_tx, high_rx) = mpsc::unbounded_channel();
+        let (normal_tx, normal_rx) = mpsc::unbounded_channel();
+        let lanes = Lanes {
+            high: high_tx,
+            normal: normal_tx,
+        };
+        let pending = Pending::default();
+        pending.update_metrics();
+        let failed = Failed::default();
+        gauge!("smser_queue_in_flight").set(0.0);
+        gauge!("smser_outbox_failed").set(0.0);
+        let history = SendHistory::default();
+        let queued = match &store {
+            Some(store) => store.queued().unwrap_or_else(|e| {
+                error!("Failed to load the outbox: {}", e);
+                Vec::new()
+            }),
+            None => Vec::new(),
+        };
+        let next_id = queued.iter().map(|m| m.id + 1).max().unwrap_or(1);
+        if !queued.is_empty() {
+            info!(
+                "Resuming {} queued message(s) from the outbox",
+                queued.len()
+            );
+        }
+        for message in queued {
+            let sms = OutgoingSms {
+                id: message.id,
+                to: message.to,
+                message: message.content,
+                client: message.client,
+                api_key: message.api_key,
+                lane: message.lane,
+                flash: message.flash,
+                // Deferred messages only count as waiting once they are due
+                queued_at: Instant::now() + delay_until(message.send_at),
+                attempts: message.attempts,
+                send_at: message.send_at,
+                reply: None,
+            };
+            pending.insert(&sms);
+            let _ = lanes.schedule(sms);
+        }
+        let stopped = Arc::new(tokio::sync::Mutex::new(false));
+        let routes = Reloadable::new(routes);
+        let worker = Worker {
+            modem: Hilink(modem),
+            routes: routes.clone(),
+            log_sensitive,
+            pending: pending.clone(),
+            failed: failed.clone(),
+            history: history.clone(),
+            modem_health,
+            store: store.clone(),
+            lanes: lanes.clone(),
+            retries,
+            audit,
+            stopped: stopped.clone(),
+        };
+        tokio::spawn(worker.run(high_rx, normal_rx));
+        Self {
+            lanes,
+            next_id: Arc::new(AtomicU64::new(next_id)),
+            pending,
+            failed,
+            history,
+            store,
+            stopped,
+            routes,
+        }
+    }
+
+    /// Replaces the routes for messages sent from now on
+    pub fn set_routes(&self, routes: Routes) {
+        self.routes.store(routes);
+    }
+
+    /// Stops the worker once the message it is sending, if any, is done,
+    /// waiting up to `timeout` for it. Returns the number of messages left in
+    /// the queue; with a store they are kept in its outbox for the next start.
+    pub async fn shutdown(&self, timeout: Duration) -> usize {
+        match tokio::time::timeout(timeout, self.stopped.lock()).await {
+            Ok(mut stopped) => *stopped = true,
+            Err(_) => warn!("Timed out waiting for the SMS being sent"),
+        }
+        self.depth()
+    }
+
+    /// Number of messages waiting for the modem
+    pub fn depth(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Refreshes the queue gauges, e.g. before metrics are scraped, so the
+    /// oldest message age keeps growing while the worker is stuck
+    pub fn update_metrics(&self) {
+        self.pending.update_metrics();
+    }
+
+    /// Results of the most recently processed messages, newest first
+    pub fn history(&self) -> Vec<SendRecord> {
+        self.history.recent()
+    }
+
+    /// Queued, sending and failed messages, oldest first
+    pub fn outbox(&self) -> Vec<OutboxEntry> {
+        let mut entries = self.pending.entries();
+        entries.extend(self.failed.entries());
+        entries.sort_by_key(|e| e.id);
+        entries
+    }
+
+    /// Queues a failed message again, with its attempts reset
+    pub fn retry(&self, id: u64) -> Result<(), OutboxError> {
+        let Some(entry) = self.failed.remove(id) else {
+            return Err(match self.pending.get(id) {
+                Some(_) => OutboxError::NotFailed,
+                None => OutboxError::NotFound,
+            });
+        };
+        let sms = OutgoingSms {
+            id,
+            to: entry.to,
+            message: entry.message,
+            client: entry.client,
+            api_key: entry.api_key,
+            lane: entry.lane,
+            flash: entry.flash,
+            queued_at: Instant::now(),
+            attempts: 0,
+            send_at: None,
+            reply: None,
+        };
+        info!("Retrying failed SMS {}", id);
+        self.send(sms).map_err(|_| OutboxError::Closed)
+    }
+
+    /// Removes a queued message before it is sent, or a failed one from the
+    /// outbox
+    pub fn cancel(&self, id: u64) -> Result<OutboxEntry, OutboxError> {
+        let entry = match self.failed.remove(id) {
+            Some(entry) => entry,
+            None => self.pending.cancel(id)?,
+        };
+        if let Some(store) = &self.store
+            && let Err(e) = store.dequeue(id)
+        {
+            error!("Failed to remove SMS {} from the outbox: {}", id, e);
+        }
+        info!("Cancelled SMS {}", id);
+        Ok(entry)
+    }
+
+    fn push(
+        &self,
+        to: Vec<String>,
+        message: String,
+        caller: Caller,
+        lane: Lane,
+        flash: bool,
+        reply: Option<oneshot::Sender<Result<(), ModemError>>>,
+    ) -> Result<u64, ModemError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.send(OutgoingSms {
+            id,
+            to,
+            message,
+            client: caller.client,
+            api_key: caller.api_key,
+            lane,
+            flash,
+            queued_at: Instant::now(),
+            attempts: 0,
+            send_at: None,
+            reply,
+        })?;
+        Ok(id)
+    }
+
+    fn send(&self, sms: OutgoingSms) -> Result<(), ModemError> {
+        let id = sms.id;
+        self.pending.insert(&sms);
+        // Callers waiting for the result get it instead of a retry
+        if sms.reply.is_none()
+            && let Some(store) = &self.store
+            && let Err(e) = store.queue(&sms.queued())
+        {
+            error!("Failed to store SMS {} in the outbox: {}", id, e);
+        }
+        self.lanes.schedule(sms).inspect_err(|_| {
+            self.pending.remove(id);
+        })
+    }
+
+    /// Queues a message and returns its ID without waiting for the modem.
+    pub fn enqueue(
+        &self,
+        to: Vec<String>,
+        message: String,
+        caller: Caller,
+        lane: Lane,
+        flash: bool,
+    ) -> Result<u64, ModemError> {
+        self.push(to, message, caller, lane, flash, None)
+    }
+
+    /// Queues a message that is not sent before `send_at`, a Unix timestamp in
+    /// seconds. With a store, it waits in the outbox, so it is sent after a
+    /// restart too.
+    pub fn enqueue_at(
+        &self,
+        to: Vec<String>,
+        message: String,
+        caller: Caller,
+        lane: Lane,
+        flash: bool,
+        send_at: u64,
+    ) -> Result<u64, ModemError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.send(OutgoingSms {
+            id,
+            to,
+            message,
+            client: caller.client,
+            api_key: caller.api_key,
+            lane,
+            flash,
+            // Only counts as waiting once it is due
+            queued_at: Instant::now() + delay_until(Some(send_at)),
+            attempts: 0,
+            send_at: Some(send_at),
+            reply: None,
+        })?;
+        Ok(id)
+    }
+
+    /// Queues a message and waits until the modem has accepted or rejected it.
+    pub async fn send_and_wait(
+        &self,
+        to: Vec<String>,
+        message: String,
+        caller: Caller,
+        lane: Lane,
+        flash: bool,
+    ) -> Result<u64, ModemError> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        let id = self.push(to, message, caller, lane, flash, Some(reply_tx))?;
+        reply_rx
+            .await
+            .map_err(|_| ModemError::Other("Send worker stopped".to_string()))??;
+        Ok(id)
+    }
+}
+
+/// Counts sent messages by the country code of the recipients
+fn count_countries(to: &[String]) {
+    for to in to {
+        let country_code = crate::server::extract_country_code(to);
+        counter!("smser_sms_country_total", "country_code" => country_code).increment(1);
+    }
+}
+
+/// Drains the queue, sending one message at a time
+struct Worker {
+    modem: Hilink,
+    routes: Reloadable<Routes>,
+    log_sensitive: bool,
+    pending: Pending,
+    failed: Failed,
+    history: SendHistory,
+    modem_health: ModemHealth,
+    store: Option<Store>,
+    /// For queueing messages again after a failed attempt
+    lanes: Lanes,
+    retries: u32,
+    audit: Option<AuditContent>,
+    stopped: Arc<tokio::sync::Mutex<bool>>,
+}
+
+impl Worker {
+    async fn run(
+        self,
+        mut high_rx: mpsc::UnboundedReceiver<OutgoingSms>,
+        mut normal_rx: mpsc::UnboundedReceiver<OutgoingSms>,
+    ) {
+        loop {
+            let sms = tokio::select! {
+                biased;
+                Some(sms) = high_rx.recv() => sms,
+                Some(sms) = normal_rx.recv() => sms,
+                else => break,
+            };
+            let stopped = self.stopped.lock().await;
+            if *stopped {
+                break;
+            }
+            self.process(sms).await;
+        }
+    }
+
+    async fn process(&self, mut sms: OutgoingSms) {
+        if !self.pending.start(sms.id) {
+            info!("Skipping cancelled SMS {}", sms.id);
+            if let Some(reply) = sms.reply {
+                let _ = reply.send(Err(ModemError::Other("Cancelled".to_string())));
+            }
+            return;
+        }
+        histogram!("smser_queue_wait_seconds", "lane" => sms.lane.to_string())
+            .record(sms.queued_at.elapsed().as_secs_f64());
+        gauge!("smser_queue_in_flight").set(1.0);
+        let started = Instant::now();
+        let report = self
+            .routes
+            .load()
+            .send(&self.modem, &sms.to, &sms.message, sms.flash)
+            .await;
+        histogram!("smser_send_duration_seconds").record(started.elapsed().as_secs_f64());
+        gauge!("smser_queue_in_flight").set(0.0);
+        for (modem, count) in report.sent {
+            counter!("smser_sms_sent_total", "modem" => modem).increment(count as u64);
+        }
+        let result = match report.failed {
+            Some(e) => {
+                if e.failed.len() < sms.to.len() {
+                    self.partially_sent(&mut sms, e.failed);
+                }
+                Err(e.error)
+            }
+            None => Ok(()),
+        };
+        self.modem_health.record(&result);
+        self.audit(&sms, &result);
+        if let Err(e) = &result
+            && sms.reply.is_none()
+            && !e.is_permanent()
+            && sms.attempts < self.retries
+        {
+            self.retry(sms, e);
+            return;
+        }
+        if let Err(e) = &result
+            && sms.reply.is_none()
+            && let Some(entry) = self.pending.get(sms.id)
+        {
+            self.failed.insert(entry, e);
+        }
+        self.pending.remove(sms.id);
+        let record = self.history.record(&sms, &result);
+        if let Some(store) = &self.store {
+            for to in &sms.to {
+                if let Err(e) =
+                    store.record_sent(to, &sms.message, record.status, record.error_code)
+                {
+                    error!("Failed to store SMS {}: {}", sms.id, e);
+                }
+            }
+            if let Err(e) = store.dequeue(sms.id) {
+                error!("Failed to remove SMS {} from the outbox: {}", sms.id, e);
+            }
+        }
+        let client = sms.client.as_deref().unwrap_or("none");
+        match &result {
+            Ok(()) => {
+                if self.log_sensitive {
+                    info!(
+                        "SMS {} sent successfully to {} (client: {})",
+                        sms.id,
+                        sms.to.join(", "),
+                        client
+                    );
+                } else {
+                    info!("SMS {} sent successfully (client: {})", sms.id, client);
+                }
+                count_countries(&sms.to);
+            }
+            Err(e) => {
+                error!("Error sending SMS {}: {}", sms.id, e);
+                counter!("smser_sms_failed_total").increment(sms.to.len() as u64);
+            }
+        }
+        if let Some(reply) = sms.reply {
+            let _ = reply.send(result);
+        }
+    }
+
+    /// Records the recipients of `sms` that got it as sent and leaves only
+    /// the `failed` ones, so a retry does not send it twice
+    fn partially_sent(&self, sms: &mut OutgoingSms, failed: Vec<String>) {
+        let delivered = OutgoingSms {
+            to: sms
+                .to
+                .iter()
+                .filter(|to| !failed.contains(to))
+                .cloned()
+                .collect(),
+            message: sms.message.clone(),
+            client: sms.client.clone(),
+            api_key: sms.api_key.clone(),
+            reply: None,
+            ..*sms
+        };
+        warn!(
+            "SMS {} sent to {} of {} recipients",
+            sms.id,
+            delivered.to.len(),
+            sms.to.len()
+        );
+        self.audit(&delivered, &Ok(()));
+        let record = self.history.record(&delivered, &Ok(()));
+        if let Some(store) = &self.store {
+            for to in &delivered.to {
+                if let Err(e) = store.record_sent(to, &sms.message, record.status, None) {
+                    error!("Failed to store SMS {}: {}", sms.id, e);
+                }
+            }
+            if let Err(e) = store.set_recipients(sms.id, &failed) {
+                error!("Failed to update SMS {} in the outbox: {}", sms.id, e);
+            }
+        }
+        count_countries(&delivered.to);
+        self.pending.set_recipients(sms.id, &failed);
+        sms.to = failed;
+    }
+
+    /// Writes the attempt to the audit log, if enabled
+    fn audit(&self, sms: &OutgoingSms, result: &Result<(), ModemError>) {
+        let (Some(store), Some(content)) = (&self.store, self.audit) else {
+            return;
+        };
+        let attempt = SendAttempt {
+            message_id: sms.id,
+            attempt: sms.attempts + 1,
+            client: sms.client.as_deref(),
+            api_key: sms.api_key.as_deref(),
+            to: &sms.to,
+            content: &sms.message,
+            status: if result.is_ok() {
+                SendStatus::Sent
+            } else {
+                SendStatus::Failed
+            },
+            error: result.as_ref().err().map(ToString::to_string),
+        };
+        if let Err(e) = store.record_attempt(&attempt, content) {
+            error!("Failed to audit SMS {}: {}", sms.id, e);
+        }
+    }
+
+    /// Queues `sms` again after a backoff delay
+    fn retry(&self, mut sms: OutgoingSms, error: &ModemError) {
+        sms.attempts += 1;
+        self.pending.requeue(sms.id, sms.attempts, error);
+        let delay = retry_delay(sms.attempts);
+        warn!(
+            "Error sending SMS {}: {}; retry {} of {} in {}s",
+            sms.id,
+            error,
+            sms.attempts,
+            self.retries,
+            delay.as_secs()
+        );
+        counter!("smser_sms_retries_total").increment(1);
+        if let Some(store) = &self.store
+            && let Err(e) = store.set_attempts(sms.id, sms.attempts)
+        {
+            error!("Failed to update SMS {} in the outbox: {}", sms.id, e);
+        }
+        let lanes = self.lanes.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let _ = lanes.send(sms);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockModem;
+
+    fn outgoing(id: u64) -> OutgoingSms {
+        OutgoingSms {
+            id,
+            to: vec!["+441234567890".to_string()],
+            message: "Hello".to_string(),
+            client: None,
+            api_key: None,
+            lane: Lane::Normal,
+            flash: false,
+            queued_at: Instant::now(),
+            attempts: 0,
+            send_at: None,
+            reply: None,
+        }
+    }
+
+    #[test]
+    fn test_pending_messages() {
+        let pending = Pending::default();
+        pending.insert(&outgoing(2));
+        let mut oldest = outgoing(1);
+        oldest.lane = Lane::High;
+        oldest.queued_at -= std::time::Duration::from_secs(30);
+        pending.insert(&oldest);
+        assert_eq!(pending.len(), 2);
+        let oldest = pending.messages.lock().unwrap().values().next().unwrap().0;
+        assert!(oldest.elapsed().as_secs() >= 30);
+        pending.remove(1);
+        pending.remove(2);
+        assert_eq!(pending.len(), 0);
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), RETRY_DELAY);
+        assert_eq!(retry_delay(3), RETRY_DELAY * 4);
+        assert_eq!(retry_delay(30), MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_from_outbox() {
+        let store = Store::open_in_memory().unwrap();
+        let modem = Arc::new(MockModem::new());
+        modem.fail_with(Some(100004));
+        let start = || {
+            SendQueue::start(
+                modem.clone(),
+                Routes::default(),
+                false,
+                ModemHealth::default(),
+                Some(store.clone()),
+                3,
+                None,
+            )
+        };
+        let to = vec!["+441234567890".to_string()];
+        let queue = start();
+        let id = queue
+            .enqueue(
+                to.clone(),
+                "Hi".to_string(),
+                Caller::default(),
+                Lane::Normal,
+                false,
+            )
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let outbox = store.queued().unwrap();
+        assert_eq!((outbox[0].id, outbox[0].attempts), (id, 1));
+        assert_eq!(queue.depth(), 1);
+        assert!(queue.history().is_empty());
+
+        // A restarted queue picks the message up again
+        modem.fail_with(None);
+        let queue = start();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(modem.sent(), [(to.clone(), "Hi".to_string())]);
+        assert!(store.queued().unwrap().is_empty());
+        assert_eq!(queue.history()[0].id, id);
+        let next = queue
+            .enqueue(
+                to,
+                "Next".to_string(),
+                Caller::default(),
+                Lane::Normal,
+                false,
+            )
+            .unwrap();
+        assert!(next > id);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_enqueue_at() {
+        let store = Store::open_in_memory().unwrap();
+        let modem = Arc::new(MockModem::new());
+        let start = || {
+            SendQueue::start(
+                modem.clone(),
+                Routes::default(),
+                false,
+                ModemHealth::default(),
+                Some(store.clone()),
+                3,
+                None,
+            )
+        };
+        let to = vec!["+441234567890".to_string()];
+        let send_at = unix_now() + 3600;
+        let queue = start();
+        let id = queue
+            .enqueue_at(
+                to.clone(),
+                "Later".to_string(),
+                Caller::default(),
+                Lane::Normal,
+                false,
+                send_at,
+            )
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert!(modem.sent().is_empty());
+        assert_eq!(queue.outbox()[0].send_at, Some(send_at));
+        assert_eq!(store.queued().unwrap()[0].send_at, Some(send_at));
+
+        // A restarted queue still waits for the send time
+        queue.shutdown(Duration::from_secs(1)).await;
+        let queue = start();
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert!(modem.sent().is_empty());
+        assert_eq!(queue.depth(), 1);
+        tokio::time::sleep(Duration::from_secs(3600)).await;
+        assert_eq!(modem.sent(), [(to, "Later".to_string())]);
+        assert_eq!(queue.history()[0].id, id);
+        assert!(store.queued().unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retries_exhausted() {
+        let modem = Arc::new(MockModem::new());
+        modem.fail_with(Some(100004));
+        let queue = SendQueue::start(
+            modem.clone(),
+            Routes::default(),
+            false,
+            ModemHealth::default(),
+            None,
+            2,
+            None,
+        );
+        let to = vec!["+441234567890".to_string()];
+        queue
+            .enqueue(
+                to.clone(),
+                "Hi".to_string(),
+                Caller::default(),
+                Lane::Normal,
+                false,
+            )
+            .unwrap();
+        tokio::time::sleep(RETRY_DELAY * 4).await;
+        assert_eq!(queue.depth(), 0);
+        assert_eq!(queue.history()[0].status, SendStatus::Failed);
+
+        // Callers waiting for the result get the first error
+        let err = queue
+            .send_and_wait(to, "Hi".to_string(), Caller::default(), Lane::Normal, false)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Some(100004));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_outbox_retry_and_cancel() {
+        let store = Store::open_in_memory().unwrap();
+        let modem = Arc::new(MockModem::new());
+        modem.fail_with(Some(100004));
+        let queue = SendQueue::start(
+            modem.clone(),
+            Routes::default(),
+            false,
+            ModemHealth::default(),
+            Some(store.clone()),
+            1,
+            None,
+        );
+        let to = vec!["+441234567890".to_string()];
+        let id = queue
+            .enqueue(
+                to.clone(),
+                "Hi".to_string(),
+                Caller::default(),
+                Lane::Normal,
+                false,
+            )
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let outbox = queue.outbox();
+        assert_eq!(outbox[0].state, OutboxState::Queued);
+        assert_eq!(outbox[0].attempts, 1);
+        assert!(outbox[0].last_error.is_some());
+        assert!(outbox[0].last_attempt_at.is_some());
+        assert_eq!(queue.retry(id), Err(OutboxError::NotFailed));
+
+        // Retries exhausted, the message is kept for retrying by hand
+        tokio::time::sleep(RETRY_DELAY * 2).await;
+        let outbox = queue.outbox();
+        assert_eq!((outbox[0].id, outbox[0].state), (id, OutboxState::Failed));
+        assert_eq!(queue.depth(), 0);
+        assert!(store.queued().unwrap().is_empty());
+
+        modem.fail_with(None);
+        queue.retry(id).unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(modem.sent(), [(to.clone(), "Hi".to_string())]);
+        assert!(queue.outbox().is_empty());
+        assert_eq!(queue.retry(id), Err(OutboxError::NotFound));
+
+        // A cancelled message waiting for a retry is not sent
+        modem.fail_with(Some(100004));
+        let id = queue
+            .enqueue(
+                to.clone(),
+                "Bye".to_string(),
+                Caller::default(),
+                Lane::Normal,
+                false,
+            )
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(queue.cancel(id).unwrap().message, "Bye");
+        assert!(store.queued().unwrap().is_empty());
+        modem.fail_with(None);
+        tokio::time::sleep(RETRY_DELAY * 2).await;
+        assert_eq!(modem.sent().len(), 1);
+        assert!(queue.outbox().is_empty());
+        assert_eq!(queue.cancel(id).unwrap_err(), OutboxError::NotFound);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_failed_recipients() {
+        use crate::config::RouteConfig;
+        use crate::transport::{Hilink, Transport};
+        use std::collections::HashMap;
+        let store = Store::open_in_memory().unwrap();
+        let modem = Arc::new(MockModem::new());
+        let other = Arc::new(MockModem::new());
+        other.fail_with(Some(100004));
+        let routes = Routes::new(
+            HashMap::from([(
+                "us".to_string(),
+                Arc::new(Hilink(other.clone())) as Arc<dyn Transport>,
+            )]),
+            vec![RouteConfig {
+                prefix: "+1".to_string(),
+                transport: "us".to_string(),
+            }],
+        );
+        let queue = SendQueue::start(
+            modem.clone(),
+            routes,
+            false,
+            ModemHealth::default(),
+            Some(store.clone()),
+            3,
+            None,
+        );
+        let to = ["+441234567890", "+15551234567"].map(String::from).to_vec();
+        let id = queue
+            .enqueue(to, "Hi".to_string(), Caller::default(), Lane::Normal, false)
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(modem.sent().len(), 1);
+        assert_eq!(queue.outbox()[0].to, ["+15551234567"]);
+        assert_eq!(store.queued().unwrap()[0].to, ["+15551234567"]);
+        assert_eq!(queue.history()[0].status, SendStatus::Sent);
+
+        // Only the recipient that did not get the message is retried
+        other.fail_with(None);
+        tokio::time::sleep(RETRY_DELAY * 2).await;
+        assert_eq!(
+            modem.sent(),
+            [(vec!["+441234567890".to_string()], "Hi".to_string())]
+        );
+        assert_eq!(
+            other.sent(),
+            [(vec!["+15551234567".to_string()], "Hi".to_string())]
+        );
+        assert!(queue.outbox().is_empty());
+        assert!(store.queued().unwrap().is_empty());
+        let history = queue.history();
+        assert_eq!(history.len(), 2);
+        assert!(history.iter().all(|record| record.id == id));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_shutdown() {
+        let store = Store::open_in_memory().unwrap();
+        let modem = Arc::new(MockModem::new());
+        let queue = SendQueue::start(
+            modem.clone(),
+            Routes::default(),
+            false,
+            ModemHealth::default(),
+            Some(store.clone()),
+            3,
+            None,
+        );
+        let to = vec!["+441234567890".to_string()];
+        queue
+            .enqueue(
+                to.clone(),
+                "Hi".to_string(),
+                Caller::default(),
+                Lane::Normal,
+                false,
+            )
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(queue.shutdown(Duration::from_secs(10)).await, 0);
+
+        // Messages queued after the shutdown are kept for the next start
+        queue
+            .enqueue(
+                to,
+                "Later".to_string(),
+                Caller::default(),
+                Lane::Normal,
+                false,
+            )
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(modem.sent().len(), 1);
+        assert_eq!(store.queued().unwrap()[0].content, "Later");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_audit_attempts() {
+        let store = Store::open_in_memory().unwrap();
+        let modem = Arc::new(MockModem::new());
+        modem.fail_with(Some(100004));
+        let queue = SendQueue::start(
+            modem.clone(),
+            Routes::default(),
+            false,
+            ModemHealth::default(),
+            Some(store.clone()),
+            1,
+            Some(AuditContent::Hash),
+        );
+        let caller = Caller {
+            client: Some("grafana".to_string()),
+            api_key: Some("ops".to_string()),
+        };
+        let id = queue
+            .enqueue(
+                vec!["+441234567890".to_string()],
+                "Hi".to_string(),
+                caller,
+                Lane::Normal,
+                false,
+            )
+            .unwrap();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        modem.fail_with(None);
+        tokio::time::sleep(RETRY_DELAY * 2).await;
+
+        let log = store
+            .audit_log(&crate::store::AuditFilter {
+                limit: 10,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!((log[0].message_id, log[0].attempt), (id, 2));
+        assert_eq!(log[0].status, SendStatus::Sent);
+        assert_eq!(log[1].status, SendStatus::Failed);
+        assert!(log[1].error.is_some());
+        assert_eq!(log[1].client.as_deref(), Some("grafana"));
+        assert_eq!(log[1].api_key.as_deref(), Some("ops"));
+        assert_eq!(log[1].content, None);
+    }
+
+    #[test]
+    fn test_mask_phone() {
+        assert_eq!(mask_phone("+441234567890"), "+44*******890");
+        assert_eq!(mask_phone("12345"), "*****");
+        assert_eq!(mask_phone(""), "");
+    }
+
+    #[test]
+    fn test_send_history_ring_buffer() {
+        let history = SendHistory::default();
+        for id in 1..=(HISTORY_SIZE as u64 + 5) {
+            history.record(&outgoing(id), &Ok(()));
+        }
+        history.record(
+            &outgoing(100),
+            &Err(ModemError::ModemError {
+                code: 113018.into(),
+                message: "busy".to_string(),
+            }),
+        );
+
+        let recent = history.recent();
+        assert_eq!(recent.len(), HISTORY_SIZE);
+        assert_eq!(recent[0].id, 100);
+        assert_eq!(recent[0].status, SendStatus::Failed);
+        assert_eq!(recent[0].error_code, Some(113018));
+        assert_eq!(recent[0].to, "+44*******890");
+        assert_eq!(recent[1].status, SendStatus::Sent);
+        assert_eq!(recent[HISTORY_SIZE - 1].id, 7);
+    }
+}
@@ -0,0 +1,113 @@
+//! Splitting messages that do not fit in one SMS into parts sized for a
+//! concatenated SMS: 153 GSM-7 septets or 67 UCS-2 units, the rest of each
+//! part being taken by the user data header.
+
+use crate::encoding::{self, SmsEncoding};
+use clap::ValueEnum;
+use strum_macros::{Display, EnumString};
+
+/// What to do with messages longer than one SMS
+#[derive(Clone, Copy, Debug, Default, PartialEq, Display, ValueEnum, EnumString)]
+#[strum(serialize_all = "kebab-case")]
+pub enum LongMessages {
+    /// Send the whole text in one request, which the modem sends as a
+    /// concatenated SMS. Texts with more parts than the modem joins are
+    /// logged with a warning.
+    #[default]
+    Concatenate,
+    /// Send each part as its own SMS, split at word boundaries
+    Split,
+}
+
+/// Units `c` takes in `encoding`
+fn units(c: char, encoding: SmsEncoding) -> usize {
+    match encoding {
+        SmsEncoding::Gsm7 => encoding::gsm7_septets(c).unwrap_or(1),
+        SmsEncoding::Ucs2 => c.len_utf16(),
+    }
+}
+
+/// Splits `text` into parts of a concatenated SMS. A text fitting in one SMS
+/// is returned as is. Parts end after the last whitespace if that keeps them
+/// at least half full, and never split an extension character or surrogate
+/// pair, so there can be more parts than [`encoding::estimate`] counts.
+pub fn split(text: &str) -> Vec<String> {
+    let estimate = encoding::estimate(text);
+    if estimate.segments <= 1 {
+        return vec![text.to_string()];
+    }
+    let limit = estimate.encoding.multipart_limit();
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut used = 0;
+        let mut end = rest.len();
+        let mut word_end = None;
+        for (i, c) in rest.char_indices() {
+            let n = units(c, estimate.encoding);
+            if used + n > limit {
+                end = i;
+                break;
+            }
+            used += n;
+            if c.is_whitespace() && used >= limit / 2 {
+                word_end = Some(i + c.len_utf8());
+            }
+        }
+        if end < rest.len()
+            && let Some(word_end) = word_end
+        {
+            end = word_end;
+        }
+        let (part, tail) = rest.split_at(end);
+        parts.push(part.to_string());
+        rest = tail;
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split("Hello"), ["Hello"]);
+        assert_eq!(split(&"a".repeat(160)).len(), 1);
+
+        let parts = split(&"a".repeat(307));
+        assert_eq!(
+            parts.iter().map(String::len).collect::<Vec<_>>(),
+            [153, 153, 1]
+        );
+
+        // Words are kept together
+        let text = "word ".repeat(40);
+        let parts = split(&text);
+        assert_eq!(parts.len(), 2);
+        assert!(parts[0].ends_with("word "));
+        assert_eq!(parts.concat(), text);
+
+        // Extension characters are not split across parts
+        let text = format!("{}€{}", "a".repeat(152), "b".repeat(10));
+        let parts = split(&text);
+        assert_eq!(parts, ["a".repeat(152), format!("€{}", "b".repeat(10))]);
+    }
+
+    #[test]
+    fn test_split_ucs2() {
+        let text = "ł".repeat(140);
+        let parts = split(&text);
+        assert_eq!(
+            parts.iter().map(|p| p.chars().count()).collect::<Vec<_>>(),
+            [67, 67, 6]
+        );
+
+        // Surrogate pairs are kept whole
+        let text = format!("{}😀{}", "ł".repeat(66), "ł".repeat(5));
+        assert_eq!(
+            split(&text),
+            ["ł".repeat(66), format!("😀{}", "ł".repeat(5))]
+        );
+    }
+}
@@ -1,19 +1,41 @@
 #[cfg(feature = "alertmanager")]
-use crate::alertmanager::{self, AlertManagerWebhook};
+use crate::alertmanager::{self, AlertManagerWebhook, AlertPolicies};
+use crate::assets;
 use crate::buildinfo;
-use crate::metrics::RateLimiter;
+use crate::client::{self, ModemClient};
+use crate::compose;
+#[cfg(feature = "alertmanager")]
+use crate::config::{AlertReceiver, SilenceSpec};
+use crate::dedup::{DuplicateAction, DuplicateGuard};
+use crate::filter::ContentFilter;
+use crate::metrics::{ClientLimit, RateLimiter};
 use crate::modem::{self, BoxType, Error as ModemError, SortType}; // Import modem module and alias Error
+use crate::notify::{IncomingWebhook, RateLimitNotifier, ReceiveCommand};
+#[cfg(feature = "alertmanager")]
+use crate::oncall::OnCall;
+use crate::queue::{Caller, Lane, OutboxError, SendQueue, SendStatus};
+#[cfg(feature = "alertmanager")]
+use crate::silence::Silences;
+use crate::store::{AuditContent, Direction, HistoryFilter, Store};
+use crate::template;
+use crate::transport::{Hilink, Routes, Transport};
+use crate::types::{ErrorDetail, ErrorKind, ErrorResponse, FieldError, NetworkInfo, SmsListJson};
+use axum::extract::rejection::{JsonRejection, QueryRejection};
 use axum::http::StatusCode; // For HTTP status codes
-use axum::response::Html;
+use axum::http::{HeaderMap, header};
+use axum::response::{Html, IntoResponse, Response};
 use axum::{
-    Json, Router,
-    extract::{Query, State},
+    Extension, Json, Router,
+    extract::{Path, Query, Request, State},
+    middleware::{self, Next},
     routing::{get, post},
 };
 use metrics::{counter, gauge};
 use metrics_exporter_prometheus::PrometheusHandle;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use tokio::net::TcpListener;
 
@@ -22,23 +44,322 @@ use axum_server::Handle;
 #[cfg(feature = "server")]
 use axum_server::tls_rustls::RustlsConfig;
 use tower_http::trace::TraceLayer;
-use tracing::{error, info};
+use tracing::{error, info, warn};
+
+/// A single phone number or a list of them
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Recipients {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Recipients {
+    pub fn into_vec(self) -> Vec<String> {
+        match self {
+            Recipients::One(phone) => vec![phone],
+            Recipients::Many(phones) => phones,
+        }
+    }
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SendSmsRequest {
-    pub to: String,
+    /// Recipients, sent the message in a single modem request
+    pub to: Recipients,
+    /// Message text, ignored if `template` is given
+    #[serde(default)]
     pub message: String,
+    /// Name of a message template from the config file
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Values for the `{{name}}` placeholders of the template or message
+    #[serde(default)]
+    pub vars: HashMap<String, String>,
+    /// Send a flash SMS, shown immediately on the recipient's phone, where
+    /// the transport supports it
+    #[serde(default)]
+    pub flash: bool,
     /// Optional client name for per-client rate limiting
     #[serde(default)]
     pub client: Option<String>,
 }
 
+impl SendSmsRequest {
+    /// Message to send: the rendered template, or the message with variables
+    /// expanded if any are given
+    fn render(&self, templates: &HashMap<String, String>) -> Result<String, ApiError> {
+        let bad_request = |e| ApiError::new(StatusCode::BAD_REQUEST, ErrorKind::BadRequest, e);
+        match &self.template {
+            Some(name) => {
+                let text = templates
+                    .get(name)
+                    .ok_or_else(|| bad_request(format!("Unknown template '{}'", name)))?;
+                template::render(text, &self.vars).map_err(bad_request)
+            }
+            None if self.vars.is_empty() => Ok(self.message.clone()),
+            None => template::render(&self.message, &self.vars).map_err(bad_request),
+        }
+    }
+}
+
+/// Error returned by API handlers, rendered as an `ErrorResponse` JSON body
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    detail: ErrorDetail,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            detail: ErrorDetail {
+                kind,
+                code: None,
+                message: message.into(),
+                fields: Vec::new(),
+            },
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.detail.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.detail.message
+    }
+
+    pub fn not_found() -> Self {
+        Self::new(StatusCode::NOT_FOUND, ErrorKind::NotFound, "Not found")
+    }
+
+    pub fn rate_limited(reason: &str) -> Self {
+        Self::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            ErrorKind::RateLimited,
+            format!("Rate limit exceeded: {}", reason),
+        )
+    }
+
+    /// Errors reported by the modem are client errors, failing to talk to it is a server error
+    pub fn modem(context: &str, e: &ModemError) -> Self {
+        let status = match e {
+            ModemError::ModemError { .. } => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        Self {
+            status,
+            detail: ErrorDetail {
+                kind: e.kind(),
+                code: e.code(),
+                message: format!("{}: {}", context, e),
+                fields: Vec::new(),
+            },
+        }
+    }
+
+    /// 422 listing the invalid `fields`
+    pub fn invalid(fields: Vec<FieldError>) -> Self {
+        let summary = fields
+            .iter()
+            .map(|f| format!("{}: {}", f.field, f.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let mut error = Self::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorKind::Invalid,
+            format!("Invalid request: {}", summary),
+        );
+        error.detail.fields = fields;
+        error
+    }
+}
+
+/// Checks the recipients and the final message text of a send request
+fn validate_send(to: &[String], message: &str) -> Result<(), ApiError> {
+    let mut fields = Vec::new();
+    if to.is_empty() {
+        fields.push(FieldError {
+            field: "to".to_string(),
+            message: "no recipients given".to_string(),
+        });
+    }
+    for (i, number) in to.iter().enumerate() {
+        if let Err(e) = compose::validate_number(number) {
+            fields.push(FieldError {
+                field: format!("to[{}]", i),
+                message: e,
+            });
+        }
+    }
+    if let Err(e) = compose::validate(message) {
+        fields.push(FieldError {
+            field: "message".to_string(),
+            message: e,
+        });
+    }
+    if fields.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::invalid(fields))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(ErrorResponse { error: self.detail })).into_response()
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(
+            rejection.status(),
+            ErrorKind::BadRequest,
+            rejection.body_text(),
+        )
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(
+            rejection.status(),
+            ErrorKind::BadRequest,
+            rejection.body_text(),
+        )
+    }
+}
+
+/// Counts a message to each of `to` against the rate limits, including those
+/// of the request's API key and of each recipient; rejections are counted and
+/// notified
+fn check_rate_limit(
+    state: &AppState,
+    client: Option<&str>,
+    api_key: Option<&ApiClient>,
+    to: &[String],
+) -> Result<(), ApiError> {
+    let api_key = api_key.map(|key| key.0.as_str());
+    state
+        .rate_limiter
+        .check_and_increment_send(client, api_key, to)
+        .map_err(|e| {
+            error!(
+                "Rate limit exceeded (client: {:?}, API key: {:?}): {}",
+                client, api_key, e
+            );
+            counter!("smser_rate_limited_total", "client" => client.unwrap_or("").to_string())
+                .increment(1);
+            if let Some(notifier) = &state.rate_limit_notifier {
+                notifier.notify(&e, client);
+            }
+            ApiError::rate_limited(&e)
+        })
+}
+
+/// Applies the content filter to an outgoing message, rejecting it with
+/// `422 Unprocessable Entity` if a blocking rule matches.
+fn filter_content(state: &AppState, message: String) -> Result<String, ApiError> {
+    match state.content_filter.load().apply(&message) {
+        Ok(std::borrow::Cow::Borrowed(_)) => Ok(message),
+        Ok(std::borrow::Cow::Owned(rewritten)) => {
+            if state.log_sensitive {
+                info!("Message rewritten by content filter: {:?}", rewritten);
+            } else {
+                info!("Message rewritten by content filter");
+            }
+            Ok(rewritten)
+        }
+        Err(rule) => {
+            if state.log_sensitive {
+                warn!("Message blocked by content filter {}: {:?}", rule, message);
+            } else {
+                warn!("Message blocked by content filter {}", rule);
+            }
+            counter!("smser_sms_blocked_total").increment(1);
+            Err(ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorKind::Blocked,
+                format!("Message blocked by content filter rule {}", rule),
+            ))
+        }
+    }
+}
+
+/// Checks a send against the duplicate window. Duplicates are counted and rejected
+/// unless the guard flags or suppresses them; returns the action taken for a
+/// duplicate, or `None` if the message is not one.
+fn check_duplicate(
+    state: &AppState,
+    to: &str,
+    message: &str,
+) -> Result<Option<DuplicateAction>, ApiError> {
+    let Some(guard) = &state.duplicate_guard else {
+        return Ok(None);
+    };
+    let Some(age) = guard.check(to, message, Instant::now()) else {
+        return Ok(None);
+    };
+    if state.log_sensitive {
+        info!(
+            "Duplicate SMS to {}, same message sent {}s ago ({})",
+            to,
+            age.as_secs(),
+            guard.action()
+        );
+    } else {
+        info!(
+            "Duplicate SMS, same message sent {}s ago ({})",
+            age.as_secs(),
+            guard.action()
+        );
+    }
+    counter!("smser_duplicates_total", "action" => guard.action().to_string()).increment(1);
+    match guard.action() {
+        DuplicateAction::Reject => Err(ApiError::new(
+            StatusCode::CONFLICT,
+            ErrorKind::Duplicate,
+            format!(
+                "Identical message already sent to {} {}s ago (duplicate window {}s)",
+                to,
+                age.as_secs(),
+                guard.window().as_secs()
+            ),
+        )),
+        action => Ok(Some(action)),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SendSmsQuery {
+    /// Wait until the modem has accepted the message instead of only queueing it
+    #[serde(default)]
+    sync: bool,
+}
+
 pub struct ServerConfig {
-    pub modem_url: String,
+    pub modem: Arc<dyn ModemClient>,
+    /// Transports other than `modem` for some recipients
+    pub routes: Routes,
     pub prometheus_handle: PrometheusHandle,
     pub rate_limiter: RateLimiter,
     #[cfg(feature = "alertmanager")]
     pub alert_phone_number: Option<String>,
+    /// Alertmanager receivers served at `/alertmanager/{receiver}`
+    #[cfg(feature = "alertmanager")]
+    pub alert_receivers: HashMap<String, AlertReceiver>,
+    /// Silences suppressing matching alerts
+    #[cfg(feature = "alertmanager")]
+    pub silences: Silences,
+    /// On-call schedules referenced by receivers
+    #[cfg(feature = "alertmanager")]
+    pub oncall: OnCall,
+    /// Severity based priorities and quiet hours
+    #[cfg(feature = "alertmanager")]
+    pub alert_policies: AlertPolicies,
     pub tls_cert: Option<PathBuf>,
     pub tls_key: Option<PathBuf>,
     /// Port for HTTP to HTTPS redirect (only used when TLS is enabled)
@@ -49,22 +370,253 @@ pub struct ServerConfig {
     pub log_sensitive: bool,
     /// Interval in seconds for polling new SMS messages (0 to disable)
     pub poll_interval: u64,
+    /// Receives every unread message found by polling; delivered messages are marked read
+    pub incoming_webhook: Option<IncomingWebhook>,
+    /// Program run for every new received message
+    pub receive_cmd: Option<ReceiveCommand>,
+    /// Interval in seconds for exporting the inbox unread/total gauges (0 to disable)
+    pub inbox_count_interval: u64,
+    /// Interval in seconds for exporting the data traffic gauges (0 to disable)
+    pub traffic_interval: u64,
+    /// Delete this many of the oldest read inbox messages when the modem storage
+    /// is found full by the inbox count task (disabled if unset)
+    pub auto_purge: Option<usize>,
+    /// Message store; when set, polled inbox messages and send results are archived into it
+    pub store: Option<Store>,
+    /// Bearer token required by the `/admin` endpoints; they are disabled if unset
+    pub admin_token: Option<String>,
+    /// API keys by client name, required by the endpoints sending, reading or
+    /// changing messages; these are open if empty
+    pub api_keys: HashMap<String, String>,
+    /// Username and password required on every route, if set
+    pub basic_auth: Option<BasicAuth>,
+    /// Notified when the rate limiter starts rejecting messages
+    pub rate_limit_notifier: Option<RateLimitNotifier>,
+    /// Catches identical messages sent to the same recipient in a short time
+    pub duplicate_guard: Option<DuplicateGuard>,
+    /// Named message templates usable by `/send-sms`
+    pub templates: HashMap<String, String>,
+    /// Rules blocking or rewriting outgoing messages
+    pub content_filter: ContentFilter,
+    /// Times a queued message is retried after a transient error
+    pub send_retries: u32,
+    /// Records every send attempt in the audit log of `store`, keeping the
+    /// message text or only its hash
+    pub audit: Option<AuditContent>,
+    /// Re-reads the config file on SIGHUP or `POST /admin/reload`
+    pub reloader: Option<ConfigReloader>,
+    /// Serves the gRPC API on this listener, if set
+    #[cfg(feature = "grpc")]
+    pub grpc_listener: Option<TcpListener>,
+}
+
+/// Settings re-read from the config file while the server is running
+pub struct ReloadedConfig {
+    pub routes: Routes,
+    pub templates: HashMap<String, String>,
+    pub content_filter: ContentFilter,
+    pub hourly_limit: u32,
+    pub daily_limit: u32,
+    pub recipient_limit: u32,
+    pub key_limits: Vec<ClientLimit>,
+    #[cfg(feature = "alertmanager")]
+    pub alert_receivers: HashMap<String, AlertReceiver>,
+    #[cfg(feature = "alertmanager")]
+    pub oncall: OnCall,
+    #[cfg(feature = "alertmanager")]
+    pub alert_policies: AlertPolicies,
+}
+
+/// Reads the config file again, failing if it is invalid
+pub type ConfigReloader = Arc<dyn Fn() -> Result<ReloadedConfig, String> + Send + Sync>;
+
+/// A setting that can be replaced while the server is running. Readers get
+/// a snapshot, which stays unchanged if the setting is replaced meanwhile.
+pub struct Reloadable<T>(Arc<RwLock<Arc<T>>>);
+
+impl<T> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(value))))
+    }
+
+    pub fn load(&self) -> Arc<T> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn store(&self, value: T) {
+        *self.0.write().unwrap() = Arc::new(value);
+    }
+}
+
+impl<T> Clone for Reloadable<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> std::fmt::Debug for Reloadable<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reloadable").finish_non_exhaustive()
+    }
 }
 
 #[derive(Clone)]
-struct AppState {
-    modem_url: String,
+pub(crate) struct AppState {
+    modem: Arc<dyn ModemClient>,
     rate_limiter: RateLimiter,
+    send_queue: SendQueue,
+    modem_health: ModemHealth,
+    store: Option<Store>,
     prometheus_handle: PrometheusHandle,
     #[cfg(feature = "alertmanager")]
     alert_phone_number: Option<String>,
+    #[cfg(feature = "alertmanager")]
+    alert_receivers: Reloadable<HashMap<String, AlertReceiver>>,
+    #[cfg(feature = "alertmanager")]
+    silences: Silences,
+    #[cfg(feature = "alertmanager")]
+    oncall: Reloadable<OnCall>,
+    #[cfg(feature = "alertmanager")]
+    alert_policies: Reloadable<AlertPolicies>,
+    admin_token: Option<Arc<str>>,
+    api_keys: Arc<HashMap<String, String>>,
+    basic_auth: Option<Arc<BasicAuth>>,
+    rate_limit_notifier: Option<RateLimitNotifier>,
+    duplicate_guard: Option<DuplicateGuard>,
+    templates: Reloadable<HashMap<String, String>>,
+    content_filter: Reloadable<ContentFilter>,
+    reloader: Option<ConfigReloader>,
+    /// New messages found by inbox polling, unset if polling is disabled
+    #[cfg(feature = "grpc")]
+    incoming: Option<broadcast::Sender<modem::SmsMessage>>,
     start_time: Instant,
     tls_enabled: bool,
     log_sensitive: bool,
 }
 
+#[cfg(feature = "grpc")]
+impl AppState {
+    /// Receives the messages found by inbox polling from now on, `None` if
+    /// polling is disabled
+    pub(crate) fn subscribe_incoming(&self) -> Option<broadcast::Receiver<modem::SmsMessage>> {
+        self.incoming.as_ref().map(broadcast::Sender::subscribe)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModemState {
+    /// No modem call has completed yet
+    Unknown,
+    Ok,
+    Error,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ModemErrorInfo {
+    /// Unix timestamp in seconds
+    pub timestamp: u64,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ModemHealthStatus {
+    pub state: ModemState,
+    /// Unix timestamp in seconds of the last successful modem call
+    pub last_success: Option<u64>,
+    pub last_error: Option<ModemErrorInfo>,
+}
+
+/// Tracks the outcome of the most recent modem calls
+#[derive(Clone, Debug)]
+pub struct ModemHealth {
+    status: Arc<Mutex<ModemHealthStatus>>,
+}
+
+impl Default for ModemHealth {
+    fn default() -> Self {
+        Self {
+            status: Arc::new(Mutex::new(ModemHealthStatus {
+                state: ModemState::Unknown,
+                last_success: None,
+                last_error: None,
+            })),
+        }
+    }
+}
+
+impl ModemHealth {
+    pub fn record<T>(&self, result: &Result<T, ModemError>) {
+        match result {
+            Ok(_) => self.record_success(),
+            Err(e) => self.record_error(e),
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut status = self.status.lock().unwrap();
+        status.state = ModemState::Ok;
+        status.last_success = Some(unix_now());
+    }
+
+    pub fn record_error(&self, error: &ModemError) {
+        let mut status = self.status.lock().unwrap();
+        status.state = ModemState::Error;
+        status.last_error = Some(ModemErrorInfo {
+            timestamp: unix_now(),
+            message: error.to_string(),
+        });
+    }
+
+    pub fn status(&self) -> ModemHealthStatus {
+        self.status.lock().unwrap().clone()
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
 use tokio::sync::oneshot; // New import
-use tokio::sync::watch;
+use tokio::sync::{broadcast, watch};
+
+/// New messages buffered for slow subscribers of [`AppState::incoming`]
+const INCOMING_CAPACITY: usize = 64;
+
+/// Longest wait on shutdown for the SMS being handed to the modem
+const QUEUE_SHUTDOWN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Resolves on SIGINT (Ctrl+C) or, on Unix, SIGTERM
+pub async fn termination_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl+C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
 
 pub async fn start_server(
     listener: TcpListener,
@@ -90,29 +642,120 @@ pub async fn start_server(
     gauge!("smser_version_info", "version" => buildinfo::version(), "git_hash" => buildinfo::git_hash()).set(1.0);
 
     let tls_enabled = config.tls_cert.is_some() && config.tls_key.is_some();
+    let modem_health = ModemHealth::default();
+    let (incoming, _) = broadcast::channel(INCOMING_CAPACITY);
     let app_state = AppState {
-        modem_url: config.modem_url.clone(),
+        modem: config.modem.clone(),
         rate_limiter: config.rate_limiter,
+        send_queue: SendQueue::start(
+            config.modem.clone(),
+            config.routes.clone(),
+            config.log_sensitive,
+            modem_health.clone(),
+            config.store.clone(),
+            config.send_retries,
+            config.audit,
+        ),
+        modem_health: modem_health.clone(),
+        store: config.store.clone(),
         prometheus_handle: config.prometheus_handle,
         #[cfg(feature = "alertmanager")]
         alert_phone_number: config.alert_phone_number,
+        #[cfg(feature = "alertmanager")]
+        alert_receivers: Reloadable::new(config.alert_receivers),
+        #[cfg(feature = "alertmanager")]
+        silences: config.silences,
+        #[cfg(feature = "alertmanager")]
+        oncall: Reloadable::new(config.oncall),
+        #[cfg(feature = "alertmanager")]
+        alert_policies: Reloadable::new(config.alert_policies),
+        admin_token: config.admin_token.map(Arc::from),
+        api_keys: Arc::new(config.api_keys),
+        basic_auth: config.basic_auth.map(Arc::new),
+        rate_limit_notifier: config.rate_limit_notifier,
+        duplicate_guard: config.duplicate_guard,
+        templates: Reloadable::new(config.templates),
+        content_filter: Reloadable::new(config.content_filter),
+        reloader: config.reloader,
+        #[cfg(feature = "grpc")]
+        incoming: (config.poll_interval > 0).then(|| incoming.clone()),
         start_time,
         tls_enabled,
         log_sensitive: config.log_sensitive,
     };
 
-    let app = Router::new()
-        .route("/", get(handler))
+    let send_queue = app_state.send_queue.clone();
+    let has_store = app_state.store.is_some();
+    let reload_state = app_state.clone();
+    #[cfg(feature = "grpc")]
+    let grpc_state = app_state.clone();
+
+    if app_state.api_keys.is_empty() {
+        warn!("No API keys configured: anyone who can reach the server can send and read SMS");
+    }
+
+    // Endpoints sending, reading or changing messages need an API key
+    let protected = Router::new()
         .route("/send-sms", post(send_sms_handler))
         .route("/get-sms", get(get_sms_handler))
-        .route("/metrics", get(metrics_handler))
-        .route("/status", get(status_handler))
-        .route("/statusz", get(status_handler));
+        .route("/device", get(device_handler))
+        .route("/history", get(history_handler))
+        .route("/purge-trash", post(purge_trash_handler))
+        .route("/sms", axum::routing::delete(delete_sms_handler))
+        .route("/mark-read", post(mark_read_handler))
+        .route("/sms/:index", axum::routing::delete(delete_one_sms_handler))
+        .route("/outbox", get(outbox_handler))
+        .route("/outbox/:id", axum::routing::delete(outbox_cancel_handler))
+        .route("/outbox/:id/retry", post(outbox_retry_handler));
 
     #[cfg(feature = "alertmanager")]
-    let app = app.route("/alertmanager", post(alertmanager_handler));
+    let protected = protected
+        .route("/alertmanager", post(alertmanager_handler))
+        .route(
+            "/alertmanager/:receiver",
+            post(alertmanager_receiver_handler),
+        )
+        .route(
+            "/silences",
+            get(list_silences_handler).post(create_silence_handler),
+        )
+        .route(
+            "/silences/:id",
+            axum::routing::delete(delete_silence_handler),
+        );
+
+    let protected = protected.route_layer(middleware::from_fn_with_state(
+        app_state.clone(),
+        require_api_key,
+    ));
+
+    let app = Router::new()
+        .route("/", get(handler))
+        .route("/ui", get(ui_handler))
+        .route("/assets/*path", get(asset_handler))
+        .route("/network", get(network_handler))
+        .route("/metrics", get(metrics_handler))
+        .route("/status", get(status_handler))
+        .route("/statusz", get(status_handler))
+        .route("/status.json", get(status_json_handler))
+        .route("/quota", get(quota_handler))
+        .route("/healthz", get(healthz_handler))
+        .route("/readyz", get(readyz_handler))
+        .route(
+            "/admin/limits",
+            get(get_limits_handler).put(update_limits_handler),
+        )
+        .route("/admin/reload", post(reload_handler))
+        .merge(protected);
 
-    let app = app.layer(TraceLayer::new_for_http()).with_state(app_state); // Pass state to the router
+    let app = app
+        .fallback(|| async { ApiError::not_found() })
+        .layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            require_basic_auth,
+        ))
+        .layer(TraceLayer::new_for_http())
+        .with_state(app_state); // Pass state to the router
 
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
     tokio::spawn(async move {
@@ -120,14 +763,98 @@ pub async fn start_server(
         let _ = shutdown_tx.send(true);
     });
 
+    // Reload the config file on SIGHUP, as daemons conventionally do
+    #[cfg(unix)]
+    if reload_state.reloader.is_some() {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut reload_shutdown_rx = shutdown_rx.clone();
+        match signal(SignalKind::hangup()) {
+            Ok(mut sighup) => {
+                tokio::spawn(async move {
+                    loop {
+                        tokio::select! {
+                            _ = reload_shutdown_rx.changed() => break,
+                            _ = sighup.recv() => {
+                                info!("Received SIGHUP, reloading the config file");
+                                let _ = reload_config(&reload_state);
+                            }
+                        }
+                    }
+                });
+            }
+            Err(e) => error!("Failed to listen for SIGHUP: {}", e),
+        }
+    }
+    #[cfg(not(unix))]
+    drop(reload_state);
+
+    // Export inbox counts so unprocessed messages piling up can be alerted on, and
+    // detect a full storage that stops the modem from receiving
+    if config.inbox_count_interval > 0 {
+        let modem = config.modem.clone();
+        let auto_purge = config.auto_purge;
+        let interval = std::time::Duration::from_secs(config.inbox_count_interval);
+        let mut count_shutdown_rx = shutdown_rx.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = count_shutdown_rx.changed() => {
+                        if *count_shutdown_rx.borrow() {
+                            break;
+                        }
+                    }
+                    _ = tokio::time::sleep(interval) => {
+                        match update_inbox_gauges(modem.as_ref()).await {
+                            Ok(count) if count.storage_full() => {
+                                handle_storage_full(modem.as_ref(), &count, auto_purge).await;
+                            }
+                            Ok(_) => {}
+                            Err(e) => error!("Failed to get SMS count: {}", e),
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    // Export data traffic so a stick using up the data plan can be noticed
+    if config.traffic_interval > 0 {
+        let modem = config.modem.clone();
+        let interval = std::time::Duration::from_secs(config.traffic_interval);
+        let mut traffic_shutdown_rx = shutdown_rx.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = traffic_shutdown_rx.changed() => {
+                        if *traffic_shutdown_rx.borrow() {
+                            break;
+                        }
+                    }
+                    _ = tokio::time::sleep(interval) => {
+                        if let Err(e) = update_traffic_gauges(modem.as_ref()).await {
+                            error!("Failed to get traffic statistics: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
     // Start SMS polling task if enabled
     if config.poll_interval > 0 {
-        let poll_modem_url = config.modem_url.clone();
+        let transport = Hilink(config.modem.clone());
         let poll_interval_secs = config.poll_interval;
         let log_sensitive = config.log_sensitive;
+        let modem_health = modem_health.clone();
+        let store = config.store.clone();
+        let webhook = config.incoming_webhook.clone();
+        let receive_cmd = config.receive_cmd.clone();
+        let incoming = incoming.clone();
         let mut poll_shutdown_rx = shutdown_rx.clone();
         tokio::spawn(async move {
             let mut last_seen_index: Option<i32> = None;
+            // The first poll always lists the inbox, later ones only when messages are unread
+            let mut listed = false;
             let mut consecutive_errors: u32 = 0;
             let mut next_delay_secs = poll_interval_secs;
 
@@ -141,13 +868,25 @@ pub async fn start_server(
                     }
                     _ = tokio::time::sleep(std::time::Duration::from_secs(next_delay_secs)) => {
                         info!("Polling for new SMS messages...");
-                        match poll_sms(&poll_modem_url, log_sensitive, last_seen_index).await {
-                            Ok((count, new_last_seen, logged)) => {
+                        let result = poll_sms(&transport, log_sensitive, last_seen_index, store.as_ref(), listed, webhook.as_ref(), receive_cmd.as_ref()).await;
+                        modem_health.record(&result);
+                        match result {
+                            Ok(None) => {
+                                consecutive_errors = 0;
+                                next_delay_secs = poll_interval_secs;
+                                info!("SMS poll complete, no unread messages");
+                            }
+                            Ok(Some((count, new_last_seen, new_messages))) => {
                                 last_seen_index = new_last_seen;
+                                listed = true;
                                 consecutive_errors = 0;
                                 next_delay_secs = poll_interval_secs;
-                                if logged > 0 {
-                                    info!("SMS poll complete, {} messages in inbox ({} new)", count, logged);
+                                if !new_messages.is_empty() {
+                                    info!("SMS poll complete, {} messages in inbox ({} new)", count, new_messages.len());
+                                    // Nobody may be streaming, so a failed send is fine
+                                    for message in new_messages {
+                                        let _ = incoming.send(message);
+                                    }
                                 } else {
                                     info!("SMS poll complete, {} messages in inbox", count);
                                 }
@@ -168,6 +907,15 @@ pub async fn start_server(
         });
     }
 
+    #[cfg(feature = "grpc")]
+    let grpc_server = config.grpc_listener.map(|listener| {
+        tokio::spawn(crate::grpc::serve(
+            listener,
+            grpc_state,
+            shutdown_rx.clone(),
+        ))
+    });
+
     // run it
     let addr = listener.local_addr().unwrap();
     println!("listening on {}", addr);
@@ -205,7 +953,7 @@ pub async fn start_server(
                         axum::response::Redirect::permanent(&redirect_url)
                     }
                 });
-                let redirect_addr = std::net::SocketAddr::from(([0, 0, 0, 0], http_port));
+                let redirect_addr = std::net::SocketAddr::new(addr.ip(), http_port);
                 let redirect_listener = tokio::net::TcpListener::bind(&redirect_addr)
                     .await
                     .expect("Failed to bind HTTP redirect port");
@@ -239,6 +987,27 @@ pub async fn start_server(
             .await
             .unwrap();
     }
+
+    // gRPC calls may still be queueing messages
+    #[cfg(feature = "grpc")]
+    if let Some(grpc_server) = grpc_server {
+        grpc_server.await.ok();
+    }
+
+    // Requests have been answered, so only messages queued for later are left
+    let left = send_queue.shutdown(QUEUE_SHUTDOWN_TIMEOUT).await;
+    if left > 0 && has_store {
+        info!(
+            "Keeping {} queued SMS in the outbox for the next start",
+            left
+        );
+    } else if left > 0 {
+        warn!(
+            "Dropping {} queued SMS; use --db-path to keep them across restarts",
+            left
+        );
+    }
+    info!("Server stopped");
 }
 
 fn html_escape(s: &str) -> String {
@@ -249,168 +1018,49 @@ fn html_escape(s: &str) -> String {
         .replace('\'', "&#39;")
 }
 
-async fn handler() -> Html<String> {
+/// Renders an embedded HTML template, failing with a 500 if it is missing
+fn render_page(template: &str, vars: &[(&str, &str)]) -> Result<Html<String>, ApiError> {
+    assets::render(template, vars).map(Html).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorKind::Internal,
+            format!("Missing embedded template '{}'", template),
+        )
+    })
+}
+
+async fn handler() -> Result<Html<String>, ApiError> {
     counter!("smser_http_requests_total", "endpoint" => "/").increment(1);
-    let html = format!(
-        r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>smser - SMS Gateway</title>
-    <link href="https://cdn.jsdelivr.net/npm/bootstrap@5.3.0/dist/css/bootstrap.min.css" rel="stylesheet">
-    <style>
-        body {{ background-color: #f8f9fa; }}
-        .container {{ max-width: 800px; margin-top: 2rem; }}
-        .msg-card {{ margin-bottom: 1rem; }}
-        .date {{ font-size: 0.85rem; color: #6c757d; }}
-    </style>
-</head>
-<body>
-    <nav class="navbar navbar-dark bg-dark mb-4">
-        <div class="container-fluid">
-            <span class="navbar-brand mb-0 h1">smser Gateway</span>
-            <div class="d-flex align-items-center">
-                <span class="badge text-bg-secondary me-2">{}</span>
-                <a href="{}" class="btn btn-outline-info btn-sm me-2" target="_blank">GitHub</a>
-                <a href="/status" class="btn btn-outline-light btn-sm me-2">Status</a>
-                <a href="/metrics" class="btn btn-outline-light btn-sm">Metrics</a>
-            </div>
-        </div>
-    </nav>
-
-    <div class="container">
-        <div class="row">
-            <div class="col-md-12">
-                <div class="card shadow-sm mb-4">
-                    <div class="card-header bg-primary text-white">
-                        <h5 class="card-title mb-0">Send SMS</h5>
-                    </div>
-                    <div class="card-body">
-                        <form id="sendForm">
-                            <div class="mb-3">
-                                <label for="to" class="form-label">Destination Number</label>
-                                <input type="text" class="form-control" id="to" placeholder="+44..." required>
-                            </div>
-                            <div class="mb-3">
-                                <label for="message" class="form-label">Message</label>
-                                <textarea class="form-control" id="message" rows="3" required></textarea>
-                            </div>
-                            <button type="submit" class="btn btn-primary" id="sendBtn">Send Message</button>
-                        </form>
-                        <div id="sendAlert" class="mt-3 d-none alert"></div>
-                    </div>
-                </div>
-            </div>
-        </div>
-
-        <div class="row">
-            <div class="col-md-12">
-                <div class="card shadow-sm">
-                    <div class="card-header d-flex justify-content-between align-items-center">
-                        <div class="d-flex align-items-center gap-2">
-                            <h5 class="card-title mb-0">Recent Messages</h5>
-                            <span id="messagesLastRefresh" class="text-muted small"></span>
-                        </div>
-                        <button class="btn btn-sm btn-secondary" onclick="fetchMessages()">Refresh</button>
-                    </div>
-                    <div class="card-body">
-                        <div id="messagesList">
-                            <div class="text-center p-4">Loading messages...</div>
-                        </div>
-                    </div>
-                </div>
-            </div>
-        </div>
-    </div>
-
-    <script>
-        function setLastRefreshLabel() {{
-            const label = document.getElementById('messagesLastRefresh');
-            const now = new Date();
-            label.textContent = `Last refresh: ${{now.toISOString()}}`;
-        }}
-
-        async function fetchMessages() {{
-            try {{
-                const response = await fetch('/get-sms?count=10');
-                const data = await response.json();
-                const list = document.getElementById('messagesList');
-
-                if (data.status === 'success' && data.messages) {{
-                    setLastRefreshLabel();
-                    if (data.messages.length === 0) {{
-                        list.innerHTML = '<div class="text-center p-4">No messages found.</div>';
-                        return;
-                    }}
-
-                    list.innerHTML = data.messages.map(msg => `
-                        <div class="card msg-card border-0 border-bottom">
-                            <div class="card-body px-0">
-                                <div class="d-flex justify-content-between align-items-start">
-                                    <h6 class="mb-1">${{msg.Phone}}</h6>
-                                    <span class="date">${{msg.Date}}</span>
-                                </div>
-                                <p class="card-text mb-0">${{msg.Content}}</p>
-                            </div>
-                        </div>
-                    `).join('');
-                }} else {{
-                    setLastRefreshLabel();
-                    list.innerHTML = `<div class="alert alert-danger">Error: ${{data.message || 'Failed to load'}}</div>`;
-                }}
-            }} catch (e) {{
-                setLastRefreshLabel();
-                document.getElementById('messagesList').innerHTML = `<div class="alert alert-danger">Error connecting to server.</div>`;
-            }}
-        }}
-
-        document.getElementById('sendForm').addEventListener('submit', async (e) => {{
-            e.preventDefault();
-            const btn = document.getElementById('sendBtn');
-            const alert = document.getElementById('sendAlert');
-            const to = document.getElementById('to').value;
-            const message = document.getElementById('message').value;
-
-            btn.disabled = true;
-            alert.className = 'mt-3 d-none alert';
-
-            try {{
-                const response = await fetch('/send-sms', {{
-                    method: 'POST',
-                    headers: {{ 'Content-Type': 'application/json' }},
-                    body: JSON.stringify({{ to, message, client: 'webclient' }})
-                }});
-                const data = await response.json();
-
-                alert.className = `mt-3 alert alert-${{data.status === 'success' ? 'success' : 'danger'}}`;
-                alert.innerText = data.message || (data.status === 'success' ? 'Sent!' : 'Failed');
-                alert.classList.remove('d-none');
-
-                if (data.status === 'success') {{
-                    document.getElementById('message').value = '';
-                }}
-            }} catch (e) {{
-                alert.className = 'mt-3 alert alert-danger';
-                alert.innerText = 'Network error.';
-                alert.classList.remove('d-none');
-            }} finally {{
-                btn.disabled = false;
-            }}
-        }});
-
-        // Initial load
-        fetchMessages();
-    </script>
-</body>
-</html>"#,
-        html_escape(&buildinfo::version_full()),
-        html_escape(buildinfo::repository())
-    );
-    Html(html.to_string())
+    render_page(
+        "index.html",
+        &[
+            ("version", &html_escape(&buildinfo::version_full())),
+            ("repository", &html_escape(buildinfo::repository())),
+        ],
+    )
+}
+
+/// Single-page UI for reading and sending messages through the JSON API
+async fn ui_handler() -> Result<Html<String>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/ui").increment(1);
+    render_page(
+        "ui.html",
+        &[("version", &html_escape(&buildinfo::version_full()))],
+    )
+}
+
+async fn asset_handler(Path(path): Path<String>) -> Response {
+    match assets::get(&path) {
+        Some(contents) => (
+            [(header::CONTENT_TYPE, assets::content_type(&path))],
+            contents,
+        )
+            .into_response(),
+        None => ApiError::not_found().into_response(),
+    }
 }
 
-fn extract_country_code(phone: &str) -> String {
+pub(crate) fn extract_country_code(phone: &str) -> String {
     if phone.starts_with('+') {
         phone.chars().take(4).collect()
     } else {
@@ -420,18 +1070,26 @@ fn extract_country_code(phone: &str) -> String {
 
 async fn metrics_handler(State(state): State<AppState>) -> String {
     counter!("smser_http_requests_total", "endpoint" => "/metrics").increment(1);
-    state.prometheus_handle.render()
+    state.send_queue.update_metrics();
+    let mut metrics = state.prometheus_handle.render();
+    metrics.push_str(&state.rate_limiter.render_key_limits());
+    metrics
 }
 
-async fn status_handler(State(state): State<AppState>) -> Html<String> {
+/// The status page, or the `/status.json` data for clients that accept JSON
+async fn status_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
     counter!("smser_http_requests_total", "endpoint" => "/status").increment(1);
+    if accepts_json(&headers) {
+        return Json(status_json(&state)).into_response();
+    }
     let status = state.rate_limiter.get_status();
     let client_status = state.rate_limiter.get_client_status();
+    let key_status = state.rate_limiter.get_key_status();
     let uptime = state.start_time.elapsed();
     let uptime_str = format_uptime(uptime);
 
     // Build client limits HTML
-    let client_limits_html = if client_status.is_empty() {
+    let client_limits_html = if client_status.is_empty() && key_status.is_empty() {
         String::from("<p>No per-client limits configured</p>")
     } else {
         let mut html = String::from(
@@ -456,80 +1114,235 @@ async fn status_handler(State(state): State<AppState>) -> Html<String> {
                 cs.daily_limit
             ));
         }
+        let limit = |limit: Option<u32>| limit.map_or("-".to_string(), |l| l.to_string());
+        for ks in &key_status {
+            html.push_str(&format!(
+                r#"<tr style="border-bottom: 1px solid #eee;">
+                <td style="padding: 0.5rem;">API key {}</td>
+                <td style="text-align: right; padding: 0.5rem;">{} / {}</td>
+                <td style="text-align: right; padding: 0.5rem;">{} / {}</td>
+            </tr>"#,
+                html_escape(&ks.name),
+                ks.hourly_usage,
+                limit(ks.hourly_limit),
+                ks.daily_usage,
+                limit(ks.daily_limit)
+            ));
+        }
         html.push_str("</table>");
         html
     };
 
-    // Build alert recipient HTML (only if alertmanager feature is enabled)
-    #[cfg(feature = "alertmanager")]
-    let alert_html = match &state.alert_phone_number {
-        Some(phone) => format!(
-            r#"<div class="stat"><span class="label">Alert Recipient:</span> {}</div>"#,
-            html_escape(phone)
-        ),
-        None => String::from(
-            r#"<div class="stat"><span class="label">Alert Recipient:</span> <em>Not configured</em></div>"#,
-        ),
-    };
+    // Build recent sends HTML
+    let history = state.send_queue.history();
+    let history_html = if history.is_empty() {
+        String::from("<p>No messages sent yet</p>")
+    } else {
+        let now = unix_now();
+        let mut html = String::from(
+            r#"<table style="width: 100%; border-collapse: collapse;">
+            <tr style="border-bottom: 1px solid #ddd;">
+                <th style="text-align: left; padding: 0.5rem;">Time</th>
+                <th style="text-align: left; padding: 0.5rem;">ID</th>
+                <th style="text-align: left; padding: 0.5rem;">Destination</th>
+                <th style="text-align: left; padding: 0.5rem;">Status</th>
+                <th style="text-align: left; padding: 0.5rem;">Error Code</th>
+            </tr>"#,
+        );
+        for record in &history {
+            let age = std::time::Duration::from_secs(now.saturating_sub(record.timestamp));
+            html.push_str(&format!(
+                r#"<tr style="border-bottom: 1px solid #eee;">
+                <td style="padding: 0.5rem;">{} ago</td>
+                <td style="padding: 0.5rem;">{}</td>
+                <td style="padding: 0.5rem;">{}</td>
+                <td style="padding: 0.5rem;">{}</td>
+                <td style="padding: 0.5rem;">{}</td>
+            </tr>"#,
+                format_uptime(age),
+                record.id,
+                html_escape(&record.to),
+                record.status,
+                record.error_code.map(|c| c.to_string()).unwrap_or_default()
+            ));
+        }
+        html.push_str("</table>");
+        html
+    };
+
+    // Build alert recipient HTML (only if alertmanager feature is enabled)
+    #[cfg(feature = "alertmanager")]
+    let alert_html = match &state.alert_phone_number {
+        Some(phone) => format!(
+            r#"<div class="stat"><span class="label">Alert Recipient:</span> {}</div>"#,
+            html_escape(phone)
+        ),
+        None => String::from(
+            r#"<div class="stat"><span class="label">Alert Recipient:</span> <em>Not configured</em></div>"#,
+        ),
+    };
     #[cfg(not(feature = "alertmanager"))]
     let alert_html = String::new();
 
+    // Show the network registration, without letting a hung modem block the page
+    let network = tokio::time::timeout(std::time::Duration::from_secs(3), async {
+        let session = state.modem.get_session().await?;
+        state.modem.get_network_info(&session).await
+    })
+    .await;
+    let network_html = match network {
+        Ok(result) => {
+            state.modem_health.record(&result);
+            match result {
+                Ok(info) => network_html(&info),
+                Err(e) => format!(
+                    r#"<div class="stat"><span class="label">Network:</span> <em>{}</em></div>"#,
+                    html_escape(&e.to_string())
+                ),
+            }
+        }
+        Err(_) => String::from(
+            r#"<div class="stat"><span class="label">Network:</span> <em>Modem did not respond</em></div>"#,
+        ),
+    };
+
     let tls_status = if state.tls_enabled {
         "Enabled"
     } else {
         "Disabled"
     };
 
-    let html = format!(
-        r#"<!DOCTYPE html>
-<html>
-<head>
-    <title>SMS Server Status</title>
-    <style>
-        body {{ font-family: sans-serif; margin: 2rem; background: #f5f5f5; }}
-        h1 {{ color: #333; }}
-        .card {{ background: white; border: 1px solid #ddd; padding: 1rem; border-radius: 4px; margin-bottom: 1rem; }}
-        .stat {{ margin: 0.5rem 0; }}
-        .label {{ font-weight: bold; }}
-        h2 {{ margin-top: 0; color: #555; font-size: 1.1rem; }}
-    </style>
-</head>
-<body>
-    <h1>SMS Server Status</h1>
-    <div class="card">
-        <h2>Configuration</h2>
-        <div class="stat"><span class="label">Version:</span> {version}</div>
-        <div class="stat"><span class="label">Modem URL:</span> {modem_url}</div>
-        <div class="stat"><span class="label">TLS:</span> {tls_status}</div>
-        {alert_html}
-    </div>
-    <div class="card">
-        <h2>Status</h2>
-        <div class="stat"><span class="label">Uptime:</span> {uptime}</div>
-    </div>
-    <div class="card">
-        <h2>Global Rate Limits</h2>
-        <div class="stat"><span class="label">Hourly Usage:</span> {hourly_usage} / {hourly_limit}</div>
-        <div class="stat"><span class="label">Daily Usage:</span> {daily_usage} / {daily_limit}</div>
-    </div>
-    <div class="card">
-        <h2>Per-Client Rate Limits</h2>
-        {client_limits_html}
-    </div>
-</body>
-</html>"#,
-        version = html_escape(&buildinfo::version_full()),
-        modem_url = html_escape(&state.modem_url),
-        tls_status = tls_status,
-        alert_html = alert_html,
-        uptime = uptime_str,
-        hourly_usage = status.hourly_usage,
-        hourly_limit = status.hourly_limit,
-        daily_usage = status.daily_usage,
-        daily_limit = status.daily_limit,
-        client_limits_html = client_limits_html
-    );
-    Html(html)
+    render_page(
+        "status.html",
+        &[
+            ("version", &html_escape(&buildinfo::version_full())),
+            ("modem_url", &html_escape(state.modem.url())),
+            ("tls_status", tls_status),
+            ("alert_html", &alert_html),
+            ("uptime", &uptime_str),
+            ("network_html", &network_html),
+            ("hourly_usage", &status.hourly_usage.to_string()),
+            ("hourly_limit", &status.hourly_limit.to_string()),
+            ("daily_usage", &status.daily_usage.to_string()),
+            ("daily_limit", &status.daily_limit.to_string()),
+            ("client_limits_html", &client_limits_html),
+            ("history_html", &history_html),
+        ],
+    )
+    .into_response()
+}
+
+/// Whether the `Accept` header prefers JSON over HTML
+fn accepts_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json") && !accept.contains("text/html"))
+}
+
+/// Status page rows for the operator, network type, connection and roaming state
+fn network_html(info: &NetworkInfo) -> String {
+    let unknown = || "<em>Unknown</em>".to_string();
+    let operator = match (&info.operator, &info.plmn) {
+        (Some(name), Some(plmn)) => format!("{} ({})", html_escape(name), html_escape(plmn)),
+        (Some(name), None) => html_escape(name),
+        (None, Some(plmn)) => html_escape(plmn),
+        (None, None) => "<em>Not registered</em>".to_string(),
+    };
+    let rows = [
+        ("Operator", operator),
+        (
+            "Network Type",
+            info.network_type
+                .as_deref()
+                .map_or_else(unknown, html_escape),
+        ),
+        (
+            "Connection",
+            info.connection.as_deref().map_or_else(unknown, html_escape),
+        ),
+        (
+            "Roaming",
+            match info.roaming {
+                Some(true) => "Yes".to_string(),
+                Some(false) => "No".to_string(),
+                None => unknown(),
+            },
+        ),
+    ];
+    rows.iter()
+        .map(|(label, value)| {
+            format!(
+                r#"<div class="stat"><span class="label">{}:</span> {}</div>"#,
+                label, value
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n        ")
+}
+
+async fn status_json_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    counter!("smser_http_requests_total", "endpoint" => "/status.json").increment(1);
+    Json(status_json(&state))
+}
+
+/// Liveness: answers as long as the process serves requests
+async fn healthz_handler() -> Json<serde_json::Value> {
+    Json(serde_json::json!({"status": "ok"}))
+}
+
+/// Readiness: opens a modem session (logging in if needed) and checks that the
+/// SIM is unlocked, answering 503 with the reason otherwise
+async fn readyz_handler(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/readyz").increment(1);
+    let not_ready = |reason: String| {
+        ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorKind::ModemUnavailable,
+            reason,
+        )
+    };
+    let session = match state.modem.get_session().await {
+        Ok(session) => session,
+        Err(e) => {
+            state.modem_health.record_error(&e);
+            return Err(not_ready(format!("Modem session failed: {}", e)));
+        }
+    };
+    let result = state.modem.get_pin_status(&session).await;
+    state.modem_health.record(&result);
+    match result {
+        Ok(pin) if pin.is_ready() => Ok(Json(serde_json::json!({
+            "status": "ready",
+            "modem_url": state.modem.url(),
+            "sim_state": pin.sim_state_name(),
+        }))),
+        Ok(pin) => Err(not_ready(format!(
+            "SIM not ready: {}",
+            pin.sim_state_name()
+        ))),
+        Err(e) => Err(not_ready(format!("SIM state unavailable: {}", e))),
+    }
+}
+
+fn status_json(state: &AppState) -> serde_json::Value {
+    serde_json::json!({
+        "version": buildinfo::version(),
+        "git_hash": buildinfo::git_hash(),
+        "uptime_seconds": state.start_time.elapsed().as_secs(),
+        "tls_enabled": state.tls_enabled,
+        "rate_limits": limits_json(&state.rate_limiter),
+        "queue_depth": state.send_queue.depth(),
+        "modems": state
+            .modem
+            .modems()
+            .into_iter()
+            .map(|(url, healthy)| serde_json::json!({"url": url, "healthy": healthy}))
+            .collect::<Vec<_>>(),
+        "modem": state.modem_health.status(),
+    })
 }
 
 fn format_uptime(duration: std::time::Duration) -> String {
@@ -552,14 +1365,60 @@ fn format_uptime(duration: std::time::Duration) -> String {
 
 async fn send_sms_handler(
     State(state): State<AppState>,
-    Json(payload): Json<SendSmsRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    api_client: Option<Extension<ApiClient>>,
+    query: Result<Query<SendSmsQuery>, QueryRejection>,
+    payload: Result<Json<SendSmsRequest>, JsonRejection>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
     counter!("smser_http_requests_total", "endpoint" => "/send-sms").increment(1);
+    let Query(query) = query?;
+    let Json(payload) = payload?;
+    let api_client = api_client.map(|Extension(key)| key);
+    match send_sms(&state, payload, api_client, query.sync).await? {
+        SendOutcome::Suppressed => Ok((
+            StatusCode::OK,
+            Json(serde_json::json!({"status": "suppressed", "duplicate": true})),
+        )),
+        SendOutcome::Queued { id, duplicate } => Ok((
+            StatusCode::ACCEPTED,
+            Json(serde_json::json!({"id": id, "status": "queued", "duplicate": duplicate})),
+        )),
+        SendOutcome::Sent { id, duplicate } => Ok((
+            StatusCode::OK,
+            Json(serde_json::json!({"id": id, "status": "sent", "duplicate": duplicate})),
+        )),
+    }
+}
+
+/// What became of a send request
+#[derive(Debug, PartialEq)]
+pub(crate) enum SendOutcome {
+    /// Every recipient got the same message recently and duplicates are suppressed
+    Suppressed,
+    /// Queued for the worker under outbox `id`
+    Queued { id: u64, duplicate: bool },
+    /// Accepted by the modem, for `sync` requests
+    Sent { id: u64, duplicate: bool },
+}
+
+/// Sends a message the way `/send-sms` does: renders it, applies the content
+/// filter, validates it and checks it against the duplicate window and the
+/// rate limits before queueing it, or with `sync` waiting for the modem.
+pub(crate) async fn send_sms(
+    state: &AppState,
+    payload: SendSmsRequest,
+    api_client: Option<ApiClient>,
+    sync: bool,
+) -> Result<SendOutcome, ApiError> {
+    let message = filter_content(state, payload.render(&state.templates.load())?)?;
+    let to = payload.to.into_vec();
+    validate_send(&to, &message)?;
 
     if state.log_sensitive {
         info!(
             "Received request to send SMS to {} (client: {:?}): {:?}",
-            payload.to, payload.client, payload.message
+            to.join(", "),
+            payload.client,
+            message
         );
     } else {
         info!(
@@ -568,79 +1427,62 @@ async fn send_sms_handler(
         );
     }
 
-    // Check rate limit
-    if let Err(e) = state
-        .rate_limiter
-        .check_and_increment(payload.client.as_deref())
-    {
-        error!("Rate limit exceeded: {}", e);
-        return Err((
-            StatusCode::TOO_MANY_REQUESTS,
-            format!("Rate limit exceeded: {}", e),
-        ));
-    }
-
-    let (session_id, token) = match modem::get_session_info(&state.modem_url).await {
-        Ok((s, t)) => (s, t),
-        Err(e) => {
-            error!("Error getting session info: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to get session info: {}", e),
-            ));
+    let mut duplicate = false;
+    let mut recipients = Vec::with_capacity(to.len());
+    for phone in to {
+        match check_duplicate(state, &phone, &message)? {
+            Some(DuplicateAction::Suppress) => duplicate = true,
+            action => {
+                duplicate |= action.is_some();
+                recipients.push(phone);
+            }
         }
+    }
+    if recipients.is_empty() {
+        return Ok(SendOutcome::Suppressed);
+    }
+    let to = recipients;
+    check_rate_limit(state, payload.client.as_deref(), api_client.as_ref(), &to)?;
+    let caller = Caller {
+        client: payload.client,
+        api_key: api_client.map(|ApiClient(name)| name),
     };
 
-    match modem::send_sms(
-        &state.modem_url,
-        &session_id,
-        &token,
-        &payload.to,
-        &payload.message,
-        false,
-    )
-    .await
-    {
-        Ok(_) => {
-            if state.log_sensitive {
-                info!(
-                    "SMS sent successfully to {} (client: {})",
-                    payload.to,
-                    payload.client.as_deref().unwrap_or("none")
-                );
-            } else {
-                info!(
-                    "SMS sent successfully (client: {})",
-                    payload.client.as_deref().unwrap_or("none")
-                );
+    if !sync {
+        return match state
+            .send_queue
+            .enqueue(to, message, caller, Lane::Normal, payload.flash)
+        {
+            Ok(id) => Ok(SendOutcome::Queued { id, duplicate }),
+            Err(e) => {
+                error!("Error queueing SMS: {}", e);
+                Err(ApiError::new(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    ErrorKind::Internal,
+                    format!("Failed to queue SMS: {}", e),
+                ))
             }
-            counter!("smser_sms_sent_total").increment(1);
-            let country_code = extract_country_code(&payload.to);
-            counter!("smser_sms_country_total", "country_code" => country_code).increment(1);
-            Ok(Json(
-                serde_json::json!({"status": "success", "message": "SMS sent successfully!"}),
-            ))
-        }
-        Err(e) => {
-            error!("Error sending SMS: {}", e);
-            let status = match e {
-                ModemError::ModemError {
-                    code: _,
-                    message: _,
-                } => StatusCode::BAD_REQUEST, // Or map specific modem codes
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
-            };
-            Err((status, format!("Failed to send SMS: {}", e)))
-        }
+        };
+    }
+
+    match state
+        .send_queue
+        .send_and_wait(to, message, caller, Lane::Normal, payload.flash)
+        .await
+    {
+        Ok(id) => Ok(SendOutcome::Sent { id, duplicate }),
+        Err(e) => Err(ApiError::modem("Failed to send SMS", &e)),
     }
 }
 
 #[cfg(feature = "alertmanager")]
 async fn alertmanager_handler(
     State(state): State<AppState>,
-    Json(payload): Json<AlertManagerWebhook>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    payload: Result<Json<AlertManagerWebhook>, JsonRejection>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
     counter!("smser_http_requests_total", "endpoint" => "/alertmanager").increment(1);
+    let Json(payload) = payload.inspect_err(|_| count_alert_failure("invalid_payload"))?;
+    count_alert_received(&payload);
 
     info!(
         "Received alert from Alert Manager: status={}",
@@ -648,63 +1490,348 @@ async fn alertmanager_handler(
     );
 
     let to = match &state.alert_phone_number {
-        Some(phone) => phone,
+        Some(phone) => phone.clone(),
         None => {
             error!("Alert Manager webhook received but no alert_phone_number configured");
-            return Err((
+            count_alert_failure("routing");
+            return Err(ApiError::new(
                 StatusCode::BAD_REQUEST,
-                "Alert phone number not configured".to_string(),
+                ErrorKind::NotConfigured,
+                "Alert phone number not configured",
             ));
         }
     };
 
-    let message = alertmanager::format_alert_message(&payload);
+    send_alert(&state, &payload, &[to], None).await
+}
+
+#[cfg(feature = "alertmanager")]
+async fn alertmanager_receiver_handler(
+    State(state): State<AppState>,
+    Path(receiver): Path<String>,
+    payload: Result<Json<AlertManagerWebhook>, JsonRejection>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/alertmanager/{receiver}").increment(1);
+    let Json(payload) = payload.inspect_err(|_| count_alert_failure("invalid_payload"))?;
+    count_alert_received(&payload);
 
-    // Check rate limit (use "alertmanager" as client name for per-client limits)
-    if let Err(e) = state.rate_limiter.check_and_increment(Some("alertmanager")) {
-        error!("Rate limit exceeded for alert SMS: {}", e);
-        return Err((
-            StatusCode::TOO_MANY_REQUESTS,
-            format!("Rate limit exceeded: {}", e),
+    info!(
+        "Received alert from Alert Manager for receiver {}: status={}",
+        receiver, payload.status
+    );
+
+    let receivers = state.alert_receivers.load();
+    let Some(config) = receivers.get(&receiver) else {
+        error!(
+            "Alert Manager webhook received for unknown receiver {}",
+            receiver
+        );
+        count_alert_failure("routing");
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorKind::NotConfigured,
+            format!("Receiver '{}' not configured", receiver),
         ));
+    };
+
+    let mut recipients = config.to.clone();
+    if let Some(schedule) = &config.oncall {
+        match state.oncall.load().current(schedule).await {
+            Ok(phone) => recipients.push(phone),
+            Err(e) => {
+                error!("Error resolving on-call for receiver {}: {}", receiver, e);
+                // Still alert the fixed recipients, if any
+                count_alert_failure("routing");
+                if recipients.is_empty() {
+                    return Err(ApiError::new(
+                        StatusCode::SERVICE_UNAVAILABLE,
+                        ErrorKind::NotConfigured,
+                        e,
+                    ));
+                }
+            }
+        }
     }
 
-    let (session_id, token) = match modem::get_session_info(&state.modem_url).await {
-        Ok((s, t)) => (s, t),
-        Err(e) => {
-            error!("Error getting session info for alert SMS: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to get session info: {}", e),
+    send_alert(&state, &payload, &recipients, config.template.as_deref()).await
+}
+
+#[cfg(feature = "alertmanager")]
+fn count_alert_received(payload: &AlertManagerWebhook) {
+    let severity = payload
+        .common_labels
+        .get("severity")
+        .map_or("none", String::as_str);
+    counter!(
+        "smser_alerts_received_total",
+        "source" => "alertmanager",
+        "status" => payload.status.clone(),
+        "severity" => severity.to_string()
+    )
+    .increment(1);
+}
+
+/// Counts alerts that could not be delivered, by the step that failed
+#[cfg(feature = "alertmanager")]
+fn count_alert_failure(reason: &'static str) {
+    counter!("smser_alerts_failed_total", "reason" => reason).increment(1);
+}
+
+/// Formats the alert and sends it to every number in `recipients`.
+#[cfg(feature = "alertmanager")]
+async fn send_alert(
+    state: &AppState,
+    payload: &AlertManagerWebhook,
+    recipients: &[String],
+    template: Option<&str>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    let policies = state.alert_policies.load();
+    let policy = policies.for_alert(&payload.common_labels, chrono::Local::now());
+    let recipients = policy.to.as_deref().unwrap_or(recipients);
+    let template = policy.template.as_deref().or(template);
+    let message = alertmanager::render_alert_message(payload, template);
+
+    if let Some(silence) = state
+        .silences
+        .find_active(&payload.common_labels, chrono::Utc::now())
+    {
+        if state.log_sensitive {
+            info!("Alert silenced by silence {}: {:?}", silence.id, message);
+        } else {
+            info!("Alert silenced by silence {}", silence.id);
+        }
+        counter!("smser_alerts_silenced_total").increment(1);
+        return Ok((
+            StatusCode::OK,
+            Json(serde_json::json!({"status": "silenced", "silence_id": silence.id})),
+        ));
+    }
+
+    let suppression_key = alertmanager::suppression_key(payload);
+    if let Some(ttl) = policy.suppress_for {
+        let now = chrono::Utc::now();
+        if policies.is_suppressed(&suppression_key, ttl, now) {
+            info!("Alert suppressed, already sent within {}", ttl);
+            counter!("smser_alerts_suppressed_total").increment(1);
+            return Ok((
+                StatusCode::OK,
+                Json(serde_json::json!({"status": "suppressed"})),
             ));
         }
-    };
+    }
+    // Silenced and suppressed alerts are not sent, so the content filter
+    // neither blocks nor counts them
+    let message = filter_content(state, message).inspect_err(|_| count_alert_failure("blocked"))?;
 
-    match modem::send_sms(&state.modem_url, &session_id, &token, to, &message, false).await {
-        Ok(_) => {
-            if state.log_sensitive {
-                info!("Alert SMS sent successfully to {}: {:?}", to, message);
-            } else {
-                info!("Alert SMS sent successfully");
+    if let Some(delay) = policy.defer {
+        let send_at = chrono::Local::now() + delay;
+        info!(
+            "Alert deferred by quiet hours until {}",
+            send_at.format("%H:%M")
+        );
+        counter!("smser_alerts_deferred_total").increment(1);
+        // Deferred messages wait in the outbox, so they survive a restart
+        let mut queued = 0;
+        let mut failed = Vec::new();
+        let mut last_error = None;
+        for to in recipients {
+            if !matches!(
+                check_duplicate(state, to, &message),
+                Ok(None | Some(DuplicateAction::Flag))
+            ) {
+                count_alert_failure("duplicate");
+                continue;
+            }
+            match defer_alert_to(state, &policy, to, &message, send_at) {
+                Ok(()) => queued += 1,
+                Err(e) => {
+                    failed.push(serde_json::json!({"to": to, "error": e.message()}));
+                    last_error = Some(e);
+                }
+            }
+        }
+        if queued == 0
+            && let Some(e) = last_error
+        {
+            return Err(e);
+        }
+        // Only once the recipients are queued, so a failed deferral is not suppressed
+        if policy.suppress_for.is_some() {
+            policies.mark_sent(&suppression_key, chrono::Utc::now());
+        }
+        if failed.is_empty() {
+            return Ok((
+                StatusCode::ACCEPTED,
+                Json(serde_json::json!({"status": "deferred", "send_at": send_at.to_rfc3339()})),
+            ));
+        }
+        return Ok((
+            StatusCode::MULTI_STATUS,
+            Json(serde_json::json!({
+                "status": "partial",
+                "send_at": send_at.to_rfc3339(),
+                "failed": failed,
+            })),
+        ));
+    }
+
+    // A failing recipient does not keep the alert from the others
+    let mut sent = 0;
+    let mut failed = Vec::new();
+    let mut last_error = None;
+    for to in recipients {
+        // A rejected or suppressed duplicate only skips this recipient, so
+        // Alertmanager does not retry
+        if !matches!(
+            check_duplicate(state, to, &message),
+            Ok(None | Some(DuplicateAction::Flag))
+        ) {
+            count_alert_failure("duplicate");
+            continue;
+        }
+        match send_alert_to(state, &policy, to, &message).await {
+            Ok(()) => sent += 1,
+            Err(e) => {
+                failed.push(serde_json::json!({"to": to, "error": e.message()}));
+                last_error = Some(e);
             }
-            counter!("smser_sms_sent_total").increment(1);
-            let country_code = extract_country_code(to);
-            counter!("smser_sms_country_total", "country_code" => country_code).increment(1);
-            Ok(Json(
+        }
+    }
+    if sent == 0
+        && let Some(e) = last_error
+    {
+        return Err(e);
+    }
+    if policy.suppress_for.is_some() {
+        policies.mark_sent(&suppression_key, chrono::Utc::now());
+    }
+
+    if failed.is_empty() {
+        Ok((
+            StatusCode::OK,
+            Json(
                 serde_json::json!({"status": "success", "message": "Alert SMS sent successfully!"}),
+            ),
+        ))
+    } else {
+        Ok((
+            StatusCode::MULTI_STATUS,
+            Json(serde_json::json!({
+                "status": "partial",
+                "message": format!("Alert SMS sent to {} of {} recipients", sent, sent + failed.len()),
+                "failed": failed,
+            })),
+        ))
+    }
+}
+
+/// Queues the alert for a single recipient until `send_at`, counting why it failed
+#[cfg(feature = "alertmanager")]
+fn defer_alert_to(
+    state: &AppState,
+    policy: &alertmanager::AlertPolicy,
+    to: &str,
+    message: &str,
+    send_at: chrono::DateTime<chrono::Local>,
+) -> Result<(), ApiError> {
+    check_rate_limit(state, Some("alertmanager"), None, &[to.to_string()])
+        .inspect_err(|_| count_alert_failure("rate_limited"))?;
+    state
+        .send_queue
+        .enqueue_at(
+            vec![to.to_string()],
+            message.to_string(),
+            Caller::client(Some("alertmanager".to_string())),
+            policy.lane,
+            policy.flash,
+            send_at.timestamp().max(0) as u64,
+        )
+        .map_err(|e| {
+            error!("Error queueing deferred alert SMS: {}", e);
+            count_alert_failure("modem");
+            ApiError::modem("Failed to queue alert SMS", &e)
+        })?;
+    Ok(())
+}
+
+/// Sends the alert to a single recipient, counting why it failed
+#[cfg(feature = "alertmanager")]
+async fn send_alert_to(
+    state: &AppState,
+    policy: &alertmanager::AlertPolicy,
+    to: &str,
+    message: &str,
+) -> Result<(), ApiError> {
+    // Use "alertmanager" as client name for per-client limits
+    check_rate_limit(state, Some("alertmanager"), None, &[to.to_string()])
+        .inspect_err(|_| count_alert_failure("rate_limited"))?;
+
+    let result = state
+        .send_queue
+        .send_and_wait(
+            vec![to.to_string()],
+            message.to_string(),
+            Caller::client(Some("alertmanager".to_string())),
+            policy.lane,
+            policy.flash,
+        )
+        .await;
+    if let Err(e) = result {
+        error!("Error sending alert SMS: {}", e);
+        count_alert_failure("modem");
+        return Err(ApiError::modem("Failed to send alert SMS", &e));
+    }
+    if state.log_sensitive {
+        info!(
+            "Alert SMS sent successfully to {} (priority {}): {:?}",
+            to, policy.priority, message
+        );
+    } else {
+        info!("Alert SMS sent successfully (priority {})", policy.priority);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "alertmanager")]
+async fn list_silences_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    counter!("smser_http_requests_total", "endpoint" => "/silences").increment(1);
+    Json(serde_json::json!({ "silences": state.silences.list() }))
+}
+
+#[cfg(feature = "alertmanager")]
+async fn create_silence_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<SilenceSpec>, JsonRejection>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/silences").increment(1);
+    let Json(spec) = payload?;
+    match state.silences.add(spec) {
+        Ok(silence) => {
+            info!("Created silence {}", silence.id);
+            Ok((
+                StatusCode::CREATED,
+                Json(serde_json::json!({ "silence": silence })),
             ))
         }
-        Err(e) => {
-            error!("Error sending alert SMS: {}", e);
-            let status = match e {
-                ModemError::ModemError {
-                    code: _,
-                    message: _,
-                } => StatusCode::BAD_REQUEST,
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
-            };
-            Err((status, format!("Failed to send alert SMS: {}", e)))
-        }
+        Err(e) => Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorKind::BadRequest,
+            e,
+        )),
+    }
+}
+
+#[cfg(feature = "alertmanager")]
+async fn delete_silence_handler(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<StatusCode, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/silences/{id}").increment(1);
+    if state.silences.remove(id) {
+        info!("Deleted silence {}", id);
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::not_found())
     }
 }
 
@@ -720,11 +1847,16 @@ pub struct GetSmsRequest {
     box_type: BoxType,
     #[serde(default = "default_sort_type")]
     sort_by: SortType,
+    #[serde(default = "default_page")]
+    page: i32,
 }
 
 fn default_count() -> u32 {
     20
 }
+fn default_page() -> i32 {
+    1
+}
 fn default_box_type() -> BoxType {
     BoxType::LocalInbox
 }
@@ -734,20 +1866,17 @@ fn default_sort_type() -> SortType {
 
 async fn get_sms_handler(
     State(state): State<AppState>,
-    Query(params): Query<GetSmsRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    params: Result<Query<GetSmsRequest>, QueryRejection>,
+) -> Result<Json<SmsListJson>, ApiError> {
     counter!("smser_http_requests_total", "endpoint" => "/get-sms").increment(1);
-
-    let (session_id, token) = match modem::get_session_info(&state.modem_url).await {
-        Ok((s, t)) => (s, t),
-        Err(e) => {
-            error!("Error getting session info: {}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to get session info: {}", e),
-            ));
-        }
-    };
+    let Query(params) = params?;
+    if params.page < 1 {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorKind::BadRequest,
+            "page starts at 1",
+        ));
+    }
 
     let sms_params = modem::SmsListParams {
         box_type: params.box_type,
@@ -756,170 +1885,2676 @@ async fn get_sms_handler(
         ascending: params.ascending,
         unread_preferred: params.unread_preferred,
     };
+    Ok(Json(SmsListJson {
+        status: "success".to_string(),
+        messages: list_sms(&state, sms_params, params.page).await?,
+    }))
+}
+
+/// Reads a page of messages from the modem, recording the outcome in the modem health
+pub(crate) async fn list_sms(
+    state: &AppState,
+    params: modem::SmsListParams,
+    page: i32,
+) -> Result<Vec<modem::SmsMessage>, ApiError> {
+    let session = match state.modem.get_session().await {
+        Ok(session) => session,
+        Err(e) => {
+            state.modem_health.record_error(&e);
+            error!("Error getting session info: {}", e);
+            return Err(ApiError::modem("Failed to get session info", &e));
+        }
+    };
 
-    match modem::get_sms_list(&state.modem_url, &session_id, &token, sms_params).await {
+    let result = state.modem.get_sms_list(&session, params, page).await;
+    state.modem_health.record(&result);
+    match result {
         Ok(response) => {
             gauge!("smser_sms_stored").set(response.count as f64);
-            Ok(Json(
-                serde_json::json!({"status": "success", "messages": response.messages.message}),
-            ))
+            Ok(response.messages.message)
         }
         Err(e) => {
             error!("Error receiving SMS: {}", e);
-            let status = match e {
-                ModemError::ModemError {
-                    code: _,
-                    message: _,
-                } => StatusCode::BAD_REQUEST,
-                _ => StatusCode::INTERNAL_SERVER_ERROR,
-            };
-            Err((status, format!("Failed to get SMS list: {}", e)))
+            Err(ApiError::modem("Failed to get SMS list", &e))
         }
     }
 }
 
-/// Poll the modem for SMS messages and log them.
-/// Returns (total count, last seen message index, new messages logged).
-async fn poll_sms(
-    modem_url: &str,
-    log_sensitive: bool,
-    last_seen_index: Option<i32>,
-) -> Result<(i32, Option<i32>, usize), ModemError> {
-    let (session_id, token) = modem::get_session_info(modem_url).await?;
-
-    let params = modem::SmsListParams {
-        box_type: BoxType::LocalInbox,
-        sort_type: SortType::Date,
-        read_count: 20,
-        ascending: false,
-        unread_preferred: true,
+async fn device_handler(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/device").increment(1);
+
+    let session = match state.modem.get_session().await {
+        Ok(session) => session,
+        Err(e) => {
+            state.modem_health.record_error(&e);
+            error!("Error getting session info: {}", e);
+            return Err(ApiError::modem("Failed to get session info", &e));
+        }
     };
 
-    let response = modem::get_sms_list(modem_url, &session_id, &token, params).await?;
+    let result = state.modem.get_device_info(&session).await;
+    state.modem_health.record(&result);
+    match result {
+        Ok(device) => Ok(Json(
+            serde_json::json!({"status": "success", "device": device}),
+        )),
+        Err(e) => {
+            error!("Error getting device information: {}", e);
+            Err(ApiError::modem("Failed to get device information", &e))
+        }
+    }
+}
 
-    // Update the stored SMS gauge
-    gauge!("smser_sms_stored").set(response.count as f64);
+async fn network_handler(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/network").increment(1);
 
-    let base_last_seen = last_seen_index;
-    let mut new_last_seen = last_seen_index;
-    let mut logged_count = 0usize;
+    let session = match state.modem.get_session().await {
+        Ok(session) => session,
+        Err(e) => {
+            state.modem_health.record_error(&e);
+            error!("Error getting session info: {}", e);
+            return Err(ApiError::modem("Failed to get session info", &e));
+        }
+    };
 
-    // Log unread messages, de-duped by message index
-    for msg in &response.messages.message {
-        if msg.smstat == modem::SmsStat::Unread {
-            let is_new = match base_last_seen {
-                Some(last) => msg.index > last,
-                None => true,
-            };
-            if is_new {
-                logged_count += 1;
-                if log_sensitive {
-                    info!(
-                        "New SMS from {}: {}",
-                        msg.phone,
-                        msg.content.chars().take(50).collect::<String>()
-                    );
-                } else {
-                    info!("New unread SMS received");
-                }
-            }
-            if new_last_seen.is_none_or(|last| msg.index > last) {
-                new_last_seen = Some(msg.index);
-            }
+    let result = state.modem.get_network_info(&session).await;
+    state.modem_health.record(&result);
+    match result {
+        Ok(network) => Ok(Json(
+            serde_json::json!({"status": "success", "network": network}),
+        )),
+        Err(e) => {
+            error!("Error getting network information: {}", e);
+            Err(ApiError::modem("Failed to get network information", &e))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PurgeTrashQuery {
+    #[serde(default = "default_purge_box_type")]
+    box_type: BoxType,
+}
+
+fn default_purge_box_type() -> BoxType {
+    BoxType::LocalTrash
+}
+
+async fn purge_trash_handler(
+    State(state): State<AppState>,
+    query: Result<Query<PurgeTrashQuery>, QueryRejection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/purge-trash").increment(1);
+    let Query(query) = query?;
+
+    let result = client::purge_box(state.modem.as_ref(), query.box_type.clone()).await;
+    state.modem_health.record(&result);
+    match result {
+        Ok(deleted) => {
+            info!("Purged {} messages from {}", deleted, query.box_type);
+            Ok(Json(serde_json::json!({
+                "status": "success",
+                "box_type": query.box_type,
+                "deleted": deleted,
+            })))
+        }
+        Err(e) => {
+            error!("Error purging {}: {}", query.box_type, e);
+            Err(ApiError::modem("Failed to purge messages", &e))
         }
     }
+}
+
+/// Message indexes in a request body, e.g. `{"indexes": [40001, 40002]}`
+#[derive(Debug, Deserialize)]
+pub struct IndexesRequest {
+    indexes: Vec<i32>,
+}
+
+impl IndexesRequest {
+    fn from_payload(payload: Result<Json<Self>, JsonRejection>) -> Result<Vec<i32>, ApiError> {
+        let Json(payload) = payload?;
+        if payload.indexes.is_empty() {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorKind::BadRequest,
+                "indexes must not be empty",
+            ));
+        }
+        Ok(payload.indexes)
+    }
+}
+
+/// Deletes the messages listed in the body
+async fn delete_sms_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<IndexesRequest>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/sms").increment(1);
+    let indexes = IndexesRequest::from_payload(payload)?;
+    delete_messages(&state, &indexes).await
+}
+
+async fn delete_one_sms_handler(
+    State(state): State<AppState>,
+    Path(index): Path<i32>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/sms/{index}").increment(1);
+    delete_messages(&state, &[index]).await
+}
+
+async fn delete_messages(
+    state: &AppState,
+    indexes: &[i32],
+) -> Result<Json<serde_json::Value>, ApiError> {
+    let session = match state.modem.get_session().await {
+        Ok(session) => session,
+        Err(e) => {
+            state.modem_health.record_error(&e);
+            error!("Error getting session info: {}", e);
+            return Err(ApiError::modem("Failed to get session info", &e));
+        }
+    };
+    let result = state.modem.delete_sms(&session, indexes).await;
+    state.modem_health.record(&result);
+    match result {
+        Ok(()) => {
+            info!("Deleted messages {:?}", indexes);
+            Ok(Json(serde_json::json!({
+                "status": "success",
+                "deleted": indexes,
+            })))
+        }
+        Err(e) => {
+            error!("Error deleting messages {:?}: {}", indexes, e);
+            Err(ApiError::modem("Failed to delete messages", &e))
+        }
+    }
+}
+
+/// Marks the messages listed in the body as read, so consumers of `/get-sms`
+/// can tell processed messages from new ones
+async fn mark_read_handler(
+    State(state): State<AppState>,
+    payload: Result<Json<IndexesRequest>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/mark-read").increment(1);
+    let indexes = IndexesRequest::from_payload(payload)?;
+    let session = match state.modem.get_session().await {
+        Ok(session) => session,
+        Err(e) => {
+            state.modem_health.record_error(&e);
+            error!("Error getting session info: {}", e);
+            return Err(ApiError::modem("Failed to get session info", &e));
+        }
+    };
+    let result = state.modem.mark_read(&session, &indexes).await;
+    state.modem_health.record(&result);
+    match result {
+        Ok(()) => Ok(Json(serde_json::json!({
+            "status": "success",
+            "marked_read": indexes,
+        }))),
+        Err(e) => {
+            error!("Error marking messages {:?} as read: {}", indexes, e);
+            Err(ApiError::modem("Failed to mark messages as read", &e))
+        }
+    }
+}
+
+/// Maximum number of messages returned by `/history`
+const HISTORY_MAX_LIMIT: u32 = 1000;
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    direction: Option<Direction>,
+    status: Option<SendStatus>,
+    #[serde(default = "default_history_limit")]
+    limit: u32,
+}
+
+fn default_history_limit() -> u32 {
+    100
+}
+
+async fn history_handler(
+    State(state): State<AppState>,
+    query: Result<Query<HistoryQuery>, QueryRejection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/history").increment(1);
+    let Query(query) = query?;
+    let Some(store) = &state.store else {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorKind::NotConfigured,
+            "Message store not configured (see --db-path)",
+        ));
+    };
+
+    let filter = HistoryFilter {
+        direction: query.direction,
+        status: query.status,
+        limit: query.limit.min(HISTORY_MAX_LIMIT),
+    };
+    match store.history(&filter) {
+        Ok(messages) => Ok(Json(serde_json::json!({ "messages": messages }))),
+        Err(e) => {
+            error!("Error reading message history: {}", e);
+            Err(ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ErrorKind::Internal,
+                format!("Failed to read history: {}", e),
+            ))
+        }
+    }
+}
+
+async fn outbox_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    counter!("smser_http_requests_total", "endpoint" => "/outbox").increment(1);
+    Json(serde_json::json!({ "messages": state.send_queue.outbox() }))
+}
+
+fn outbox_error(id: u64, error: OutboxError) -> ApiError {
+    match error {
+        OutboxError::NotFound => ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorKind::NotFound,
+            format!("No queued or failed message {}", id),
+        ),
+        OutboxError::Sending => ApiError::new(
+            StatusCode::CONFLICT,
+            ErrorKind::BadRequest,
+            format!("Message {} is being sent", id),
+        ),
+        OutboxError::NotFailed => ApiError::new(
+            StatusCode::CONFLICT,
+            ErrorKind::BadRequest,
+            format!("Message {} has not failed, it is still queued", id),
+        ),
+        OutboxError::Closed => ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            ErrorKind::Internal,
+            "Send queue is closed",
+        ),
+    }
+}
+
+async fn outbox_retry_handler(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/outbox/retry").increment(1);
+    state
+        .send_queue
+        .retry(id)
+        .map_err(|e| outbox_error(id, e))?;
+    Ok(Json(serde_json::json!({"id": id, "status": "queued"})))
+}
+
+async fn outbox_cancel_handler(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/outbox/cancel").increment(1);
+    state
+        .send_queue
+        .cancel(id)
+        .map_err(|e| outbox_error(id, e))?;
+    Ok(Json(serde_json::json!({"id": id, "status": "cancelled"})))
+}
+
+/// Checks the `Authorization: Bearer` header, or `X-Admin-Token` where the
+/// former carries basic auth, against the configured admin token
+fn check_admin_token(state: &AppState, headers: &HeaderMap) -> Result<(), ApiError> {
+    let Some(expected) = &state.admin_token else {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorKind::NotConfigured,
+            "Admin API disabled (see --admin-token)",
+        ));
+    };
+    let token = headers
+        .get("x-admin-token")
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        })
+        .unwrap_or_default();
+    if !constant_time_eq(token.as_bytes(), expected.as_bytes()) {
+        return Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            ErrorKind::Unauthorized,
+            "Missing or invalid admin token",
+        ));
+    }
+    Ok(())
+}
+
+/// Credentials for HTTP Basic auth on the whole server
+#[derive(Clone, Debug)]
+pub struct BasicAuth {
+    pub username: String,
+    pub password: String,
+}
+
+impl BasicAuth {
+    /// Whether an `Authorization` header value carries these credentials
+    fn accepts(&self, authorization: &str) -> bool {
+        use base64::Engine;
+
+        let Some(credentials) = authorization
+            .strip_prefix("Basic ")
+            .and_then(|encoded| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(encoded.trim())
+                    .ok()
+            })
+            .and_then(|decoded| String::from_utf8(decoded).ok())
+        else {
+            return false;
+        };
+        let (username, password) = credentials.split_once(':').unwrap_or((&credentials, ""));
+        // Compare both so the response time doesn't tell which one is wrong
+        constant_time_eq(username.as_bytes(), self.username.as_bytes())
+            & constant_time_eq(password.as_bytes(), self.password.as_bytes())
+    }
+}
+
+/// Routes open without basic auth
+const HEALTH_PATHS: [&str; 2] = ["/healthz", "/readyz"];
+
+/// Requires the `--basic-auth-user` credentials on every route but the
+/// [`HEALTH_PATHS`], if set
+async fn require_basic_auth(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(auth) = &state.basic_auth else {
+        return next.run(request).await;
+    };
+    // Probes by orchestrators and load balancers rarely carry credentials
+    if HEALTH_PATHS.contains(&request.uri().path()) {
+        return next.run(request).await;
+    }
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| auth.accepts(v));
+    if authorized {
+        return next.run(request).await;
+    }
+    counter!("smser_unauthorized_requests_total").increment(1);
+    let mut response = ApiError::new(
+        StatusCode::UNAUTHORIZED,
+        ErrorKind::Unauthorized,
+        "Missing or invalid username or password",
+    )
+    .into_response();
+    // Makes browsers ask for the credentials
+    response.headers_mut().insert(
+        header::WWW_AUTHENTICATE,
+        header::HeaderValue::from_static("Basic realm=\"smser\""),
+    );
+    response
+}
+
+/// Name of the client whose API key authenticated the request
+#[derive(Clone, Debug)]
+pub struct ApiClient(pub String);
+
+/// Requires a configured API key in `X-Api-Key` or `Authorization: Bearer`,
+/// unless no keys are configured. The key's client name is added to the
+/// request as [`ApiClient`].
+async fn require_api_key(
+    State(state): State<AppState>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let headers = request.headers();
+    let key = headers
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.strip_prefix("Bearer "))
+        })
+        .unwrap_or_default();
+    if let Some(client) = authorize_api_key(&state, key)? {
+        request.extensions_mut().insert(client);
+    }
+    Ok(next.run(request).await)
+}
+
+/// Looks up the client of an API key, rejecting a missing or unknown one.
+/// Without configured keys every caller is let in as `None`.
+pub(crate) fn authorize_api_key(
+    state: &AppState,
+    key: &str,
+) -> Result<Option<ApiClient>, ApiError> {
+    if state.api_keys.is_empty() {
+        return Ok(None);
+    }
+    // Check every key so the response time doesn't tell which one is close
+    let client = state
+        .api_keys
+        .iter()
+        .filter(|(_, expected)| constant_time_eq(key.as_bytes(), expected.as_bytes()))
+        .map(|(name, _)| name.clone())
+        .last();
+    match client {
+        Some(client) if !key.is_empty() => Ok(Some(ApiClient(client))),
+        _ => {
+            counter!("smser_unauthorized_requests_total").increment(1);
+            Err(ApiError::new(
+                StatusCode::UNAUTHORIZED,
+                ErrorKind::Unauthorized,
+                "Missing or invalid API key (send X-Api-Key or Authorization: Bearer)",
+            ))
+        }
+    }
+}
+
+/// Compares without returning early so response times don't leak the token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn limits_json(rate_limiter: &RateLimiter) -> serde_json::Value {
+    serde_json::json!({
+        "global": rate_limiter.get_status(),
+        "clients": rate_limiter.get_client_status(),
+        "api_keys": rate_limiter.get_key_status(),
+    })
+}
+
+async fn quota_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    counter!("smser_http_requests_total", "endpoint" => "/quota").increment(1);
+    Json(limits_json(&state.rate_limiter))
+}
+
+async fn get_limits_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/admin/limits").increment(1);
+    check_admin_token(&state, &headers)?;
+    Ok(Json(limits_json(&state.rate_limiter)))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClientLimitUpdate {
+    hourly_limit: u32,
+    daily_limit: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LimitsUpdate {
+    hourly_limit: Option<u32>,
+    daily_limit: Option<u32>,
+    /// Per-client limits to add or replace
+    #[serde(default)]
+    clients: HashMap<String, ClientLimitUpdate>,
+    /// Reset all usage counters
+    #[serde(default)]
+    reset: bool,
+}
+
+async fn update_limits_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Result<Json<LimitsUpdate>, JsonRejection>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/admin/limits").increment(1);
+    check_admin_token(&state, &headers)?;
+    let Json(update) = payload?;
+
+    let client_limits: Vec<ClientLimit> = update
+        .clients
+        .into_iter()
+        .map(|(name, limits)| ClientLimit {
+            name,
+            hourly_limit: limits.hourly_limit,
+            daily_limit: limits.daily_limit,
+        })
+        .collect();
+    state
+        .rate_limiter
+        .set_limits(update.hourly_limit, update.daily_limit, &client_limits);
+    if update.reset {
+        state.rate_limiter.reset();
+    }
+    info!(
+        "Rate limits updated via admin API (reset: {}): {:?}",
+        update.reset,
+        state.rate_limiter.get_status()
+    );
+    Ok(Json(limits_json(&state.rate_limiter)))
+}
+
+/// Re-reads the config file and applies its routes, templates, content
+/// filter, limits and alert settings. Global limits changed via
+/// `/admin/limits` are kept. On error the current settings are kept.
+fn reload_config(state: &AppState) -> Result<(), String> {
+    let Some(reloader) = &state.reloader else {
+        return Err("Reloading is not available".to_string());
+    };
+    let config = match reloader() {
+        Ok(config) => config,
+        Err(e) => {
+            error!(
+                "Failed to reload the config file, keeping the current settings: {}",
+                e
+            );
+            counter!("smser_config_reloads_total", "result" => "error").increment(1);
+            return Err(e);
+        }
+    };
+    state.send_queue.set_routes(config.routes);
+    state.templates.store(config.templates);
+    state.content_filter.store(config.content_filter);
+    if state
+        .rate_limiter
+        .set_config_limits(config.hourly_limit, config.daily_limit)
+    {
+        info!("Keeping the global limits set via /admin/limits");
+    }
+    state.rate_limiter.set_key_limits(config.key_limits);
+    state
+        .rate_limiter
+        .set_recipient_limit(config.recipient_limit);
+    #[cfg(feature = "alertmanager")]
+    {
+        state.alert_receivers.store(config.alert_receivers);
+        state.oncall.store(config.oncall);
+        config
+            .alert_policies
+            .keep_suppressions(&state.alert_policies.load());
+        state.alert_policies.store(config.alert_policies);
+    }
+    info!("Config file reloaded");
+    counter!("smser_config_reloads_total", "result" => "success").increment(1);
+    Ok(())
+}
+
+async fn reload_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    counter!("smser_http_requests_total", "endpoint" => "/admin/reload").increment(1);
+    check_admin_token(&state, &headers)?;
+    if state.reloader.is_none() {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorKind::NotConfigured,
+            "Config reload not available",
+        ));
+    }
+    reload_config(&state).map_err(|e| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorKind::Internal,
+            format!("Failed to reload config: {}", e),
+        )
+    })?;
+    Ok(Json(serde_json::json!({"status": "reloaded"})))
+}
+
+/// Sets the `smser_inbox_unread`, `smser_inbox_total` and `smser_storage_full`
+/// gauges from the modem's SMS count
+async fn update_inbox_gauges(modem: &dyn ModemClient) -> Result<modem::SmsCount, ModemError> {
+    let session = modem.get_session().await?;
+    let count = modem.get_sms_count(&session).await?;
+    gauge!("smser_inbox_unread").set(count.local_unread as f64);
+    gauge!("smser_inbox_total").set(count.local_inbox as f64);
+    gauge!("smser_storage_full").set(if count.storage_full() { 1.0 } else { 0.0 });
+    Ok(count)
+}
+
+/// Logs a full modem storage and, with `auto_purge`, deletes that many of the
+/// oldest read inbox messages to make room
+async fn handle_storage_full(
+    modem: &dyn ModemClient,
+    count: &modem::SmsCount,
+    auto_purge: Option<usize>,
+) {
+    error!(
+        "Modem SMS storage is full ({} of {} messages), new messages cannot be received",
+        count.local_used(),
+        count.local_max
+    );
+    let Some(limit) = auto_purge else { return };
+    match client::purge_oldest_read(modem, BoxType::LocalInbox, limit).await {
+        Ok(0) => error!("Auto-purge found no read messages to delete"),
+        Ok(deleted) => {
+            counter!("smser_auto_purged_total").increment(deleted as u64);
+            info!("Auto-purged the {} oldest read messages", deleted);
+        }
+        Err(e) => error!("Auto-purge failed: {}", e),
+    }
+}
+
+/// Sets the `smser_traffic_*` gauges from the modem's traffic statistics, both for
+/// the current connection (`period="current"`) and since they were last cleared
+/// (`period="total"`)
+async fn update_traffic_gauges(modem: &dyn ModemClient) -> Result<(), ModemError> {
+    let session = modem.get_session().await?;
+    let stats = modem.get_traffic_statistics(&session).await?;
+    let periods = [
+        (
+            "current",
+            stats.current_upload,
+            stats.current_download,
+            stats.current_connect_time,
+        ),
+        (
+            "total",
+            stats.total_upload,
+            stats.total_download,
+            stats.total_connect_time,
+        ),
+    ];
+    for (period, upload, download, connect_time) in periods {
+        gauge!("smser_traffic_upload_bytes", "period" => period).set(upload as f64);
+        gauge!("smser_traffic_download_bytes", "period" => period).set(download as f64);
+        gauge!("smser_traffic_connect_time_seconds", "period" => period).set(connect_time as f64);
+    }
+    Ok(())
+}
+
+/// Poll the transport for SMS messages, log them, archive them into the store,
+/// run `receive_cmd` for new ones and forward unread ones to the `webhook`.
+/// Returns (total count, last seen message index, new messages oldest first). With
+/// `skip_if_read`, `None` is returned without listing the inbox if nothing is
+/// unread.
+async fn poll_sms(
+    transport: &dyn Transport,
+    log_sensitive: bool,
+    last_seen_index: Option<i32>,
+    store: Option<&Store>,
+    skip_if_read: bool,
+    webhook: Option<&IncomingWebhook>,
+    receive_cmd: Option<&ReceiveCommand>,
+) -> Result<Option<(i32, Option<i32>, Vec<modem::SmsMessage>)>, ModemError> {
+    let Some(response) = transport.receive(20, skip_if_read).await? else {
+        return Ok(None);
+    };
+
+    // Update the stored SMS gauge
+    gauge!("smser_sms_stored").set(response.count as f64);
+
+    let base_last_seen = last_seen_index;
+    let mut new_last_seen = last_seen_index;
+    let mut new_messages = Vec::new();
+
+    // Log unread messages, de-duped by message index
+    for msg in &response.messages.message {
+        if msg.smstat == modem::SmsStat::Unread {
+            let is_new = match base_last_seen {
+                Some(last) => msg.index > last,
+                None => true,
+            };
+            if is_new {
+                new_messages.push(msg);
+                if log_sensitive {
+                    info!(
+                        "New SMS from {}: {}",
+                        msg.phone,
+                        msg.content.chars().take(50).collect::<String>()
+                    );
+                } else {
+                    info!("New unread SMS received");
+                }
+            }
+            if new_last_seen.is_none_or(|last| msg.index > last) {
+                new_last_seen = Some(msg.index);
+            }
+        }
+    }
+
+    if let Some(store) = store {
+        match store.archive_received(&response.messages.message) {
+            Ok(archived) if archived > 0 => info!("Archived {} new messages", archived),
+            Ok(_) => {}
+            Err(e) => error!("Failed to archive messages: {}", e),
+        }
+    }
+
+    new_messages.sort_by_key(|m| m.index);
+    if let Some(receive_cmd) = receive_cmd {
+        for message in &new_messages {
+            match receive_cmd.run(message).await {
+                Ok(()) => {
+                    counter!("smser_receive_cmd_total", "result" => "success").increment(1);
+                }
+                Err(e) => {
+                    counter!("smser_receive_cmd_total", "result" => "failure").increment(1);
+                    error!(
+                        "Receive command '{}' failed for message {}: {}",
+                        receive_cmd.command(),
+                        message.index,
+                        e
+                    );
+                }
+            }
+        }
+    }
+
+    if let Some(webhook) = webhook {
+        forward_unread(transport, webhook, &response.messages.message).await?;
+    }
+
+    let new_messages = new_messages.into_iter().cloned().collect();
+    Ok(Some((response.count, new_last_seen, new_messages)))
+}
+
+/// Forwards unread `messages` to `webhook`, oldest first, and marks the
+/// delivered ones read. Stops at the first failure so the rest are retried in
+/// order on the next poll.
+async fn forward_unread(
+    transport: &dyn Transport,
+    webhook: &IncomingWebhook,
+    messages: &[modem::SmsMessage],
+) -> Result<(), ModemError> {
+    let mut unread: Vec<&modem::SmsMessage> = messages
+        .iter()
+        .filter(|m| m.smstat == modem::SmsStat::Unread)
+        .collect();
+    unread.sort_by_key(|m| m.index);
+    let mut delivered = Vec::new();
+    for message in unread {
+        match webhook.forward(message).await {
+            Ok(()) => {
+                counter!("smser_incoming_webhook_total", "result" => "success").increment(1);
+                delivered.push(message.index);
+            }
+            Err(e) => {
+                counter!("smser_incoming_webhook_total", "result" => "failure").increment(1);
+                error!(
+                    "Failed to forward message {} to {}: {}",
+                    message.index,
+                    webhook.url(),
+                    e
+                );
+                break;
+            }
+        }
+    }
+    if !delivered.is_empty() {
+        transport.mark_read(&delivered).await?;
+        info!(
+            "Forwarded {} messages to {}",
+            delivered.len(),
+            webhook.url()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::setup_metrics;
+    use crate::notify::NotifyFormat;
+    use axum::http::StatusCode;
+    use reqwest::Client;
+    use std::time::Duration; // For StatusCode in tests
+
+    fn test_config(modem_url: String) -> ServerConfig {
+        ServerConfig {
+            modem: Arc::new(client::HttpModem::new(modem_url, None)),
+            routes: Routes::default(),
+            prometheus_handle: setup_metrics(),
+            rate_limiter: RateLimiter::new(100, 1000, vec![]),
+            #[cfg(feature = "alertmanager")]
+            alert_phone_number: None,
+            #[cfg(feature = "alertmanager")]
+            alert_receivers: HashMap::new(),
+            #[cfg(feature = "alertmanager")]
+            silences: Silences::default(),
+            #[cfg(feature = "alertmanager")]
+            oncall: OnCall::default(),
+            #[cfg(feature = "alertmanager")]
+            alert_policies: AlertPolicies::default(),
+            tls_cert: None,
+            tls_key: None,
+            http_redirect_port: None,
+            redirect_host: None,
+            log_sensitive: true,
+            poll_interval: 0,
+            incoming_webhook: None,
+            receive_cmd: None,
+            inbox_count_interval: 0,
+            traffic_interval: 0,
+            auto_purge: None,
+            store: None,
+            admin_token: None,
+            api_keys: HashMap::new(),
+            basic_auth: None,
+            rate_limit_notifier: None,
+            duplicate_guard: None,
+            templates: HashMap::new(),
+            content_filter: ContentFilter::default(),
+            send_retries: 0,
+            audit: None,
+            reloader: None,
+            #[cfg(feature = "grpc")]
+            grpc_listener: None,
+        }
+    }
+
+    #[test]
+    fn test_api_error_from_modem_error() {
+        let err = ApiError::modem(
+            "Failed to send SMS",
+            &ModemError::ModemError {
+                code: 113004.into(),
+                message: "busy".to_string(),
+            },
+        );
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            serde_json::to_value(ErrorResponse { error: err.detail }).unwrap(),
+            serde_json::json!({"error": {
+                "kind": "modem_error",
+                "code": 113004,
+                "message": "Failed to send SMS: Modem error code 113004 (the modem is still busy sending a previous message): busy"
+            }})
+        );
+
+        let err = ApiError::modem("x", &ModemError::Other("boom".to_string()));
+        assert_eq!(err.status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(err.detail.kind, ErrorKind::ModemUnavailable);
+    }
+
+    #[test]
+    fn test_html_escape() {
+        let input = r#"<div class="x">Tom & Jerry's</div>"#;
+        let escaped = html_escape(input);
+        assert_eq!(
+            escaped,
+            "&lt;div class=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/div&gt;"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_server_hello_world() {
+        // Find an available port for testing
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string(); // Dummy URL for the test
+
+        let (tx, rx) = tokio::sync::oneshot::channel(); // New
+        // Spawn the server in a background task
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        // Give the server a moment to start
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Make a request to the server
+        let client = Client::new();
+        let response = client
+            .get(format!("http://127.0.0.1:{}", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+
+        // Assert the response
+        assert!(response.status().is_success());
+        let body = response.text().await.expect("Failed to get response body");
+        assert!(body.contains("smser Gateway"));
+        tx.send(()).unwrap(); // New, send shutdown signal
+        server_handle.await.unwrap(); // Wait for server to shut down cleanly. // New
+    }
+
+    #[tokio::test]
+    async fn test_ui_page() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let response = Client::new()
+            .get(format!("http://127.0.0.1:{}/ui", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert!(response.status().is_success());
+        let body = response.text().await.unwrap();
+        assert!(body.contains("/assets/ui.js"));
+        assert!(!body.contains("{{version}}"));
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_embedded_assets() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let response = client
+            .get(format!("http://127.0.0.1:{}", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        let body = response.text().await.unwrap();
+        assert!(body.contains(&html_escape(&buildinfo::version_full())));
+
+        // Embedded assets are served with their content type
+        let response = client
+            .get(format!("http://127.0.0.1:{}/assets/index.js", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert!(response.status().is_success());
+        assert_eq!(
+            response.headers()["content-type"],
+            "text/javascript; charset=utf-8"
+        );
+        let response = client
+            .get(format!("http://127.0.0.1:{}/assets/missing.js", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            crate::metrics::update_limits_metrics(100, 1000);
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let response = client
+            .get(format!("http://127.0.0.1:{}/metrics", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+
+        assert!(response.status().is_success());
+        let body = response.text().await.expect("Failed to get response body");
+        assert!(body.contains("smser_hourly_limit 100"));
+        assert!(body.contains("smser_daily_limit 1000"));
+        assert!(body.contains("smser_http_requests_total"));
+        assert!(body.contains("endpoint=\"/metrics\""));
+        assert!(body.contains("smser_start_time_seconds"));
+        assert!(body.contains("smser_version_info"));
+        assert!(body.contains("version="));
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_queue_metrics() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // The queue gauges are updated on every scrape
+        let body = Client::new()
+            .get(format!("http://127.0.0.1:{}/metrics", port))
+            .send()
+            .await
+            .expect("Failed to send request")
+            .text()
+            .await
+            .expect("Failed to get response body");
+        assert!(body.contains("smser_queue_depth{lane=\"high\"}"));
+        assert!(body.contains("smser_queue_oldest_age_seconds"));
+        assert!(body.contains("smser_queue_in_flight"));
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_status_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let response = client
+            .get(format!("http://127.0.0.1:{}/status", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+
+        assert!(response.status().is_success());
+        let body = response.text().await.expect("Failed to get response body");
+        assert!(body.contains("SMS Server Status"));
+        assert!(body.contains("Modem URL:</span> http://localhost:8080"));
+        assert!(body.contains("Hourly Usage:</span> 0 / 100"));
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_status_recent_sends() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mock = Arc::new(client::MockModem::new());
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let modem = mock.clone();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                modem,
+                ..test_config(String::new())
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let status_url = format!("http://127.0.0.1:{}/status", port);
+        let body = client
+            .get(&status_url)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(body.contains("Recent Sends"));
+        assert!(body.contains("No messages sent yet"));
+
+        let response = client
+            .post(format!("http://127.0.0.1:{}/send-sms?sync=true", port))
+            .json(&serde_json::json!({"to": "+441234567890", "message": "Hi"}))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let body = client
+            .get(&status_url)
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(!body.contains("No messages sent yet"));
+        assert!(body.contains("Destination"));
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_status_json_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let response = client
+            .get(format!("http://127.0.0.1:{}/status.json", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+
+        assert!(response.status().is_success());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["version"], buildinfo::version());
+        assert_eq!(body["rate_limits"]["global"]["hourly_limit"], 100);
+        assert_eq!(body["rate_limits"]["global"]["daily_usage"], 0);
+        assert_eq!(body["queue_depth"], 0);
+        assert_eq!(body["modem"]["state"], "unknown");
+        assert!(body["modem"]["last_error"].is_null());
+        assert_eq!(
+            body["modems"],
+            serde_json::json!([{"url": "http://localhost:8080", "healthy": null}])
+        );
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/status", port))
+            .header("Accept", "application/json")
+            .send()
+            .await
+            .expect("Failed to send request");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["rate_limits"]["global"]["hourly_limit"], 100);
+        let response = client
+            .get(format!("http://127.0.0.1:{}/status", port))
+            .header("Accept", "text/html,application/json;q=0.9")
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert!(response.text().await.unwrap().contains("<html"));
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/quota", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert!(response.status().is_success());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["global"]["hourly_remaining"], 100);
+        assert_eq!(body["global"]["daily_remaining"], 1000);
+        assert!(body["global"]["hourly_reset_seconds"].as_u64().unwrap() <= 3600);
+        assert_eq!(body["clients"], serde_json::json!([]));
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_history_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let store = Store::open_in_memory().unwrap();
+        store
+            .record_sent(
+                "+441234567890",
+                "Disk full",
+                SendStatus::Failed,
+                Some(113004),
+            )
+            .unwrap();
+        store
+            .record_sent("+441234567890", "Recovered", SendStatus::Sent, None)
+            .unwrap();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                store: Some(store),
+                ..test_config(modem_url)
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let response = client
+            .get(format!(
+                "http://127.0.0.1:{}/history?direction=out&status=failed&limit=100",
+                port
+            ))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert!(response.status().is_success());
+        let body: serde_json::Value = response.json().await.unwrap();
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["content"], "Disk full");
+        assert_eq!(messages[0]["direction"], "out");
+        assert_eq!(messages[0]["status"], "failed");
+        assert_eq!(messages[0]["error_code"], 113004);
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/history?status=bogus", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_api_keys() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mock = Arc::new(client::MockModem::new());
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let modem = mock.clone();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                modem,
+                api_keys: HashMap::from([
+                    ("grafana".to_string(), "key-1".to_string()),
+                    ("backup".to_string(), "key-2".to_string()),
+                ]),
+                rate_limiter: RateLimiter::new(100, 1000, vec![]).with_key_limits(vec![
+                    ClientLimit {
+                        name: "grafana".to_string(),
+                        hourly_limit: 1,
+                        daily_limit: 10,
+                    },
+                ]),
+                ..test_config(String::new())
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let url = format!("http://127.0.0.1:{}/send-sms?sync=true", port);
+        let payload = serde_json::json!({"to": "+441234567890", "message": "Hi"});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error.kind, ErrorKind::Unauthorized);
+        let response = client
+            .post(&url)
+            .header("X-Api-Key", "key-3")
+            .json(&payload)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert!(mock.sent().is_empty());
+
+        let response = client
+            .post(&url)
+            .header("X-Api-Key", "key-1")
+            .json(&payload)
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let response = client
+            .get(format!("http://127.0.0.1:{}/get-sms", port))
+            .bearer_auth("key-2")
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(mock.sent().len(), 1);
+
+        // The key's own limit applies on top of the global one
+        let response = client
+            .post(&url)
+            .header("X-Api-Key", "key-1")
+            .json(&payload)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+        let response = client
+            .post(&url)
+            .header("X-Api-Key", "key-2")
+            .json(&payload)
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // Monitoring endpoints stay open
+        let response = client
+            .get(format!("http://127.0.0.1:{}/status.json", port))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let body: serde_json::Value = response.json().await.unwrap();
+        let keys = &body["rate_limits"]["api_keys"];
+        assert_eq!(keys[0]["name"], "backup");
+        assert_eq!(keys[0]["hourly_usage"], 1);
+        assert_eq!(keys[0]["hourly_limit"], serde_json::Value::Null);
+        assert_eq!(keys[1]["name"], "grafana");
+        assert_eq!(keys[1]["hourly_remaining"], 0);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoints() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mock = Arc::new(client::MockModem::new());
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let modem = mock.clone();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                modem,
+                ..test_config(String::new())
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let readyz = format!("http://127.0.0.1:{}/readyz", port);
+        let response = client.get(&readyz).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["sim_state"], "ready");
+
+        mock.set_sim_state(260);
+        let response = client.get(&readyz).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error.message, "SIM not ready: PIN required");
+
+        mock.fail_with(Some(125002));
+        let response = client.get(&readyz).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert!(body.error.message.starts_with("Modem session failed"));
+
+        // Liveness doesn't depend on the modem
+        let response = client
+            .get(format!("http://127.0.0.1:{}/healthz", port))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                basic_auth: Some(BasicAuth {
+                    username: "hass".to_string(),
+                    password: "pa:ss".to_string(),
+                }),
+                admin_token: Some("secret".to_string()),
+                ..test_config("http://localhost:8080".to_string())
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let url = format!("http://127.0.0.1:{}/status.json", port);
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[header::WWW_AUTHENTICATE],
+            "Basic realm=\"smser\""
+        );
+        let response = client
+            .get(&url)
+            .basic_auth("hass", Some("wrong"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let response = client
+            .get(&url)
+            .basic_auth("hass", Some("pa:ss"))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // Unknown routes don't tell whether they exist
+        let response = client
+            .get(format!("http://127.0.0.1:{}/nope", port))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let response = client
+            .get(format!("http://127.0.0.1:{}/healthz", port))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // The admin token moves to its own header
+        let response = client
+            .get(format!("http://127.0.0.1:{}/admin/limits", port))
+            .basic_auth("hass", Some("pa:ss"))
+            .header("X-Admin-Token", "secret")
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_admin_limits_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                admin_token: Some("secret".to_string()),
+                ..test_config(modem_url)
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let url = format!("http://127.0.0.1:{}/admin/limits", port);
+        let response = client.get(&url).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error.kind, ErrorKind::Unauthorized);
+
+        let response = client.get(&url).bearer_auth("wrong").send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+        let response = client
+            .put(&url)
+            .bearer_auth("secret")
+            .json(&serde_json::json!({
+                "hourly_limit": 5,
+                "clients": {"grafana": {"hourly_limit": 1, "daily_limit": 2}},
+                "reset": true,
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["global"]["hourly_limit"], 5);
+        assert_eq!(body["global"]["daily_limit"], 1000);
+        assert_eq!(body["clients"][0]["name"], "grafana");
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/status.json", port))
+            .send()
+            .await
+            .unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["rate_limits"]["global"]["hourly_limit"], 5);
+
+        let response = client
+            .put(&url)
+            .bearer_auth("secret")
+            .json(&serde_json::json!({"hourly_limit": -1}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNPROCESSABLE_ENTITY);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_admin_reload() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mock = Arc::new(client::MockModem::new());
+        let broken = Arc::new(std::sync::atomic::AtomicBool::new(false));
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let modem = mock.clone();
+        let reload_broken = broken.clone();
+        let server_handle = tokio::spawn(async move {
+            let reloader: ConfigReloader = Arc::new(move || {
+                if reload_broken.load(std::sync::atomic::Ordering::Relaxed) {
+                    return Err("Invalid config smser.toml".to_string());
+                }
+                Ok(ReloadedConfig {
+                    routes: Routes::default(),
+                    templates: HashMap::from([("hello".to_string(), "Hello {{name}}".to_string())]),
+                    content_filter: ContentFilter::default(),
+                    hourly_limit: 7,
+                    daily_limit: 70,
+                    recipient_limit: 0,
+                    key_limits: vec![],
+                    #[cfg(feature = "alertmanager")]
+                    alert_receivers: HashMap::new(),
+                    #[cfg(feature = "alertmanager")]
+                    oncall: OnCall::default(),
+                    #[cfg(feature = "alertmanager")]
+                    alert_policies: AlertPolicies::default(),
+                })
+            });
+            let config = ServerConfig {
+                modem,
+                admin_token: Some("secret".to_string()),
+                reloader: Some(reloader),
+                ..test_config(String::new())
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let send_url = format!("http://127.0.0.1:{}/send-sms?sync=true", port);
+        let payload = serde_json::json!({
+            "to": "+441234567890",
+            "template": "hello",
+            "vars": {"name": "Jo"}
+        });
+        let response = client.post(&send_url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let reload_url = format!("http://127.0.0.1:{}/admin/reload", port);
+        let response = client.post(&reload_url).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = client
+            .post(&reload_url)
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        let response = client.post(&send_url).json(&payload).send().await.unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(mock.sent()[0].1, "Hello Jo");
+        let response = client
+            .get(format!("http://127.0.0.1:{}/admin/limits", port))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["global"]["hourly_limit"], 7);
+
+        // A broken config file keeps the current settings
+        broken.store(true, std::sync::atomic::Ordering::Relaxed);
+        let response = client
+            .post(&reload_url)
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert!(body.error.message.contains("Invalid config"));
+        let response = client.post(&send_url).json(&payload).send().await.unwrap();
+        assert!(response.status().is_success());
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_admin_api_disabled() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            start_server(listener, rx, test_config(modem_url)).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let response = Client::new()
+            .get(format!("http://127.0.0.1:{}/admin/limits", port))
+            .bearer_auth("secret")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error.kind, ErrorKind::NotConfigured);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "alertmanager")]
+    async fn test_alertmanager_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                alert_phone_number: Some("+441234567890".to_string()),
+                ..test_config(modem_url)
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let json = r#"{
+  "version": "4",
+  "groupKey": "{}:{alertname=\"TestAlert\"}",
+  "truncatedAlerts": 0,
+  "status": "firing",
+  "receiver": "webhook",
+  "groupLabels": {},
+  "commonLabels": {
+    "alertname": "TestAlert",
+    "severity": "critical"
+  },
+  "commonAnnotations": {
+    "summary": "Something is broken"
+  },
+  "externalURL": "http://localhost:9093",
+  "alerts": []
+}"#;
+
+        let client = Client::new();
+        let response = client
+            .post(format!("http://127.0.0.1:{}/alertmanager", port))
+            .header("Content-Type", "application/json")
+            .body(json)
+            .send()
+            .await
+            .expect("Failed to send request");
+
+        // It will fail because modem is not there, but it should reach the modem call
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = response.text().await.expect("Failed to get response body");
+        assert!(body.contains("Failed to send alert SMS"));
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    /// Alertmanager webhook payload with a firing `TestAlert` and no alerts
+    #[cfg(feature = "alertmanager")]
+    const TEST_ALERT: &str = r#"{
+  "version": "4",
+  "groupKey": "{}:{alertname=\"TestAlert\"}",
+  "truncatedAlerts": 0,
+  "status": "firing",
+  "receiver": "webhook",
+  "groupLabels": {},
+  "commonLabels": {
+    "alertname": "TestAlert",
+    "severity": "critical"
+  },
+  "commonAnnotations": {
+    "summary": "Something is broken"
+  },
+  "externalURL": "http://localhost:9093",
+  "alerts": []
+}"#;
+
+    #[tokio::test]
+    #[cfg(feature = "alertmanager")]
+    async fn test_alertmanager_receivers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                alert_receivers: HashMap::from([(
+                    "sms-oncall-db".to_string(),
+                    AlertReceiver {
+                        to: vec!["+441234567891".to_string()],
+                        oncall: None,
+                        template: Some("{{alertname}} on {{instance}}".to_string()),
+                    },
+                )]),
+                ..test_config(modem_url)
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Configured receivers are routed like /alertmanager, failing at the modem
+        let client = Client::new();
+        let response = client
+            .post(format!(
+                "http://127.0.0.1:{}/alertmanager/sms-oncall-db",
+                port
+            ))
+            .header("Content-Type", "application/json")
+            .body(TEST_ALERT)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = response.text().await.unwrap();
+        assert!(body.contains("Failed to send alert SMS"));
+
+        let response = client
+            .post(format!("http://127.0.0.1:{}/alertmanager/unknown", port))
+            .header("Content-Type", "application/json")
+            .body(TEST_ALERT)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["error"]["kind"], "not_configured");
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "alertmanager")]
+    async fn test_alertmanager_partial_failure() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mock = Arc::new(client::MockModem::new());
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let modem = mock.clone();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                modem,
+                rate_limiter: RateLimiter::new(1, 10, vec![]),
+                alert_receivers: HashMap::from([(
+                    "team".to_string(),
+                    AlertReceiver {
+                        to: vec!["+441234567891".to_string(), "+441234567892".to_string()],
+                        oncall: None,
+                        template: None,
+                    },
+                )]),
+                ..test_config(String::new())
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // The hourly limit lets only the first recipient through
+        let client = Client::new();
+        let url = format!("http://127.0.0.1:{}/alertmanager/team", port);
+        let response = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .body(TEST_ALERT)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "partial");
+        assert_eq!(body["failed"].as_array().unwrap().len(), 1);
+        assert_eq!(body["failed"][0]["to"], "+441234567892");
+        assert_eq!(mock.sent().len(), 1);
+
+        // Failing for every recipient is an error
+        let response = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .body(TEST_ALERT)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(mock.sent().len(), 1);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    /// Alert policies with quiet hours around now that defer critical alerts,
+    /// plus `extra` config
+    #[cfg(feature = "alertmanager")]
+    fn quiet_now_policies(extra: &str) -> AlertPolicies {
+        let now = chrono::Local::now();
+        let config = crate::config::Config::parse(&format!(
+            "[quiet_hours]\nstart = \"{}\"\nend = \"{}\"\n\n\
+             [severities.critical]\ndefer_in_quiet_hours = true\n\n{}",
+            (now - chrono::TimeDelta::hours(1)).format("%H:%M"),
+            (now + chrono::TimeDelta::hours(1)).format("%H:%M"),
+            extra
+        ))
+        .unwrap();
+        AlertPolicies::from_config(&config).unwrap()
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "alertmanager")]
+    async fn test_alertmanager_deferred_partial_failure() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let mock = Arc::new(client::MockModem::new());
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let modem = mock.clone();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                modem,
+                rate_limiter: RateLimiter::new(1, 10, vec![]),
+                alert_receivers: HashMap::from([(
+                    "team".to_string(),
+                    AlertReceiver {
+                        to: vec!["+441234567891".to_string(), "+441234567892".to_string()],
+                        oncall: None,
+                        template: None,
+                    },
+                )]),
+                alert_policies: quiet_now_policies(""),
+                ..test_config(String::new())
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // The hourly limit lets only the first recipient be queued
+        let response = Client::new()
+            .post(format!("http://127.0.0.1:{}/alertmanager/team", port))
+            .header("Content-Type", "application/json")
+            .body(TEST_ALERT)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "partial");
+        assert!(body["send_at"].is_string());
+        assert_eq!(body["failed"][0]["to"], "+441234567892");
+        assert!(mock.sent().is_empty());
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "alertmanager")]
+    async fn test_alertmanager_deferred_failure_not_suppressed() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                rate_limiter: RateLimiter::new(0, 0, vec![]),
+                alert_phone_number: Some("+441234567890".to_string()),
+                alert_policies: quiet_now_policies(
+                    "[alertnames.TestAlert]\nsuppress_for = \"1h\"\n",
+                ),
+                ..test_config(modem_url)
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Nothing was queued, so the repeat is not suppressed and fails again
+        let client = Client::new();
+        for _ in 0..2 {
+            let response = client
+                .post(format!("http://127.0.0.1:{}/alertmanager", port))
+                .header("Content-Type", "application/json")
+                .body(TEST_ALERT)
+                .send()
+                .await
+                .expect("Failed to send request");
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        }
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "alertmanager")]
+    async fn test_alertmanager_silenced() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                alert_phone_number: Some("+441234567890".to_string()),
+                ..test_config(modem_url)
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        // Silenced alerts are accepted but not sent
+        let response = client
+            .post(format!("http://127.0.0.1:{}/silences", port))
+            .json(&serde_json::json!({
+                "matchers": {"alertname": "TestAlert"},
+                "ends_at": "2999-01-01T00:00:00Z",
+                "comment": "Maintenance",
+            }))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body: serde_json::Value = response.json().await.unwrap();
+        let silence_id = body["silence"]["id"].as_u64().unwrap();
+
+        let response = client
+            .post(format!("http://127.0.0.1:{}/alertmanager", port))
+            .header("Content-Type", "application/json")
+            .body(TEST_ALERT)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "silenced");
+        assert_eq!(body["silence_id"], silence_id);
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/silences", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["silences"][0]["comment"], "Maintenance");
+
+        let response = client
+            .delete(format!("http://127.0.0.1:{}/silences/{}", port, silence_id))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let response = client
+            .post(format!("http://127.0.0.1:{}/silences", port))
+            .json(&serde_json::json!({"cron": "0 2 * * *"}))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "alertmanager")]
+    async fn test_alertmanager_silenced_before_filter() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let filters = [crate::config::FilterConfig {
+                pattern: None,
+                keyword: Some("broken".to_string()),
+                replace: None,
+            }];
+            let config = ServerConfig {
+                alert_phone_number: Some("+441234567890".to_string()),
+                content_filter: ContentFilter::from_config(&filters).unwrap(),
+                ..test_config(modem_url)
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let url = format!("http://127.0.0.1:{}/alertmanager", port);
+        let response = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .body(TEST_ALERT)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // A silenced alert is not blocked, as it would not be sent anyway
+        let response = client
+            .post(format!("http://127.0.0.1:{}/silences", port))
+            .json(&serde_json::json!({
+                "matchers": {"alertname": "TestAlert"},
+                "ends_at": "2999-01-01T00:00:00Z",
+            }))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let response = client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .body(TEST_ALERT)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "silenced");
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg(feature = "alertmanager")]
+    async fn test_alertmanager_metrics() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://localhost:8080".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                alert_phone_number: Some("+441234567890".to_string()),
+                ..test_config(modem_url)
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // One alert failing at the modem, one for a receiver that does not exist
+        let client = Client::new();
+        for path in ["alertmanager", "alertmanager/unknown"] {
+            let response = client
+                .post(format!("http://127.0.0.1:{}/{}", port, path))
+                .header("Content-Type", "application/json")
+                .body(TEST_ALERT)
+                .send()
+                .await
+                .expect("Failed to send request");
+            assert!(!response.status().is_success());
+        }
+
+        let metrics = client
+            .get(format!("http://127.0.0.1:{}/metrics", port))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(
+            metrics
+                .contains("smser_alerts_received_total{source=\"alertmanager\",status=\"firing\"")
+        );
+        assert!(metrics.contains("smser_alerts_failed_total{reason=\"modem\"}"));
+        assert!(metrics.contains("smser_alerts_failed_total{reason=\"routing\"}"));
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_sms_endpoint_error() {
+        // Find an available port for testing
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://nonexistent.com".to_string(); // Simulate unavailable modem
+
+        let (tx, rx) = tokio::sync::oneshot::channel(); // New
+        // Spawn the server in a background task
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        // Give the server a moment to start
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        // Make a request to the server
+        let client = Client::new();
+        let response = client
+            .get(format!(
+                "http://127.0.0.1:{}/get-sms?count=1&box_type=1",
+                port
+            ))
+            .send()
+            .await
+            .expect("Failed to send request");
+
+        // Assert the response
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: ErrorResponse = response.json().await.expect("Expected error envelope");
+        assert_eq!(body.error.kind, ErrorKind::ModemUnavailable);
+        assert_eq!(body.error.code, None);
+        assert!(body.error.message.contains("Failed to get session info"));
+
+        tx.send(()).unwrap(); // New, send shutdown signal
+        server_handle.await.unwrap(); // Wait for server to shut down cleanly. // New
+    }
+
+    #[tokio::test]
+    async fn test_error_envelope() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://nonexistent.com".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        // Malformed requests and unknown routes get the error envelope
+        let response = client
+            .post(format!("http://127.0.0.1:{}/send-sms", port))
+            .header("Content-Type", "application/json")
+            .body("{not json")
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: ErrorResponse = response.json().await.expect("Expected error envelope");
+        assert_eq!(body.error.kind, ErrorKind::BadRequest);
+
+        let response = client
+            .get(format!("http://127.0.0.1:{}/no-such-page", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: ErrorResponse = response.json().await.expect("Expected error envelope");
+        assert_eq!(body.error.kind, ErrorKind::NotFound);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_purge_trash_endpoint_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://nonexistent.com".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let response = client
+            .post(format!("http://127.0.0.1:{}/purge-trash?box_type=7", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: ErrorResponse = response.json().await.expect("Expected error envelope");
+        assert!(body.error.message.contains("Failed to purge messages"));
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_device_endpoint_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://nonexistent.com".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let response = client
+            .get(format!("http://127.0.0.1:{}/device", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: ErrorResponse = response.json().await.expect("Expected error envelope");
+        assert_eq!(body.error.kind, ErrorKind::ModemUnavailable);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_network_endpoint_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://nonexistent.com".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let response = client
+            .get(format!("http://127.0.0.1:{}/network", port))
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body: ErrorResponse = response.json().await.expect("Expected error envelope");
+        assert_eq!(body.error.kind, ErrorKind::ModemUnavailable);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_sms_endpoint_queued() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://nonexistent.com".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = test_config(modem_url);
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let url = format!("http://127.0.0.1:{}/send-sms", port);
+        let payload = serde_json::json!({"to": "+441234567890", "message": "Hello"});
+
+        // Without sync the message is only queued
+        let response = client
+            .post(&url)
+            .json(&payload)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "queued");
+        let first_id = body["id"].as_u64().expect("Missing id");
+
+        // With sync=true the request waits for the (unavailable) modem
+        let response = client
+            .post(format!("{}?sync=true", url))
+            .json(&payload)
+            .send()
+            .await
+            .expect("Failed to send request");
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = response.text().await.expect("Failed to get response body");
+        assert!(body.contains("Failed to send SMS"));
+
+        // IDs keep increasing across requests
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert!(body["id"].as_u64().unwrap() > first_id);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mock_modem_endpoints() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let inbox = modem::SmsMessage {
+            smstat: modem::SmsStat::Unread,
+            index: 40001,
+            phone: "+441234567890".to_string(),
+            content: "Ping".to_string(),
+            date: "2026-10-16 10:00:00".to_string(),
+            sca: String::new(),
+            save_type: 0,
+            priority: modem::Priority::Normal,
+            sms_type: modem::SmsType::Single,
+        };
+        let mock =
+            Arc::new(client::MockModem::new().with_messages(BoxType::LocalInbox, vec![inbox]));
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let modem = mock.clone();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                modem,
+                ..test_config(String::new())
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let response = client
+            .post(format!("http://127.0.0.1:{}/send-sms?sync=true", port))
+            .json(&serde_json::json!({"to": "+441234567890", "message": "Pong"}))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(
+            mock.sent(),
+            [(vec!["+441234567890".to_string()], "Pong".to_string())]
+        );
+
+        let response = client
+            .get(format!(
+                "http://127.0.0.1:{}/get-sms?count=5&box_type=1",
+                port
+            ))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let body: SmsListJson = response.json().await.unwrap();
+        assert_eq!(body.messages[0].content, "Ping");
+        assert_eq!(body.messages[0].smstat, modem::SmsStat::Unread);
+
+        let response = client
+            .get(format!(
+                "http://127.0.0.1:{}/get-sms?count=5&box_type=1&page=2",
+                port
+            ))
+            .send()
+            .await
+            .unwrap();
+        let body: SmsListJson = response.json().await.unwrap();
+        assert!(body.messages.is_empty());
+        let response = client
+            .get(format!("http://127.0.0.1:{}/get-sms?page=0", port))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = client
+            .post(format!("http://127.0.0.1:{}/mark-read", port))
+            .json(&serde_json::json!({"indexes": [40001]}))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        assert_eq!(
+            mock.messages(BoxType::LocalInbox)[0].smstat,
+            modem::SmsStat::Read
+        );
+
+        let response = client
+            .delete(format!("http://127.0.0.1:{}/sms", port))
+            .json(&serde_json::json!({"indexes": []}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = client
+            .delete(format!("http://127.0.0.1:{}/sms/40001", port))
+            .send()
+            .await
+            .unwrap();
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["deleted"], serde_json::json!([40001]));
+        assert!(mock.messages(BoxType::LocalInbox).is_empty());
+        let response = client
+            .delete(format!("http://127.0.0.1:{}/sms", port))
+            .json(&serde_json::json!({"indexes": [40002, 40003]}))
+            .send()
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+
+        // Modem error codes reach the client in the error envelope
+        mock.fail_with(Some(113004));
+        let response = client
+            .get(format!("http://127.0.0.1:{}/device", port))
+            .send()
+            .await
+            .unwrap();
+        let body: ErrorResponse = response.json().await.expect("Expected error envelope");
+        assert_eq!(body.error.code, Some(113004));
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
 
-    Ok((response.count, new_last_seen, logged_count))
-}
+    #[tokio::test]
+    async fn test_incoming_webhook() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let hook = Router::new()
+            .route(
+                "/ok",
+                post({
+                    let received = received.clone();
+                    move |Json(body): Json<serde_json::Value>| async move {
+                        received.lock().unwrap().push(body);
+                    }
+                }),
+            )
+            .route(
+                "/fail",
+                post(|| async { StatusCode::INTERNAL_SERVER_ERROR }),
+            );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, hook).await.unwrap() });
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::metrics::setup_metrics;
-    use axum::http::StatusCode;
-    use reqwest::Client;
-    use std::time::Duration; // For StatusCode in tests
+        let message = |index: i32, content: &str| modem::SmsMessage {
+            smstat: modem::SmsStat::Unread,
+            index,
+            phone: "+441234567890".to_string(),
+            content: content.to_string(),
+            date: "2026-10-16 10:00:00".to_string(),
+            sca: String::new(),
+            save_type: 0,
+            priority: modem::Priority::Normal,
+            sms_type: modem::SmsType::Single,
+        };
+        let mock = Arc::new(client::MockModem::new().with_messages(
+            BoxType::LocalInbox,
+            vec![message(40002, "Second"), message(40001, "First")],
+        ));
+        let transport = Hilink(mock.clone());
 
-    #[test]
-    fn test_html_escape() {
-        let input = r#"<div class="x">Tom & Jerry's</div>"#;
-        let escaped = html_escape(input);
+        // A failing webhook leaves the messages unread for the next poll
+        let failing = IncomingWebhook::new(format!("http://127.0.0.1:{}/fail", port));
+        poll_sms(&transport, false, None, None, false, Some(&failing), None)
+            .await
+            .unwrap();
+        assert!(
+            mock.messages(BoxType::LocalInbox)
+                .iter()
+                .all(|m| m.smstat == modem::SmsStat::Unread)
+        );
+
+        let webhook = IncomingWebhook::new(format!("http://127.0.0.1:{}/ok", port));
+        poll_sms(&transport, false, None, None, false, Some(&webhook), None)
+            .await
+            .unwrap();
         assert_eq!(
-            escaped,
-            "&lt;div class=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/div&gt;"
+            *received.lock().unwrap(),
+            [
+                serde_json::json!({"index": 40001, "from": "+441234567890", "content": "First", "date": "2026-10-16 10:00:00"}),
+                serde_json::json!({"index": 40002, "from": "+441234567890", "content": "Second", "date": "2026-10-16 10:00:00"}),
+            ]
+        );
+        assert!(
+            mock.messages(BoxType::LocalInbox)
+                .iter()
+                .all(|m| m.smstat == modem::SmsStat::Read)
         );
+
+        // Read messages are not forwarded again
+        poll_sms(&transport, false, None, None, false, Some(&webhook), None)
+            .await
+            .unwrap();
+        assert_eq!(received.lock().unwrap().len(), 2);
     }
 
     #[tokio::test]
-    async fn test_start_server_hello_world() {
-        // Find an available port for testing
+    async fn test_outbox_endpoints() {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
         let port = listener.local_addr().unwrap().port();
-        let modem_url = "http://localhost:8080".to_string(); // Dummy URL for the test
+        let mock = Arc::new(client::MockModem::new());
+        mock.fail_with(Some(100004));
 
-        let (tx, rx) = tokio::sync::oneshot::channel(); // New
-        // Spawn the server in a background task
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let modem = mock.clone();
         let server_handle = tokio::spawn(async move {
-            let handle = setup_metrics();
-            let rate_limiter = RateLimiter::new(100, 1000, vec![]);
             let config = ServerConfig {
-                modem_url,
-                prometheus_handle: handle,
-                rate_limiter,
-                #[cfg(feature = "alertmanager")]
-                alert_phone_number: None,
-                tls_cert: None,
-                tls_key: None,
-                http_redirect_port: None,
-                redirect_host: None,
-                log_sensitive: true,
-                poll_interval: 0,
+                modem,
+                ..test_config(String::new())
             };
             start_server(listener, rx, config).await;
         });
 
-        // Give the server a moment to start
         tokio::time::sleep(Duration::from_millis(100)).await;
 
-        // Make a request to the server
         let client = Client::new();
+        let base = format!("http://127.0.0.1:{}", port);
         let response = client
-            .get(format!("http://127.0.0.1:{}", port))
+            .post(format!("{}/send-sms", base))
+            .json(&serde_json::json!({"to": "+441234567890", "message": "Hello"}))
             .send()
             .await
-            .expect("Failed to send request");
+            .unwrap();
+        let id = response.json::<serde_json::Value>().await.unwrap()["id"]
+            .as_u64()
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
 
-        // Assert the response
+        // Without retries the failed message is kept in the outbox
+        let body: serde_json::Value = client
+            .get(format!("{}/outbox", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let entry = &body["messages"][0];
+        assert_eq!(entry["id"], id);
+        assert_eq!(entry["state"], "failed");
+        assert_eq!(entry["to"], serde_json::json!(["+441234567890"]));
+        assert!(entry["last_error"].is_string());
+        assert!(entry["queued_at"].is_u64());
+
+        mock.fail_with(None);
+        let response = client
+            .post(format!("{}/outbox/{}/retry", base, id))
+            .send()
+            .await
+            .unwrap();
         assert!(response.status().is_success());
-        let body = response.text().await.expect("Failed to get response body");
-        assert!(body.contains("smser Gateway"));
-        tx.send(()).unwrap(); // New, send shutdown signal
-        server_handle.await.unwrap(); // Wait for server to shut down cleanly. // New
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(mock.sent().len(), 1);
+        let body: serde_json::Value = client
+            .get(format!("{}/outbox", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(body["messages"], serde_json::json!([]));
+
+        let response = client
+            .delete(format!("{}/outbox/{}", base, id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error.kind, ErrorKind::NotFound);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_metrics_endpoint() {
+    async fn test_send_template() {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
         let port = listener.local_addr().unwrap().port();
-        let modem_url = "http://localhost:8080".to_string();
+        let mock = Arc::new(client::MockModem::new());
 
         let (tx, rx) = tokio::sync::oneshot::channel();
+        let modem = mock.clone();
         let server_handle = tokio::spawn(async move {
-            let handle = setup_metrics();
-            crate::metrics::update_limits_metrics(100, 1000);
-            let rate_limiter = RateLimiter::new(100, 1000, vec![]);
             let config = ServerConfig {
-                modem_url,
-                prometheus_handle: handle,
-                rate_limiter,
-                #[cfg(feature = "alertmanager")]
-                alert_phone_number: None,
-                tls_cert: None,
-                tls_key: None,
-                http_redirect_port: None,
-                redirect_host: None,
-                log_sensitive: true,
-                poll_interval: 0,
+                modem,
+                templates: HashMap::from([(
+                    "low-disk".to_string(),
+                    "Disk on {{host}} at {{pct}}%".to_string(),
+                )]),
+                ..test_config(String::new())
             };
             start_server(listener, rx, config).await;
         });
@@ -927,48 +4562,61 @@ mod tests {
         tokio::time::sleep(Duration::from_millis(100)).await;
 
         let client = Client::new();
+        let url = format!("http://127.0.0.1:{}/send-sms?sync=true", port);
         let response = client
-            .get(format!("http://127.0.0.1:{}/metrics", port))
+            .post(&url)
+            .json(&serde_json::json!({
+                "to": "+441234567890",
+                "template": "low-disk",
+                "vars": {"host": "db1", "pct": "92"}
+            }))
             .send()
             .await
-            .expect("Failed to send request");
-
+            .unwrap();
         assert!(response.status().is_success());
-        let body = response.text().await.expect("Failed to get response body");
-        assert!(body.contains("smser_hourly_limit 100"));
-        assert!(body.contains("smser_daily_limit 1000"));
-        assert!(body.contains("smser_http_requests_total"));
-        assert!(body.contains("endpoint=\"/metrics\""));
-        assert!(body.contains("smser_start_time_seconds"));
-        assert!(body.contains("smser_version_info"));
-        assert!(body.contains("version="));
+        assert_eq!(mock.sent()[0].1, "Disk on db1 at 92%");
+
+        for payload in [
+            serde_json::json!({"to": "+441234567890", "template": "missing"}),
+            serde_json::json!({"to": "+441234567890", "template": "low-disk", "vars": {"host": "db1"}}),
+        ] {
+            let response = client.post(&url).json(&payload).send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        }
+        assert_eq!(mock.sent().len(), 1);
 
         tx.send(()).unwrap();
         server_handle.await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_status_endpoint() {
+    async fn test_rate_limit_notification() {
+        // Webhook receiver forwarding notification bodies into a channel
+        let (hook_tx, mut hook_rx) = tokio::sync::mpsc::unbounded_channel();
+        let hook_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let hook_port = hook_listener.local_addr().unwrap().port();
+        let hook = Router::new().route(
+            "/hook",
+            post(move |Json(body): Json<serde_json::Value>| async move {
+                hook_tx.send(body).unwrap();
+            }),
+        );
+        tokio::spawn(async move { axum::serve(hook_listener, hook).await.unwrap() });
+
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
         let port = listener.local_addr().unwrap().port();
-        let modem_url = "http://localhost:8080".to_string();
+        let modem_url = "http://nonexistent.com".to_string();
 
         let (tx, rx) = tokio::sync::oneshot::channel();
         let server_handle = tokio::spawn(async move {
-            let handle = setup_metrics();
-            let rate_limiter = RateLimiter::new(100, 1000, vec![]);
             let config = ServerConfig {
-                modem_url,
-                prometheus_handle: handle,
-                rate_limiter,
-                #[cfg(feature = "alertmanager")]
-                alert_phone_number: None,
-                tls_cert: None,
-                tls_key: None,
-                http_redirect_port: None,
-                redirect_host: None,
-                log_sensitive: true,
-                poll_interval: 0,
+                rate_limiter: RateLimiter::new(1, 10, vec![]),
+                rate_limit_notifier: Some(RateLimitNotifier::new(
+                    format!("http://127.0.0.1:{}/hook", hook_port),
+                    NotifyFormat::Json,
+                    Duration::from_secs(3600),
+                )),
+                ..test_config(modem_url)
             };
             start_server(listener, rx, config).await;
         });
@@ -976,134 +4624,216 @@ mod tests {
         tokio::time::sleep(Duration::from_millis(100)).await;
 
         let client = Client::new();
-        let response = client
-            .get(format!("http://127.0.0.1:{}/status", port))
+        let url = format!("http://127.0.0.1:{}/send-sms", port);
+        let payload =
+            serde_json::json!({"to": "+441234567890", "message": "Hello", "client": "app"});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        for _ in 0..2 {
+            let response = client.post(&url).json(&payload).send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        }
+
+        let body = tokio::time::timeout(Duration::from_secs(5), hook_rx.recv())
+            .await
+            .expect("No notification")
+            .unwrap();
+        assert_eq!(body["event"], "rate_limited");
+        assert_eq!(body["client"], "app");
+        assert!(body["reason"].as_str().unwrap().contains("Hourly limit"));
+        // The second rejection does not notify again
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(hook_rx.try_recv().is_err());
+
+        let metrics = client
+            .get(format!("http://127.0.0.1:{}/metrics", port))
             .send()
             .await
-            .expect("Failed to send request");
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert!(metrics.contains("smser_rate_limited_total"));
 
-        assert!(response.status().is_success());
-        let body = response.text().await.expect("Failed to get response body");
-        assert!(body.contains("SMS Server Status"));
-        assert!(body.contains("Modem URL:</span> http://localhost:8080"));
-        assert!(body.contains("Hourly Usage:</span> 0 / 100"));
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_sms_multiple_recipients() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://nonexistent.com".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                rate_limiter: RateLimiter::new(3, 10, vec![]),
+                ..test_config(modem_url)
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let client = Client::new();
+        let url = format!("http://127.0.0.1:{}/send-sms", port);
+        let payload =
+            serde_json::json!({"to": ["+441234567890", "+441234567891"], "message": "Hi"});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        // Each recipient counts against the limits, so two more do not fit
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let payload = serde_json::json!({"to": "+441234567890", "message": "Hi"});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let payload = serde_json::json!({"to": [], "message": "Hi"});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // Every invalid field is reported, and nothing is counted or queued
+        let payload = serde_json::json!({"to": ["+441234567890", "not a number"], "message": " "});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error.kind, ErrorKind::Invalid);
+        let fields: Vec<&str> = body.error.fields.iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(fields, ["to[1]", "message"]);
+        let payload = serde_json::json!({"to": "+441234567890", "message": "a".repeat(153 * compose::MAX_SEGMENTS + 1)});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
 
         tx.send(()).unwrap();
         server_handle.await.unwrap();
     }
 
     #[tokio::test]
-    #[cfg(feature = "alertmanager")]
-    async fn test_alertmanager_endpoint() {
+    async fn test_duplicate_window() {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
         let port = listener.local_addr().unwrap().port();
-        let modem_url = "http://localhost:8080".to_string();
+        let modem_url = "http://nonexistent.com".to_string();
 
         let (tx, rx) = tokio::sync::oneshot::channel();
         let server_handle = tokio::spawn(async move {
-            let handle = setup_metrics();
-            let rate_limiter = RateLimiter::new(100, 1000, vec![]);
             let config = ServerConfig {
-                modem_url,
-                prometheus_handle: handle,
-                rate_limiter,
-                alert_phone_number: Some("+441234567890".to_string()),
-                tls_cert: None,
-                tls_key: None,
-                http_redirect_port: None,
-                redirect_host: None,
-                log_sensitive: true,
-                poll_interval: 0,
+                duplicate_guard: Some(DuplicateGuard::new(
+                    Duration::from_secs(600),
+                    DuplicateAction::Reject,
+                )),
+                ..test_config(modem_url)
             };
             start_server(listener, rx, config).await;
         });
 
         tokio::time::sleep(Duration::from_millis(100)).await;
 
-        let json = r#"{
-  "version": "4",
-  "groupKey": "{}:{alertname=\"TestAlert\"}",
-  "truncatedAlerts": 0,
-  "status": "firing",
-  "receiver": "webhook",
-  "groupLabels": {},
-  "commonLabels": {
-    "alertname": "TestAlert",
-    "severity": "critical"
-  },
-  "commonAnnotations": {
-    "summary": "Something is broken"
-  },
-  "externalURL": "http://localhost:9093",
-  "alerts": []
-}"#;
+        let client = Client::new();
+        let url = format!("http://127.0.0.1:{}/send-sms", port);
+        let payload = serde_json::json!({"to": "+441234567890", "message": "Disk full"});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error.kind, ErrorKind::Duplicate);
+
+        // A different recipient is not a duplicate
+        let payload = serde_json::json!({"to": "+441234567891", "message": "Disk full"});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["duplicate"], false);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_content_filter() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let modem_url = "http://nonexistent.com".to_string();
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let server_handle = tokio::spawn(async move {
+            let filters = [crate::config::FilterConfig {
+                pattern: None,
+                keyword: Some("confidential".to_string()),
+                replace: None,
+            }];
+            let config = ServerConfig {
+                content_filter: ContentFilter::from_config(&filters).unwrap(),
+                ..test_config(modem_url)
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
 
         let client = Client::new();
-        let response = client
-            .post(format!("http://127.0.0.1:{}/alertmanager", port))
-            .header("Content-Type", "application/json")
-            .body(json)
-            .send()
-            .await
-            .expect("Failed to send request");
+        let url = format!("http://127.0.0.1:{}/send-sms", port);
+        let payload = serde_json::json!({"to": "+441234567890", "message": "Confidential: Q3"});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.error.kind, ErrorKind::Blocked);
 
-        // It will fail because modem is not there, but it should reach the modem call
-        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
-        let body = response.text().await.expect("Failed to get response body");
-        assert!(body.contains("Failed to get session info"));
+        let payload = serde_json::json!({"to": "+441234567890", "message": "Disk full"});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
 
         tx.send(()).unwrap();
         server_handle.await.unwrap();
     }
 
     #[tokio::test]
-    async fn test_get_sms_endpoint_error() {
-        // Find an available port for testing
+    async fn test_duplicate_suppress() {
         let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
         let port = listener.local_addr().unwrap().port();
-        let modem_url = "http://nonexistent.com".to_string(); // Simulate unavailable modem
+        let modem_url = "http://nonexistent.com".to_string();
 
-        let (tx, rx) = tokio::sync::oneshot::channel(); // New
-        // Spawn the server in a background task
+        let (tx, rx) = tokio::sync::oneshot::channel();
         let server_handle = tokio::spawn(async move {
-            let handle = setup_metrics();
-            let rate_limiter = RateLimiter::new(100, 1000, vec![]);
             let config = ServerConfig {
-                modem_url,
-                prometheus_handle: handle,
-                rate_limiter,
-                #[cfg(feature = "alertmanager")]
-                alert_phone_number: None,
-                tls_cert: None,
-                tls_key: None,
-                http_redirect_port: None,
-                redirect_host: None,
-                log_sensitive: true,
-                poll_interval: 0,
+                duplicate_guard: Some(DuplicateGuard::new(
+                    Duration::from_secs(600),
+                    DuplicateAction::Suppress,
+                )),
+                ..test_config(modem_url)
             };
             start_server(listener, rx, config).await;
         });
 
-        // Give the server a moment to start
         tokio::time::sleep(Duration::from_millis(100)).await;
 
-        // Make a request to the server
         let client = Client::new();
-        let response = client
-            .get(format!(
-                "http://127.0.0.1:{}/get-sms?count=1&box_type=1",
-                port
-            ))
-            .send()
-            .await
-            .expect("Failed to send request");
+        let url = format!("http://127.0.0.1:{}/send-sms", port);
+        let payload = serde_json::json!({"to": "+441234567890", "message": "Disk full"});
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
 
-        // Assert the response
-        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
-        let body = response.text().await.expect("Failed to get response body");
-        assert!(body.contains("Failed to get session info"));
-        tx.send(()).unwrap(); // New, send shutdown signal
-        server_handle.await.unwrap(); // Wait for server to shut down cleanly. // New
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "suppressed");
+
+        // Only the new recipient is queued
+        let payload = serde_json::json!({
+            "to": ["+441234567890", "+441234567891"],
+            "message": "Disk full"
+        });
+        let response = client.post(&url).json(&payload).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["status"], "queued");
+        assert_eq!(body["duplicate"], true);
+
+        tx.send(()).unwrap();
+        server_handle.await.unwrap();
     }
 
     #[tokio::test]
@@ -1131,20 +4861,10 @@ mod tests {
 
         // Spawn the server in a background task
         let server_handle = tokio::spawn(async move {
-            let handle = setup_metrics();
-            let rate_limiter = RateLimiter::new(100, 1000, vec![]);
             let config = ServerConfig {
-                modem_url,
-                prometheus_handle: handle,
-                rate_limiter,
-                #[cfg(feature = "alertmanager")]
-                alert_phone_number: None,
                 tls_cert: Some(cert_path_clone),
                 tls_key: Some(key_path_clone),
-                http_redirect_port: None,
-                redirect_host: None,
-                log_sensitive: true,
-                poll_interval: 0,
+                ..test_config(modem_url)
             };
             start_server(listener, rx, config).await;
         });
@@ -1176,4 +4896,120 @@ mod tests {
         let _ = std::fs::remove_file(cert_path);
         let _ = std::fs::remove_file(key_path);
     }
+
+    #[cfg(feature = "grpc")]
+    #[tokio::test]
+    async fn test_grpc_api() {
+        use crate::grpc::proto;
+        use proto::smser_client::SmserClient;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let grpc_listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let grpc_port = grpc_listener.local_addr().unwrap().port();
+        let inbox = modem::SmsMessage {
+            smstat: modem::SmsStat::Unread,
+            index: 40001,
+            phone: "+441234567890".to_string(),
+            content: "Ping".to_string(),
+            date: "2026-10-16 10:00:00".to_string(),
+            sca: String::new(),
+            save_type: 0,
+            priority: modem::Priority::Normal,
+            sms_type: modem::SmsType::Single,
+        };
+        let mock =
+            Arc::new(client::MockModem::new().with_messages(BoxType::LocalInbox, vec![inbox]));
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let modem = mock.clone();
+        let server_handle = tokio::spawn(async move {
+            let config = ServerConfig {
+                modem,
+                poll_interval: 1,
+                api_keys: HashMap::from([("app".to_string(), "secret".to_string())]),
+                grpc_listener: Some(grpc_listener),
+                ..test_config(String::new())
+            };
+            start_server(listener, rx, config).await;
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let mut client = SmserClient::connect(format!("http://127.0.0.1:{}", grpc_port))
+            .await
+            .unwrap();
+        fn authorized<T>(message: T) -> tonic::Request<T> {
+            let mut request = tonic::Request::new(message);
+            request
+                .metadata_mut()
+                .insert("x-api-key", "secret".parse().unwrap());
+            request
+        }
+
+        // Subscribe before the first poll finds the unread message
+        let mut incoming = client
+            .stream_incoming(authorized(proto::StreamIncomingRequest {}))
+            .await
+            .unwrap()
+            .into_inner();
+
+        let status = client
+            .list_sms(proto::ListSmsRequest::default())
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let response = client
+            .send_sms(authorized(proto::SendSmsRequest {
+                to: vec!["+441234567890".to_string()],
+                message: "Pong".to_string(),
+                sync: true,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.status, "sent");
+        assert!(response.id.is_some());
+        assert_eq!(
+            mock.sent(),
+            [(vec!["+441234567890".to_string()], "Pong".to_string())]
+        );
+
+        let status = client
+            .send_sms(authorized(proto::SendSmsRequest {
+                to: vec!["not a number".to_string()],
+                message: "Pong".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+
+        let messages = client
+            .list_sms(authorized(proto::ListSmsRequest {
+                box_type: "local-inbox".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .messages;
+        assert_eq!(messages[0].content, "Ping");
+        assert!(messages[0].unread);
+
+        let message = tokio::time::timeout(Duration::from_secs(5), incoming.message())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(message.index, 40001);
+        assert_eq!(message.phone, "+441234567890");
+
+        // Shutting down ends the stream
+        tx.send(()).unwrap();
+        let end = tokio::time::timeout(Duration::from_secs(5), incoming.message()).await;
+        assert!(matches!(end, Ok(Ok(None)) | Ok(Err(_))));
+        server_handle.await.unwrap();
+    }
 }
@@ -0,0 +1,249 @@
+use crate::config::SilenceSpec;
+use crate::cron::{Cron, parse_duration};
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Longest allowed recurring silence
+const MAX_RECURRING_DURATION: TimeDelta = TimeDelta::days(7);
+
+#[derive(Clone, Debug)]
+enum Period {
+    Once {
+        starts_at: DateTime<Utc>,
+        ends_at: DateTime<Utc>,
+    },
+    Recurring {
+        cron: Cron,
+        duration: TimeDelta,
+    },
+}
+
+/// A silence suppressing matching alerts
+#[derive(Clone, Debug, Serialize)]
+pub struct Silence {
+    pub id: u64,
+    #[serde(flatten)]
+    pub spec: SilenceSpec,
+    #[serde(skip)]
+    period: Period,
+}
+
+fn parse_time(s: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| format!("Invalid time '{}': {}", s, e))
+}
+
+impl Silence {
+    /// Validates `spec`. One-off silences without a start begin at `now`.
+    pub fn new(id: u64, mut spec: SilenceSpec, now: DateTime<Utc>) -> Result<Self, String> {
+        let period = match (&spec.ends_at, &spec.cron, &spec.duration) {
+            (Some(ends_at), None, None) => {
+                let starts_at = match &spec.starts_at {
+                    Some(s) => parse_time(s)?,
+                    None => now,
+                };
+                let ends_at = parse_time(ends_at)?;
+                if ends_at <= starts_at {
+                    return Err("Silence must end after it starts".to_string());
+                }
+                spec.starts_at = Some(starts_at.to_rfc3339());
+                Period::Once { starts_at, ends_at }
+            }
+            (None, Some(cron), Some(duration)) if spec.starts_at.is_none() => {
+                let duration = parse_duration(duration)?;
+                if duration > MAX_RECURRING_DURATION {
+                    return Err("Recurring silences can last at most 7 days".to_string());
+                }
+                Period::Recurring {
+                    cron: Cron::parse(cron)?,
+                    duration,
+                }
+            }
+            _ => {
+                return Err(
+                    "A silence needs either 'ends_at' (and optionally 'starts_at') or 'cron' and 'duration'"
+                        .to_string(),
+                );
+            }
+        };
+        Ok(Self { id, spec, period })
+    }
+
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        match &self.period {
+            Period::Once { starts_at, ends_at } => *starts_at <= now && now < *ends_at,
+            Period::Recurring { cron, duration } => {
+                cron.fired_within(&now.with_timezone(&Local), *duration)
+            }
+        }
+    }
+
+    fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        matches!(self.period, Period::Once { ends_at, .. } if ends_at <= now)
+    }
+
+    /// Whether all matchers are present with the same value in `labels`
+    pub fn matches(&self, labels: &HashMap<String, String>) -> bool {
+        self.spec
+            .matchers
+            .iter()
+            .all(|(name, value)| labels.get(name) == Some(value))
+    }
+}
+
+/// Configured and ad-hoc silences, shared between requests
+#[derive(Clone, Debug, Default)]
+pub struct Silences {
+    silences: Arc<Mutex<Vec<Silence>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Silences {
+    pub fn from_specs(specs: Vec<SilenceSpec>) -> Result<Self, String> {
+        let silences = Self::default();
+        for spec in specs {
+            silences.add(spec)?;
+        }
+        Ok(silences)
+    }
+
+    pub fn add(&self, spec: SilenceSpec) -> Result<Silence, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let silence = Silence::new(id, spec, Utc::now())?;
+        self.silences.lock().unwrap().push(silence.clone());
+        Ok(silence)
+    }
+
+    /// Removes a silence, returning whether it existed
+    pub fn remove(&self, id: u64) -> bool {
+        let mut silences = self.silences.lock().unwrap();
+        let len = silences.len();
+        silences.retain(|s| s.id != id);
+        silences.len() != len
+    }
+
+    /// All silences that have not yet ended
+    pub fn list(&self) -> Vec<Silence> {
+        let now = Utc::now();
+        let mut silences = self.silences.lock().unwrap();
+        silences.retain(|s| !s.is_expired(now));
+        silences.clone()
+    }
+
+    /// Returns the first silence active at `now` that matches `labels`
+    pub fn find_active(
+        &self,
+        labels: &HashMap<String, String>,
+        now: DateTime<Utc>,
+    ) -> Option<Silence> {
+        self.silences
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|s| s.is_active(now) && s.matches(labels))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_one_off_silence() {
+        let now = Utc::now();
+        let silences = Silences::default();
+        let silence = silences
+            .add(SilenceSpec {
+                matchers: labels(&[("alertname", "DiskFull")]),
+                starts_at: Some(now.to_rfc3339()),
+                ends_at: Some((now + TimeDelta::hours(1)).to_rfc3339()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(silence.id, 1);
+        assert!(silence.spec.starts_at.is_some());
+
+        let disk_full = labels(&[("alertname", "DiskFull"), ("severity", "critical")]);
+        assert!(silences.find_active(&disk_full, now).is_some());
+        assert!(
+            silences
+                .find_active(&labels(&[("alertname", "Other")]), now)
+                .is_none()
+        );
+        assert!(
+            silences
+                .find_active(&disk_full, now + TimeDelta::hours(2))
+                .is_none()
+        );
+
+        assert_eq!(silences.list().len(), 1);
+        assert!(silences.remove(1));
+        assert!(!silences.remove(1));
+        assert!(silences.find_active(&disk_full, now).is_none());
+    }
+
+    #[test]
+    fn test_recurring_silence() {
+        let silence = Silence::new(
+            1,
+            SilenceSpec {
+                cron: Some("* * * * *".to_string()),
+                duration: Some("1m".to_string()),
+                ..Default::default()
+            },
+            Utc::now(),
+        )
+        .unwrap();
+        // Empty matchers silence everything
+        assert!(silence.matches(&labels(&[("alertname", "Any")])));
+        assert!(silence.is_active(Utc::now()));
+        assert!(!silence.is_expired(Utc::now() + TimeDelta::days(365)));
+    }
+
+    #[test]
+    fn test_invalid_silences() {
+        let now = Utc::now();
+        let invalid = [
+            SilenceSpec::default(),
+            SilenceSpec {
+                ends_at: Some("tomorrow".to_string()),
+                ..Default::default()
+            },
+            SilenceSpec {
+                starts_at: Some("2026-10-20T12:00:00Z".to_string()),
+                ends_at: Some("2026-10-20T08:00:00Z".to_string()),
+                ..Default::default()
+            },
+            SilenceSpec {
+                cron: Some("0 2 * * *".to_string()),
+                ..Default::default()
+            },
+            SilenceSpec {
+                cron: Some("0 2 * * *".to_string()),
+                duration: Some("8d".to_string()),
+                ..Default::default()
+            },
+            SilenceSpec {
+                cron: Some("0 2 * * *".to_string()),
+                duration: Some("1h".to_string()),
+                ends_at: Some("2026-10-20T08:00:00Z".to_string()),
+                ..Default::default()
+            },
+        ];
+        for spec in invalid {
+            assert!(Silence::new(1, spec.clone(), now).is_err(), "{:?}", spec);
+        }
+    }
+}
@@ -0,0 +1,651 @@
+pub use crate::args::AuditContent;
+use crate::queue::{Lane, SendStatus};
+use crate::types::SmsMessage;
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use strum_macros::{Display, EnumString};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Display, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Direction {
+    /// Received by the modem
+    In,
+    /// Sent by smser
+    Out,
+}
+
+/// A message stored in the database
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct StoredMessage {
+    pub id: i64,
+    pub direction: Direction,
+    pub phone: String,
+    pub content: String,
+    /// Date as reported by the modem for received messages
+    pub date: Option<String>,
+    /// Index of a received message on the modem
+    pub modem_index: Option<i32>,
+    /// Result of a sent message
+    pub status: Option<SendStatus>,
+    /// Modem error code, if the modem rejected a sent message
+    pub error_code: Option<i32>,
+    /// Unix timestamp in seconds when the message was stored
+    pub recorded_at: i64,
+}
+
+/// Persistent SQLite store of sent and received messages
+#[derive(Clone, Debug)]
+pub struct Store {
+    conn: Arc<Mutex<Connection>>,
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS messages (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    direction TEXT NOT NULL,
+    phone TEXT NOT NULL,
+    content TEXT NOT NULL,
+    date TEXT,
+    modem_index INTEGER,
+    status TEXT,
+    error_code INTEGER,
+    recorded_at INTEGER NOT NULL,
+    UNIQUE (direction, modem_index, phone, date)
+);
+CREATE INDEX IF NOT EXISTS messages_recorded_at ON messages (recorded_at);
+CREATE TABLE IF NOT EXISTS outbox (
+    id INTEGER PRIMARY KEY,
+    recipients TEXT NOT NULL,
+    content TEXT NOT NULL,
+    client TEXT,
+    lane TEXT NOT NULL,
+    flash INTEGER NOT NULL,
+    attempts INTEGER NOT NULL,
+    queued_at INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recorded_at INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    attempt INTEGER NOT NULL,
+    client TEXT,
+    api_key TEXT,
+    recipient TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    content TEXT,
+    status TEXT NOT NULL,
+    error TEXT
+);
+CREATE INDEX IF NOT EXISTS audit_recorded_at ON audit (recorded_at);
+";
+
+/// Columns added after a table was first created, as (table, column, type)
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("outbox", "api_key", "TEXT"),
+    ("outbox", "send_at", "INTEGER"),
+];
+
+/// One attempt to send a message to a recipient, as kept in the audit log
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    /// Unix timestamp in seconds when the attempt finished
+    pub recorded_at: i64,
+    /// ID of the message in the send queue
+    pub message_id: u64,
+    /// 1 for the first attempt, counting up with every retry
+    pub attempt: u32,
+    /// Client name given with the request
+    pub client: Option<String>,
+    /// Name of the API key the request was authenticated with
+    pub api_key: Option<String>,
+    pub recipient: String,
+    /// Hex SHA-256 hash of the message text
+    pub content_hash: String,
+    /// Message text, if the audit log keeps it
+    pub content: Option<String>,
+    pub status: SendStatus,
+    pub error: Option<String>,
+}
+
+/// A send attempt to record with [`Store::record_attempt`]
+#[derive(Clone, Debug)]
+pub struct SendAttempt<'a> {
+    pub message_id: u64,
+    pub attempt: u32,
+    pub client: Option<&'a str>,
+    pub api_key: Option<&'a str>,
+    pub to: &'a [String],
+    pub content: &'a str,
+    pub status: SendStatus,
+    pub error: Option<String>,
+}
+
+/// Filter for [`Store::audit_log`]
+#[derive(Clone, Debug, Default)]
+pub struct AuditFilter {
+    pub client: Option<String>,
+    pub api_key: Option<String>,
+    pub recipient: Option<String>,
+    /// Only attempts recorded at or after this Unix timestamp
+    pub since: Option<i64>,
+    pub limit: u32,
+}
+
+/// A queued outgoing message, kept until it is sent or given up on
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueuedMessage {
+    /// ID of the message in the send queue
+    pub id: u64,
+    pub to: Vec<String>,
+    pub content: String,
+    pub client: Option<String>,
+    /// Name of the API key the message was sent with
+    pub api_key: Option<String>,
+    pub lane: Lane,
+    pub flash: bool,
+    /// Failed send attempts so far
+    pub attempts: u32,
+    /// Unix timestamp in seconds before which the message is not sent
+    pub send_at: Option<u64>,
+}
+
+/// Filter for [`Store::history`]
+#[derive(Clone, Debug, Default)]
+pub struct HistoryFilter {
+    pub direction: Option<Direction>,
+    pub status: Option<SendStatus>,
+    pub limit: u32,
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+impl Store {
+    /// Opens (and if needed creates) the database at `path`.
+    pub fn open(path: &Path) -> Result<Self, rusqlite::Error> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Opens a temporary in-memory database, mainly for tests.
+    pub fn open_in_memory() -> Result<Self, rusqlite::Error> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, rusqlite::Error> {
+        conn.execute_batch(SCHEMA)?;
+        for (table, column, kind) in ADDED_COLUMNS {
+            let exists = conn
+                .prepare(&format!(
+                    "SELECT 1 FROM pragma_table_info('{}') WHERE name = ?1",
+                    table
+                ))?
+                .exists([column])?;
+            if !exists {
+                conn.execute_batch(&format!(
+                    "ALTER TABLE {} ADD COLUMN {} {}",
+                    table, column, kind
+                ))?;
+            }
+        }
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Archives received messages, skipping ones that are already stored.
+    /// Returns the number of newly stored messages.
+    pub fn archive_received(&self, messages: &[SmsMessage]) -> Result<usize, rusqlite::Error> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut inserted = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR IGNORE INTO messages
+                 (direction, phone, content, date, modem_index, recorded_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            let now = unix_now();
+            for msg in messages {
+                inserted += stmt.execute(params![
+                    Direction::In.to_string(),
+                    msg.phone,
+                    msg.content,
+                    msg.date,
+                    msg.index,
+                    now
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(inserted)
+    }
+
+    /// Records the result of a sent message.
+    pub fn record_sent(
+        &self,
+        phone: &str,
+        content: &str,
+        status: SendStatus,
+        error_code: Option<i32>,
+    ) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO messages (direction, phone, content, status, error_code, recorded_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                Direction::Out.to_string(),
+                phone,
+                content,
+                status.to_string(),
+                error_code,
+                unix_now()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Adds a message to the outbox, replacing one with the same ID.
+    pub fn queue(&self, message: &QueuedMessage) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO outbox
+             (id, recipients, content, client, api_key, lane, flash, attempts, queued_at, send_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                message.id as i64,
+                message.to.join(","),
+                message.content,
+                message.client,
+                message.api_key,
+                message.lane.to_string(),
+                message.flash,
+                message.attempts,
+                unix_now(),
+                message.send_at.map(|at| at as i64)
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Updates the number of failed attempts of an outbox message.
+    pub fn set_attempts(&self, id: u64, attempts: u32) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE outbox SET attempts = ?2 WHERE id = ?1",
+            params![id as i64, attempts],
+        )?;
+        Ok(())
+    }
+
+    /// Replaces the recipients of an outbox message, e.g. with those a partly
+    /// failed send did not reach.
+    pub fn set_recipients(&self, id: u64, to: &[String]) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE outbox SET recipients = ?2 WHERE id = ?1",
+            params![id as i64, to.join(",")],
+        )?;
+        Ok(())
+    }
+
+    /// Removes a message that was sent or given up on from the outbox.
+    pub fn dequeue(&self, id: u64) -> Result<(), rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM outbox WHERE id = ?1", params![id as i64])?;
+        Ok(())
+    }
+
+    /// Messages left in the outbox, oldest first.
+    pub fn queued(&self) -> Result<Vec<QueuedMessage>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, recipients, content, client, api_key, lane, flash, attempts, send_at
+             FROM outbox ORDER BY id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            let recipients: String = row.get(1)?;
+            let lane: String = row.get(5)?;
+            Ok(QueuedMessage {
+                id: id as u64,
+                to: recipients.split(',').map(String::from).collect(),
+                content: row.get(2)?,
+                client: row.get(3)?,
+                api_key: row.get(4)?,
+                lane: lane.parse().unwrap_or_default(),
+                flash: row.get(6)?,
+                attempts: row.get(7)?,
+                send_at: row.get::<_, Option<i64>>(8)?.map(|at| at as u64),
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Appends a send attempt to the audit log, one entry per recipient.
+    /// The message text is kept as `content` says.
+    pub fn record_attempt(
+        &self,
+        attempt: &SendAttempt,
+        content: AuditContent,
+    ) -> Result<(), rusqlite::Error> {
+        let hash: String = Sha256::digest(attempt.content.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        let text = (content == AuditContent::Full).then_some(attempt.content);
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO audit
+                 (recorded_at, message_id, attempt, client, api_key, recipient,
+                  content_hash, content, status, error)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            )?;
+            let now = unix_now();
+            for to in attempt.to {
+                stmt.execute(params![
+                    now,
+                    attempt.message_id as i64,
+                    attempt.attempt,
+                    attempt.client,
+                    attempt.api_key,
+                    to,
+                    hash,
+                    text,
+                    attempt.status.to_string(),
+                    attempt.error
+                ])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Returns up to `filter.limit` audit log entries matching the filter, newest first.
+    pub fn audit_log(&self, filter: &AuditFilter) -> Result<Vec<AuditEntry>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, recorded_at, message_id, attempt, client, api_key, recipient,
+                    content_hash, content, status, error
+             FROM audit
+             WHERE (?1 IS NULL OR client = ?1) AND (?2 IS NULL OR api_key = ?2)
+               AND (?3 IS NULL OR recipient = ?3) AND (?4 IS NULL OR recorded_at >= ?4)
+             ORDER BY id DESC LIMIT ?5",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                filter.client,
+                filter.api_key,
+                filter.recipient,
+                filter.since,
+                filter.limit
+            ],
+            |row| {
+                let message_id: i64 = row.get(2)?;
+                let status: String = row.get(9)?;
+                Ok(AuditEntry {
+                    id: row.get(0)?,
+                    recorded_at: row.get(1)?,
+                    message_id: message_id as u64,
+                    attempt: row.get(3)?,
+                    client: row.get(4)?,
+                    api_key: row.get(5)?,
+                    recipient: row.get(6)?,
+                    content_hash: row.get(7)?,
+                    content: row.get(8)?,
+                    status: status.parse().unwrap_or(SendStatus::Failed),
+                    error: row.get(10)?,
+                })
+            },
+        )?;
+        rows.collect()
+    }
+
+    /// Returns up to `filter.limit` stored messages matching the filter, newest first.
+    pub fn history(&self, filter: &HistoryFilter) -> Result<Vec<StoredMessage>, rusqlite::Error> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, direction, phone, content, date, modem_index, status, error_code, recorded_at
+             FROM messages
+             WHERE (?1 IS NULL OR direction = ?1) AND (?2 IS NULL OR status = ?2)
+             ORDER BY id DESC LIMIT ?3",
+        )?;
+        let direction = filter.direction.map(|d| d.to_string());
+        let status = filter.status.map(|s| s.to_string());
+        let rows = stmt.query_map(params![direction, status, filter.limit], |row| {
+            let direction: String = row.get(1)?;
+            let status: Option<String> = row.get(6)?;
+            Ok(StoredMessage {
+                id: row.get(0)?,
+                direction: direction.parse().unwrap_or(Direction::In),
+                phone: row.get(2)?,
+                content: row.get(3)?,
+                date: row.get(4)?,
+                modem_index: row.get(5)?,
+                status: status.and_then(|s| s.parse().ok()),
+                error_code: row.get(7)?,
+                recorded_at: row.get(8)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Priority, SmsStat, SmsType};
+
+    fn message(index: i32, content: &str) -> SmsMessage {
+        SmsMessage {
+            smstat: SmsStat::Unread,
+            index,
+            phone: "+441234567890".to_string(),
+            content: content.to_string(),
+            date: format!("2025-01-01 12:00:{:02}", index),
+            sca: String::new(),
+            save_type: 0,
+            priority: Priority::Normal,
+            sms_type: SmsType::Single,
+        }
+    }
+
+    #[test]
+    fn test_archive_received_dedupes() {
+        let store = Store::open_in_memory().unwrap();
+        let batch = vec![message(1, "first"), message(2, "second")];
+        assert_eq!(store.archive_received(&batch).unwrap(), 2);
+        // Polling again returns the same messages plus a new one
+        let batch = vec![
+            message(1, "first"),
+            message(2, "second"),
+            message(3, "third"),
+        ];
+        assert_eq!(store.archive_received(&batch).unwrap(), 1);
+
+        let all = HistoryFilter {
+            limit: 10,
+            ..Default::default()
+        };
+        let stored = store.history(&all).unwrap();
+        assert_eq!(stored.len(), 3);
+        assert_eq!(stored[0].content, "third");
+        assert_eq!(stored[0].direction, Direction::In);
+        assert_eq!(stored[0].modem_index, Some(3));
+        let one = HistoryFilter { limit: 1, ..all };
+        assert_eq!(store.history(&one).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_history_filter() {
+        let store = Store::open_in_memory().unwrap();
+        store.archive_received(&[message(1, "incoming")]).unwrap();
+        store
+            .record_sent("+441234567890", "ok", SendStatus::Sent, None)
+            .unwrap();
+        store
+            .record_sent(
+                "+441234567890",
+                "rejected",
+                SendStatus::Failed,
+                Some(113004),
+            )
+            .unwrap();
+
+        let failed = store
+            .history(&HistoryFilter {
+                direction: Some(Direction::Out),
+                status: Some(SendStatus::Failed),
+                limit: 100,
+            })
+            .unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].content, "rejected");
+        assert_eq!(failed[0].error_code, Some(113004));
+        assert_eq!(failed[0].date, None);
+
+        let outgoing = store
+            .history(&HistoryFilter {
+                direction: Some(Direction::Out),
+                status: None,
+                limit: 100,
+            })
+            .unwrap();
+        assert_eq!(outgoing.len(), 2);
+
+        let incoming = store
+            .history(&HistoryFilter {
+                direction: Some(Direction::In),
+                status: None,
+                limit: 100,
+            })
+            .unwrap();
+        assert_eq!(incoming.len(), 1);
+        assert_eq!(incoming[0].status, None);
+    }
+
+    #[test]
+    fn test_outbox() {
+        let store = Store::open_in_memory().unwrap();
+        let queued = |id, to: &[&str]| QueuedMessage {
+            id,
+            to: to.iter().map(|s| s.to_string()).collect(),
+            content: "Disk full".to_string(),
+            client: Some("grafana".to_string()),
+            api_key: Some("ops".to_string()),
+            lane: Lane::High,
+            flash: true,
+            attempts: 0,
+            send_at: None,
+        };
+        store.queue(&queued(7, &["+441234567890"])).unwrap();
+        store
+            .queue(&queued(3, &["+441234567891", "+441234567892"]))
+            .unwrap();
+        store.set_attempts(7, 2).unwrap();
+
+        let outbox = store.queued().unwrap();
+        assert_eq!(outbox.len(), 2);
+        assert_eq!(outbox[0], queued(3, &["+441234567891", "+441234567892"]));
+        assert_eq!(outbox[1].attempts, 2);
+
+        store.dequeue(3).unwrap();
+        assert_eq!(store.queued().unwrap().len(), 1);
+
+        let deferred = QueuedMessage {
+            send_at: Some(1_700_000_000),
+            ..queued(8, &["+441234567890"])
+        };
+        store.queue(&deferred).unwrap();
+        assert_eq!(store.queued().unwrap()[1], deferred);
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let store = Store::open_in_memory().unwrap();
+        let to = vec!["+441234567890".to_string(), "+441234567891".to_string()];
+        let attempt = SendAttempt {
+            message_id: 4,
+            attempt: 1,
+            client: Some("grafana"),
+            api_key: Some("ops"),
+            to: &to,
+            content: "Disk full",
+            status: SendStatus::Failed,
+            error: Some("modem busy".to_string()),
+        };
+        store.record_attempt(&attempt, AuditContent::Hash).unwrap();
+        store
+            .record_attempt(
+                &SendAttempt {
+                    attempt: 2,
+                    status: SendStatus::Sent,
+                    error: None,
+                    ..attempt.clone()
+                },
+                AuditContent::Full,
+            )
+            .unwrap();
+
+        let all = store
+            .audit_log(&AuditFilter {
+                limit: 10,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(all.len(), 4);
+        assert_eq!(all[0].attempt, 2);
+        assert_eq!(all[0].status, SendStatus::Sent);
+        assert_eq!(all[0].content.as_deref(), Some("Disk full"));
+        assert_eq!(all[3].content, None);
+        assert_eq!(all[3].error.as_deref(), Some("modem busy"));
+        assert_eq!(all[0].content_hash, all[3].content_hash);
+        assert_eq!(all[0].content_hash.len(), 64);
+
+        let filtered = store
+            .audit_log(&AuditFilter {
+                recipient: Some("+441234567891".to_string()),
+                api_key: Some("ops".to_string()),
+                limit: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].recipient, "+441234567891");
+        assert!(
+            store
+                .audit_log(&AuditFilter {
+                    client: Some("other".to_string()),
+                    limit: 10,
+                    ..Default::default()
+                })
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_migrate_outbox() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE outbox (
+                id INTEGER PRIMARY KEY, recipients TEXT NOT NULL, content TEXT NOT NULL,
+                client TEXT, lane TEXT NOT NULL, flash INTEGER NOT NULL,
+                attempts INTEGER NOT NULL, queued_at INTEGER NOT NULL
+            )",
+        )
+        .unwrap();
+        let store = Store::init(conn).unwrap();
+        assert!(store.queued().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+/// Parses a variable in "key=value" format
+pub fn parse_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("Invalid variable '{}'. Expected 'key=value'", s)),
+    }
+}
+
+/// Returns the names of all `{{name}}` placeholders in a template, in order
+pub fn placeholders(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                names.push(after[..end].trim());
+                rest = &after[end + 2..];
+            }
+            None => break,
+        }
+    }
+    names
+}
+
+/// Renders a template, replacing every `{{name}}` with its variable.
+/// Fails if any placeholder has no value.
+pub fn render(template: &str, vars: &HashMap<String, String>) -> Result<String, String> {
+    let missing: Vec<&str> = placeholders(template)
+        .into_iter()
+        .filter(|name| !vars.contains_key(*name))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "Missing template variables: {}",
+            missing.join(", ")
+        ));
+    }
+
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else { break };
+        out.push_str(&rest[..start]);
+        out.push_str(&vars[after[..end].trim()]);
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_var() {
+        assert_eq!(
+            parse_var("host=db1").unwrap(),
+            ("host".to_string(), "db1".to_string())
+        );
+        assert_eq!(parse_var("eq=a=b").unwrap().1, "a=b");
+        assert!(parse_var("novalue").is_err());
+        assert!(parse_var("=x").is_err());
+    }
+
+    #[test]
+    fn test_render() {
+        let vars = HashMap::from([
+            ("host".to_string(), "db1".to_string()),
+            ("pct".to_string(), "92".to_string()),
+        ]);
+        assert_eq!(
+            render("Disk on {{host}} at {{ pct }}%", &vars).unwrap(),
+            "Disk on db1 at 92%"
+        );
+        assert_eq!(render("No vars", &vars).unwrap(), "No vars");
+        assert_eq!(
+            render("{{host}} {{user}} {{ttl}}", &vars).unwrap_err(),
+            "Missing template variables: user, ttl"
+        );
+    }
+}
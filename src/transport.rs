@@ -0,0 +1,515 @@
+//! Sending SMS through named transports: the modem at `--modem-url`, other
+//! HiLink modems, or an SMS provider's HTTP API. Routes from the config file
+//! pick the transport by the recipient's number prefix. The server polls for
+//! received messages through the transport of the modem at `--modem-url`.
+
+use crate::client::{HttpModem, ModemClient, ModemFuture, SessionCache};
+use crate::config::{RouteConfig, TransportConfig};
+use crate::modem::{BoxType, Credentials, Error, SmsListParams, SmsListResponse, SortType};
+use crate::template;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+/// A send that failed for some or all recipients
+#[derive(Debug)]
+pub struct SendError {
+    pub error: Error,
+    /// Recipients that did not get the message; the others did
+    pub failed: Vec<String>,
+}
+
+impl SendError {
+    /// An error for all of `to`
+    fn all(error: Error, to: &[String]) -> Self {
+        Self {
+            error,
+            failed: to.to_vec(),
+        }
+    }
+}
+
+/// Future returned by [`Transport::send`]
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<String, SendError>> + Send + 'a>>;
+
+/// Something that can send SMS
+pub trait Transport: Send + Sync {
+    /// Sends `message` to every number in `to`, as a flash SMS if `flash` is
+    /// set and supported. Returns a label for what sent it, e.g. the modem's URL.
+    fn send<'a>(&'a self, to: &'a [String], message: &'a str, flash: bool) -> SendFuture<'a>;
+
+    /// Lists up to `count` received messages, unread ones first and then
+    /// newest first. With `skip_if_read`, returns `None` without listing if no
+    /// message is unread.
+    fn receive(&self, count: u32, skip_if_read: bool) -> ModemFuture<'_, Option<SmsListResponse>> {
+        let _ = (count, skip_if_read);
+        Box::pin(async {
+            Err(Error::Other(
+                "This transport cannot receive messages".to_string(),
+            ))
+        })
+    }
+
+    /// Marks the received messages with `indexes` read
+    fn mark_read<'a>(&'a self, indexes: &'a [i32]) -> ModemFuture<'a, ()> {
+        let _ = indexes;
+        Box::pin(async {
+            Err(Error::Other(
+                "This transport cannot receive messages".to_string(),
+            ))
+        })
+    }
+}
+
+/// Transport over a HiLink modem. The HiLink API has no message class, so
+/// flash SMS are sent as normal ones.
+pub struct Hilink(pub Arc<dyn ModemClient>);
+
+impl Transport for Hilink {
+    fn send<'a>(&'a self, to: &'a [String], message: &'a str, flash: bool) -> SendFuture<'a> {
+        Box::pin(async move {
+            if flash {
+                warn!(
+                    "Modem {} cannot send flash SMS, sending a normal SMS",
+                    self.0.url()
+                );
+            }
+            // One request for all recipients, so it fails for all of them
+            let session = self
+                .0
+                .get_session()
+                .await
+                .map_err(|e| SendError::all(e, to))?;
+            self.0
+                .send_sms(&session, to, message)
+                .await
+                .map_err(|e| SendError::all(e, to))
+        })
+    }
+
+    /// Asks the cheap check-notifications API first with `skip_if_read`
+    fn receive(&self, count: u32, skip_if_read: bool) -> ModemFuture<'_, Option<SmsListResponse>> {
+        Box::pin(async move {
+            let session = self.0.get_session().await?;
+            if skip_if_read && self.0.check_notifications(&session).await?.unread_message == 0 {
+                return Ok(None);
+            }
+            let params = SmsListParams {
+                box_type: BoxType::LocalInbox,
+                sort_type: SortType::Date,
+                read_count: count,
+                ascending: false,
+                unread_preferred: true,
+            };
+            Ok(Some(self.0.get_sms_list(&session, params, 1).await?))
+        })
+    }
+
+    fn mark_read<'a>(&'a self, indexes: &'a [i32]) -> ModemFuture<'a, ()> {
+        Box::pin(async move {
+            let session = self.0.get_session().await?;
+            self.0.mark_read(&session, indexes).await
+        })
+    }
+}
+
+/// Transport posting each message as JSON to an SMS provider's API, one
+/// request per recipient. Body fields are templates with the variables `to`,
+/// `message` and `flash` (`true` or `false`).
+pub struct HttpProvider {
+    name: String,
+    url: String,
+    fields: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    client: reqwest::Client,
+}
+
+impl HttpProvider {
+    pub fn new(
+        name: String,
+        url: String,
+        fields: HashMap<String, String>,
+        headers: HashMap<String, String>,
+    ) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()?;
+        Ok(Self {
+            name,
+            url,
+            fields,
+            headers,
+            client,
+        })
+    }
+
+    /// JSON body for one recipient
+    fn body(&self, to: &str, message: &str, flash: bool) -> Result<serde_json::Value, Error> {
+        let vars = HashMap::from([
+            ("to".to_string(), to.to_string()),
+            ("message".to_string(), message.to_string()),
+            ("flash".to_string(), flash.to_string()),
+        ]);
+        let mut body = serde_json::Map::new();
+        for (name, value) in &self.fields {
+            let value = template::render(value, &vars).map_err(Error::Other)?;
+            body.insert(name.clone(), value.into());
+        }
+        Ok(body.into())
+    }
+
+    /// Posts the message for one recipient
+    async fn send_one(&self, to: &str, message: &str, flash: bool) -> Result<(), Error> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .json(&self.body(to, message, flash)?);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().await.unwrap_or_default();
+            return Err(Error::Other(format!(
+                "{} returned {}: {}",
+                self.url, status, text
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Transport for HttpProvider {
+    /// Tries every recipient; a failed request does not stop the others
+    fn send<'a>(&'a self, to: &'a [String], message: &'a str, flash: bool) -> SendFuture<'a> {
+        Box::pin(async move {
+            let mut result: Option<SendError> = None;
+            for phone in to {
+                if let Err(error) = self.send_one(phone, message, flash).await {
+                    result
+                        .get_or_insert(SendError {
+                            error,
+                            failed: Vec::new(),
+                        })
+                        .failed
+                        .push(phone.clone());
+                }
+            }
+            match result {
+                Some(e) => Err(e),
+                None => Ok(self.name.clone()),
+            }
+        })
+    }
+}
+
+/// Named transports and the routes choosing between them. Numbers without a
+/// matching route go to the default transport passed to [`Routes::send`].
+#[derive(Clone, Default)]
+pub struct Routes {
+    transports: HashMap<String, Arc<dyn Transport>>,
+    routes: Vec<RouteConfig>,
+}
+
+impl Routes {
+    /// Routes between `transports`, e.g. custom [`Transport`] implementations
+    pub fn new(transports: HashMap<String, Arc<dyn Transport>>, routes: Vec<RouteConfig>) -> Self {
+        Self { transports, routes }
+    }
+
+    pub fn from_config(
+        transports: &HashMap<String, TransportConfig>,
+        routes: &[RouteConfig],
+    ) -> Result<Self, String> {
+        let transports = transports
+            .iter()
+            .map(|(name, config)| Ok((name.clone(), build_transport(name, config)?)))
+            .collect::<Result<HashMap<_, _>, String>>()?;
+        if let Some(route) = routes
+            .iter()
+            .find(|route| !transports.contains_key(&route.transport))
+        {
+            return Err(format!(
+                "Route for '{}' uses unknown transport '{}'",
+                route.prefix, route.transport
+            ));
+        }
+        Ok(Self::new(transports, routes.to_vec()))
+    }
+
+    /// Name of the transport for `phone`, `None` for the default
+    pub fn route(&self, phone: &str) -> Option<&str> {
+        self.routes
+            .iter()
+            .find(|route| phone.starts_with(&route.prefix))
+            .map(|route| route.transport.as_str())
+    }
+
+    /// Sends `message` to `to`, one request per transport. All transports are
+    /// tried, so some recipients may get the message even if others fail.
+    pub async fn send(
+        &self,
+        default: &dyn Transport,
+        to: &[String],
+        message: &str,
+        flash: bool,
+    ) -> SendReport {
+        let mut groups: Vec<(Option<&str>, Vec<String>)> = Vec::new();
+        for phone in to {
+            let name = self.route(phone);
+            match groups.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, phones)) => phones.push(phone.clone()),
+                None => groups.push((name, vec![phone.clone()])),
+            }
+        }
+        let mut report = SendReport::default();
+        for (name, phones) in groups {
+            let transport = match name {
+                Some(name) => self.transports[name].as_ref(),
+                None => default,
+            };
+            match transport.send(&phones, message, flash).await {
+                Ok(label) => report.sent.push((label, phones.len())),
+                Err(e) => {
+                    // Only configured transports send per recipient, labelled
+                    // by their name
+                    let delivered = phones.len() - e.failed.len();
+                    if let Some(name) = name
+                        && delivered > 0
+                    {
+                        report.sent.push((name.to_string(), delivered));
+                    }
+                    match &mut report.failed {
+                        Some(failed) => failed.failed.extend(e.failed),
+                        None => report.failed = Some(e),
+                    }
+                }
+            }
+        }
+        report
+    }
+}
+
+/// Outcome of [`Routes::send`]
+#[derive(Debug, Default)]
+pub struct SendReport {
+    /// Label of each transport that sent the message, with its number of recipients
+    pub sent: Vec<(String, usize)>,
+    /// The first transport's error, with every recipient that did not get the message
+    pub failed: Option<SendError>,
+}
+
+fn build_transport(name: &str, config: &TransportConfig) -> Result<Arc<dyn Transport>, String> {
+    Ok(match config {
+        TransportConfig::Hilink {
+            modem_url,
+            username,
+            password,
+            device_profile,
+        } => {
+            let credentials = password.as_ref().map(|password| Credentials {
+                username: username.clone(),
+                password: password.clone(),
+            });
+            let profile = match device_profile {
+                Some(profile) => profile.parse().map_err(|_| {
+                    format!("Transport '{}': unknown device_profile '{}'", name, profile)
+                })?,
+                None => Default::default(),
+            };
+            let modem = HttpModem::new(modem_url.clone(), credentials).with_profile(profile);
+            Arc::new(Hilink(Arc::new(SessionCache::new(modem))))
+        }
+        TransportConfig::HttpProvider {
+            url,
+            fields,
+            headers,
+        } => Arc::new(
+            HttpProvider::new(
+                name.to_string(),
+                url.clone(),
+                fields.clone(),
+                headers.clone(),
+            )
+            .map_err(|e| format!("Transport '{}': {}", name, e))?,
+        ),
+        TransportConfig::Serial | TransportConfig::Smpp => {
+            return Err(format!("Transport '{}': unsupported transport kind", name));
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockModem;
+
+    #[tokio::test]
+    async fn test_routes_send() {
+        let default = Arc::new(MockModem::new().with_url("http://default"));
+        let other = Arc::new(MockModem::new().with_url("http://other"));
+        let routes = Routes::new(
+            HashMap::from([(
+                "us".to_string(),
+                Arc::new(Hilink(other.clone())) as Arc<dyn Transport>,
+            )]),
+            vec![RouteConfig {
+                prefix: "+1".to_string(),
+                transport: "us".to_string(),
+            }],
+        );
+        assert_eq!(routes.route("+15551234567"), Some("us"));
+        assert_eq!(routes.route("+441234567890"), None);
+
+        let to = ["+441234567890", "+15551234567", "+491761234567"].map(String::from);
+        let report = routes
+            .send(&Hilink(default.clone()), &to, "Hi", false)
+            .await;
+        assert!(report.failed.is_none());
+        assert_eq!(
+            report.sent,
+            [
+                ("http://default".to_string(), 2),
+                ("http://other".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            default.sent(),
+            [(
+                vec!["+441234567890".to_string(), "+491761234567".to_string()],
+                "Hi".to_string()
+            )]
+        );
+        assert_eq!(
+            other.sent(),
+            [(vec!["+15551234567".to_string()], "Hi".to_string())]
+        );
+
+        // A failing transport does not stop the others
+        other.fail_with(Some(113004));
+        let report = routes
+            .send(&Hilink(default.clone()), &to, "Again", false)
+            .await;
+        assert_eq!(report.sent, [("http://default".to_string(), 2)]);
+        let failed = report.failed.unwrap();
+        assert_eq!(failed.error.code(), Some(113004));
+        assert_eq!(failed.failed, ["+15551234567"]);
+        assert_eq!(default.sent().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_receive() {
+        use crate::modem::{Priority, SmsMessage, SmsStat, SmsType};
+        let message = |index: i32, smstat: SmsStat| SmsMessage {
+            smstat,
+            index,
+            phone: "+441234567890".to_string(),
+            content: "Hi".to_string(),
+            date: "2026-10-16 10:00:00".to_string(),
+            sca: String::new(),
+            save_type: 0,
+            priority: Priority::Normal,
+            sms_type: SmsType::Single,
+        };
+        let modem = Arc::new(
+            MockModem::new()
+                .with_messages(BoxType::LocalInbox, vec![message(40001, SmsStat::Read)]),
+        );
+        let hilink = Hilink(modem.clone());
+        // Nothing unread, so the inbox is not listed
+        assert!(hilink.receive(20, true).await.unwrap().is_none());
+        let inbox = hilink.receive(20, false).await.unwrap().unwrap();
+        assert_eq!(inbox.messages.message, [message(40001, SmsStat::Read)]);
+
+        let modem = Arc::new(
+            MockModem::new()
+                .with_messages(BoxType::LocalInbox, vec![message(40002, SmsStat::Unread)]),
+        );
+        let hilink = Hilink(modem.clone());
+        assert_eq!(hilink.receive(20, true).await.unwrap().unwrap().count, 1);
+        hilink.mark_read(&[40002]).await.unwrap();
+        assert_eq!(modem.messages(BoxType::LocalInbox)[0].smstat, SmsStat::Read);
+
+        // Providers only send
+        let provider = HttpProvider::new(
+            "provider".to_string(),
+            "http://127.0.0.1:9/send".to_string(),
+            HashMap::new(),
+            HashMap::new(),
+        )
+        .unwrap();
+        assert!(provider.receive(20, false).await.is_err());
+    }
+
+    #[test]
+    fn test_routes_unknown_transport() {
+        let routes = [RouteConfig {
+            prefix: "+1".to_string(),
+            transport: "missing".to_string(),
+        }];
+        assert!(Routes::from_config(&HashMap::new(), &routes).is_err());
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_http_provider_partial_failure() {
+        use axum::{Json, Router, http::StatusCode, routing::post};
+        let received = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let api = Router::new().route(
+            "/send",
+            post({
+                let received = received.clone();
+                move |Json(body): Json<serde_json::Value>| async move {
+                    let to = body["to"].as_str().unwrap_or_default().to_string();
+                    received.lock().unwrap().push(to.clone());
+                    if to.starts_with("+49") {
+                        StatusCode::BAD_GATEWAY
+                    } else {
+                        StatusCode::OK
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, api).await.unwrap() });
+
+        let provider = HttpProvider::new(
+            "provider".to_string(),
+            format!("http://127.0.0.1:{}/send", port),
+            HashMap::from([("to".to_string(), "{{to}}".to_string())]),
+            HashMap::new(),
+        )
+        .unwrap();
+        let to = ["+491761234567", "+441234567890"].map(String::from);
+        let err = provider.send(&to, "Hi", false).await.unwrap_err();
+        assert!(err.error.to_string().contains("502"));
+        assert_eq!(err.failed, ["+491761234567"]);
+        // The recipient after the failed one is still tried
+        assert_eq!(*received.lock().unwrap(), to);
+    }
+
+    #[test]
+    fn test_http_provider_body() {
+        let provider = HttpProvider::new(
+            "provider".to_string(),
+            "http://localhost".to_string(),
+            HashMap::from([
+                ("to".to_string(), "{{to}}".to_string()),
+                ("text".to_string(), "smser: {{message}}".to_string()),
+                ("flash".to_string(), "{{flash}}".to_string()),
+            ]),
+            HashMap::new(),
+        )
+        .unwrap();
+        let body = provider.body("+15551234567", "say \"hi\"", true).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"to": "+15551234567", "text": "smser: say \"hi\"", "flash": "true"})
+        );
+    }
+}
@@ -0,0 +1,485 @@
+//! Interactive terminal inbox (`smser tui`): browse and read the messages on
+//! the modem, compose new ones, reply and delete.
+
+use crate::client::ModemClient;
+use crate::encoding;
+use crate::modem::{BoxType, Error, SmsListParams, SortType};
+use crate::types::{SmsMessage, SmsStat};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+/// Number of inbox messages fetched, newest first
+const INBOX_SIZE: u32 = 50;
+
+#[derive(Debug, PartialEq)]
+enum Mode {
+    List,
+    View,
+    Compose(Draft),
+    /// Asking whether to delete the selected message
+    ConfirmDelete,
+}
+
+/// A message being written
+#[derive(Debug, Default, PartialEq)]
+struct Draft {
+    to: String,
+    text: String,
+    /// Whether keys go to the recipient rather than the text
+    editing_to: bool,
+}
+
+/// Modem calls requested by a key press
+#[derive(Debug, PartialEq)]
+enum Action {
+    Refresh,
+    Send { to: String, text: String },
+    Delete(i32),
+    Quit,
+}
+
+struct App {
+    messages: Vec<SmsMessage>,
+    list: ListState,
+    mode: Mode,
+    /// Result of the last action, shown at the bottom
+    status: String,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            messages: Vec::new(),
+            list: ListState::default(),
+            mode: Mode::List,
+            status: String::new(),
+        }
+    }
+
+    fn selected(&self) -> Option<&SmsMessage> {
+        self.messages.get(self.list.selected()?)
+    }
+
+    fn compose(&mut self, to: Option<String>) {
+        self.mode = Mode::Compose(Draft {
+            editing_to: to.is_none(),
+            to: to.unwrap_or_default(),
+            text: String::new(),
+        });
+    }
+
+    /// Updates the screen state for `key`, returning the modem call it asks for
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Action> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl && key.code == KeyCode::Char('c') {
+            return Some(Action::Quit);
+        }
+        match &mut self.mode {
+            Mode::Compose(draft) => {
+                match key.code {
+                    KeyCode::Esc => self.mode = Mode::List,
+                    KeyCode::Char('s') if ctrl => {
+                        return Some(Action::Send {
+                            to: draft.to.trim().to_string(),
+                            text: draft.text.clone(),
+                        });
+                    }
+                    KeyCode::Tab | KeyCode::BackTab => draft.editing_to = !draft.editing_to,
+                    KeyCode::Enter if draft.editing_to => draft.editing_to = false,
+                    KeyCode::Enter => draft.text.push('\n'),
+                    KeyCode::Backspace if draft.editing_to => {
+                        draft.to.pop();
+                    }
+                    KeyCode::Backspace => {
+                        draft.text.pop();
+                    }
+                    KeyCode::Char(c) if draft.editing_to => draft.to.push(c),
+                    KeyCode::Char(c) => draft.text.push(c),
+                    _ => {}
+                }
+                None
+            }
+            Mode::ConfirmDelete => {
+                self.mode = Mode::List;
+                match (key.code, self.selected()) {
+                    (KeyCode::Char('y'), Some(message)) => Some(Action::Delete(message.index)),
+                    _ => {
+                        self.status = "Not deleted".to_string();
+                        None
+                    }
+                }
+            }
+            Mode::List | Mode::View => {
+                match key.code {
+                    KeyCode::Char('q') if self.mode == Mode::List => return Some(Action::Quit),
+                    KeyCode::Esc | KeyCode::Char('q') => self.mode = Mode::List,
+                    KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+                    KeyCode::Down | KeyCode::Char('j')
+                        if self
+                            .list
+                            .selected()
+                            .is_some_and(|i| i + 1 < self.messages.len()) =>
+                    {
+                        self.list.select_next();
+                    }
+                    KeyCode::Enter if self.selected().is_some() => self.mode = Mode::View,
+                    KeyCode::Char('n') => self.compose(None),
+                    KeyCode::Char('r') => {
+                        if let Some(message) = self.selected() {
+                            let to = message.phone.clone();
+                            self.compose(Some(to));
+                        }
+                    }
+                    KeyCode::Char('d') | KeyCode::Delete if self.selected().is_some() => {
+                        self.mode = Mode::ConfirmDelete;
+                    }
+                    KeyCode::Char('R') | KeyCode::F(5) => return Some(Action::Refresh),
+                    _ => {}
+                }
+                None
+            }
+        }
+    }
+
+    /// Runs `action` against the modem, reporting the outcome in the status line
+    async fn perform(&mut self, modem: &dyn ModemClient, action: Action) {
+        match action {
+            Action::Refresh => match fetch_inbox(modem).await {
+                Ok(messages) => {
+                    self.status = format!("{} message(s) in the inbox", messages.len());
+                    self.messages = messages;
+                    let selected = match self.list.selected() {
+                        _ if self.messages.is_empty() => None,
+                        Some(i) => Some(i.min(self.messages.len() - 1)),
+                        None => Some(0),
+                    };
+                    self.list.select(selected);
+                }
+                Err(e) => self.status = format!("Error fetching messages: {}", e),
+            },
+            Action::Send { to, text } => {
+                if to.is_empty() || text.is_empty() {
+                    self.status = "Enter a recipient and a message".to_string();
+                    return;
+                }
+                let result = async {
+                    let session = modem.get_session().await?;
+                    modem
+                        .send_sms(&session, std::slice::from_ref(&to), &text)
+                        .await
+                }
+                .await;
+                match result {
+                    Ok(_) => {
+                        self.status = format!("Sent to {}", to);
+                        self.mode = Mode::List;
+                    }
+                    // Keep the draft so it can be sent again
+                    Err(e) => self.status = format!("Error sending: {}", e),
+                }
+            }
+            Action::Delete(index) => {
+                let result = async {
+                    let session = modem.get_session().await?;
+                    modem.delete_sms(&session, &[index]).await
+                }
+                .await;
+                match result {
+                    Ok(()) => {
+                        self.messages.retain(|m| m.index != index);
+                        if self.messages.is_empty() {
+                            self.list.select(None);
+                        } else if let Some(i) = self.list.selected() {
+                            self.list.select(Some(i.min(self.messages.len() - 1)));
+                        }
+                        self.status = "Deleted".to_string();
+                    }
+                    Err(e) => self.status = format!("Error deleting: {}", e),
+                }
+            }
+            Action::Quit => {}
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help, status] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let keys = match &self.mode {
+            Mode::List => "↑/↓ select  Enter read  n new  r reply  d delete  R refresh  q quit",
+            Mode::View => "↑/↓ previous/next  r reply  d delete  Esc back",
+            Mode::Compose(_) => "Tab switch field  Ctrl+S send  Esc cancel",
+            Mode::ConfirmDelete => "Delete this message? y/N",
+        };
+        frame.render_widget(
+            Paragraph::new(keys).style(Style::new().add_modifier(Modifier::REVERSED)),
+            help,
+        );
+        frame.render_widget(Paragraph::new(self.status.as_str()), status);
+
+        match &self.mode {
+            Mode::List | Mode::ConfirmDelete => {
+                let items: Vec<ListItem> = self
+                    .messages
+                    .iter()
+                    .map(|m| {
+                        let style = if m.smstat == SmsStat::Unread {
+                            Style::new().add_modifier(Modifier::BOLD)
+                        } else {
+                            Style::new()
+                        };
+                        let preview = m.content.lines().next().unwrap_or_default();
+                        ListItem::new(Line::from(vec![
+                            Span::raw(format!("{}  {:<16} ", m.date, m.phone)),
+                            Span::raw(preview.to_string()),
+                        ]))
+                        .style(style)
+                    })
+                    .collect();
+                let list = List::new(items)
+                    .block(Block::bordered().title(" Inbox "))
+                    .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+                frame.render_stateful_widget(list, main, &mut self.list);
+            }
+            Mode::View => {
+                let Some(message) = self.selected() else {
+                    return;
+                };
+                let mut lines = vec![
+                    Line::from(format!("From: {}", message.phone)),
+                    Line::from(format!("Date: {}", message.date)),
+                    Line::default(),
+                ];
+                lines.extend(message.content.lines().map(|l| Line::from(l.to_string())));
+                frame.render_widget(
+                    Paragraph::new(lines)
+                        .block(Block::bordered().title(" Message "))
+                        .wrap(Wrap { trim: false }),
+                    main,
+                );
+            }
+            Mode::Compose(draft) => {
+                let [to, text] =
+                    Layout::vertical([Constraint::Length(3), Constraint::Min(3)]).areas(main);
+                let focused = Style::new().add_modifier(Modifier::BOLD);
+                let estimate = encoding::estimate(&draft.text);
+                frame.render_widget(
+                    Paragraph::new(draft.to.as_str()).block(
+                        Block::bordered()
+                            .title(" To ")
+                            .border_style(if draft.editing_to {
+                                focused
+                            } else {
+                                Style::new()
+                            }),
+                    ),
+                    to,
+                );
+                frame.render_widget(
+                    Paragraph::new(draft.text.as_str())
+                        .wrap(Wrap { trim: false })
+                        .block(
+                            Block::bordered()
+                                .title(format!(
+                                    " Message ({}, {} segment(s)) ",
+                                    estimate.encoding, estimate.segments
+                                ))
+                                .border_style(if draft.editing_to {
+                                    Style::new()
+                                } else {
+                                    focused
+                                }),
+                        ),
+                    text,
+                );
+            }
+        }
+    }
+}
+
+async fn fetch_inbox(modem: &dyn ModemClient) -> Result<Vec<SmsMessage>, Error> {
+    let session = modem.get_session().await?;
+    let params = SmsListParams {
+        box_type: BoxType::LocalInbox,
+        sort_type: SortType::Date,
+        read_count: INBOX_SIZE,
+        ascending: false,
+        unread_preferred: false,
+    };
+    let response = modem.get_sms_list(&session, params, 1).await?;
+    Ok(response.messages.message)
+}
+
+/// Runs the inbox in the terminal until the user quits
+pub async fn run(modem: &dyn ModemClient) -> std::io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, modem).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    modem: &dyn ModemClient,
+) -> std::io::Result<()> {
+    let mut app = App::new();
+    app.status = format!("Loading messages from {}", modem.url());
+    terminal.draw(|frame| app.draw(frame))?;
+    app.perform(modem, Action::Refresh).await;
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.handle_key(key) {
+            Some(Action::Quit) => return Ok(()),
+            Some(action) => app.perform(modem, action).await,
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockModem;
+    use crate::types::{Priority, SmsType};
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+
+    fn message(index: i32, content: &str) -> SmsMessage {
+        SmsMessage {
+            smstat: SmsStat::Unread,
+            index,
+            phone: format!("+49176000000{}", index),
+            content: content.to_string(),
+            date: format!("2026-10-16 10:0{}:00", index),
+            sca: String::new(),
+            save_type: 0,
+            priority: Priority::Normal,
+            sms_type: SmsType::Single,
+        }
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> Option<Action> {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_reply_delete() {
+        let modem = MockModem::new().with_messages(
+            BoxType::LocalInbox,
+            vec![message(1, "Hello"), message(2, "Second\nline")],
+        );
+        let mut app = App::new();
+        app.perform(&modem, Action::Refresh).await;
+        // Newest first
+        assert_eq!(app.messages[0].index, 2);
+        assert_eq!(app.list.selected(), Some(0));
+
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.list.selected(), Some(1));
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::View);
+
+        // Reply to the message being read
+        press(&mut app, KeyCode::Char('r'));
+        type_text(&mut app, "Thanks");
+        let send = app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL));
+        assert_eq!(
+            send,
+            Some(Action::Send {
+                to: "+491760000001".to_string(),
+                text: "Thanks".to_string()
+            })
+        );
+        app.perform(&modem, send.unwrap()).await;
+        assert_eq!(app.mode, Mode::List);
+        assert_eq!(
+            modem.sent(),
+            [(vec!["+491760000001".to_string()], "Thanks".to_string())]
+        );
+
+        // Deleting asks first
+        press(&mut app, KeyCode::Char('d'));
+        assert_eq!(press(&mut app, KeyCode::Char('n')), None);
+        assert_eq!(app.messages.len(), 2);
+        press(&mut app, KeyCode::Char('d'));
+        let delete = press(&mut app, KeyCode::Char('y'));
+        assert_eq!(delete, Some(Action::Delete(1)));
+        app.perform(&modem, delete.unwrap()).await;
+        assert_eq!(modem.messages(BoxType::LocalInbox).len(), 1);
+        assert_eq!(app.list.selected(), Some(0));
+
+        assert_eq!(press(&mut app, KeyCode::Char('q')), Some(Action::Quit));
+    }
+
+    #[tokio::test]
+    async fn test_compose() {
+        let modem = MockModem::new();
+        let mut app = App::new();
+        press(&mut app, KeyCode::Char('n'));
+        type_text(&mut app, "+441234567890");
+        press(&mut app, KeyCode::Enter);
+        type_text(&mut app, "Hi");
+        press(&mut app, KeyCode::Enter);
+        type_text(&mut app, "there");
+        assert_eq!(
+            app.mode,
+            Mode::Compose(Draft {
+                to: "+441234567890".to_string(),
+                text: "Hi\nthere".to_string(),
+                editing_to: false,
+            })
+        );
+
+        // A failed send keeps the draft
+        modem.fail_once(100002);
+        let send = app.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL));
+        app.perform(&modem, send.unwrap()).await;
+        assert!(matches!(app.mode, Mode::Compose(_)));
+        assert!(app.status.starts_with("Error sending"));
+
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.mode, Mode::List);
+        assert!(modem.sent().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_draw() {
+        let modem = MockModem::new().with_messages(BoxType::LocalInbox, vec![message(1, "Hello")]);
+        let mut app = App::new();
+        app.perform(&modem, Action::Refresh).await;
+        let mut terminal = Terminal::new(TestBackend::new(80, 10)).unwrap();
+        for key in [KeyCode::Enter, KeyCode::Char('r')] {
+            terminal.draw(|frame| app.draw(frame)).unwrap();
+            let screen: String = terminal
+                .backend()
+                .buffer()
+                .content()
+                .iter()
+                .map(|cell| cell.symbol())
+                .collect();
+            assert!(screen.contains("+491760000001"), "{}", screen);
+            press(&mut app, key);
+        }
+    }
+}
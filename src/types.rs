@@ -69,11 +69,15 @@ pub enum Priority {
 pub enum SmsStat {
     Unread = 0,
     Read = 1,
+    /// A message in the sent box that was delivered to the network
+    Sent = 3,
+    /// A message in the sent box that could not be sent
+    SendFailed = 4,
     Unknown = -1,
 }
 
 /// Represents a single SMS message
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename = "Message")]
 pub struct SmsMessage {
     #[serde(rename = "Smstat")]
@@ -95,3 +99,146 @@ pub struct SmsMessage {
     #[serde(rename = "SmsType")]
     pub sms_type: SmsType,
 }
+
+/// Modem model, identifiers and firmware versions, from /api/device/information. Fields vary
+/// between models, so all of them are optional.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "response")]
+pub struct DeviceInfo {
+    #[serde(rename = "DeviceName", default)]
+    pub device_name: Option<String>,
+    #[serde(rename = "SerialNumber", default)]
+    pub serial_number: Option<String>,
+    #[serde(rename = "Imei", default)]
+    pub imei: Option<String>,
+    #[serde(rename = "Imsi", default)]
+    pub imsi: Option<String>,
+    #[serde(rename = "Iccid", default)]
+    pub iccid: Option<String>,
+    /// Phone number of the SIM, if the operator stores it on the SIM
+    #[serde(rename = "Msisdn", default)]
+    pub msisdn: Option<String>,
+    #[serde(rename = "HardwareVersion", default)]
+    pub hardware_version: Option<String>,
+    #[serde(rename = "SoftwareVersion", default)]
+    pub software_version: Option<String>,
+    #[serde(rename = "WebUIVersion", default)]
+    pub webui_version: Option<String>,
+    #[serde(rename = "MacAddress1", default)]
+    pub mac_address: Option<String>,
+    #[serde(rename = "ProductFamily", default)]
+    pub product_family: Option<String>,
+    #[serde(rename = "workmode", default)]
+    pub work_mode: Option<String>,
+}
+
+/// Registered operator and connection state, combined from /api/net/current-plmn
+/// and /api/monitoring/status. Fields the modem does not report are omitted.
+#[derive(Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct NetworkInfo {
+    pub operator: Option<String>,
+    /// Mobile country and network code, e.g. `26201`
+    pub plmn: Option<String>,
+    /// Radio access technology, e.g. `LTE` or `HSPA+`
+    pub network_type: Option<String>,
+    /// State of the data connection, e.g. `connected`
+    pub connection: Option<String>,
+    pub roaming: Option<bool>,
+}
+
+/// Category of an API error, also used to pick the CLI exit code
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize, Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ErrorKind {
+    BadRequest,
+    NotFound,
+    NotConfigured,
+    /// A missing or wrong API token
+    Unauthorized,
+    RateLimited,
+    /// The same message was already sent to the recipient recently
+    Duplicate,
+    /// The message matches a content filter rule that blocks it
+    Blocked,
+    /// Fields of a well-formed request have values that cannot be sent, see
+    /// [`ErrorDetail::fields`]
+    Invalid,
+    /// The modem rejected the request with an error code
+    ModemError,
+    /// The modem could not be reached or returned an unexpected response
+    ModemUnavailable,
+    Internal,
+    #[serde(other)]
+    Unknown,
+}
+
+impl ErrorKind {
+    /// Process exit code for a command that failed with this kind of error
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorKind::Internal | ErrorKind::Unknown => 1,
+            ErrorKind::BadRequest | ErrorKind::Invalid => 2,
+            ErrorKind::RateLimited => 3,
+            ErrorKind::ModemError => 4,
+            ErrorKind::ModemUnavailable => 5,
+            ErrorKind::NotConfigured => 6,
+            ErrorKind::NotFound => 7,
+            ErrorKind::Unauthorized => 8,
+            ErrorKind::Duplicate => 9,
+            ErrorKind::Blocked => 10,
+        }
+    }
+}
+
+/// Represents the Messages wrapper in the SMS list response
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "Messages")]
+pub struct SmsMessages {
+    #[serde(rename = "Message", default)]
+    pub message: Vec<SmsMessage>,
+}
+
+/// Represents the SMS list response XML
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename = "response")]
+pub struct SmsListResponse {
+    #[serde(rename = "Count")]
+    pub count: i32,
+    #[serde(rename = "Messages")]
+    pub messages: SmsMessages,
+}
+
+/// JSON body of a successful `/get-sms` response
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct SmsListJson {
+    pub status: String,
+    pub messages: Vec<SmsMessage>,
+}
+
+/// Details of an API error
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ErrorDetail {
+    pub kind: ErrorKind,
+    /// Modem error code, if the error came from the modem
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<i32>,
+    pub message: String,
+    /// The invalid fields, for `invalid` errors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+/// A request field that failed validation
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FieldError {
+    /// Name of the field, with the position for list items, e.g. `to[1]`
+    pub field: String,
+    pub message: String,
+}
+
+/// JSON body of every error response: `{"error": {"kind": ..., "code": ..., "message": ...}}`
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ErrorResponse {
+    pub error: ErrorDetail,
+}